@@ -0,0 +1,30 @@
+/* C interface of x8, available when the library is built with the `ffi` feature:
+ *
+ *     cargo rustc --lib --release --features ffi --crate-type cdylib
+ *
+ * or with --crate-type staticlib for the static library.
+ *
+ * The config and the findings are json strings, see the Embedding section of docs.md.
+ */
+
+#ifndef X8_H
+#define X8_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* scans the targets from the json config like {"args": ["-u", "https://example.com/", "-w", "params.txt"]}
+ * and returns the findings as a json string. Errors that stop the whole scan are returned as {"error": "..."}.
+ * Blocks the calling thread until the scan is finished. Returns NULL if config_json is NULL.
+ * The returned string should be freed with x8_free() */
+char *x8_scan(const char *config_json);
+
+/* frees the string returned by x8_scan() */
+void x8_free(char *findings_json);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* X8_H */
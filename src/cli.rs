@@ -0,0 +1,114 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+
+use clap::{App, Arg, Shell, SubCommand};
+
+use crate::{
+    config::args::{app, arg_groups},
+    error::EXIT_OK,
+    merge::merge_app,
+    retest::verify_report_app,
+    serve::serve_app,
+    triage::triage_app,
+    worker::worker_app,
+};
+
+/// (injection place, description, command)
+const EXAMPLES: &[(&str, &str, &str)] = &[
+    ("query", "GET parameters", "x8 -u https://example.com/ -w params.txt"),
+    ("query", "the query of the existing endpoint", "x8 -u 'https://example.com/?id=1&%s' -w params.txt"),
+    ("body", "urlencoded body", "x8 -u https://example.com/ -X POST -w params.txt"),
+    ("body", "json body", "x8 -u https://example.com/ -X POST --body '{\"user\":{%s}}' -t json -w params.txt"),
+    ("body", "parameters within the body of GET requests", "x8 -u https://example.com/ --invert -w params.txt"),
+    ("body", "the request from a file, e.g. saved from Burp", "x8 -r request.txt -w params.txt"),
+    ("headers", "headers", "x8 -u https://example.com/ --headers -w headers.txt"),
+    ("header value", "cookies", "x8 -u https://example.com/ --cookies -w params.txt"),
+    ("header value", "the value of a custom header", "x8 -u https://example.com/ -H 'X-Settings: {\"a\":1,%s}' -w params.txt"),
+    ("value", "the value of the existing parameter", "x8 -u 'https://example.com/?filter=a' --inject-into filter -w params.txt"),
+    ("trailers", "trailer fields of the chunked body", "x8 -u https://example.com/ -X POST --trailers -w headers.txt"),
+    ("chunk extensions", "extensions of the chunk size line", "x8 -u https://example.com/ -X POST --chunk-extensions -w params.txt"),
+    ("other", "several urls and methods with json output", "x8 -u urls.txt -X GET POST -w params.txt -O json -o report.json"),
+];
+
+/// the arguments of x8 completions. Also used to generate shell completions
+pub fn completions_app() -> App<'static, 'static> {
+    App::new("completions")
+        .bin_name("x8 completions")
+        .about("Print the shell completion script")
+        .arg(
+            Arg::with_name("shell")
+                .help("Example: x8 completions bash > /etc/bash_completion.d/x8")
+                .possible_values(&Shell::variants())
+                .required(true),
+        )
+}
+
+/// x8 completions <shell>.
+/// args are the command line starting with the program name and "completions". Returns the exit code
+pub fn completions(args: Vec<String>) -> Result<i32, Box<dyn Error>> {
+    let args = completions_app().get_matches_from(args.into_iter().skip(1));
+
+    let shell: Shell = args.value_of("shell").unwrap().parse()?;
+
+    // the subcommands are dispatched before the scan arguments are parsed, so they're added only here
+    let self_test = SubCommand::with_name("self-test")
+        .about("Check the detection against the embedded test server. Accepts the scan options")
+        .args(&arg_groups().into_iter().flat_map(|(_, args)| args).collect::<Vec<Arg>>());
+
+    app()
+        .subcommands(vec![
+            self_test,
+            serve_app(),
+            worker_app(),
+            merge_app(),
+            triage_app(),
+            verify_report_app(),
+            completions_app(),
+            examples_app(),
+        ])
+        .gen_completions_to("x8", shell, &mut io::stdout());
+
+    Ok(EXIT_OK)
+}
+
+/// the arguments of x8 examples. Also used to generate shell completions
+pub fn examples_app() -> App<'static, 'static> {
+    App::new("examples")
+        .bin_name("x8 examples")
+        .about("Print common invocations for every injection place")
+        .arg(
+            Arg::with_name("place")
+                .help("Show only the examples of the place, e.g. body"),
+        )
+}
+
+/// x8 examples [place].
+/// args are the command line starting with the program name and "examples". Returns the exit code
+pub fn examples(args: Vec<String>) -> Result<i32, Box<dyn Error>> {
+    let args = examples_app().get_matches_from(args.into_iter().skip(1));
+
+    let mut last_place = "";
+
+    for (place, description, command) in EXAMPLES
+        .iter()
+        .filter(|(place, _, _)| args.value_of("place").map_or(true, |x| x == *place))
+    {
+        if *place != last_place {
+            writeln!(io::stdout(), "\n{}:", place).ok();
+            last_place = place;
+        }
+
+        writeln!(io::stdout(), "  # {}\n  {}", description, command).ok();
+    }
+
+    if last_place.is_empty() {
+        Err(format!(
+            "No examples for {}. The places are: query, body, headers, header value, value, trailers, chunk extensions, other",
+            args.value_of("place").unwrap_or_default()
+        ))?
+    }
+
+    Ok(EXIT_OK)
+}
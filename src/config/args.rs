@@ -1,371 +1,969 @@
 use crate::{
     config::{
         structs::Config,
-        utils::{convert_to_string_if_some, parse_request},
+        utils::{
+            convert_to_string_if_some, parse_duration, parse_error_budget, parse_proxy_map, parse_request, parse_shard,
+            parse_header, parse_tls_version, parse_vars, expand_url_vars,
+            read_custom_values,
+        },
     },
-    network::utils::{DataType, Headers},
+    network::{
+        proxy::parse_proxy,
+        utils::{ActiveHours, CertVerification, DataType, DuplicatePosition, Headers, InjectionPlace, IpRange},
+        watchdog::StallAction,
+    },
+    runner::output::report_args,
 };
 use clap::{crate_version, App, AppSettings, Arg};
-use std::{collections::HashMap, error::Error, fs, io::{self, Write}};
+use linked_hash_map::LinkedHashMap;
+use regex::Regex;
+use std::{collections::HashMap, error::Error, fs, io::{self, Write}, path::Path};
 use tokio::time::Duration;
 use url::Url;
 
-use super::utils::{read_urls_if_possible, mimic_browser_headers, add_default_headers};
+use crate::utils::read_stdin_lines;
+
+use super::utils::{
+    read_urls_if_possible, mimic_browser_headers, add_default_headers, read_auth_profiles, strip_args, strip_multi_args,
+    OUTPUT_ARGS, REDACTED, TARGET_ARGS, USER_AGENTS,
+};
+
+const AFTER_HELP: &str = "EXAMPLES:
+    x8 -u https://example.com/ -w params.txt
+    x8 -u https://example.com/ -X POST -w params.txt
+    x8 -u https://example.com/ -w headers.txt --headers
+    x8 -r request.txt -w params.txt
+
+SUBCOMMANDS:
+    examples       Print common invocations for every injection place
+    completions    Print the shell completion script: x8 completions <bash|zsh|fish|powershell|elvish>
+    self-test, serve, worker, merge, verify-report, triage
+                   See docs.md";
 
 pub fn get_config() -> Result<Config, Box<dyn Error>> {
+    get_config_from(std::env::args().collect())
+}
+
+/// parses the command line including the program name
+pub fn get_config_from(cmd_args: Vec<String>) -> Result<Config, Box<dyn Error>> {
+    config_from(cmd_args, true)
+}
+
+/// same as get_config_from() but wrong arguments, --help and --version are returned as errors instead of exiting.
+/// Used when x8 is embedded into other programs
+pub fn try_config_from(cmd_args: Vec<String>) -> Result<Config, Box<dyn Error>> {
+    config_from(cmd_args, false)
+}
+
+/// restores the config of the scan from the arguments saved within its json output.
+/// The targets are replaced with the given one, the output and the messages are turned off
+pub fn report_config(report_args: Vec<String>, method: &str, url: &str) -> Result<Config, Box<dyn Error>> {
+    let mut cmd_args = vec!["x8".to_string()];
+    cmd_args.extend(strip_multi_args(
+        strip_args(report_args, OUTPUT_ARGS),
+        &[TARGET_ARGS, &["-v", "--verbose", "--disable-progress-bar", "--from-report"]].concat(),
+    ));
+    cmd_args.extend(
+        ["-u", url, "-X", method, "-v", "0", "--disable-progress-bar"]
+            .iter()
+            .map(|x| x.to_string()),
+    );
+
+    try_config_from(cmd_args)
+}
+
+/// the argument parser of the scan. Also used to generate shell completions
+pub fn app() -> App<'static, 'static> {
     let app = App::new("x8")
         .setting(AppSettings::ArgRequiredElseHelp)
         .version(crate_version!())
         .author("sh1yo <sh1yo@tuta.io>")
         .about("Hidden parameters discovery suite")
-        .arg(Arg::with_name("url")
-            .short("u")
-            .long("url")
-            .help("You can add a custom injection point with %s.\nMultiple urls and filenames are supported:\n-u filename.txt\n-u https://url1 http://url2")
-            .takes_value(true)
-            .min_values(1)
-            .conflicts_with("request")
-        )
-        .arg(Arg::with_name("request")
-            .short("r")
-            .long("request")
-            .help("The file with the raw http request")
-            .takes_value(true)
-            .conflicts_with("url")
-        )
-        .arg(Arg::with_name("proto")
-            .long("proto")
-            .help("Protocol to use with request file (default is \"https\")")
-            .takes_value(true)
-            .requires("request")
-            .conflicts_with("url")
-        )
-        .arg(Arg::with_name("port")
-            .long("port")
-            .short("-p")
-            .help("Port to use with request file")
-            .takes_value(true)
-            .requires("request")
-            .conflicts_with("url")
-        )
-        .arg(Arg::with_name("split-by")
-            .long("split-by")
-            .help("Split the request into lines by the provided sequence. By default splits by \\r, \\n and \\r\\n")
-            .takes_value(true)
-            .requires("request")
-            .conflicts_with("url")
-        )
-        .arg(
-            Arg::with_name("wordlist")
-                .short("w")
-                .long("wordlist")
-                .help("The file with parameters (leave empty to read from stdin)")
-                .default_value("")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("parameter-template")
-                .short("P")
-                .long("param-template")
-                .help("%k - key, %v - value. Example: --param-template 'user[%k]=%v'\nDefault: urlencoded - <%k=%v>, json - <\"%k\":%v>, headers - <%k=%v>")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("joiner")
-            .short("j")
-            .long("joiner")
-            .help("How to join parameter templates. Example: --joiner '&'\nDefault: urlencoded - '&', json - ', ', header values - '; '")
-            .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("body")
-                .short("b")
-                .long("body")
-                .help("Example: --body '{\"x\":{%s}}'\nAvailable variables: {{random}}")
-                .value_name("body")
-                .conflicts_with("request")
-        )
-        .arg(
-            Arg::with_name("data-type")
-                .short("t")
-                .long("data-type")
-                .help("Available: urlencode, json\nCan be detected automatically if --body is specified (default is \"urlencode\")")
-                .value_name("data-type")
-        )
-        .arg(
-            Arg::with_name("proxy")
-                .short("x")
-                .long("proxy")
-                .value_name("proxy")
-                .takes_value(true)
-        )
-        .arg(
-            Arg::with_name("burp-proxy")
-                .short("B")
-                .help("Equal to -x http://localhost:8080")
-                .conflicts_with("proxy")
-        )
-        .arg(
-            Arg::with_name("delay")
-                .short("d")
-                .long("delay")
-                .value_name("Delay between requests in milliseconds")
-                .default_value("0")
-                .takes_value(true)
-        )
-        .arg(
-            Arg::with_name("output")
-                .short("o")
-                .long("output")
-                .value_name("file")
-                .takes_value(true)
-        )
-        .arg(
-            Arg::with_name("output-format")
-                .short("O")
-                .long("output-format")
-                .help("standart, json, url, request")
-                .default_value("standart")
-                .takes_value(true)
-        )
-        .arg(
-            Arg::with_name("append")
-                .long("append")
-                .help("Append to the output file instead of overwriting it.")
-        )
-        .arg(
-            Arg::with_name("remove-empty")
-                .long("remove-empty")
-                .requires("output")
-                .help("Skip writing to file outputs of url:method pairs without found parameters")
-        )
-        .arg(
-            Arg::with_name("method")
-                .short("X")
-                .long("method")
-                .value_name("methods")
-                .help("Multiple values are supported: -X GET POST")
-                .takes_value(true)
-                .min_values(1)
-                .conflicts_with("request")
-        )
-        .arg(
-            Arg::with_name("headers")
-                .short("H")
-                .help("Example: -H 'one:one' 'two:two'")
-                .takes_value(true)
-                .min_values(1)
-                .conflicts_with("request")
-        )
-        .arg(
-            Arg::with_name("invert")
-                .long("invert")
-                .help("By default, parameters are sent within the body only in case POST,PUT,PATCH,DELETE methods are used.
+        .after_help(AFTER_HELP);
+
+    arg_groups().iter().fold(app, |app, (_, args)| app.args(args))
+}
+
+/// --help with the arguments split into sections.
+/// clap 2 can't group them, so every section is rendered separately
+pub fn grouped_help() -> String {
+    let mut help = Vec::new();
+    app()
+        .template("{bin} {version}\n{author}\n{about}\n\nUSAGE:\n    {usage}\n\nFLAGS:\n    -h, --help       Prints help information\n    -V, --version    Prints version information")
+        .write_help(&mut help)
+        .ok();
+
+    for (heading, args) in arg_groups() {
+        help.extend(format!("\n\n{}:\n", heading).as_bytes());
+        App::new("x8")
+            .settings(&[AppSettings::DisableHelpFlags, AppSettings::DisableVersion, AppSettings::UnifiedHelpMessage])
+            .args(&args)
+            .template("{unified}")
+            .write_help(&mut help)
+            .ok();
+    }
+
+    help.extend(format!("\n\n{}\n", AFTER_HELP).as_bytes());
+
+    String::from_utf8_lossy(&help).into_owned()
+}
+
+/// the sections of --help and their arguments
+pub fn arg_groups() -> Vec<(&'static str, Vec<Arg<'static, 'static>>)> {
+    vec![
+        (
+            "TARGET",
+            vec![
+                Arg::with_name("url")
+                    .short("u")
+                    .long("url")
+                    .help("You can add a custom injection point with %s.\nMultiple urls and filenames are supported:\n-u filename.txt\n-u https://url1 http://url2")
+                    .takes_value(true)
+                    .min_values(1)
+                    .conflicts_with("request"),
+                Arg::with_name("var")
+                    .long("var")
+                    .value_name("name=values")
+                    .help("Values of the {{name}} placeholder within the url. Every combination is checked as a separate url.\nExample: -u 'https://example.com/{{version}}/users' --var version=v1,v2,v3")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .conflicts_with("request"),
+                Arg::with_name("stdin")
+                    .long("stdin")
+                    .help("Read newline-delimited urls from stdin. Found parameters are printed in JSONL unless --output-format is specified")
+                    .conflicts_with("url")
+                    .conflicts_with("request"),
+                Arg::with_name("request")
+                    .short("r")
+                    .long("request")
+                    .help("The file with the raw http request")
+                    .takes_value(true)
+                    .conflicts_with("url"),
+                Arg::with_name("proto")
+                    .long("proto")
+                    .help("Protocol to use with request file (default is \"https\")")
+                    .takes_value(true)
+                    .requires("request")
+                    .conflicts_with("url"),
+                Arg::with_name("port")
+                    .long("port")
+                    .short("-p")
+                    .help("Port to use with request file")
+                    .takes_value(true)
+                    .requires("request")
+                    .conflicts_with("url"),
+                Arg::with_name("split-by")
+                    .long("split-by")
+                    .help("Split the request into lines by the provided sequence. By default splits by \\r, \\n and \\r\\n")
+                    .takes_value(true)
+                    .requires("request")
+                    .conflicts_with("url"),
+                Arg::with_name("method")
+                    .short("X")
+                    .long("method")
+                    .value_name("methods")
+                    .help("Multiple values are supported: -X GET POST")
+                    .takes_value(true)
+                    .min_values(1)
+                    .conflicts_with("request"),
+                Arg::with_name("body")
+                    .short("b")
+                    .long("body")
+                    .help("Example: --body '{\"x\":{%s}}'\nAvailable variables: {{random}}")
+                    .value_name("body")
+                    .conflicts_with("request"),
+                Arg::with_name("data-type")
+                    .short("t")
+                    .long("data-type")
+                    .help("Available: urlencode, json\nCan be detected automatically if --body is specified (default is \"urlencode\")")
+                    .value_name("data-type"),
+                Arg::with_name("headers")
+                    .short("H")
+                    .help("Example: -H 'one:one' 'two:two'")
+                    .takes_value(true)
+                    .min_values(1)
+                    .conflicts_with("request"),
+                Arg::with_name("headers-file")
+                    .long("headers-file")
+                    .value_name("file")
+                    .help("Read headers from the file with 'Name: value' lines, e.g. copied from the browser.\n-H headers override them")
+                    .takes_value(true)
+                    .conflicts_with("request"),
+                Arg::with_name("cookies")
+                    .long("cookies")
+                    .help("Shortcut for adding injection point to cookies"),
+                Arg::with_name("http")
+                    .long("http")
+                    .help("HTTP version. Supported versions: --http 1.1, --http 2, --http 3.\nHTTP/3 requires the http3 cargo feature and falls back to h2/h1.1 in case the host doesn't accept QUIC")
+                    .takes_value(true),
+                Arg::with_name("invert")
+                    .long("invert")
+                    .help("By default, parameters are sent within the body only in case POST,PUT,PATCH,DELETE methods are used.
 It's possible to overwrite this behavior by specifying the option")
-                .conflicts_with("headers-discovery")
-        )
-        .arg(
-            Arg::with_name("headers-discovery")
-                .long("headers")
-                .help("Switch to header discovery mode.\nNOTE Content-Length and Host headers are automatically removed from the list")
-                .conflicts_with("invert")
-                .conflicts_with("param-template")
-        )
-        .arg(
-            Arg::with_name("force")
-                .long("force")
-                .help("Force searching for parameters on pages > 25MB. Remove an error in case there's 1 worker with --one-worker-per-host option.")
-        )
-        .arg(
-            Arg::with_name("disable-custom-parameters")
-                .long("disable-custom-parameters")
-                .help("Do not automatically check parameters like admin=true")
-        )
-        .arg(
-            Arg::with_name("disable-colors")
-                .long("disable-colors")
-        )
-        .arg(
-            Arg::with_name("force-enable-colors")
-                .long("force-enable-colors")
-        )
-        .arg(
-            Arg::with_name("disable-trustdns")
-                .long("disable-trustdns")
-                .help("Can solve some dns related problems")
-        )
-        .arg(
-            Arg::with_name("disable-progress-bar")
-                .long("disable-progress-bar")
-        )
-        .arg(
-            Arg::with_name("progress-bar-len")
-                .long("progress-bar-len")
-                .default_value("26")
-        )
-        .arg(
-            Arg::with_name("replay-once")
-                .long("replay-once")
-                .help("If a replay proxy is specified, send all found parameters within one request.")
-                .requires("replay-proxy")
-        )
-        .arg(
-            Arg::with_name("replay-proxy")
-                .takes_value(true)
-                .long("replay-proxy")
-                .help("Request target with every found parameter via the replay proxy at the end.")
-        )
-        .arg(
-            Arg::with_name("custom-parameters")
-                .long("custom-parameters")
-                .help("Check these parameters with non-random values like true/false yes/no\n(default is \"admin bot captcha debug disable encryption env show sso test waf\")")
-                .takes_value(true)
-                .min_values(1)
-                .conflicts_with("disable-custom-parameters")
-        )
-        .arg(
-            Arg::with_name("custom-values")
-                .long("custom-values")
-                .help("Values for custom parameters (default is \"1 0 false off null true yes no\")")
-                .takes_value(true)
-                .min_values(1)
-                .conflicts_with("disable-custom-parameters")
-        )
-        .arg(
-            Arg::with_name("follow-redirects")
-                .long("follow-redirects")
-                .short("L")
-                .help("Follow redirections")
-        )
-        .arg(
-            Arg::with_name("encode")
-                .long("encode")
-                .help("Encodes query or body before making a request, i.e & -> %26, = -> %3D\nList of chars to encode: \", `, , <, >, &, #, ;, /, =, %")
-        )
-        .arg(
-            Arg::with_name("strict")
-                .long("strict")
-                .help("Only report parameters that have changed the different parts of a page")
-        )
-        .arg(
-            Arg::with_name("test")
-                .long("test")
-                .help("Prints request and response")
-        )
-        .arg(
-            Arg::with_name("verbose")
-                .long("verbose")
-                .short("v")
-                .help("Verbose level 0/1/2")
-                .default_value("1")
-                .takes_value(true)
-        )
-        .arg(
-            Arg::with_name("save-responses")
-                .long("save-responses")
-                .help("Save request and response to a directory when a parameter is found")
-                .takes_value(true)
-        )
-        .arg(
-            Arg::with_name("learn-requests-count")
-                .long("learn-requests")
-                .help("Set the custom number of learn requests.")
-                .default_value("9")
-                .takes_value(true)
-        )
-        .arg(
-            Arg::with_name("recursion-depth")
-                .long("recursion-depth")
-                .help("Check the same list of parameters with the found parameters until there are no new parameters to be found.
+                    .conflicts_with("headers-discovery"),
+                Arg::with_name("expect-code")
+                    .long("expect-code")
+                    .value_name("codes")
+                    .help("Status codes of the normal page, e.g. --expect-code 401 403 for pages that require authentication.\nThe scan stops in case the initial response has a different code")
+                    .takes_value(true)
+                    .min_values(1),
+                Arg::with_name("from-report")
+                    .long("from-report")
+                    .help("Rerun the scan with the arguments from the json or jsonl output of the previous run.\nOnly the output options can be changed")
+                    .value_name("file")
+                    .takes_value(true),
+                // handled before parsing the arguments, see run_job_file()
+                Arg::with_name("job-file")
+                    .long("job-file")
+                    .help("Run the scan described by the json file and print the result as one json document")
+                    .value_name("file")
+                    .takes_value(true),
+            ],
+        ),
+        (
+            "PARAMETERS",
+            vec![
+                Arg::with_name("wordlist")
+                    .short("w")
+                    .long("wordlist")
+                    .help("The file with parameters (leave empty to read from stdin)")
+                    .default_value("")
+                    .takes_value(true),,
+                Arg::with_name("lowercase")
+                    .long("lowercase")
+                    .help("Lowercase the parameter names from the wordlist. Repeated names are removed afterwards"),
+                Arg::with_name("wordlist-parts")
+                    .long("wordlist-parts")
+                    .value_name("uint")
+                    .help("Read the wordlist lazily in parts of <uint> parameters. Every url is learned once and its parts are checked one by one,\nso huge wordlists don't have to fit into memory")
+                    .takes_value(true),
+                Arg::with_name("parameter-template")
+                    .short("P")
+                    .long("param-template")
+                    .help("%k - key, %v - value. Example: --param-template 'user[%k]=%v'\nDefault: urlencoded - <%k=%v>, json - <\"%k\":%v>, headers - <%k=%v>")
+                    .takes_value(true),,
+                Arg::with_name("joiner")
+                    .short("j")
+                    .long("joiner")
+                    .help("How to join parameter templates. Example: --joiner '&'\nDefault: urlencoded - '&', json - ', ', header values - '; '")
+                    .takes_value(true),,
+                Arg::with_name("query-separator")
+                    .long("query-separator")
+                    .help("The delimiter between query parameters, e.g. --query-separator ';' for legacy Java and embedded servers.\nUsed for the query and urlencoded bodies and while parsing query strings on the page")
+                    .takes_value(true),,
+                Arg::with_name("encode")
+                    .long("encode")
+                    .help("Encodes query or body before making a request, i.e & -> %26, = -> %3D\nList of chars to encode: \", `, , <, >, &, #, ;, /, =, %"),
+                Arg::with_name("custom-parameters")
+                    .long("custom-parameters")
+                    .help("Check these parameters with non-random values like true/false yes/no\n(default is \"admin bot captcha debug disable encryption env show sso test waf\")")
+                    .takes_value(true)
+                    .min_values(1)
+                    .conflicts_with("disable-custom-parameters"),
+                Arg::with_name("custom-values")
+                    .long("custom-values")
+                    .help("Values for custom parameters (default is \"1 0 false off null true yes no\")\nAlso accepts a file with additional values and per-parameter lists like `debug: 1,2,on`")
+                    .takes_value(true)
+                    .min_values(1)
+                    .conflicts_with("disable-custom-parameters"),
+                Arg::with_name("disable-custom-parameters")
+                    .long("disable-custom-parameters")
+                    .help("Do not automatically check parameters like admin=true"),
+                Arg::with_name("max")
+                    .short("m")
+                    .long("max")
+                    .help("Change the maximum number of parameters per request.\n(default is <= 256 for query and headers, 64 for header values and 512 for body)")
+                    .takes_value(true),
+                Arg::with_name("max-requests")
+                    .long("max-requests")
+                    .help("The maximum number of requests to check parameters with per url.\nParameters that don't fit are skipped")
+                    .takes_value(true),
+                Arg::with_name("sample")
+                    .long("sample")
+                    .help("Check a random subset of parameters instead of the first ones when --max-requests is exceeded")
+                    .requires("max-requests"),
+                Arg::with_name("shard")
+                    .long("shard")
+                    .help("Check only the k-th of n disjoint parts of the wordlist, so n instances can share the wordlist.\nExample: --shard 2/4")
+                    .value_name("k/n")
+                    .takes_value(true),
+                Arg::with_name("no-mining")
+                    .long("no-mining")
+                    .help("Don't check parameter names found within responses like json keys, data-* attributes and error messages"),
+                Arg::with_name("extra-params-watch")
+                    .long("extra-params-watch")
+                    .help("Check names appended to the file or named pipe while the scan is running, e.g. by a crawler")
+                    .value_name("file")
+                    .takes_value(true),
+                Arg::with_name("learned-db")
+                    .long("learned-db")
+                    .value_name("file")
+                    .help("Check parameters found on targets with the same technology first.\nFound parameters are added to the file")
+                    .takes_value(true),
+                Arg::with_name("fingerprint")
+                    .long("fingerprint")
+                    .help("Detect the framework or CMS by headers, cookies, a few common paths and the favicon,\nand check its specific parameters first"),
+                Arg::with_name("recursion-depth")
+                    .long("recursion-depth")
+                    .help("Check the same list of parameters with the found parameters until there are no new parameters to be found.
 Conflicts with --verify for now.")
-                .takes_value(true)
-                .conflicts_with("verify")
-        )
-        .arg(
-            Arg::with_name("max")
-                .short("m")
-                .long("max")
-                .help("Change the maximum number of parameters per request.\n(default is <= 256 for query, 64 for headers and 512 for body)")
-                .takes_value(true)
-        )
-        .arg(
-            Arg::with_name("timeout")
-                .long("timeout")
-                .help("HTTP request timeout in seconds.")
-                .default_value("15")
-                .takes_value(true)
-        )
-        .arg(
-            Arg::with_name("concurrency")
-                .short("c")
-                .help("The number of concurrent requests per url")
-                .default_value("1")
-                .takes_value(true)
-        )
-        .arg(
-            Arg::with_name("workers")
-                .short("W")
-                .long("workers")
-                .help("The number of concurrent url checks.\nUse -W0 to run everything in parallel")
-                .default_value("1")
-                .takes_value(true)
-        )
-        .arg(
-            Arg::with_name("verify")
-                .long("verify")
-                .help("Verify found parameters.")
-        )
-        .arg(
-            Arg::with_name("reflected-only")
-                .long("reflected-only")
-                .help("Disable page comparison and search for reflected parameters only.")
-        )
-        .arg(
-            Arg::with_name("one-worker-per-host")
-                .long("one-worker-per-host")
-                .help("Multiple urls with the same host will be checked one after another,\nwhile urls with different hosts - are in parallel.\nDoesn't increase the number of workers")
-        )
-        .arg(
-            Arg::with_name("mimic-browser")
-                .long("mimic-browser")
-                .help("Add default headers that browsers usually set.")
-                .conflicts_with("request")
-        )
-        .arg(
-            Arg::with_name("http")
-                .long("http")
-                .help("HTTP version. Supported versions: --http 1.1, --http 2")
-                .takes_value(true)
-        ).arg(
-            Arg::with_name("check-binary")
-                .long("check-binary")
-                .help("Check the body of responses with binary content types")
-        ).arg(
-            Arg::with_name("cookies")
-                .long("cookies")
-                .help("Shortcut for adding injection point to cookies")
-        ).arg(
-            Arg::with_name("remove-banner")
-                .long("remove-banner")
-                .help("Do not print initial banner")
-        );
-
-    let args = app.clone().get_matches();
-
-    if args.value_of("url").is_none() && args.value_of("request").is_none() {
+                    .takes_value(true)
+                    .conflicts_with("verify"),
+            ],
+        ),
+        (
+            "INJECTION PLACES",
+            vec![
+                Arg::with_name("headers-discovery")
+                    .long("headers")
+                    .help("Switch to header discovery mode.\nNOTE Content-Length and Host headers are automatically removed from the list")
+                    .conflicts_with("invert")
+                    .conflicts_with("param-template"),
+                Arg::with_name("inject-into")
+                    .long("inject-into")
+                    .value_name("parameter")
+                    .help("Append parameters to the value of the existing parameter instead of adding new ones.\nExample: filter=a -> filter=a;param1=value1;param2=value2. Use --joiner to change ';'")
+                    .takes_value(true),
+                Arg::with_name("jwt")
+                    .long("jwt")
+                    .help("Inject parameters as claims into the payload of the JWT from the request.\nThe token is sent unsigned (alg: none) unless --jwt-key is provided")
+                    .conflicts_with("protobuf")
+                    .conflicts_with("inject-into")
+                    .conflicts_with("headers-discovery"),
+                Arg::with_name("jwt-key")
+                    .long("jwt-key")
+                    .value_name("secret")
+                    .help("Re-sign tokens with the key. Only HS256, HS384 and HS512 are supported")
+                    .takes_value(true)
+                    .requires("jwt"),
+                Arg::with_name("wsdl")
+                    .long("wsdl")
+                    .value_name("file/url")
+                    .help("Check child elements of every SOAP operation from the WSDL.\n--url is the SOAP endpoint")
+                    .takes_value(true)
+                    .conflicts_with("request")
+                    .conflicts_with("protobuf")
+                    .conflicts_with("inject-into")
+                    .conflicts_with("headers-discovery")
+                    .conflicts_with("jwt"),
+                Arg::with_name("protobuf")
+                    .long("protobuf")
+                    .help("Send parameters as protobuf string fields. Parameters are field numbers or names from --proto-file.\n--body is treated as a hex encoded base message"),
+                Arg::with_name("proto-file")
+                    .long("proto-file")
+                    .value_name("file")
+                    .help("The .proto file to map field names to numbers")
+                    .takes_value(true)
+                    .requires("protobuf"),
+                Arg::with_name("grpc-web")
+                    .long("grpc-web")
+                    .help("Wrap protobuf messages into gRPC-web frames")
+                    .requires("protobuf"),
+                Arg::with_name("trailers")
+                    .long("trailers")
+                    .help("Send parameters as trailer fields after the last chunk of a chunked body.\nThe requests are sent over HTTP/1.1 byte by byte")
+                    .conflicts_with_all(&["headers-discovery", "chunk-extensions"]),
+                Arg::with_name("chunk-extensions")
+                    .long("chunk-extensions")
+                    .help("Send parameters as extensions of the chunk size line, e.g. 5;name=value.\nThe requests are sent over HTTP/1.1 byte by byte")
+                    .conflicts_with("headers-discovery"),
+                Arg::with_name("duplicate-names")
+                    .long("duplicate-names")
+                    .help("Pair every parameter with a duplicate of a known parameter from the url or the body.\n'before' places the duplicate before every parameter, 'after' places it after")
+                    .possible_values(&["before", "after"])
+                    .takes_value(true),,
+                Arg::with_name("duplicate-of")
+                    .long("duplicate-of")
+                    .value_name("name")
+                    .help("The known parameter to duplicate with --duplicate-names. Default: the first one")
+                    .requires("duplicate-names")
+                    .takes_value(true),,
+                Arg::with_name("query-split")
+                    .long("query-split")
+                    .help("Split body chunks between the query and the body in case a probe confirms that the backend merges them"),
+            ],
+        ),
+        (
+            "DETECTION",
+            vec![
+                Arg::with_name("learn-requests-count")
+                    .long("learn-requests")
+                    .help("Set the custom number of learn requests.")
+                    .default_value("9")
+                    .takes_value(true),
+                Arg::with_name("persistent-diffs")
+                    .long("persistent-diffs")
+                    .value_name("percent")
+                    .help("Ignore only the diffs that appeared in at least this percentage of learn responses. Rarer diffs are still treated as changes.\nThe frequency of every diff is printed with -v 2")
+                    .default_value("0")
+                    .takes_value(true),
+                Arg::with_name("reflected-only")
+                    .long("reflected-only")
+                    .help("Disable page comparison and search for reflected parameters only."),
+                Arg::with_name("strict")
+                    .long("strict")
+                    .help("Only report parameters that have changed the different parts of a page"),
+                Arg::with_name("ignore-numbers")
+                    .long("ignore-numbers")
+                    .help("Consider lines that differ only in digits (counters, timestamps, prices) equal while comparing responses"),
+                Arg::with_name("reflection-exclude")
+                    .long("reflection-exclude")
+                    .value_name("regex")
+                    .help("Regexes of regions where reflections aren't counted.\nExample: --reflection-exclude '<form action=\"[^\"]*\"' to skip the echoed url")
+                    .takes_value(true)
+                    .min_values(1),
+                Arg::with_name("check-binary")
+                    .long("check-binary")
+                    .help("Check the body of responses with binary content types"),
+                Arg::with_name("force")
+                    .long("force")
+                    .help("Force searching for parameters on pages > 25MB. Remove an error in case there's 1 worker with --one-worker-per-host option."),
+                Arg::with_name("verify")
+                    .long("verify")
+                    .help("Verify found parameters."),
+                Arg::with_name("warmup")
+                    .long("warmup")
+                    .value_name("n")
+                    .help("Send n throwaway requests before the initial one to fill caches and wake up backends")
+                    .default_value("0")
+                    .takes_value(true),
+                Arg::with_name("freeze-cookies")
+                    .long("freeze-cookies")
+                    .help("Ignore Set-Cookie headers once the first page is learned, so session rotation doesn't change the responses")
+                    .requires("cookie-jar"),
+                Arg::with_name("cookie-jar")
+                    .long("cookie-jar")
+                    .help("Keep cookies from Set-Cookie headers within one jar per url|method pair, shared between all the clients of the target"),
+                Arg::with_name("cachebuster")
+                    .long("cachebuster")
+                    .value_name("name")
+                    .help("The name of the query parameter with a unique value that is added to every request when a CDN or a cache is detected")
+                    .default_value("x8cb")
+                    .takes_value(true),,
+                Arg::with_name("no-cachebuster")
+                    .long("no-cachebuster")
+                    .help("Don't add the cachebuster parameter, e.g. to check for cache poisoning"),,
+                Arg::with_name("mimic-browser")
+                    .long("mimic-browser")
+                    .help("Add default headers that browsers usually set.")
+                    .conflicts_with("request"),
+                Arg::with_name("follow-redirects")
+                    .long("follow-redirects")
+                    .short("L")
+                    .help("Follow redirections"),
+            ],
+        ),
+        (
+            "FOLLOW-UP PROBES",
+            vec![
+                Arg::with_name("redirect-canary")
+                    .long("redirect-canary")
+                    .help("Check whether found parameters that influence redirects can redirect to this url.\nExample: --redirect-canary https://example.com/")
+                    .takes_value(true),
+                Arg::with_name("ssrf-canary")
+                    .long("ssrf-canary")
+                    .help("Send this callback url to found parameters that accept urls. {{id}} is replaced with a unique id.\nExample: --ssrf-canary 'http://{{id}}.callback.example.com/'")
+                    .takes_value(true),
+                Arg::with_name("ssrf-poll")
+                    .long("ssrf-poll")
+                    .help("The url that returns the requests received by the callback server.\nParameters whose ids are found there are reported as ssrf")
+                    .takes_value(true)
+                    .requires("ssrf-canary"),
+                Arg::with_name("ssrf-poll-delay")
+                    .long("ssrf-poll-delay")
+                    .value_name("duration")
+                    .help("How long to wait for the callback server to receive the requests before requesting --ssrf-poll")
+                    .default_value("5s")
+                    .takes_value(true)
+                    .requires("ssrf-poll"),
+                Arg::with_name("parser-probes")
+                    .long("parser-probes")
+                    .help("Send edge-case encodings of found reflected parameters (repeated names, double and %u encodings, overlong utf-8)\nto fingerprint the backend's parser"),
+                Arg::with_name("error-signatures")
+                    .long("error-signatures")
+                    .help("Additional regexes of error messages to look for in pages with found parameters.\nBuilt-in ones detect sql, template and stack-trace errors")
+                    .takes_value(true)
+                    .min_values(1),
+                Arg::with_name("replay-proxy")
+                    .takes_value(true)
+                    .long("replay-proxy")
+                    .help("Request target with every found parameter via the replay proxy at the end."),
+                Arg::with_name("replay-once")
+                    .long("replay-once")
+                    .help("If a replay proxy is specified, send all found parameters within one request.")
+                    .requires("replay-proxy"),
+            ],
+        ),
+        (
+            "NETWORK",
+            vec![
+                Arg::with_name("proxy")
+                    .short("x")
+                    .long("proxy")
+                    .value_name("proxy")
+                    .takes_value(true),
+                Arg::with_name("proxy-map")
+                    .long("proxy-map")
+                    .help("Proxies for specific hosts and their subdomains like internal.corp=socks5://127.0.0.1:1080.\n'direct' sends the requests without a proxy. Hosts from NO_PROXY skip --proxy")
+                    .value_name("host=proxy")
+                    .takes_value(true)
+                    .min_values(1),
+                Arg::with_name("proxy-fallback")
+                    .long("proxy-fallback")
+                    .help("Send the requests directly in case the proxy fails the check before the scan.\nThe check compares the responses via the proxy with direct ones then"),
+                Arg::with_name("no-proxy-check")
+                    .long("no-proxy-check")
+                    .help("Don't check whether the proxy opens CONNECT tunnels, answers requests and keeps the response headers before the scan")
+                    .conflicts_with("proxy-fallback"),
+                Arg::with_name("burp-proxy")
+                    .long("burp-proxy")
+                    .short("B")
+                    .help("Equal to -x http://localhost:8080")
+                    .conflicts_with("proxy"),
+                Arg::with_name("engine")
+                    .long("engine")
+                    .help("reqwest or raw. The raw engine sends HTTP/1.1 requests byte by byte,\nkeeping the order, the casing and the spacing of the headers")
+                    .default_value("reqwest")
+                    .takes_value(true),
+                Arg::with_name("timeout")
+                    .long("timeout")
+                    .help("HTTP request timeout in seconds.")
+                    .default_value("15")
+                    .takes_value(true),
+                Arg::with_name("disable-trustdns")
+                    .long("disable-trustdns")
+                    .help("Can solve some dns related problems"),
+                Arg::with_name("tls-min")
+                    .long("tls-min")
+                    .help("The lowest TLS version to negotiate. Supported versions: 1.2, 1.3")
+                    .takes_value(true),
+                Arg::with_name("tls-max")
+                    .long("tls-max")
+                    .help("The highest TLS version to negotiate. Supported versions: 1.2, 1.3")
+                    .takes_value(true),
+                Arg::with_name("ciphers")
+                    .long("ciphers")
+                    .help("TLS cipher suites to offer. Example: --ciphers TLS13_AES_128_GCM_SHA256 TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256")
+                    .takes_value(true)
+                    .min_values(1),
+                Arg::with_name("no-tls-tickets")
+                    .long("no-tls-tickets")
+                    .help("Don't resume TLS sessions, every connection makes the full handshake")
+                    .conflicts_with("tls-early-data"),
+                Arg::with_name("tls-early-data")
+                    .long("tls-early-data")
+                    .help("Allow TLS 1.3 0-RTT early data when sessions are resumed. Requires --engine raw.\nEarly data can be replayed by anyone on the network path, use it only with idempotent requests")
+                    .conflicts_with("no-tls-tickets"),
+                Arg::with_name("sni")
+                    .long("sni")
+                    .help("Send this server name within the TLS handshake instead of the target's host")
+                    .takes_value(true),
+                Arg::with_name("insecure")
+                    .long("insecure")
+                    .help("Which certificate checks to skip: all, hostname, none")
+                    .default_value("all")
+                    .takes_value(true),
+                Arg::with_name("pin-ip")
+                    .long("pin-ip")
+                    .help("Connect only to this address instead of resolving the target's host")
+                    .takes_value(true)
+                    .conflicts_with("ip-failover"),
+                Arg::with_name("source-ip")
+                    .long("source-ip")
+                    .help("Send the requests from this local address")
+                    .value_name("ip")
+                    .takes_value(true),
+                Arg::with_name("interface")
+                    .long("interface")
+                    .help("Send the requests through this network interface (Linux only)")
+                    .value_name("name")
+                    .takes_value(true),
+                Arg::with_name("ip-failover")
+                    .long("ip-failover")
+                    .help("Stick to one of the addresses the host resolves to and switch to the next one only when it starts erroring")
+                    .conflicts_with("pin-ip"),
+                Arg::with_name("allow-malformed")
+                    .long("allow-malformed")
+                    .help("Send requests that servers can parse differently, e.g. with bare LFs or both Content-Length and Transfer-Encoding headers.\nBy default they're refused to avoid request smuggling"),
+                Arg::with_name("expect-continue")
+                    .long("expect-continue")
+                    .help("Send Expect: 100-continue with bodies of at least that size in bytes.\nOnly the raw engine waits for 100 Continue before sending the body")
+                    .takes_value(true),
+            ],
+        ),
+        (
+            "RATE AND CONCURRENCY",
+            vec![
+                Arg::with_name("delay")
+                    .short("d")
+                    .long("delay")
+                    .value_name("Delay between requests in milliseconds")
+                    .default_value("0")
+                    .takes_value(true),
+                Arg::with_name("concurrency")
+                    .long("concurrency")
+                    .short("c")
+                    .help("The number of concurrent requests per url")
+                    .default_value("1")
+                    .takes_value(true),
+                Arg::with_name("adaptive-concurrency")
+                    .long("adaptive-concurrency")
+                    .help("Increase the number of concurrent requests while there are no errors and the latency is stable,\ndecrease otherwise. -c sets the initial number"),
+                Arg::with_name("max-concurrency")
+                    .long("max-concurrency")
+                    .help("The upper bound for --adaptive-concurrency")
+                    .default_value("32")
+                    .takes_value(true),
+                Arg::with_name("workers")
+                    .short("W")
+                    .long("workers")
+                    .help("The number of concurrent url checks.\nUse -W0 to run everything in parallel")
+                    .default_value("1")
+                    .takes_value(true),
+                Arg::with_name("one-worker-per-host")
+                    .long("one-worker-per-host")
+                    .help("Multiple urls with the same host will be checked one after another,\nwhile urls with different hosts - are in parallel.\nDoesn't increase the number of workers"),
+                Arg::with_name("max-host-concurrency")
+                    .long("max-host-concurrency")
+                    .value_name("uint")
+                    .help("The maximum number of concurrent requests to a single host across all workers.\nFree slots are given to the urls of the host in turns, so one host doesn't get hammered while checking many urls")
+                    .takes_value(true),
+                Arg::with_name("circuit-breaker")
+                    .long("circuit-breaker")
+                    .help("Pause the scan after <num> consecutive connection errors and resume once the target is reachable again.\nThe failed chunks are retested. 0 to disable")
+                    .default_value("5")
+                    .takes_value(true),
+                Arg::with_name("error-budget")
+                    .long("error-budget")
+                    .help("The amount of failed requests allowed per phase before aborting the url.\nDefault: learning=0,discovery=10,narrowing=10,verification=3")
+                    .value_name("phase=num,..")
+                    .takes_value(true),
+                Arg::with_name("active-hours")
+                    .long("active-hours")
+                    .value_name("from-to")
+                    .help("Send requests only within the local time window, e.g. --active-hours 22:00-06:00.\nThe scan is paused outside of it")
+                    .takes_value(true),
+                Arg::with_name("control-file")
+                    .long("control-file")
+                    .value_name("file")
+                    .help("Apply the delay, concurrency and rate values from the file while the scan is running.\nThe file is reread on changes and on SIGHUP. Example of the content:\ndelay = 200\nconcurrency = 4\nrate = 10\nconcurrency caps the requests in flight across all urls, it can't raise the concurrency above -c and -W")
+                    .takes_value(true),
+                Arg::with_name("stall-timeout")
+                    .long("stall-timeout")
+                    .value_name("duration")
+                    .help("Report the requests in flight, their connections and the last events when no responses arrive for the duration, e.g. 2m.\nThe hanging requests are resent or the scan is aborted depending on --stall-action")
+                    .takes_value(true),
+                Arg::with_name("stall-action")
+                    .long("stall-action")
+                    .help("What to do with the hanging requests after --stall-timeout")
+                    .possible_values(&["retry", "abort"])
+                    .default_value("retry")
+                    .takes_value(true),
+                Arg::with_name("shared-state")
+                    .long("shared-state")
+                    .help("Share learned diffs, found parameters and rate limits (429 responses) between urls of the same host"),
+                Arg::with_name("state-file")
+                    .long("state-file")
+                    .value_name("file")
+                    .help("Keep the finished url|method pairs within the file and skip them once the scan is started again")
+                    .takes_value(true)
+                    .requires("active-hours"),
+            ],
+        ),
+        (
+            "HOOKS AND EVASION",
+            vec![
+                Arg::with_name("random-agent")
+                    .long("random-agent")
+                    .help("Send a random browser User-Agent with every request after the page is learned")
+                    .conflicts_with("agents-file"),
+                Arg::with_name("agents-file")
+                    .long("agents-file")
+                    .value_name("file")
+                    .help("The same as --random-agent but with User-Agent values from the file, one per line")
+                    .takes_value(true),
+                Arg::with_name("rotate-ip")
+                    .long("rotate-ip")
+                    .value_name("cidr")
+                    .help("Send a random address within X-Forwarded-For and X-Real-IP with every request, optionally from the CIDR.\nOff by default. Use only when the engagement allows to spoof client addresses")
+                    .takes_value(true)
+                    .min_values(0),
+                Arg::with_name("challenge-cmd")
+                    .long("challenge-cmd")
+                    .value_name("cmd")
+                    .help("The command receives the url of the page with an anti-bot challenge on stdin and returns headers to add on stdout, e.g. Cookie: cf_clearance=..\nOtherwise, the header is asked from the user")
+                    .takes_value(true),
+                Arg::with_name("script")
+                    .long("script")
+                    .value_name("file")
+                    .help("Rhai script with on_request(req) and/or on_response(resp) functions.\nThey receive maps and return the modified ones, e.g. to sign requests")
+                    .takes_value(true),
+                Arg::with_name("pre-request-cmd")
+                    .long("pre-request-cmd")
+                    .value_name("cmd")
+                    .help("The command receives every request on stdin and returns headers to add/replace on stdout.\nExample: --pre-request-cmd 'python3 sign.py'")
+                    .takes_value(true),
+            ],
+        ),
+        (
+            "OUTPUT",
+            vec![
+                Arg::with_name("output")
+                    .short("o")
+                    .long("output")
+                    .value_name("file")
+                    .takes_value(true),
+                Arg::with_name("output-dir")
+                    .long("output-dir")
+                    .help("Save results of every url to <dir>/<host>/<hash>/ and write the summary to <dir>/index.json.\nAlready checked urls are skipped when the same directory is used again")
+                    .value_name("dir")
+                    .takes_value(true),
+                Arg::with_name("output-format")
+                    .short("O")
+                    .long("output-format")
+                    .help("standart, json, jsonl, url, request")
+                    .default_value("standart")
+                    .takes_value(true),
+                Arg::with_name("append")
+                    .long("append")
+                    .help("Append to the output file instead of overwriting it."),
+                Arg::with_name("remove-empty")
+                    .long("remove-empty")
+                    .requires("output")
+                    .help("Skip writing to file outputs of url:method pairs without found parameters"),
+                Arg::with_name("min-score")
+                    .long("min-score")
+                    .help("Do not report parameters with a lower score. Found parameters are sorted by the score")
+                    .default_value("0")
+                    .takes_value(true),
+                Arg::with_name("sort-by")
+                    .long("sort-by")
+                    .help("The order of found parameters")
+                    .possible_values(&["score", "discovery"])
+                    .default_value("score")
+                    .takes_value(true),
+                Arg::with_name("no-dedup")
+                    .long("no-dedup")
+                    .help("Don't collapse the same parameter found at several urls of the host with the same behavior into one finding"),
+                Arg::with_name("save-responses")
+                    .long("save-responses")
+                    .help("Save request and response to a directory when a parameter is found")
+                    .takes_value(true),
+                Arg::with_name("snapshots")
+                    .long("snapshots")
+                    .help("Store request and response that confirmed every found parameter to a directory under the hash of their content.\nThe location is referenced from the output")
+                    .takes_value(true),
+                Arg::with_name("test")
+                    .long("test")
+                    .help("Prints request and response"),
+                Arg::with_name("verbose")
+                    .long("verbose")
+                    .short("v")
+                    .help("Verbose level 0/1/2")
+                    .default_value("1")
+                    .takes_value(true),
+                Arg::with_name("show-diffs")
+                    .long("show-diffs")
+                    .help("Print removed and added lines for parameters that change the page"),
+                Arg::with_name("quiet")
+                    .short("q")
+                    .long("quiet")
+                    .help("Print only the findings to stdout, without colors, the banner and the progress bars.\nErrors are printed to stderr"),
+                Arg::with_name("remove-banner")
+                    .long("remove-banner")
+                    .help("Do not print initial banner"),
+                Arg::with_name("disable-colors")
+                    .long("disable-colors"),
+                Arg::with_name("force-enable-colors")
+                    .long("force-enable-colors"),
+                Arg::with_name("disable-progress-bar")
+                    .long("disable-progress-bar"),
+                Arg::with_name("progress-bar-len")
+                    .long("progress-bar-len")
+                    .default_value("26"),
+                Arg::with_name("triage")
+                    .long("triage")
+                    .help("Resend, inspect and mark the findings after the scan. Requires -o with the json or jsonl output.\nCan't be used with --watch because the scan never ends")
+                    .requires("output")
+                    .conflicts_with("watch"),
+                Arg::with_name("fail-on-found")
+                    .long("fail-on-found")
+                    .help("Exit with code 1 in case at least one parameter was found"),
+            ],
+        ),
+        (
+            "MONITORING",
+            vec![
+                Arg::with_name("baseline")
+                    .long("baseline")
+                    .value_name("previous.json")
+                    .help("The json or jsonl output of a previous run.\nPrints only new parameters and exits with code 1 in case there are any")
+                    .takes_value(true),
+                Arg::with_name("watch")
+                    .long("watch")
+                    .value_name("interval")
+                    .help("Rerun the scan every <interval> (e.g. 30m, 24h) and print only the changes in found parameters")
+                    .takes_value(true),
+                Arg::with_name("watch-history")
+                    .long("watch-history")
+                    .help("The file with found parameters from every --watch run")
+                    .default_value("x8-history.jsonl")
+                    .takes_value(true),
+                Arg::with_name("webhook")
+                    .long("webhook")
+                    .help("Send changes found within --watch to the url as json")
+                    .takes_value(true)
+                    .requires("watch"),
+                Arg::with_name("diff-against")
+                    .long("diff-against")
+                    .value_name("base url")
+                    .help("Run the same scan against another environment, e.g. https://staging.example.com,\nand print the parameters accepted by only one of them")
+                    .takes_value(true)
+                    .conflicts_with("watch"),
+                Arg::with_name("auth-profiles")
+                    .long("auth-profiles")
+                    .value_name("file")
+                    .help("Run the scan once per profile from the file and print the parameters visible only to some of them.\nThe file is yaml, every profile is a name followed by indented 'Header: value' lines")
+                    .takes_value(true)
+                    .conflicts_with_all(&["watch", "diff-against", "cookie-jar"]),
+            ],
+        ),
+    ]
+}
+
+fn config_from(mut cmd_args: Vec<String>, exit_on_error: bool) -> Result<Config, Box<dyn Error>> {
+    let app = app();
+
+    let matches = |cmd_args: &[String]| {
+        if exit_on_error {
+            Ok(app.clone().get_matches_from(cmd_args))
+        } else {
+            app.clone().get_matches_from_safe(cmd_args)
+        }
+    };
+
+    // clap prints all the arguments within one list, so the grouped help is printed instead
+    if exit_on_error && (cmd_args.len() <= 1 || cmd_args[1..].iter().any(|x| x == "-h" || x == "--help")) {
+        write!(io::stdout(), "{}", grouped_help()).ok();
+        std::process::exit(if cmd_args.len() <= 1 { 1 } else { 0 });
+    }
+
+    let mut args = matches(&cmd_args)?;
+
+    // the arguments of the previous run are followed by the current ones, so the output options can be changed
+    if let Some(path) = args.value_of("from-report").map(|x| x.to_string()) {
+        let mut replayed = strip_args(report_args(&path)?, OUTPUT_ARGS);
+
+        if replayed.iter().any(|x| x.contains(REDACTED)) {
+            Err("The secrets of the scan aren't saved within the report. Rerun it with the original arguments")?
+        }
+
+        replayed.insert(0, cmd_args[0].clone());
+        replayed.extend(strip_args(cmd_args[1..].to_vec(), &[("--from-report", true)]));
+
+        cmd_args = replayed;
+        args = matches(&cmd_args)?;
+    }
+
+    if args.value_of("url").is_none() && args.value_of("request").is_none() && !args.is_present("stdin") {
         Err("A target was not provided")?;
     }
 
+    // stdin is used for the targets so the parameters can be read only from the wordlist
+    if args.is_present("stdin") && args.value_of("wordlist").unwrap().is_empty() {
+        Err("--stdin requires --wordlist")?;
+    }
+
+    let raw_engine = match args.value_of("engine").unwrap() {
+        "reqwest" => false,
+        "raw" => true,
+        _ => Err("--engine should be reqwest or raw")?,
+    };
+
+    if raw_engine && args.value_of("http") == Some("2") {
+        Err("The raw engine sends requests over HTTP/1.1 only")?;
+    }
+
+    let chunked = if args.is_present("trailers") {
+        Some(InjectionPlace::Trailers)
+    } else if args.is_present("chunk-extensions") {
+        Some(InjectionPlace::ChunkExtensions)
+    } else {
+        None
+    };
+
+    if chunked.is_some() && args.value_of("http") == Some("2") {
+        Err("Trailers and chunk extensions are sent over HTTP/1.1 only")?;
+    }
+
+    if args.is_present("triage") && !["json", "jsonl"].contains(&args.value_of("output-format").unwrap()) {
+        Err("--triage requires the json or jsonl output format")?;
+    }
+
+    let http3 = args.value_of("http") == Some("3");
+
+    // QUIC is sent by a separate engine that is built only with the feature
+    if http3 && !cfg!(feature = "http3") {
+        Err("--http 3 requires x8 built with the http3 feature: cargo build --release --features http3")?;
+    }
+
+    if http3 && (raw_engine || chunked.is_some()) {
+        Err("The raw engine, trailers and chunk extensions are sent over HTTP/1.1 only")?;
+    }
+
     // parse numbers
     let delay = Duration::from_millis(args.value_of("delay").unwrap().parse()?);
 
     let learn_requests_count = args.value_of("learn-requests-count").unwrap().parse()?;
+    let warmup = args.value_of("warmup").unwrap().parse()?;
+    let persistent_diffs = args.value_of("persistent-diffs").unwrap().parse()?;
+    if persistent_diffs > 100 {
+        Err("--persistent-diffs is a percentage and can't be bigger than 100")?;
+    }
     let concurrency = args.value_of("concurrency").unwrap().parse()?;
+    let max_concurrency = args.value_of("max-concurrency").unwrap().parse()?;
+    let circuit_breaker = args.value_of("circuit-breaker").unwrap().parse()?;
+    let error_budget = parse_error_budget(args.value_of("error-budget").unwrap_or(""))?;
+
+    let stall_timeout = match args.value_of("stall-timeout") {
+        Some(val) => match parse_duration(val)? {
+            val if val.as_secs() == 0 => Err("--stall-timeout should be at least 1s")?,
+            val => Some(val),
+        },
+        None => None,
+    };
+
+    let watch = match args.value_of("watch") {
+        Some(val) => Some(parse_duration(val)?),
+        None => None,
+    };
     let workers = args.value_of("workers").unwrap().parse()?;
-    let verbose = args.value_of("verbose").unwrap().parse()?;
+    let verbose = if args.is_present("quiet") {
+        0
+    } else {
+        args.value_of("verbose").unwrap().parse()?
+    };
     let timeout = args.value_of("timeout").unwrap().parse()?;
     let recursion_depth = args.value_of("recursion-depth").unwrap_or("0").parse()?;
     let progress_bar_len = args.value_of("progress-bar-len").unwrap().parse()?;
+    let min_score = args.value_of("min-score").unwrap().parse()?;
+
+    let expect_continue: Option<usize> = match args.value_of("expect-continue") {
+        Some(val) => Some(val.parse()?),
+        None => None,
+    };
+
+    let max_requests: Option<usize> = if args.is_present("max-requests") {
+        Some(args.value_of("max-requests").unwrap().parse()?)
+    } else {
+        None
+    };
+
+    let shard = match args.value_of("shard") {
+        Some(val) => Some(parse_shard(val)?),
+        None => None,
+    };
 
     let max: Option<usize> = if args.is_present("max") {
         Some(args.value_of("max").unwrap().parse()?)
@@ -379,6 +977,108 @@ So there's no point in --one-worker-per-host with 1 worker. \
 Increase the amount of workers to remove the error or use --force.")?;
     }
 
+    let tls_min = parse_tls_version(args.value_of("tls-min"))?;
+    let tls_max = parse_tls_version(args.value_of("tls-max"))?;
+
+    if args.is_present("tls-early-data") {
+        if tls_max == Some(reqwest::tls::Version::TLS_1_2) {
+            Err("--tls-early-data requires TLS 1.3")?
+        }
+
+        // reqwest's connector never sends early data
+        if !raw_engine && chunked.is_none() {
+            Err("--tls-early-data works only with --engine raw")?
+        }
+
+        writeln!(
+            io::stderr(),
+            "[#] 0-RTT early data can be replayed by anyone on the network path. Make sure the checked requests are idempotent"
+        ).ok();
+    }
+
+    let error_signatures = match args.values_of("error-signatures") {
+        Some(val) => val.map(Regex::new).collect::<Result<Vec<Regex>, regex::Error>>()?,
+        None => Vec::new(),
+    };
+
+    let user_agents = if let Some(path) = args.value_of("agents-file") {
+        let agents: Vec<String> = fs::read_to_string(path)?
+            .lines()
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect();
+
+        if agents.is_empty() {
+            Err("The agents file is empty")?
+        }
+
+        agents
+    } else if args.is_present("random-agent") {
+        USER_AGENTS.iter().map(|x| x.to_string()).collect()
+    } else {
+        Vec::new()
+    };
+
+    let rotate_ip = if args.is_present("rotate-ip") {
+        Some(IpRange::parse(args.value_of("rotate-ip").unwrap_or(""))?)
+    } else {
+        None
+    };
+
+    let wordlist_parts = match args.value_of("wordlist-parts") {
+        Some(_) if args.value_of("wordlist").unwrap().is_empty() => Err("--wordlist-parts requires --wordlist")?,
+        Some(val) => match val.parse::<usize>()? {
+            0 => Err("--wordlist-parts should be greater than 0")?,
+            val => Some(val),
+        },
+        None => None,
+    };
+
+    let auth_profiles = match args.value_of("auth-profiles") {
+        Some(val) => read_auth_profiles(val)?,
+        None => Vec::new(),
+    };
+
+    let max_host_concurrency = match args.value_of("max-host-concurrency") {
+        Some(val) => match val.parse::<usize>()? {
+            0 => Err("--max-host-concurrency should be greater than 0")?,
+            val => Some(val),
+        },
+        None => None,
+    };
+
+    let active_hours = match args.value_of("active-hours") {
+        Some(val) => Some(ActiveHours::parse(val)?),
+        None => None,
+    };
+
+    let expect_codes = match args.values_of("expect-code") {
+        Some(val) => val.map(|x| x.parse()).collect::<Result<Vec<u16>, std::num::ParseIntError>>()?,
+        None => Vec::new(),
+    };
+
+    let reflection_exclusions = match args.values_of("reflection-exclude") {
+        Some(val) => val.map(Regex::new).collect::<Result<Vec<Regex>, regex::Error>>()?,
+        None => Vec::new(),
+    };
+
+    let pin_ip = match args.value_of("pin-ip") {
+        Some(val) => Some(val.parse()?),
+        None => None,
+    };
+
+    let source_ip = match args.value_of("source-ip") {
+        Some(val) => Some(val.parse().map_err(|_| format!("Wrong --source-ip address: {}", val))?),
+        None => None,
+    };
+
+    let insecure = match args.value_of("insecure").unwrap() {
+        "all" => CertVerification::Skip,
+        "hostname" => CertVerification::SkipHostname,
+        "none" => CertVerification::Full,
+        _ => Err("Incorrect --insecure value specified")?,
+    };
+
     // try to read request file
     let request = match args.value_of("request") {
         Some(val) => fs::read_to_string(val)?,
@@ -427,24 +1127,28 @@ Increase the amount of workers to remove the error or use --force.")?;
             vec!["GET".to_string()]
         };
 
-        let mut headers: HashMap<&str, String> = HashMap::new();
+        // declared before the headers because they borrow the names from it
+        let headers_file = match args.value_of("headers-file") {
+            Some(path) => fs::read_to_string(path).map_err(|err| format!("Unable to read the headers file {}: {}", path, err))?,
+            None => String::new(),
+        };
+
+        // the order is kept, the raw engine sends the headers the same way they're given
+        let mut headers: LinkedHashMap<&str, String> = LinkedHashMap::new();
+
+        // HTTP/2 pseudo-headers like :authority are skipped, so headers can be copied from browser devtools as is
+        for line in headers_file.lines().map(|x| x.trim_end_matches('\r')) {
+            if line.trim().is_empty() || line.starts_with('#') || line.starts_with(':') {
+                continue;
+            }
+
+            let (key, value) = parse_header(line, raw_engine)?;
+            headers.insert(key, value);
+        }
 
         if let Some(val) = args.values_of("headers") {
             for header in val {
-                let mut k_v = header.split(':');
-                let key = match k_v.next() {
-                    Some(val) => val,
-                    None => Err("Unable to parse headers")?,
-                };
-                let value = [
-                    match k_v.next() {
-                        Some(val) => val.trim().to_owned(),
-                        None => Err("Unable to parse headers")?,
-                    },
-                    k_v.map(|x| ":".to_owned() + x).collect(),
-                ]
-                .concat();
-
+                let (key, value) = parse_header(header, raw_engine)?;
                 headers.insert(key, value);
             }
         };
@@ -472,9 +1176,11 @@ Increase the amount of workers to remove the error or use --force.")?;
             match  args.value_of("http").unwrap() {
                 "1.1" => Some(http::Version::HTTP_11),
                 "2" => Some(http::Version::HTTP_2),
+                // the reqwest clients negotiate h2/h1.1 in case the host doesn't accept QUIC
+                "3" => None,
                 _ => {
                     writeln!(
-                        io::stdout(),
+                        io::stderr(),
                         "[#] Incorrect http version provided. The argument is ignored"
                     ).ok();
                     None
@@ -484,18 +1190,36 @@ Increase the amount of workers to remove the error or use --force.")?;
             None
         };
 
-        let urls = args
-            .values_of("url")
-            .unwrap();
+        let urls: Vec<String> = if args.is_present("stdin") {
+            read_stdin_lines()
+                .into_iter()
+                .map(|x| x.trim().to_string())
+                .filter(|x| !x.is_empty())
+                .collect()
+        } else {
+            let urls = args
+                .values_of("url")
+                .unwrap();
 
-        let urls = if urls.len() == 1 && !urls.clone().any(|x| x.contains("://")) {
-            // it can be a file
-            match read_urls_if_possible(urls.clone().next().unwrap())? {
-                Some(urls) => urls,
-                None => Err("The provided --url value is neither url nor a filename.")?
+            if urls.len() == 1 && !urls.clone().any(|x| x.contains("://")) {
+                // it can be a file
+                match read_urls_if_possible(urls.clone().next().unwrap())? {
+                    Some(urls) => urls,
+                    None => Err("The provided --url value is neither url nor a filename.")?
+                }
+            } else {
+                urls.map(|x| x.to_string()).collect()
             }
-        } else {
-            urls.map(|x| x.to_string()).collect()
+        };
+
+        if urls.is_empty() {
+            Err("A target was not provided")?;
+        }
+
+        // https://example.com/{{version}}/users -> https://example.com/v1/users, https://example.com/v2/users
+        let urls = match args.values_of("var") {
+            Some(val) => expand_url_vars(urls, &parse_vars(val)?)?,
+            None => urls,
         };
 
         let urls = urls.iter().map(|x| Url::parse(x))
@@ -542,12 +1266,26 @@ Increase the amount of workers to remove the error or use --force.")?;
         .collect(),
     };
 
+    let default_custom_values: Vec<String> = ["1", "0", "false", "off", "null", "true", "yes", "no"]
+        .iter()
+        .map(|x| x.to_string())
+        .collect();
+
+    let mut custom_values_per_parameter = HashMap::new();
+
     let custom_values: Vec<String> = match args.values_of("custom-values") {
+        // a file with additional values
+        Some(val) if val.len() == 1 && Path::new(args.value_of("custom-values").unwrap()).is_file() => {
+            let (mut values, per_parameter) = read_custom_values(args.value_of("custom-values").unwrap())?;
+            custom_values_per_parameter = per_parameter;
+
+            let mut custom_values = default_custom_values;
+            values.retain(|x| !custom_values.contains(x));
+            custom_values.append(&mut values);
+            custom_values
+        }
         Some(val) => val.map(|x| x.to_string()).collect(),
-        None => ["1", "0", "false", "off", "null", "true", "yes", "no"]
-            .iter()
-            .map(|x| x.to_string())
-            .collect(),
+        None => default_custom_values,
     };
 
     let mut custom_parameters: HashMap<String, Vec<String>> =
@@ -560,6 +1298,11 @@ Increase the amount of workers to remove the error or use --force.")?;
         custom_parameters.insert(key.to_string(), values);
     }
 
+    // per-parameter lists replace the common values and add new custom parameters
+    for (key, values) in custom_values_per_parameter {
+        custom_parameters.insert(key, values);
+    }
+
     // disable colors
     if args.is_present("disable-colors") {
         colored::control::set_override(false);
@@ -584,6 +1327,20 @@ Increase the amount of workers to remove the error or use --force.")?;
         args.value_of("proxy").unwrap_or("").to_string()
     };
 
+    // proxies are chosen per request, so the wrong ones are reported before the scan
+    if !proxy.is_empty() {
+        parse_proxy(&proxy)?;
+    }
+
+    if let Some(replay_proxy) = args.value_of("replay-proxy") {
+        parse_proxy(replay_proxy).map_err(|err| format!("Wrong --replay-proxy: {}", err))?;
+    }
+
+    let proxy_map = match args.values_of("proxy-map") {
+        Some(val) => parse_proxy_map(val)?,
+        None => Vec::new(),
+    };
+
     if args.is_present("cookies") {
         if let Some(index) = headers.get_index_case_insensitive("cookie") {
             headers[index] = (headers[index].0.clone(), headers[index].1.clone()+";%s")
@@ -594,27 +1351,59 @@ Increase the amount of workers to remove the error or use --force.")?;
 
     // TODO maybe replace empty with None
     Ok(Config {
+        args: cmd_args[1..].to_vec(),
         urls,
         methods,
         wordlist: args.value_of("wordlist").unwrap_or("").to_string(),
+        wordlist_parts,
+        lowercase: args.is_present("lowercase"),
         custom_parameters,
         proxy,
+        proxy_map,
+        proxy_fallback: args.is_present("proxy-fallback"),
+        no_proxy_check: args.is_present("no-proxy-check"),
         replay_proxy: args.value_of("replay-proxy").unwrap_or("").to_string(),
         replay_once: args.is_present("replay-once"),
         output_file: args.value_of("output").unwrap_or("").to_string(),
         save_responses: args.value_of("save-responses").unwrap_or("").to_string(),
-        output_format: args.value_of("output-format").unwrap_or("").to_string(),
+        output_dir: convert_to_string_if_some(args.value_of("output-dir")),
+        snapshots: convert_to_string_if_some(args.value_of("snapshots")),
+        output_format: if args.is_present("stdin") && args.occurrences_of("output-format") == 0 {
+            "jsonl".to_string()
+        } else {
+            args.value_of("output-format").unwrap_or("").to_string()
+        },
         append: args.is_present("append"),
-        remove_empty: args.is_present("remove-empty"),
+        triage: args.is_present("triage"),
+        remove_empty: args.is_present("remove-empty") || args.is_present("quiet"),
+        no_dedup: args.is_present("no-dedup"),
+        query_split: args.is_present("query-split"),
+        diff_against: convert_to_string_if_some(args.value_of("diff-against")),
+        auth_profiles,
         force: args.is_present("force"),
         strict: args.is_present("strict"),
-        disable_progress_bar: args.is_present("disable-progress-bar"),
+        disable_progress_bar: args.is_present("disable-progress-bar") || args.is_present("quiet"),
         progress_bar_len,
         follow_redirects: args.is_present("follow-redirects"),
         test: args.is_present("test"),
+        show_diffs: args.is_present("show-diffs"),
         verbose,
         learn_requests_count,
+        persistent_diffs,
+        warmup,
         concurrency,
+        adaptive_concurrency: args.is_present("adaptive-concurrency"),
+        max_concurrency,
+        circuit_breaker,
+        error_budget,
+        fail_on_found: args.is_present("fail-on-found"),
+        baseline: convert_to_string_if_some(args.value_of("baseline")),
+        watch,
+        watch_history: args.value_of("watch-history").unwrap().to_string(),
+        webhook: convert_to_string_if_some(args.value_of("webhook")),
+        sort_by: args.value_of("sort-by").unwrap().to_string(),
+        learned_db: convert_to_string_if_some(args.value_of("learned-db")),
+        fingerprint: args.is_present("fingerprint"),
         workers,
         timeout,
         recursion_depth,
@@ -622,12 +1411,59 @@ Increase the amount of workers to remove the error or use --force.")?;
         reflected_only: args.is_present("reflected-only"),
         http_version,
         template: convert_to_string_if_some(args.value_of("parameter-template")),
-        joiner: convert_to_string_if_some(args.value_of("joiner")),
+        stall_timeout,
+        control_file: convert_to_string_if_some(args.value_of("control-file")),
+        stall_action: match args.value_of("stall-action") {
+            Some("abort") => StallAction::Abort,
+            _ => StallAction::Retry,
+        },
+        cachebuster: if args.is_present("no-cachebuster") {
+            None
+        } else {
+            args.value_of("cachebuster").map(|x| x.to_string())
+        },
+        duplicate_names: match args.value_of("duplicate-names") {
+            Some("before") => Some(DuplicatePosition::Before),
+            Some("after") => Some(DuplicatePosition::After),
+            _ => None,
+        },
+        duplicate_of: convert_to_string_if_some(args.value_of("duplicate-of")),
+        query_separator: convert_to_string_if_some(args.value_of("query-separator")).filter(|x| !x.is_empty()),
+        // candidates within a value are usually separated by ;
+        joiner: match args.value_of("joiner") {
+            Some(val) => Some(val.to_string()),
+            None if args.is_present("inject-into") => Some(";".to_string()),
+            None => None,
+        },
+        inject_into: convert_to_string_if_some(args.value_of("inject-into")),
+        protobuf: args.is_present("protobuf"),
+        proto: convert_to_string_if_some(args.value_of("proto-file")),
+        grpc_web: args.is_present("grpc-web"),
+        max_host_concurrency,
+        shared_state: args.is_present("shared-state"),
+        script: convert_to_string_if_some(args.value_of("script")),
+        pre_request_cmd: convert_to_string_if_some(args.value_of("pre-request-cmd")),
+        jwt: args.is_present("jwt"),
+        jwt_key: convert_to_string_if_some(args.value_of("jwt-key")),
+        wsdl: convert_to_string_if_some(args.value_of("wsdl")),
+        soap_operation: None,
+        reflection_exclusions,
+        expect_codes,
+        user_agents,
+        rotate_ip,
+        active_hours,
+        state_file: convert_to_string_if_some(args.value_of("state-file")),
+        challenge_cmd: convert_to_string_if_some(args.value_of("challenge-cmd")),
+        cookie_jar: args.is_present("cookie-jar"),
+        freeze_cookies: args.is_present("freeze-cookies"),
         encode: args.is_present("encode"),
         disable_custom_parameters: args.is_present("disable-custom-parameters"),
         one_worker_per_host: args.is_present("one-worker-per-host"),
         invert: args.is_present("invert"),
         headers_discovery: args.is_present("headers-discovery") || args.is_present("cookies"),
+        chunked,
+        raw_engine,
+        http3,
         body,
         delay,
         custom_headers: headers
@@ -637,8 +1473,38 @@ Increase the amount of workers to remove the error or use --force.")?;
         data_type,
         max,
         disable_colors: args.is_present("disable-colors"),
-        remove_banner: args.is_present("remove-banner"),
+        remove_banner: args.is_present("remove-banner") || args.is_present("quiet"),
+        quiet: args.is_present("quiet"),
         disable_trustdns: args.is_present("disable-trustdns"),
         check_binary: args.is_present("check-binary"),
+        ignore_numbers: args.is_present("ignore-numbers"),
+        tls_min,
+        tls_max,
+        ciphers: args
+            .values_of("ciphers")
+            .map(|x| x.map(|x| x.to_string()).collect())
+            .unwrap_or_default(),
+        no_tls_tickets: args.is_present("no-tls-tickets"),
+        tls_early_data: args.is_present("tls-early-data"),
+        sni: convert_to_string_if_some(args.value_of("sni")),
+        insecure,
+        pin_ip,
+        source_ip,
+        interface: convert_to_string_if_some(args.value_of("interface")),
+        ip_failover: args.is_present("ip-failover"),
+        allow_malformed: args.is_present("allow-malformed"),
+        expect_continue,
+        max_requests,
+        sample: args.is_present("sample"),
+        shard,
+        no_mining: args.is_present("no-mining"),
+        extra_params_watch: convert_to_string_if_some(args.value_of("extra-params-watch")),
+        min_score,
+        redirect_canary: convert_to_string_if_some(args.value_of("redirect-canary")),
+        ssrf_canary: convert_to_string_if_some(args.value_of("ssrf-canary")),
+        ssrf_poll: convert_to_string_if_some(args.value_of("ssrf-poll")),
+        ssrf_poll_delay: parse_duration(args.value_of("ssrf-poll-delay").unwrap())?,
+        parser_probes: args.is_present("parser-probes"),
+        error_signatures,
     })
 }
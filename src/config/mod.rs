@@ -1,3 +1,4 @@
 pub mod args;
 pub mod structs;
 pub mod utils;
+pub mod validate;
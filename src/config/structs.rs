@@ -1,9 +1,17 @@
-use std::{collections::HashMap, time::Duration};
+use std::{collections::HashMap, net::IpAddr, time::Duration};
 
-use crate::network::utils::DataType;
+use regex::Regex;
+
+use crate::network::{
+    utils::{ActiveHours, CertVerification, DataType, DuplicatePosition, InjectionPlace, IpRange},
+    watchdog::StallAction,
+};
 
 #[derive(Debug, Clone)]
 pub struct Config {
+    /// the command line without the program name. Saved within json outputs for --from-report
+    pub args: Vec<String>,
+
     /// default urls without any changes (except from when used from request file, maybe change this logic TODO)
     pub urls: Vec<String>,
 
@@ -19,6 +27,12 @@ pub struct Config {
     /// user supplied wordlist file
     pub wordlist: String,
 
+    /// the amount of parameters to read from the wordlist at once
+    pub wordlist_parts: Option<usize>,
+
+    /// lowercase names from the wordlist
+    pub lowercase: bool,
+
     /// max amount of parameters to send per request.
     /// Can be specified by user otherwise detects automatically based on the request method
     pub max: Option<usize>,
@@ -29,6 +43,90 @@ pub struct Config {
     /// how to join parameters, for example '&'
     pub joiner: Option<String>,
 
+    /// the delimiter between query parameters
+    pub query_separator: Option<String>,
+
+    /// pair every parameter with a duplicate of the known one, placed before or after it
+    pub duplicate_names: Option<DuplicatePosition>,
+
+    /// the known parameter to duplicate. The first one from the url or the body by default
+    pub duplicate_of: Option<String>,
+
+    /// the query parameter to make every request unique when the target is behind a CDN. None with --no-cachebuster
+    pub cachebuster: Option<String>,
+
+    /// how long to wait for any response before reporting the requests in flight
+    pub stall_timeout: Option<Duration>,
+
+    /// whether to resend the hanging requests or abort the scan
+    pub stall_action: StallAction,
+
+    /// the file with delay, concurrency and rate values applied while the scan is running
+    pub control_file: Option<String>,
+
+    /// the existing parameter to inject parameters into the value of
+    pub inject_into: Option<String>,
+
+    /// send parameters as protobuf fields. The body is a hex encoded base message
+    pub protobuf: bool,
+
+    /// .proto file to map field names to numbers
+    pub proto: Option<String>,
+
+    /// wrap protobuf messages into gRPC-web frames
+    pub grpc_web: bool,
+
+    /// the max amount of concurrent requests per host across all workers
+    pub max_host_concurrency: Option<usize>,
+
+    /// share learned diffs, found parameters and rate limits between urls of the same host
+    pub shared_state: bool,
+
+    /// rhai script with on_request and on_response hooks
+    pub script: Option<String>,
+
+    /// the command that receives the raw request on stdin and returns headers to add
+    pub pre_request_cmd: Option<String>,
+
+    /// inject parameters as claims into the JWT from the request
+    pub jwt: bool,
+
+    /// key to re-sign tokens with. Tokens are unsigned otherwise
+    pub jwt_key: Option<String>,
+
+    /// WSDL file or url to check every SOAP operation from
+    pub wsdl: Option<String>,
+
+    /// the SOAP operation that is checked. Set per operation in case --wsdl is used
+    pub soap_operation: Option<String>,
+
+    /// regions of the page where reflections aren't counted
+    pub reflection_exclusions: Vec<Regex>,
+
+    /// status codes of the normal page
+    pub expect_codes: Vec<u16>,
+
+    /// User-Agent values to rotate. Empty to send the default one
+    pub user_agents: Vec<String>,
+
+    /// send random addresses within X-Forwarded-For and X-Real-IP
+    pub rotate_ip: Option<IpRange>,
+
+    /// local time window when requests are allowed
+    pub active_hours: Option<ActiveHours>,
+
+    /// the file with the finished url|method pairs
+    pub state_file: Option<String>,
+
+    /// the command that solves anti-bot challenges
+    pub challenge_cmd: Option<String>,
+
+    /// share cookies from Set-Cookie headers between clients
+    pub cookie_jar: bool,
+
+    /// ignore Set-Cookie headers after learning
+    pub freeze_cookies: bool,
+
     /// whether to encode the query like param1=value1&param2=value2 -> param1%3dvalue1%26param2%3dvalue2
     pub encode: bool,
 
@@ -45,6 +143,15 @@ pub struct Config {
     /// proxy server with schema or http:// by default.
     pub proxy: String,
 
+    /// host -> proxy url or "direct"
+    pub proxy_map: Vec<(String, String)>,
+
+    /// send the requests directly in case the proxy fails the preflight check
+    pub proxy_fallback: bool,
+
+    /// skip the preflight check of the proxies
+    pub no_proxy_check: bool,
+
     /// file to output
     pub output_file: String,
 
@@ -54,12 +161,27 @@ pub struct Config {
     /// do not print outputs of pairs url:method without found parameters
     pub remove_empty: bool,
 
+    /// report the same parameter for every url of the host
+    pub no_dedup: bool,
+
+    /// split body chunks between the query and the body in case the backend merges them
+    pub query_split: bool,
+
     /// output format for file & stdout outputs
     pub output_format: String,
 
+    /// resend, inspect and mark the findings from the output file after the scan
+    pub triage: bool,
+
     /// a directory for saving request & responses with found parameters
     pub save_responses: String,
 
+    /// content-addressed directory for saving request & responses that confirmed found parameters
+    pub snapshots: Option<String>,
+
+    /// a directory for saving results of every url|method pair separately: <dir>/<host>/<hash>/
+    pub output_dir: Option<String>,
+
     /// ignore some custom errors like when page's size > MAX_PAGE_SIZE
     pub force: bool,
 
@@ -86,6 +208,9 @@ pub struct Config {
     /// can be useful for checking whether the program parsed the input parameters successfully
     pub test: bool,
 
+    /// print changed lines for parameters that change the page
+    pub show_diffs: bool,
+
     /// 0 - print only critical errors and output
     /// 1 - print intermediate results and progress bar
     pub verbose: usize,
@@ -94,13 +219,62 @@ pub struct Config {
     /// doesn't include first two requests made for cookies and initial response
     pub learn_requests_count: usize,
 
+    /// the percentage of learn responses a diff should appear in to be ignored afterwards
+    pub persistent_diffs: usize,
+
+    /// the amount of throwaway requests before the initial one
+    pub warmup: usize,
+
     /// checks the same list of parameters with the found parameters until there are no new parameters to be found.
     /// conflicts with --verify for now. Will be updated in the future.
     pub recursion_depth: usize,
 
     /// amount of concurrent requests per url
+    /// the initial amount in case adaptive_concurrency is used
     pub concurrency: usize,
 
+    /// change the amount of concurrent requests depending on error rate and latency
+    pub adaptive_concurrency: bool,
+
+    /// the upper bound for adaptive concurrency
+    pub max_concurrency: usize,
+
+    /// pause the scan after this amount of consecutive connection errors (0 to disable)
+    pub circuit_breaker: usize,
+
+    /// the amount of allowed errors for learning, discovery, narrowing and verification phases
+    pub error_budget: [usize; 4],
+
+    /// exit with 1 in case parameters were found
+    pub fail_on_found: bool,
+
+    /// the json output of a previous run. Only new parameters are reported
+    pub baseline: Option<String>,
+
+    /// rerun the scan with this interval
+    pub watch: Option<Duration>,
+
+    /// the base url of the environment to compare found parameters with
+    pub diff_against: Option<String>,
+
+    /// profile names and their headers to run the scan with
+    pub auth_profiles: Vec<(String, Vec<(String, String)>)>,
+
+    /// the file with found parameters from every --watch run
+    pub watch_history: String,
+
+    /// the url to send changes to
+    pub webhook: Option<String>,
+
+    /// the order of found parameters: score or discovery
+    pub sort_by: String,
+
+    /// jsonl file with parameters found during previous scans per technology
+    pub learned_db: Option<String>,
+
+    /// detect the framework or CMS and check its specific parameters first
+    pub fingerprint: bool,
+
     /// amount of concurrent url checks
     pub workers: usize,
 
@@ -127,14 +301,102 @@ pub struct Config {
     /// true in case the injection points is within the header or the headers are injection point itself
     pub headers_discovery: bool,
 
+    /// trailers or chunk extensions of the chunked body as the injection place
+    pub chunked: Option<InjectionPlace>,
+
+    /// send the requests byte by byte instead of reqwest
+    pub raw_engine: bool,
+
+    /// send the requests over QUIC with --http 3, the reqwest clients are used in case the host doesn't accept it
+    pub http3: bool,
+
     pub follow_redirects: bool,
 
     pub disable_colors: bool,
 
     pub remove_banner: bool,
 
+    /// only findings are printed to stdout
+    pub quiet: bool,
+
     pub disable_trustdns: bool,
 
     /// check body of responses with binary content type
     pub check_binary: bool,
+
+    /// lines that differ only in digits aren't diffs
+    pub ignore_numbers: bool,
+
+    /// the lowest and the highest tls versions to negotiate
+    pub tls_min: Option<reqwest::tls::Version>,
+    pub tls_max: Option<reqwest::tls::Version>,
+
+    /// tls cipher suites to offer. Empty to offer the default ones
+    pub ciphers: Vec<String>,
+
+    /// make the full tls handshake for every connection
+    pub no_tls_tickets: bool,
+
+    /// allow tls 1.3 0-rtt early data
+    pub tls_early_data: bool,
+
+    /// server name to send within the tls handshake instead of the target's host
+    pub sni: Option<String>,
+
+    /// which certificate checks to skip
+    pub insecure: CertVerification,
+
+    /// connect only to this address instead of resolving the host
+    pub pin_ip: Option<IpAddr>,
+
+    /// the local address to send the requests from
+    pub source_ip: Option<IpAddr>,
+
+    /// the network interface to send the requests through
+    pub interface: Option<String>,
+
+    /// stick to one of the host's addresses and switch to the next one only when it starts erroring
+    pub ip_failover: bool,
+
+    /// send requests that can be parsed differently by servers on the way
+    pub allow_malformed: bool,
+
+    /// the min body size to send Expect: 100-continue with
+    pub expect_continue: Option<usize>,
+
+    /// the max amount of requests to check the parameters with per url|method pair
+    pub max_requests: Option<usize>,
+
+    /// check a random subset of parameters instead of the first ones when --max-requests is exceeded
+    pub sample: bool,
+
+    /// (k, n): check only the k-th of n parts of the wordlist
+    pub shard: Option<(usize, usize)>,
+
+    /// don't check parameter names found within responses
+    pub no_mining: bool,
+
+    /// the file with names that are added while the scan is running
+    pub extra_params_watch: Option<String>,
+
+    /// do not report parameters with a lower score
+    pub min_score: usize,
+
+    /// external url to check whether found parameters lead to open redirects
+    pub redirect_canary: Option<String>,
+
+    /// callback url to send to parameters that accept urls. {{id}} is replaced with a unique id
+    pub ssrf_canary: Option<String>,
+
+    /// url that returns requests received by the callback server
+    pub ssrf_poll: Option<String>,
+
+    /// how long to wait before requesting --ssrf-poll
+    pub ssrf_poll_delay: Duration,
+
+    /// fingerprint the backend's parser with edge-case encodings of found parameters
+    pub parser_probes: bool,
+
+    /// user supplied error messages to look for in pages with found parameters
+    pub error_signatures: Vec<Regex>,
 }
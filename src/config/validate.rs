@@ -0,0 +1,99 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, Write},
+};
+
+use super::structs::Config;
+
+/// methods that send parameters within the body unless --invert is used
+const BODY_METHODS: &[&str] = &["POST", "PUT", "PATCH", "DELETE"];
+
+/// checks the combination of the arguments before any request is sent.
+/// Every problem is reported at once so they can be fixed in one go, arguments without effect are only warned about
+pub fn validate(config: &Config) -> Result<(), Box<dyn Error>> {
+    if !config.quiet {
+        for warning in warnings(config) {
+            writeln!(io::stderr(), "[~] {}", warning).ok();
+        }
+    }
+
+    let problems = problems(config);
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    Err(format!(
+        "Found {} problem{} with the arguments:\n{}",
+        problems.len(),
+        if problems.len() == 1 { "" } else { "s" },
+        problems.iter().map(|x| format!("  - {}", x)).collect::<Vec<String>>().join("\n")
+    ))?
+}
+
+/// the list of problems with the config, empty if it's fine
+pub fn problems(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if !config.wordlist.is_empty() {
+        if let Err(err) = File::open(&config.wordlist) {
+            problems.push(format!(
+                "The wordlist {} can't be read ({}). Check the path passed to -w",
+                config.wordlist, err
+            ));
+        }
+    }
+
+    if let Some(template) = config.template.as_ref() {
+        if !template.contains("%k") {
+            problems.push(format!(
+                "--param-template '{}' doesn't contain %k, so the names wouldn't be sent. Example: --param-template 'user[%k]=%v'",
+                template
+            ));
+        }
+    }
+
+    // the injection point is chosen by the method, so %s at the other place would be sent as is
+    if !config.headers_discovery && config.chunked.is_none() && config.inject_into.is_none() {
+        let url_point = config.urls.iter().any(|x| x.contains("%s"));
+        let body_point = config.body.contains("%s");
+
+        if url_point && body_point {
+            problems.push("Both the url and the body contain %s. Only one injection point is supported per scan".to_string());
+        }
+
+        for method in config.methods.iter() {
+            let body_place = BODY_METHODS.contains(&method.as_str()) != config.invert;
+
+            if body_point && !url_point && !body_place {
+                problems.push(format!(
+                    "The body contains %s, but parameters are sent within the query with {}. Use --invert to send them within the body",
+                    method
+                ));
+            } else if url_point && !body_point && body_place {
+                problems.push(format!(
+                    "The url contains %s, but parameters are sent within the body with {}. Use --invert to send them within the query",
+                    method
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+/// arguments that don't break the scan but have no effect
+pub fn warnings(config: &Config) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if config.reflected_only && config.persistent_diffs > 0 {
+        warnings.push("--persistent-diffs has no effect with --reflected-only because the body isn't compared".to_string());
+    }
+
+    if config.reflected_only && config.ignore_numbers {
+        warnings.push("--ignore-numbers has no effect with --reflected-only because the body isn't compared".to_string());
+    }
+
+    warnings
+}
@@ -1,26 +1,91 @@
 //! Mostly taken from https://github.com/changeutils/diff-rs/blob/master/src/lib.rs
 
-use std::{collections::VecDeque, io};
+use std::{collections::VecDeque, fmt, io};
+
+/// changed part of the text as line ranges.
+/// Rendered as -old_start,old_len +new_start,new_len
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+}
+
+impl fmt::Display for Hunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "-{},{} +{},{}", self.old_start, self.old_len, self.new_start, self.new_len)
+    }
+}
 
 pub fn diff(text1: &str, text2: &str) -> io::Result<Vec<String>> {
+    Ok(diff_lines(
+        &text1.lines().collect::<Vec<&str>>(),
+        &text2.lines().collect::<Vec<&str>>(),
+    )?
+    .iter()
+    .map(|x| x.to_string())
+    .collect())
+}
+
+/// same as diff() but for already splitted lines so they can borrow from different buffers
+pub fn diff_lines(lines1: &[&str], lines2: &[&str]) -> io::Result<Vec<Hunk>> {
     let mut processor = Processor::new();
     {
         let mut replace = diffs::Replace::new(&mut processor);
-        diffs::myers::diff(
-            &mut replace,
-            &text1.lines().collect::<Vec<&str>>(),
-            &text2.lines().collect::<Vec<&str>>(),
-        )?;
+        diffs::myers::diff(&mut replace, lines1, lines2)?;
     }
     Ok(processor.result())
 }
 
+/// returns removed (starting with -) and added (starting with +) lines
+pub fn changed_lines(text1: &str, text2: &str) -> io::Result<Vec<String>> {
+    let old = text1.lines().collect::<Vec<&str>>();
+    let new = text2.lines().collect::<Vec<&str>>();
+
+    let mut collector = LinesCollector {
+        old: &old,
+        new: &new,
+        lines: Vec::new(),
+    };
+
+    diffs::myers::diff(&mut collector, &old, &new)?;
+
+    Ok(collector.lines)
+}
+
+struct LinesCollector<'a> {
+    old: &'a [&'a str],
+    new: &'a [&'a str],
+    lines: Vec<String>,
+}
+
+impl diffs::Diff for LinesCollector<'_> {
+    type Error = io::Error;
+
+    fn delete(&mut self, old: usize, len: usize) -> Result<(), Self::Error> {
+        for line in &self.old[old..old + len] {
+            self.lines.push(format!("-{}", line));
+        }
+
+        Ok(())
+    }
+
+    fn insert(&mut self, _old: usize, new: usize, new_len: usize) -> Result<(), Self::Error> {
+        for line in &self.new[new..new + new_len] {
+            self.lines.push(format!("+{}", line));
+        }
+
+        Ok(())
+    }
+}
+
 struct Processor {
     inserted: usize,
     removed: usize,
 
     context: Context,
-    result: Vec<String>,
+    result: Vec<Hunk>,
 }
 
 impl Processor {
@@ -34,14 +99,14 @@ impl Processor {
         }
     }
 
-    pub fn result(self) -> Vec<String> {
+    pub fn result(self) -> Vec<Hunk> {
         self.result
     }
 }
 
 struct Context {
     pub start: Option<usize>,
-    pub data: VecDeque<String>,
+    pub data: VecDeque<Hunk>,
     pub changed: bool,
 
     pub counter: usize,
@@ -64,7 +129,7 @@ impl Context {
         }
     }
 
-    pub fn to_vec(&self, removed: usize, inserted: usize) -> Vec<String> {
+    pub fn to_vec(&self, removed: usize, inserted: usize) -> Vec<Hunk> {
         let mut start = if let Some(start) = self.start {
             start
         } else {
@@ -75,15 +140,14 @@ impl Context {
         }
         let mut data = Vec::with_capacity(self.data.len() + 1);
         if self.changed {
-            data.push(format!(
-                "-{},{} +{},{}",
-                start,
-                self.equaled + self.removed,
-                start + inserted - removed,
-                self.equaled + self.inserted,
-            ));
+            data.push(Hunk {
+                old_start: start,
+                old_len: self.equaled + self.removed,
+                new_start: start + inserted - removed,
+                new_len: self.equaled + self.inserted,
+            });
             for s in self.data.iter() {
-                data.push(s.to_owned());
+                data.push(*s);
             }
         }
         data
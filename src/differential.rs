@@ -0,0 +1,102 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+};
+
+use crate::watch::{Failed, Findings};
+
+/// replaces the scheme, host and port of the url with the ones from base.
+/// The path of base is prepended to the path of the url:
+/// (https://example.com/api?a=b, https://staging.example.com/v2) -> https://staging.example.com/v2/api?a=b
+pub fn mirror_url(url: &str, base: &str) -> Result<String, Box<dyn Error>> {
+    let path = match url.split_once("://") {
+        Some((_, rest)) => &rest[rest.find(|x| x == '/' || x == '?').unwrap_or(rest.len())..],
+        None => Err(format!("Unable to parse the url: {}", url))?,
+    };
+
+    if !base.contains("://") {
+        Err("--diff-against should be a base url like https://staging.example.com")?
+    }
+
+    Ok(format!("{}{}", base.trim_end_matches('/'), path))
+}
+
+/// returns the lines like "< GET https://example.com/ debug" for parameters found only within the primary environment
+/// and "> GET https://staging.example.com/ debug" for the ones found only within the other one.
+/// Urls that failed within either environment aren't compared and are listed as "! GET https://example.com/ ..."
+pub fn differences(primary: (&Findings, &Failed), other: (&Findings, &Failed), base: &str) -> Vec<String> {
+    let (primary, primary_failed) = primary;
+    let (other, other_failed) = other;
+
+    let mut differences = Vec::new();
+    let empty = Vec::new();
+
+    // the pairs of the other environment that have a counterpart within the primary one
+    let mut compared = BTreeSet::new();
+
+    for (method, url) in primary.keys().chain(primary_failed.iter()) {
+        let mirrored = match mirror_url(url, base) {
+            Ok(val) => val,
+            Err(_) => continue,
+        };
+
+        let key = (method.clone(), mirrored.clone());
+        if !compared.insert(key.clone()) {
+            continue;
+        }
+
+        if primary_failed.contains(&(method.clone(), url.clone())) {
+            differences.push(format!("! {} {} the scan failed within the primary environment", method, url));
+            continue;
+        }
+
+        if other_failed.contains(&key) {
+            differences.push(format!("! {} {} the scan failed within the other environment", method, mirrored));
+            continue;
+        }
+
+        let names = primary.get(&(method.clone(), url.clone())).unwrap_or(&empty);
+        let other_names = other.get(&key).unwrap_or(&empty);
+
+        for name in names.iter().filter(|x| !other_names.contains(x)) {
+            differences.push(format!("< {} {} {}", method, url, name));
+        }
+
+        for name in other_names.iter().filter(|x| !names.contains(x)) {
+            differences.push(format!("> {} {} {}", method, mirrored, name));
+        }
+    }
+
+    // e.g. the url was redirected or normalized differently within the other environment
+    for (key, names) in other.iter().filter(|(x, _)| !compared.contains(*x)) {
+        for name in names {
+            differences.push(format!("> {} {} {}", key.0, key.1, name));
+        }
+    }
+
+    for key in other_failed.iter().filter(|x| !compared.contains(*x)) {
+        differences.push(format!("! {} {} the scan failed within the other environment", key.0, key.1));
+    }
+
+    differences
+}
+
+/// returns the lines like "GET https://example.com/ debug: admin, user" for parameters
+/// that weren't found with every profile
+pub fn profile_differences(profiles: &[(String, Findings)]) -> Vec<String> {
+    let mut visible: BTreeMap<(&str, &str, &str), Vec<&str>> = BTreeMap::new();
+
+    for (profile, findings) in profiles {
+        for ((method, url), names) in findings {
+            for name in names {
+                visible.entry((method.as_str(), url.as_str(), name.as_str())).or_default().push(profile.as_str());
+            }
+        }
+    }
+
+    visible
+        .into_iter()
+        .filter(|(_, x)| x.len() < profiles.len())
+        .map(|((method, url, name), x)| format!("{} {} {}: {}", method, url, name, x.join(", ")))
+        .collect()
+}
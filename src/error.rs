@@ -0,0 +1,123 @@
+use std::{error::Error, fmt, io};
+
+/// exit codes of the cli
+pub const EXIT_OK: i32 = 0;
+/// parameters were found and --fail-on-found is used, the self-test failed or verify-report found gone parameters
+pub const EXIT_FOUND: i32 = 1;
+pub const EXIT_UNSTABLE: i32 = 2;
+/// the target wasn't checked because of network errors, expired session, anti-bot challenge or too many errors
+pub const EXIT_NETWORK: i32 = 3;
+pub const EXIT_CONFIG: i32 = 4;
+/// unexpected errors that are neither about the target nor about the arguments, e.g. failed writes
+pub const EXIT_OTHER: i32 = 5;
+
+/// errors that can stop checking a url
+/// allows to distinguish the reason of the failure instead of parsing error messages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum X8Error {
+    /// unable to reach the target
+    Network(String),
+
+    /// the page changes even without parameters
+    UnstableTarget(String),
+
+    /// there's nothing to check
+    NoParameters,
+
+    /// incorrect arguments or files
+    Config(String),
+
+    /// the session seems to be expired during the scan
+    AuthExpired(String),
+
+    /// too many errors within one of the phases
+    ErrorBudget(String),
+
+    /// the target responded with an anti-bot challenge
+    Challenge(String),
+
+    /// the server closed the keep-alive connection. The request can be resent right away
+    ConnectionClosed(String),
+
+    Other(String),
+}
+
+impl fmt::Display for X8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            X8Error::Network(msg) => write!(f, "Network error: {}", msg),
+            X8Error::UnstableTarget(msg) => write!(f, "{}", msg),
+            X8Error::NoParameters => write!(f, "No parameters to check"),
+            X8Error::Config(msg) => write!(f, "{}", msg),
+            X8Error::AuthExpired(msg) => write!(f, "The session seems to be expired ({})", msg),
+            X8Error::ErrorBudget(msg) => write!(f, "{}", msg),
+            X8Error::Challenge(msg) => write!(f, "Anti-bot challenge: {}", msg),
+            X8Error::ConnectionClosed(msg) => write!(f, "The connection was closed: {}", msg),
+            X8Error::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl Error for X8Error {}
+
+impl X8Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            X8Error::NoParameters => EXIT_OK,
+            X8Error::UnstableTarget(_) => EXIT_UNSTABLE,
+            X8Error::Config(_) => EXIT_CONFIG,
+            X8Error::Network(_)
+            | X8Error::AuthExpired(_)
+            | X8Error::ErrorBudget(_)
+            | X8Error::Challenge(_)
+            | X8Error::ConnectionClosed(_) => EXIT_NETWORK,
+            X8Error::Other(_) => EXIT_OTHER,
+        }
+    }
+}
+
+/// the exit code for an error that may be X8Error
+pub fn exit_code_of(err: &(dyn Error + 'static), default: i32) -> i32 {
+    err.downcast_ref::<X8Error>()
+        .map_or(default, |x| x.exit_code())
+}
+
+impl From<reqwest::Error> for X8Error {
+    fn from(err: reqwest::Error) -> Self {
+        X8Error::Network(err.to_string())
+    }
+}
+
+impl From<io::Error> for X8Error {
+    fn from(err: io::Error) -> Self {
+        X8Error::Other(err.to_string())
+    }
+}
+
+impl From<url::ParseError> for X8Error {
+    fn from(err: url::ParseError) -> Self {
+        X8Error::Config(err.to_string())
+    }
+}
+
+/// for the functions that are still returning Box<dyn Error>
+impl From<Box<dyn Error>> for X8Error {
+    fn from(err: Box<dyn Error>) -> Self {
+        match err.downcast::<X8Error>() {
+            Ok(err) => *err,
+            Err(err) => X8Error::Other(err.to_string()),
+        }
+    }
+}
+
+impl From<String> for X8Error {
+    fn from(msg: String) -> Self {
+        X8Error::Other(msg)
+    }
+}
+
+impl From<&str> for X8Error {
+    fn from(msg: &str) -> Self {
+        X8Error::Other(msg.to_string())
+    }
+}
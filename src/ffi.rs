@@ -0,0 +1,57 @@
+use std::{
+    error::Error,
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use crate::job::Job;
+
+/// runs the scan within its own runtime and serializes the findings.
+/// Errors that stop the whole scan are returned as {"error": "..."}
+fn scan_json(config_json: &str) -> String {
+    let result = serde_json::from_str::<Job>(config_json)
+        .map_err(|err| -> Box<dyn Error> { format!("Unable to parse the config: {}", err).into() })
+        .and_then(|job| job.run_blocking());
+
+    match result {
+        Ok(result) => serde_json::to_string(&result),
+        Err(err) => serde_json::to_string(&serde_json::json!({ "error": err.to_string() })),
+    }
+    .unwrap_or_default()
+}
+
+/// scans the targets from the json config like {"args": ["-u", "https://example.com/", "-w", "params.txt"]}
+/// and returns the findings as a json string. The returned string should be freed with x8_free().
+/// Blocks the calling thread until the scan is finished
+///
+/// # Safety
+///
+/// config_json should be a valid nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn x8_scan(config_json: *const c_char) -> *mut c_char {
+    if config_json.is_null() {
+        return ptr::null_mut();
+    }
+
+    let config_json = CStr::from_ptr(config_json).to_string_lossy().into_owned();
+
+    // panics can't unwind into the caller's code
+    let findings_json = panic::catch_unwind(AssertUnwindSafe(|| scan_json(&config_json)))
+        .unwrap_or_else(|_| r#"{"error":"x8 panicked during the scan"}"#.to_string());
+
+    CString::new(findings_json).map_or(ptr::null_mut(), |x| x.into_raw())
+}
+
+/// frees the string returned by x8_scan()
+///
+/// # Safety
+///
+/// findings_json should be returned by x8_scan() and not freed before
+#[no_mangle]
+pub unsafe extern "C" fn x8_free(findings_json: *mut c_char) {
+    if !findings_json.is_null() {
+        drop(CString::from_raw(findings_json));
+    }
+}
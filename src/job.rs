@@ -0,0 +1,319 @@
+use std::{
+    error::Error,
+    fs,
+    io::{self, Write},
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tokio::sync::oneshot;
+
+use crate::{
+    config::{args::try_config_from, validate::validate},
+    error::{exit_code_of, EXIT_CONFIG},
+    network::proxy::check_proxies,
+    runner::output::RunnerOutput,
+    scan::scan,
+    utils::{read_lines, sort_by_weight},
+    wordlist::{normalize, shard},
+};
+
+/// options that jobs from other hosts (the service and worker modes) can use.
+/// The rest run commands, read or write files on the host, or never finish.
+/// Every option of REMOTE_SHORT_OPTIONS is listed here by its long name as well
+const REMOTE_OPTIONS: &[&str] = &[
+    "url", "var", "lowercase", "param-template", "joiner", "duplicate-names", "duplicate-of", "cachebuster", "no-cachebuster",
+    "query-separator", "body", "data-type", "proxy", "proxy-map", "proxy-fallback", "no-proxy-check", "burp-proxy",
+    "concurrency", "delay", "output-format", "min-score", "query-split", "no-dedup", "method", "invert", "headers", "engine",
+    "trailers", "chunk-extensions", "force", "disable-custom-parameters", "disable-colors", "force-enable-colors",
+    "disable-trustdns", "disable-progress-bar", "progress-bar-len", "custom-parameters", "follow-redirects", "encode",
+    "strict", "verbose", "show-diffs", "learn-requests", "stall-timeout", "stall-action", "warmup", "persistent-diffs",
+    "recursion-depth", "max", "timeout", "adaptive-concurrency", "max-concurrency", "circuit-breaker", "error-budget",
+    "sort-by", "protobuf", "grpc-web", "max-host-concurrency", "shared-state", "jwt", "jwt-key", "random-agent",
+    "rotate-ip", "cookie-jar", "freeze-cookies", "expect-code", "reflection-exclude", "inject-into", "fingerprint",
+    "workers", "verify", "reflected-only", "one-worker-per-host", "mimic-browser", "redirect-canary", "ssrf-canary",
+    "ssrf-poll", "ssrf-poll-delay", "parser-probes", "error-signatures", "http", "check-binary", "ignore-numbers",
+    "cookies", "remove-banner", "quiet", "tls-min", "tls-max", "ciphers", "no-tls-tickets", "tls-early-data", "sni",
+    "insecure", "pin-ip", "source-ip", "interface", "ip-failover", "allow-malformed", "expect-continue",
+    "max-requests", "sample", "shard", "no-mining",
+];
+
+/// short names of the options that remote jobs can use and whether they take values
+const REMOTE_SHORT_OPTIONS: &[(char, bool)] = &[
+    ('u', true), ('P', true), ('j', true), ('b', true), ('t', true), ('x', true), ('B', false), ('d', true), ('O', true),
+    ('X', true), ('H', true), ('L', false), ('v', true), ('m', true), ('c', true), ('W', true), ('q', false),
+];
+
+/// a scan submitted by other programs, e.g. through the C ABI, the service mode or --job-file.
+/// Every field is converted to the command line arguments
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Job {
+    pub url: Option<String>,
+    pub method: Option<String>,
+
+    /// "Name: value" pairs
+    pub headers: Map<String, Value>,
+
+    pub body: Option<String>,
+
+    /// the path to the wordlist
+    pub wordlist: Option<String>,
+
+    /// other options by their names, e.g. {"max": 50, "verify": true, "H": ["X-Custom: 1"]}
+    pub options: Map<String, Value>,
+
+    /// raw command line arguments without the program name, e.g. ["-u", "https://example.com/", "-w", "params.txt"]
+    pub args: Vec<String>,
+
+    /// parameters to check instead of the wordlist
+    pub params: Vec<String>,
+
+    /// the file to write the result to. Only used with --job-file, the result is printed to stdout otherwise
+    pub result_file: Option<String>,
+
+    /// copied to the result message within the worker mode
+    pub id: Option<Value>,
+}
+
+/// an url|method pair that wasn't checked
+#[derive(Debug, Serialize)]
+pub struct JobError {
+    pub method: String,
+    pub url: String,
+    pub error: String,
+}
+
+impl JobError {
+    pub fn new(method: &str, url: &str, err: &dyn Error) -> Self {
+        Self {
+            method: method.to_owned(),
+            url: url.to_owned(),
+            error: err.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct JobResult {
+    pub outputs: Vec<RunnerOutput>,
+    pub errors: Vec<JobError>,
+
+    /// the same exit code as the command line would return
+    #[serde(skip)]
+    pub exit_code: i32,
+}
+
+impl Job {
+    /// the command line including the program name
+    pub fn cmd_args(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut cmd_args = vec!["x8".to_string(), "--disable-progress-bar".to_string()];
+
+        let fields = [("url", &self.url), ("method", &self.method), ("body", &self.body), ("wordlist", &self.wordlist)];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                cmd_args.extend([format!("--{}", name), value.to_owned()]);
+            }
+        }
+
+        if !self.headers.is_empty() {
+            cmd_args.push("-H".to_string());
+
+            for (k, v) in &self.headers {
+                cmd_args.push(format!("{}: {}", k, option_values(k, v)?.join(", ")));
+            }
+        }
+
+        for (name, value) in &self.options {
+            let name = name.trim_start_matches('-');
+            let values = option_values(name, value)?;
+
+            // false turns a flag off, the same as not mentioning it
+            if value == &Value::Bool(false) || value.is_null() {
+                continue;
+            }
+
+            cmd_args.push(if name.len() == 1 {
+                format!("-{}", name)
+            } else {
+                format!("--{}", name)
+            });
+
+            if value != &Value::Bool(true) {
+                cmd_args.extend(values);
+            }
+        }
+
+        cmd_args.extend(self.args.iter().cloned());
+
+        Ok(cmd_args)
+    }
+
+    /// rejects the options that jobs from other hosts can't use, see REMOTE_OPTIONS.
+    /// Urls should be urls because --url also accepts files
+    pub fn check_remote(&self) -> Result<(), Box<dyn Error>> {
+        let mut is_url = false;
+
+        for arg in self.cmd_args()?.iter().skip(1) {
+            let (name, value) = if let Some(arg) = arg.strip_prefix("--") {
+                let (name, value) = match arg.split_once('=') {
+                    Some((name, value)) => (name, Some(value)),
+                    None => (arg, None),
+                };
+
+                if !REMOTE_OPTIONS.contains(&name) {
+                    Err(format!("--{} can't be used within remote jobs", name))?
+                }
+
+                (name.to_string(), value)
+            } else if arg.len() > 1 && arg.starts_with('-') && !arg[1..].starts_with(|x: char| x.is_ascii_digit()) {
+                let short = arg[1..].chars().next().unwrap();
+                let rest = arg[1 + short.len_utf8()..].trim_start_matches('=');
+
+                match REMOTE_SHORT_OPTIONS.iter().find(|(x, _)| *x == short) {
+                    Some((_, false)) if !rest.is_empty() => {
+                        Err(format!("Pass {} separately from other short options within remote jobs", arg))?
+                    }
+                    Some(_) => (),
+                    None => Err(format!("-{} can't be used within remote jobs", short))?,
+                }
+
+                (short.to_string(), Some(rest).filter(|x| !x.is_empty()))
+            } else {
+                // a value of the previous option
+                if is_url && !arg.contains("://") {
+                    Err(format!("{} isn't an url. Remote jobs can't read urls from files", arg))?
+                }
+                continue;
+            };
+
+            is_url = name == "url" || name == "u";
+
+            if let Some(value) = value {
+                if is_url && !value.contains("://") {
+                    Err(format!("{} isn't an url. Remote jobs can't read urls from files", value))?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// checks every url|method pair the same way as the command line does.
+    /// Errors of separate targets are collected within the result
+    pub async fn run(self) -> Result<JobResult, Box<dyn Error>> {
+        let mut config = try_config_from(self.cmd_args()?)?;
+        validate(&config)?;
+        check_proxies(&mut config).await?;
+
+        let mut params = self.params;
+        if params.is_empty() && !config.wordlist.is_empty() {
+            params = read_lines(&config.wordlist)?.flatten().collect();
+        }
+        let (mut params, _) = normalize(sort_by_weight(params), config.lowercase, config.headers_discovery);
+        if let Some((k, n)) = config.shard {
+            params = shard(params, k, n);
+        }
+
+        let result = Mutex::new(JobResult::default());
+        let exit_code = scan(&config, &params, None, None, None, Some(&result)).await?;
+
+        let mut result = result.into_inner();
+        result.exit_code = exit_code;
+
+        Ok(result)
+    }
+
+    /// runs the job within its own runtime, so it can be called outside of async code
+    /// or on a separate thread without requiring the scan to be Send
+    pub fn run_blocking(self) -> Result<JobResult, Box<dyn Error>> {
+        tokio::runtime::Runtime::new()?.block_on(self.run())
+    }
+
+    /// runs the job on its own thread and returns the serialized result.
+    /// Errors are returned as strings because the original ones can't be sent between threads
+    pub async fn run_on_thread(self) -> Result<Value, String> {
+        let (tx, rx) = oneshot::channel();
+
+        std::thread::spawn(move || {
+            let result = self
+                .run_blocking()
+                .and_then(|x| serde_json::to_value(&x).map_err(|err| err.into()))
+                .map_err(|err| err.to_string());
+
+            tx.send(result).ok();
+        });
+
+        // the thread panicked in case the sender is dropped
+        rx.await.unwrap_or_else(|_| Err("The scan panicked".to_string()))
+    }
+}
+
+/// strings, numbers and arrays of them as the command line values
+fn option_values(name: &str, value: &Value) -> Result<Vec<String>, Box<dyn Error>> {
+    Ok(match value {
+        Value::String(val) => vec![val.to_owned()],
+        Value::Number(val) => vec![val.to_string()],
+        Value::Bool(_) | Value::Null => Vec::new(),
+        Value::Array(values) => values
+            .iter()
+            .map(|x| match x {
+                Value::String(val) => Ok(val.to_owned()),
+                Value::Number(val) => Ok(val.to_string()),
+                _ => Err(format!("{} should contain only strings and numbers", name)),
+            })
+            .collect::<Result<Vec<String>, String>>()?,
+        Value::Object(_) => Err(format!("{} can't be an object", name))?,
+    })
+}
+
+/// runs the job from the --job-file file and writes the result as one json document.
+/// args are the whole command line, the rest of the arguments are appended to the job's ones.
+/// Returns the exit code
+pub async fn run_job_file(mut args: Vec<String>) -> Result<i32, Box<dyn Error>> {
+    let pos = args
+        .iter()
+        .position(|x| x == "--job-file" || x.starts_with("--job-file="))
+        .unwrap_or(0);
+
+    let path = match args.get(pos).and_then(|x| x.strip_prefix("--job-file=")) {
+        Some(path) if pos != 0 && !path.is_empty() => {
+            let path = path.to_string();
+            args.remove(pos);
+            path
+        }
+        None if pos != 0 && pos + 1 < args.len() => args.drain(pos..pos + 2).nth(1).unwrap(),
+        _ => Err("--job-file requires the path to the job")?,
+    };
+
+    let mut job: Job = serde_json::from_str(&fs::read_to_string(&path)?)
+        .map_err(|err| format!("Unable to parse the job: {}", err))?;
+    job.args.extend(args.into_iter().skip(1));
+
+    let result_file = job.result_file.clone();
+
+    // stdout contains only the result
+    let verbose = job.args.iter().any(|x| x == "-v" || x == "--verbose")
+        || job.options.contains_key("v")
+        || job.options.contains_key("verbose");
+
+    if result_file.is_none() && !verbose {
+        job.args.extend(["-v".to_string(), "0".to_string()]);
+    }
+
+    // the errors of the targets are mapped within the scan, the same as for the command line
+    let (result, exit_code) = match job.run().await {
+        Ok(result) => (serde_json::to_value(&result)?, result.exit_code),
+        Err(err) => (json!({ "error": err.to_string() }), exit_code_of(err.as_ref(), EXIT_CONFIG)),
+    };
+
+    let result = serde_json::to_string_pretty(&result)?;
+
+    match result_file {
+        Some(path) => fs::write(path, result)?,
+        None => writeln!(io::stdout(), "{}", result)?,
+    }
+
+    Ok(exit_code)
+}
@@ -1,8 +1,23 @@
+pub mod cli;
 pub mod config;
 pub mod diff;
+pub mod differential;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod job;
+pub mod merge;
 pub mod network;
+pub mod retest;
 pub mod runner;
+pub mod scan;
+pub mod selftest;
+pub mod serve;
+pub mod triage;
 pub mod utils;
+pub mod watch;
+pub mod wordlist;
+pub mod worker;
 
 const RANDOM_CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
 
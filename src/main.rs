@@ -1,31 +1,36 @@
 extern crate x8;
 use std::{
     error::Error,
-    sync::Arc,
     io::{self, Write},
-    iter::FromIterator,
 };
 
 use parking_lot::Mutex;
-use tokio::{fs::{self, OpenOptions}, io::AsyncWriteExt};
+use tokio::fs;
 use atty::Stream;
-use futures::StreamExt;
-use indicatif::ProgressBar;
-use colored::Colorize;
 
 use x8::{
-    config::args::get_config,
-    config::{structs::Config, utils::write_banner_config},
+    cli::{completions, examples},
+    config::args::get_config_from,
+    config::{structs::Config, utils::write_banner_config, validate::validate},
+    differential::{differences, mirror_url, profile_differences},
+    error::{exit_code_of, X8Error, EXIT_CONFIG, EXIT_FOUND, EXIT_OK},
+    job::run_job_file,
+    merge::merge_reports,
     network::{
+        proxy::check_proxies,
         request::{Request, RequestDefaults},
-        utils::Headers,
+        soap::{parse_wsdl, read_wsdl},
     },
-    runner::{
-        output::{ParseOutputs, RunnerOutput},
-        runner::Runner,
-        utils::{Parameters, ReasonKind},
-    },
-    utils::{self, init_progress, read_lines, read_stdin_lines},
+    runner::output::Baseline,
+    retest::verify_report,
+    scan::scan,
+    selftest::self_test,
+    serve::serve,
+    triage::{triage, triage_report},
+    utils::{self, read_lines, read_stdin_lines, sort_by_weight},
+    watch::{append_history, changes, notify, read_history, Failed, Findings},
+    wordlist::{count_lines, normalize, prepare_wordlist, shard, Dropped},
+    worker::worker,
 };
 
 #[cfg(windows)]
@@ -33,10 +38,12 @@ use x8::{
 async fn main() {
     colored::control::set_virtual_terminal(true).unwrap();
     std::process::exit(match init().await {
-        Ok(_) => 0,
+        Ok(code) => code,
         Err(err) => {
+            // errors before the scan are usually caused by wrong arguments or files
+            let code = exit_code_of(err.as_ref(), EXIT_CONFIG);
             utils::error(err, None, None, None);
-            1
+            code
         }
     });
 }
@@ -45,20 +52,49 @@ async fn main() {
 #[tokio::main]
 async fn main() {
     std::process::exit(match init().await {
-        Ok(_) => 0,
+        Ok(code) => code,
         Err(err) => {
+            // errors before the scan are usually caused by wrong arguments or files
+            let code = exit_code_of(err.as_ref(), EXIT_CONFIG);
             utils::error(err, None, None, None);
-            1
+            code
         }
     });
 }
 
 /// initializes runners and passes them to run()
 /// also manages outputs. Probably better to rename?
-async fn init() -> Result<(), Box<dyn Error>> {
+/// returns the exit code
+async fn init() -> Result<i32, Box<dyn Error>> {
     env_logger::init();
 
-    let config: Config = get_config()?;
+    let cmd_args: Vec<String> = std::env::args().collect();
+    match cmd_args.get(1).map(|x| x.as_str()) {
+        Some("self-test") => return self_test(cmd_args).await,
+        Some("serve") => return serve(cmd_args).await,
+        Some("worker") => return worker(cmd_args).await,
+        Some("merge") => return merge_reports(cmd_args),
+        Some("verify-report") => return verify_report(cmd_args).await,
+        Some("triage") => return triage(cmd_args).await,
+        Some("completions") => return completions(cmd_args),
+        Some("examples") => return examples(cmd_args),
+        _ => (),
+    }
+
+    if cmd_args.iter().any(|x| x == "--job-file" || x.starts_with("--job-file=")) {
+        return run_job_file(cmd_args).await;
+    }
+
+    let mut config: Config = get_config_from(cmd_args).map_err(|err| X8Error::Config(err.to_string()))?;
+    validate(&config).map_err(|err| X8Error::Config(err.to_string()))?;
+
+    // a proxy that rejects CONNECT would otherwise fail every request of the scan
+    check_proxies(&mut config).await?;
+
+    // the output is meant to be parsed by other tools
+    if config.quiet {
+        colored::control::set_override(false);
+    }
 
     //if --test option is used - print request/response and quit
     if config.test {
@@ -85,269 +121,240 @@ async fn init() -> Result<(), Box<dyn Error>> {
             .print_all()
         )
         .ok();
-        return Ok(());
+        return Ok(EXIT_OK);
     }
 
     if !config.save_responses.is_empty() {
         fs::create_dir_all(&config.save_responses).await?;
     }
 
-    let mut params: Vec<String> = Vec::new();
-
-    if !config.wordlist.is_empty() {
-        // read parameters from a file
-        for line in read_lines(&config.wordlist)?.flatten() {
-            params.push(line);
-        }
-    // just accept piped stdin
-    } else if !atty::is(Stream::Stdin) {
-        // read parameters from stdin
-        params = read_stdin_lines();
-    }
-
-    if !config.remove_banner {
-        write_banner_config(&config, &params);
+    if let Some(dir) = config.output_dir.as_ref() {
+        fs::create_dir_all(dir).await?;
     }
 
-    // such headers usually cause server to timeout
-    // especially when http/2 is used
-    // probably better to add a flag for keeping such parameters?
-    if config.headers_discovery {
-        params.retain(|x| "content-length" != x.to_lowercase() && "host" != x.to_lowercase());
+    if let Some(dir) = config.snapshots.as_ref() {
+        fs::create_dir_all(dir).await?;
     }
 
-    // -W 0 is a special option to run everything in parallel
-    let workers = if config.workers == 0 {
-        config.urls.len()*config.methods.len()
-    } else {
-        config.workers
+    let baseline = match config.baseline.as_ref() {
+        Some(path) => Some(Baseline::read(path).map_err(|err| X8Error::Config(err.to_string()))?),
+        None => None,
     };
 
-    // open output file
-    let mut output_file = if !config.output_file.is_empty() {
-        let mut file = OpenOptions::new();
+    let mut params: Vec<String> = Vec::new();
 
-        let file = if config.append {
-            file.write(true).append(true)
-        } else {
-            file.write(true).truncate(true)
-        };
+    // with --wordlist-parts every runner reads the prepared wordlist part by part
+    let _prepared_wordlist = if config.wordlist_parts.is_some() {
+        if !config.remove_banner {
+            write_banner_config(&config, count_lines(&config.wordlist)?);
+        }
 
-        let file = match file.open(&config.output_file).await {
-            Ok(file) => file,
-            Err(_) => fs::File::create(&config.output_file).await?,
-        };
+        let (prepared, dropped) = prepare_wordlist(&config)?;
+        report_dropped(&config, &dropped);
+        config.wordlist = prepared.path.to_string_lossy().to_string();
 
-        Some(file)
+        Some(prepared)
     } else {
+        if !config.wordlist.is_empty() {
+            // read parameters from a file
+            for line in read_lines(&config.wordlist)?.flatten() {
+                params.push(line);
+            }
+        // just accept piped stdin
+        } else if !atty::is(Stream::Stdin) {
+            // read parameters from stdin
+            params = read_stdin_lines();
+        }
+
+        if !config.remove_banner {
+            write_banner_config(&config, params.len());
+        }
+
+        let (prepared, dropped) = prepare_params(&config, params);
+        report_dropped(&config, &dropped);
+        params = prepared;
+
         None
     };
 
-    let shared_output_file = Arc::new(Mutex::new(&mut output_file));
-
-    let runner_outputs =
-        futures::stream::iter(init_progress(&config).iter().enumerate().skip(1).map(
-            |(id, (progress_bar, url_set))| {
-
-                let shared_output_file = Arc::clone(&shared_output_file);
-
-                // each url set should have each own list of parameters
-                let params = params.clone();
-
-                // each url set should have it's own immutable pointer to config
-                let config = &config;
-
-                //let output_file = output_file.as_ref().unwrap().try_clone();
-
-                async move {
-                    let mut runner_outputs = Vec::new();
-
-                    // for now url set are used only in case --one-worker-per-host option is provided
-                    // otherwise it's just url sets of 1 url
-                    for url in url_set {
-                        for method in &config.methods.clone() {
-                            // each method should have each own list of parameters (we're changing this list through the run)
-                            let mut params = params.clone();
-
-                            let mut request_defaults = match RequestDefaults::from_config(
-                                config,
-                                method.as_str(),
-                                url.as_str(),
-                            ) {
-                                Ok(val) => val,
-                                Err(err) => {
-                                    utils::error(err, Some(url), Some(progress_bar), Some(config));
-                                    continue;
-                                }
-                            };
-
-                            // get cookies
-                            if let Err(err) =
-                                Request::new(&request_defaults, Vec::new()).send().await
-                            {
-                                utils::error(err, Some(url), Some(progress_bar), Some(config));
-                                continue;
-                            };
-
-                            match run(
-                                config,
-                                &mut request_defaults,
-                                &mut params,
-                                &progress_bar,
-                                id,
-                            )
-                            .await
-                            {
-                                Ok(val) => {
-                                    // if output format is not json we can print output and write to file in real time
-                                    if config.output_format != "json" {
-                                        let mut output_file = shared_output_file.lock();
-                                        let output = val.parse(config);
-
-                                        if output_file.is_some() && !(config.remove_empty && val.found_params.is_empty()) {
-
-                                            match output_file.as_mut().unwrap().write_all(
-                                                &strip_ansi_escapes::strip(&(output.normal().clear().to_string()+"\n").as_bytes()).unwrap()
-                                            ).await {
-                                                Ok(()) => output_file.as_mut().unwrap().flush().await.unwrap(),
-                                                Err(err) => utils::error(err, Some(url), Some(progress_bar), Some(config)),
-                                            };
-                                        }
-
-                                        let msg = if config.verbose > 0 {
-                                            format!("\n{}\n\n", output)
-                                        } else {
-                                            format!("{}", output)
-                                        };
-
-                                        if config.disable_progress_bar {
-                                            writeln!(io::stdout(), "{}", msg).ok();
-                                        } else {
-                                            progress_bar.println(msg);
-                                        }
-
-                                    } else {
-                                        runner_outputs.push(val)
-                                    }
-                                },
-                                Err(err) => {
-                                    utils::error(err, Some(url), Some(progress_bar), Some(config))
-                                }
-                            }
-                        }
-                    }
-                    runner_outputs
-                }
-            },
-        ))
-        .buffer_unordered(workers)
-        .collect::<Vec<Vec<RunnerOutput>>>()
-        .await;
-
-    // works only in case json output is used.
-    // otherwise runner_outputs is an empty vector
-    // and all the printing work is done within the futures above
-    if !runner_outputs.is_empty() {
-        let output = runner_outputs
-            .into_iter()
-            .flatten()
-            .filter(|x| !(config.remove_empty && x.found_params.is_empty()))
-            .collect::<Vec<RunnerOutput>>()
-            .parse_output(&config);
-
-        if output_file.is_some() {
-            output_file.as_mut().unwrap().write_all(output.as_bytes()).await?;
-            output_file.as_mut().unwrap().flush().await?;
+    // every SOAP operation is checked as a separate target
+    let configs: Vec<Config> = match config.wsdl.as_ref() {
+        Some(wsdl) => parse_wsdl(&read_wsdl(&config, wsdl).await?)?
+            .iter()
+            .enumerate()
+            .map(|(i, operation)| {
+                let mut operation_config = operation.config(&config);
+                // the output file is shared between operations
+                operation_config.append |= i > 0;
+                operation_config
+            })
+            .collect(),
+        None => vec![config.clone()],
+    };
+
+    // the same scan is run against the other environment and only the differences are printed
+    if let Some(base) = config.diff_against.as_ref() {
+        let primary = Mutex::new(Findings::new());
+        let other = Mutex::new(Findings::new());
+        let (primary_failed, other_failed) = (Mutex::new(Failed::new()), Mutex::new(Failed::new()));
+        let mut exit_code = EXIT_OK;
+
+        for config in &configs {
+            exit_code = exit_code.max(scan(config, &params, baseline.as_ref(), Some(&primary), Some(&primary_failed), None).await?);
+
+            let mut other_config = config.clone();
+            other_config.urls = config
+                .urls
+                .iter()
+                .map(|x| mirror_url(x, base))
+                .collect::<Result<Vec<String>, Box<dyn Error>>>()?;
+            // the output file is shared between the environments
+            other_config.append = true;
+
+            exit_code = exit_code.max(scan(&other_config, &params, baseline.as_ref(), Some(&other), Some(&other_failed), None).await?);
         }
 
-        write!(io::stdout(), "\n{}", output).ok();
+        let differences = differences(
+            (&primary.into_inner(), &primary_failed.into_inner()),
+            (&other.into_inner(), &other_failed.into_inner()),
+            base,
+        );
+
+        // stdout is kept for the findings
+        if !config.quiet {
+            if differences.is_empty() {
+                writeln!(io::stderr(), "\nThe environments accept the same parameters").ok();
+            } else {
+                writeln!(io::stderr(), "\n{}", differences.join("\n")).ok();
+            }
+        }
+
+        if !differences.is_empty() && config.fail_on_found {
+            exit_code = exit_code.max(EXIT_FOUND);
+        }
+
+        // the output contains the findings of both environments
+        if config.triage {
+            triage_report(&config.output_file).await?;
+        }
+
+        return Ok(exit_code);
     }
 
-    Ok(())
-}
+    // the scan is run once per profile and only the parameters that aren't visible to every profile are printed
+    if !config.auth_profiles.is_empty() {
+        let mut profiles = Vec::new();
+        let mut exit_code = EXIT_OK;
 
-async fn run(
-    config: &Config,
-    request_defaults: &mut RequestDefaults,
-    params: &mut Vec<String>,
-    progress_bar: &ProgressBar,
-    id: usize,
-) -> Result<RunnerOutput, Box<dyn Error>> {
-    let mut runner_output = Runner::new(config, request_defaults, progress_bar, id)
-        .await?
-        .run(params)
-        .await?;
-
-    // the whole block related to the recursive searching
-    if !runner_output.found_params.is_empty() {
-        for depth in 1..config.recursion_depth + 1 {
-            // remove already found parameters from the list to prevent duplicates
-            params.retain(|x| !runner_output.found_params.contains_name(x));
-
-            // custom parameters work badly with recursion enabled
-            request_defaults.disable_custom_parameters = true;
-
-            // so we are keeping parameters that don't change pages' code
-            // or change it to 200
-            // we cant simply overwrite request_defaults.parameters because there's user-supplied parameters as well.
-            request_defaults.parameters.append(&mut Vec::from_iter(
-                runner_output
-                    .found_params
-                    .iter()
-                    .filter(|x| {
-                        !request_defaults.parameters.contains_key(&x.name)
-                            && (x.reason_kind != ReasonKind::Code || x.status == 200)
-                    })
-                    .map(|x| (x.get())),
-            ));
-
-            utils::info(
-                config,
-                id,
-                progress_bar,
-                "recursion",
-                format!(
-                    "({}) repeating with {}",
-                    depth,
-                    request_defaults
-                        .parameters
-                        .iter()
-                        .map(|x| x.0.as_str())
-                        .collect::<Vec<&str>>()
-                        .join(", ")
-                ),
-            );
-
-            let mut new_found_params = Runner::new(config, request_defaults, progress_bar, id)
-                .await?
-                .run(params)
-                .await?
-                .found_params;
-
-            // no new params where found - just quit the loop
-            if !new_found_params
-                .iter()
-                .any(|x| !runner_output.found_params.contains_name(&x.name))
-            {
-                break;
+        for (i, (profile, headers)) in config.auth_profiles.iter().enumerate() {
+            let findings = Mutex::new(Findings::new());
+
+            for config in &configs {
+                let mut profile_config = config.clone();
+                profile_config.custom_headers.retain(|(k, _)| !headers.iter().any(|(x, _)| x.eq_ignore_ascii_case(k)));
+                profile_config.custom_headers.extend(headers.iter().cloned());
+                // the output file is shared between the profiles
+                profile_config.append |= i > 0;
+
+                exit_code = exit_code.max(scan(&profile_config, &params, baseline.as_ref(), Some(&findings), None, None).await?);
+            }
+
+            profiles.push((profile.to_owned(), findings.into_inner()));
+        }
+
+        let differences = profile_differences(&profiles);
+
+        // stdout is kept for the findings
+        if !config.quiet {
+            if differences.is_empty() {
+                writeln!(io::stderr(), "\nEvery profile sees the same parameters").ok();
+            } else {
+                writeln!(io::stderr(), "\n{}", differences.join("\n")).ok();
             }
+        }
 
-            runner_output.found_params.append(&mut new_found_params);
+        if !differences.is_empty() && config.fail_on_found {
+            exit_code = exit_code.max(EXIT_FOUND);
         }
+
+        // the output contains the findings of every profile
+        if config.triage {
+            triage_report(&config.output_file).await?;
+        }
+
+        return Ok(exit_code);
     }
 
-    // we probably changed request_defaults.parameters within the loop above
-    // so we are removing all of the added parameters in there
-    // leaving only user-supplied ones
-    // (to not cause double parameters in some output types)
-    request_defaults.parameters = request_defaults
-        .parameters
-        .iter()
-        .filter(|x| !runner_output.found_params.contains_name(&x.0))
-        .map(|x| x.to_owned())
-        .collect();
+    let interval = match config.watch {
+        Some(val) => val,
+        None => {
+            let mut exit_code = EXIT_OK;
 
-    runner_output.prepare(config, request_defaults);
+            for config in &configs {
+                exit_code = exit_code.max(scan(config, &params, baseline.as_ref(), None, None, None).await?);
+            }
+
+            if config.triage {
+                triage_report(&config.output_file).await?;
+            }
+
+            return Ok(exit_code);
+        }
+    };
+
+    // rerun the scan on the interval and notify only about changes
+    let mut previous = read_history(&config.watch_history)?;
+
+    loop {
+        let findings = Mutex::new(Findings::new());
 
-    Ok(runner_output)
+        for config in &configs {
+            if let Err(err) = scan(config, &params, baseline.as_ref(), Some(&findings), None, None).await {
+                utils::error(err, None, None, None);
+            }
+        }
+
+        let findings = findings.into_inner();
+        let changes = changes(&previous, &findings);
+
+        if let Err(err) = append_history(&config.watch_history, &findings) {
+            utils::error(format!("Unable to write the history: {}", err), None, None, None);
+        }
+
+        if !changes.is_empty() {
+            // stdout is kept for the findings
+            if !config.quiet {
+                writeln!(io::stderr(), "{}", changes.join("\n")).ok();
+            }
+
+            if let Some(webhook) = config.webhook.as_ref() {
+                if let Err(err) = notify(webhook, &changes).await {
+                    utils::error(format!("Unable to notify the webhook: {}", err), None, None, None);
+                }
+            }
+        }
+
+        previous.extend(findings);
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// puts the most probable parameters first and removes the ones that can't be checked
+/// or belong to other shards
+fn prepare_params(config: &Config, params: Vec<String>) -> (Vec<String>, Dropped) {
+    // the most probable parameters are checked within the first requests
+    let (params, dropped) = normalize(sort_by_weight(params), config.lowercase, config.headers_discovery);
+
+    match config.shard {
+        Some((k, n)) => (shard(params, k, n), dropped),
+        None => (params, dropped),
+    }
+}
+
+/// prints why wordlist entries were dropped
+fn report_dropped(config: &Config, dropped: &Dropped) {
+    if !config.remove_banner && dropped.total() > 0 {
+        writeln!(io::stderr(), "{}\n", dropped).ok();
+    }
 }
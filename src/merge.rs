@@ -0,0 +1,154 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    io::{self, Write},
+};
+
+use clap::{App, Arg};
+use serde_json::Value;
+use url::Url;
+
+use crate::{error::EXIT_OK, runner::output::read_outputs};
+
+/// host, injection place and name of the parameter
+type MergeKey = (String, String, String);
+
+/// combines the outputs of several runs into one output per method|url|injection place.
+/// A parameter found at the same host and place several times is kept once, with the strongest evidence
+pub fn merge(outputs: Vec<Value>) -> Vec<Value> {
+    // the first output of every method|url|place pair keeps the metadata
+    let mut merged: Vec<Value> = Vec::new();
+    let mut positions: HashMap<(String, String, String), usize> = HashMap::new();
+
+    // (index of the merged output, the parameter)
+    let mut best: HashMap<MergeKey, (usize, Value)> = HashMap::new();
+    let mut order: Vec<MergeKey> = Vec::new();
+
+    for mut output in outputs {
+        let method = output["method"].as_str().unwrap_or_default().to_string();
+        let url = output["url"].as_str().unwrap_or_default().to_string();
+        let place = output["injection_place"].to_string();
+        let host = Url::parse(&url).ok().and_then(|x| x.host_str().map(|x| x.to_string())).unwrap_or_default();
+
+        let found_params = match output.get_mut("found_params").map(|x| x.take()) {
+            Some(Value::Array(val)) => val,
+            _ => Vec::new(),
+        };
+
+        let index = *positions.entry((method, url.clone(), place.clone())).or_insert_with(|| {
+            merged.push(output);
+            merged.len() - 1
+        });
+
+        for param in found_params {
+            let key = (host.clone(), place.clone(), param["name"].as_str().unwrap_or_default().to_string());
+
+            match best.get_mut(&key) {
+                Some((best_index, best_param)) => {
+                    let (weaker_index, mut weaker) = if evidence(&param) > evidence(best_param) {
+                        (std::mem::replace(best_index, index), std::mem::replace(best_param, param))
+                    } else {
+                        (index, param)
+                    };
+
+                    // the urls of the dropped finding aren't lost
+                    let mut urls = vec![merged[weaker_index]["url"].clone()];
+                    if let Some(Value::Array(val)) = weaker.get_mut("also_found_at").map(|x| x.take()) {
+                        urls.extend(val);
+                    }
+
+                    let best_url = merged[*best_index]["url"].clone();
+                    if !best_param["also_found_at"].is_array() {
+                        best_param["also_found_at"] = Value::Array(Vec::new());
+                    }
+
+                    if let Some(Value::Array(also_found_at)) = best_param.get_mut("also_found_at") {
+                        for url in urls {
+                            if url != best_url && !also_found_at.contains(&url) {
+                                also_found_at.push(url);
+                            }
+                        }
+                    }
+                }
+                None => {
+                    order.push(key.clone());
+                    best.insert(key, (index, param));
+                }
+            }
+        }
+    }
+
+    for output in merged.iter_mut() {
+        output["found_params"] = Value::Array(Vec::new());
+    }
+
+    for key in order {
+        let (index, param) = best.remove(&key).unwrap();
+
+        if let Some(Value::Array(found_params)) = merged[index].get_mut("found_params") {
+            found_params.push(param);
+        }
+    }
+
+    merged
+}
+
+/// the higher the stronger: score, the amount of confirmed behaviors and whether the request is stored
+fn evidence(param: &Value) -> (u64, usize, bool) {
+    (
+        param["score"].as_u64().unwrap_or(0),
+        param["enrichments"].as_array().map_or(0, |x| x.len()),
+        !param["snapshot"].is_null(),
+    )
+}
+
+/// the arguments of x8 merge. Also used to generate shell completions
+pub fn merge_app() -> App<'static, 'static> {
+    App::new("merge")
+        .bin_name("x8 merge")
+        .about("Combine json or jsonl outputs of several runs, shards or environments")
+        .arg(
+            Arg::with_name("reports")
+                .help("The json or jsonl outputs to combine")
+                .required(true)
+                .min_values(1),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .help("The file to write the combined output to. It's printed to stdout otherwise")
+                .takes_value(true),
+        )
+}
+
+/// x8 merge out1.json out2.json -o combined.json.
+/// args are the command line starting with the program name and "merge". Returns the exit code
+pub fn merge_reports(args: Vec<String>) -> Result<i32, Box<dyn Error>> {
+    let args = merge_app().get_matches_from(args.into_iter().skip(1));
+
+    let mut outputs = Vec::new();
+    let mut amount = 0;
+
+    for path in args.values_of("reports").unwrap() {
+        let report = read_outputs(path)?;
+        amount += report.iter().filter_map(|x| x["found_params"].as_array()).map(|x| x.len()).sum::<usize>();
+        outputs.extend(report);
+    }
+
+    let merged = merge(outputs);
+    let json = serde_json::to_string_pretty(&merged)?;
+
+    match args.value_of("output") {
+        Some(path) => {
+            fs::write(path, json)?;
+
+            let kept: usize = merged.iter().filter_map(|x| x["found_params"].as_array()).map(|x| x.len()).sum();
+            writeln!(io::stdout(), "[#] {} of {} findings are unique, saved to {}", kept, amount, path).ok();
+        }
+        None => writeln!(io::stdout(), "{}", json)?,
+    }
+
+    Ok(EXIT_OK)
+}
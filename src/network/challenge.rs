@@ -0,0 +1,121 @@
+use std::{
+    io,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use parking_lot::Mutex;
+
+use crate::utils::error;
+
+use super::utils::run_headers_cmd;
+
+/// (kind, header or body marker) of anti-bot challenges and captchas
+const CHALLENGE_MARKERS: &[(&str, &str)] = &[
+    ("cloudflare", "cf-mitigated: challenge"),
+    ("cloudflare", "<title>Just a moment...</title>"),
+    ("cloudflare", "/cdn-cgi/challenge-platform/"),
+    ("akamai", "/_sec/cp_challenge/"),
+    ("akamai", "bm-verify"),
+    ("captcha", "g-recaptcha"),
+    ("captcha", "h-captcha"),
+    ("captcha", "cf-turnstile"),
+];
+
+/// returns the kind of the challenge in case the response is an anti-bot page
+/// successful pages are skipped because they may contain captchas within forms
+pub fn detect_challenge(code: u16, headers: &[(String, String)], text: &str) -> Option<&'static str> {
+    if ![403, 429, 503].contains(&code) {
+        return None;
+    }
+
+    let mitigated = headers
+        .iter()
+        .any(|(k, v)| k.eq_ignore_ascii_case("cf-mitigated") && v.contains("challenge"));
+
+    CHALLENGE_MARKERS
+        .iter()
+        .find(|(_, marker)| {
+            if marker.starts_with("cf-mitigated") {
+                mitigated
+            } else {
+                text.contains(marker)
+            }
+        })
+        .map(|(kind, _)| *kind)
+}
+
+/// headers received after solving the challenge, like Cookie: cf_clearance=..
+/// shared between clones of request defaults
+#[derive(Debug, Default)]
+pub struct Clearance {
+    headers: Mutex<Vec<(String, String)>>,
+
+    /// increases after every solved challenge
+    generation: AtomicUsize,
+
+    /// only one request asks for the solution at a time
+    solving: tokio::sync::Mutex<()>,
+}
+
+impl Clearance {
+    pub fn headers(&self) -> Vec<(String, String)> {
+        self.headers.lock().clone()
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// pauses the requests and gets the clearance headers either from the command or from the user
+    /// does nothing in case the challenge was solved after the request with the generation was sent
+    pub async fn solve(&self, generation: usize, cmd: Option<&str>, url: &str, kind: &str) -> Result<(), String> {
+        let _solving = self.solving.lock().await;
+
+        if self.generation() != generation {
+            return Ok(());
+        }
+
+        let headers = match cmd {
+            Some(cmd) => run_headers_cmd("--challenge-cmd", cmd, url).await?,
+            None if atty::is(atty::Stream::Stdin) => {
+                error(
+                    format!(
+                        "{} challenge. Solve it at {} and paste the clearance header, e.g. Cookie: cf_clearance=..",
+                        kind, url
+                    ),
+                    None,
+                    None,
+                    None,
+                );
+
+                read_header().await?
+            }
+            None => Err(format!("{} challenge. Use --challenge-cmd to solve it automatically", kind))?,
+        };
+
+        let mut clearance = self.headers.lock();
+        for (k, v) in headers {
+            clearance.retain(|x| !x.0.eq_ignore_ascii_case(&k));
+            clearance.push((k, v));
+        }
+
+        self.generation.fetch_add(1, Ordering::SeqCst);
+
+        Ok(())
+    }
+}
+
+async fn read_header() -> Result<Vec<(String, String)>, String> {
+    let line = tokio::task::spawn_blocking(|| {
+        let mut line = String::new();
+        io::stdin().read_line(&mut line).map(|_| line)
+    })
+    .await
+    .map_err(|err| err.to_string())?
+    .map_err(|err| err.to_string())?;
+
+    match line.trim().split_once(':') {
+        Some((k, v)) => Ok(vec![(k.trim().to_string(), v.trim().to_string())]),
+        None => Err(format!("Wrong header: {}", line.trim())),
+    }
+}
@@ -0,0 +1,42 @@
+use encoding_rs::{Encoding, UTF_8};
+use lazy_static::lazy_static;
+use regex::bytes::Regex;
+
+/// how far to look for <meta charset> within the body
+const META_SCAN_LEN: usize = 1024;
+
+/// the charset of the body: the BOM takes precedence over Content-Type, and Content-Type over <meta>
+pub fn detect(headers: &[(String, String)], body: &[u8]) -> &'static Encoding {
+    lazy_static! {
+        static ref RE_HEADER_CHARSET: regex::Regex = regex::Regex::new(r#"(?i)charset\s*=\s*["']?([\w.:-]+)"#).unwrap();
+        static ref RE_META_CHARSET: Regex = Regex::new(r#"(?i)<meta[^>]+charset\s*=\s*["']?([\w.:-]+)"#).unwrap();
+    }
+
+    if let Some((encoding, _)) = Encoding::for_bom(body) {
+        return encoding;
+    }
+
+    let from_header = headers
+        .iter()
+        .find(|x| x.0.eq_ignore_ascii_case("content-type"))
+        .and_then(|x| RE_HEADER_CHARSET.captures(&x.1))
+        .and_then(|x| Encoding::for_label(x[1].as_bytes()));
+
+    if let Some(encoding) = from_header {
+        return encoding;
+    }
+
+    RE_META_CHARSET
+        .captures(&body[..body.len().min(META_SCAN_LEN)])
+        .and_then(|x| Encoding::for_label(&x[1]))
+        // <meta charset="utf-16"> can only be read in an ascii compatible encoding
+        .map(|x| x.output_encoding())
+        .unwrap_or(UTF_8)
+}
+
+/// transcodes the body to utf-8 so the reflections of the sent values can be found and compared.
+/// Invalid sequences are replaced the same way as with String::from_utf8_lossy
+pub fn decode(headers: &[(String, String)], body: &[u8]) -> String {
+    let (text, _, _) = detect(headers, body).decode(body);
+    text.into_owned()
+}
@@ -0,0 +1,34 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use reqwest::{
+    cookie::{CookieStore, Jar},
+    header::HeaderValue,
+};
+use url::Url;
+
+/// cookie store that honors Set-Cookie until it's frozen.
+/// Every target gets its own one in case --cookie-jar is provided, shared by all the clients of the target
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    jar: Jar,
+    frozen: AtomicBool,
+}
+
+impl CookieJar {
+    /// ignore Set-Cookie from now on so session rotation doesn't change the responses
+    pub fn freeze(&self) {
+        self.frozen.store(true, Ordering::SeqCst);
+    }
+}
+
+impl CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        if !self.frozen.load(Ordering::SeqCst) {
+            self.jar.set_cookies(cookie_headers, url);
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        self.jar.cookies(url)
+    }
+}
@@ -0,0 +1,151 @@
+use std::error::Error;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use ring::hmac;
+
+use crate::utils::{base64_encode, BASE64URL};
+
+lazy_static! {
+    static ref RE_JWT: Regex = Regex::new(r"eyJ[A-Za-z0-9_-]*\.eyJ[A-Za-z0-9_-]*\.[A-Za-z0-9_-]*").unwrap();
+}
+
+/// where the token was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JwtLocation {
+    Header,
+    Url,
+    Body,
+}
+
+/// how to build tokens in case --jwt is used
+#[derive(Debug, Clone)]
+pub struct JwtSettings {
+    /// encoded header of the token
+    header: String,
+
+    /// decoded payload without the closing '}'
+    payload: String,
+
+    /// hmac algorithm and the key to re-sign tokens with.
+    /// Tokens are unsigned (alg: none) otherwise
+    key: Option<(&'static str, Vec<u8>)>,
+}
+
+impl JwtSettings {
+    pub fn new(token: &str, key: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let mut parts = token.split('.');
+
+        let (header, payload) = match (parts.next(), parts.next()) {
+            (Some(header), Some(payload)) => (base64url_decode(header)?, base64url_decode(payload)?),
+            _ => Err("Unable to parse the token")?,
+        };
+
+        let mut header: serde_json::Value = serde_json::from_slice(&header)
+            .map_err(|err| format!("Unable to parse the token's header: {}", err))?;
+
+        let key = match key {
+            Some(key) => match header["alg"].as_str() {
+                Some("HS256") => Some(("HS256", key.as_bytes().to_vec())),
+                Some("HS384") => Some(("HS384", key.as_bytes().to_vec())),
+                Some("HS512") => Some(("HS512", key.as_bytes().to_vec())),
+                _ => Err("Only HS256, HS384 and HS512 tokens can be re-signed with --jwt-key")?,
+            },
+            None => {
+                header["alg"] = serde_json::Value::String("none".to_string());
+                None
+            }
+        };
+
+        let mut payload = String::from_utf8(payload)?.trim().to_string();
+
+        if !payload.starts_with('{') || payload.pop() != Some('}') {
+            Err("The token's payload isn't a json object")?
+        }
+
+        Ok(Self {
+            header: base64_encode(serde_json::to_string(&header)?.as_bytes(), true),
+            payload,
+            key,
+        })
+    }
+
+    /// returns the token with claims added to the payload
+    /// claims are a part of json object like "claim1":"value1","claim2":"value2"
+    pub fn token(&self, claims: &str) -> String {
+        let payload = if claims.is_empty() {
+            format!("{}}}", self.payload)
+        } else if self.payload.trim_end().ends_with('{') {
+            format!("{}{}}}", self.payload, claims)
+        } else {
+            format!("{},{}}}", self.payload, claims)
+        };
+
+        let unsigned = format!("{}.{}", self.header, base64_encode(payload.as_bytes(), true));
+
+        let signature = match self.key.as_ref() {
+            Some((alg, key)) => {
+                let algorithm = match *alg {
+                    "HS384" => hmac::HMAC_SHA384,
+                    "HS512" => hmac::HMAC_SHA512,
+                    _ => hmac::HMAC_SHA256,
+                };
+
+                base64_encode(hmac::sign(&hmac::Key::new(algorithm, key), unsigned.as_bytes()).as_ref(), true)
+            }
+            None => String::new(),
+        };
+
+        format!("{}.{}", unsigned, signature)
+    }
+}
+
+/// replaces the first token within headers, the url or the body with the injection point
+pub fn extract_jwt(
+    url: &mut String,
+    body: &mut String,
+    headers: &mut Vec<(String, String)>,
+) -> Result<(String, JwtLocation), Box<dyn Error>> {
+    for (_, value) in headers.iter_mut() {
+        if let Some(token) = RE_JWT.find(value).map(|x| x.as_str().to_string()) {
+            *value = value.replacen(&token, "%s", 1);
+            return Ok((token, JwtLocation::Header));
+        }
+    }
+
+    for (place, location) in [(url, JwtLocation::Url), (body, JwtLocation::Body)] {
+        if let Some(token) = RE_JWT.find(place).map(|x| x.as_str().to_string()) {
+            *place = place.replacen(&token, "%s", 1);
+            return Ok((token, location));
+        }
+    }
+
+    Err("Unable to find a JWT within the request")?
+}
+
+pub fn base64url_decode(data: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut decoded = Vec::with_capacity(data.len() * 3 / 4);
+
+    let (mut n, mut bits) = (0u32, 0);
+
+    for c in data.trim_end_matches('=').bytes() {
+        let value = match BASE64URL.iter().position(|x| *x == c) {
+            Some(val) => val as u32,
+            // tokens are sometimes encoded with the standard alphabet
+            None if c == b'+' => 62,
+            None if c == b'/' => 63,
+            None => Err("Unable to decode the token")?,
+        };
+
+        n = n << 6 | value;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((n >> bits) as u8);
+            n &= (1 << bits) - 1;
+        }
+    }
+
+    Ok(decoded)
+}
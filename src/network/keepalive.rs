@@ -0,0 +1,132 @@
+use std::{
+    error::Error,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// parts of transport errors that appear when the server closes a keep-alive connection
+const DROP_MARKERS: &[&str] = &[
+    "connection closed before message completed",
+    "connection reset",
+    "broken pipe",
+    "unexpected eof",
+];
+
+/// tracks servers that close keep-alive connections after a fixed amount of requests.
+/// Once the amount is known, connections are closed by x8 right before it.
+/// reqwest doesn't tell which pooled connection was used, so requests are counted only while they're sent one at a time
+/// and every request goes through the same connection.
+/// Shared between clones of the request defaults
+#[derive(Debug, Default)]
+pub struct KeepAlive {
+    /// successfully sent requests
+    sent: AtomicUsize,
+
+    /// the amount of sent requests at the moment of the last drop
+    last_drop: AtomicUsize,
+
+    /// the amount of requests between the two last drops
+    last_gap: AtomicUsize,
+
+    /// requests per connection. 0 while unknown
+    limit: AtomicUsize,
+
+    /// connections can't be closed with the Connection header within http/2
+    http2: AtomicBool,
+
+    /// requests that are being sent right now
+    in_flight: AtomicUsize,
+
+    /// several requests were sent at once, so they went through different connections
+    concurrent: AtomicBool,
+}
+
+/// a request that is being sent. The request is finished once dropped
+pub struct InFlight<'a>(&'a KeepAlive);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl KeepAlive {
+    /// the request should be held until its response is received
+    pub fn start(&self) -> InFlight<'_> {
+        if self.in_flight.fetch_add(1, Ordering::SeqCst) > 0 {
+            self.concurrent.store(true, Ordering::SeqCst);
+        }
+
+        InFlight(self)
+    }
+
+    pub fn record_sent(&self, version: http::Version) {
+        self.sent.fetch_add(1, Ordering::SeqCst);
+
+        if version == http::Version::HTTP_2 {
+            self.http2.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// returns the limit in case the drop revealed it.
+    /// Drops after the same amount of requests twice in a row mean the server has a fixed limit
+    pub fn record_drop(&self) -> Option<usize> {
+        if self.concurrent.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        let sent = self.sent.load(Ordering::SeqCst);
+        let gap = sent - self.last_drop.swap(sent, Ordering::SeqCst);
+        let last_gap = self.last_gap.swap(gap, Ordering::SeqCst);
+
+        if gap < 2 || last_gap < 2 || gap.max(last_gap) * 4 > gap.min(last_gap) * 5 {
+            return None;
+        }
+
+        let limit = gap.min(last_gap) - 1;
+
+        if self.limit.swap(limit, Ordering::SeqCst) == 0 {
+            Some(limit)
+        } else {
+            None
+        }
+    }
+
+    /// e.g. "120 requests sent, closed after 100 requests". Used within the stall report
+    pub fn state(&self) -> String {
+        let limit = match self.limit.load(Ordering::SeqCst) {
+            _ if self.http2.load(Ordering::SeqCst) => "http/2".to_string(),
+            _ if self.concurrent.load(Ordering::SeqCst) => "the connection limit isn't tracked with concurrent requests".to_string(),
+            0 => "the connection limit is unknown".to_string(),
+            limit => format!("closed after {} requests", limit + 1),
+        };
+
+        format!("{} requests sent, {}", self.sent.load(Ordering::SeqCst), limit)
+    }
+
+    /// whether the request should close its connection to stay below the server's limit
+    pub fn should_close(&self) -> bool {
+        let limit = self.limit.load(Ordering::SeqCst);
+
+        limit > 0
+            && !self.http2.load(Ordering::SeqCst)
+            && !self.concurrent.load(Ordering::SeqCst)
+            && self.sent.load(Ordering::SeqCst) % limit == limit - 1
+    }
+}
+
+/// whether the error is caused by the server closing the connection
+pub fn is_connection_drop(err: &(dyn Error + 'static)) -> bool {
+    let mut source = Some(err);
+
+    while let Some(err) = source {
+        let msg = err.to_string().to_lowercase();
+
+        if DROP_MARKERS.iter().any(|x| msg.contains(x)) {
+            return true;
+        }
+
+        source = err.source();
+    }
+
+    false
+}
@@ -1,5 +1,18 @@
+pub mod challenge;
+pub mod charset;
+pub mod cookies;
+pub mod jwt;
+pub mod keepalive;
+pub mod protobuf;
+pub mod proxy;
+#[cfg(feature = "http3")]
+pub mod quic;
+pub mod raw;
 pub mod request;
 pub mod response;
+pub mod script;
+pub mod soap;
 pub mod utils;
+pub mod watchdog;
 
 mod tests;
@@ -0,0 +1,205 @@
+use std::{collections::HashMap, error::Error, fs};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// how to build protobuf bodies in case --protobuf is used
+#[derive(Debug, Clone, Default)]
+pub struct ProtobufSettings {
+    /// the message to append fields to
+    pub base: Vec<u8>,
+
+    /// field names from the .proto file -> field numbers
+    pub fields: HashMap<String, u64>,
+
+    /// wrap messages into gRPC-web frames
+    pub grpc_web: bool,
+}
+
+impl ProtobufSettings {
+    /// base is a hex encoded message
+    pub fn new(base: &str, proto: Option<&str>, grpc_web: bool) -> Result<Self, Box<dyn Error>> {
+        let base = decode_hex(base.trim())?;
+
+        let fields = match proto {
+            Some(path) => parse_proto_fields(&fs::read_to_string(path)?),
+            None => HashMap::new(),
+        };
+
+        Ok(Self { base, fields, grpc_web })
+    }
+
+    /// parameter names are either field numbers or names from the .proto file
+    pub fn field_number(&self, name: &str) -> Option<u64> {
+        match self.fields.get(name) {
+            Some(number) => Some(*number),
+            None => name.parse().ok().filter(|x| *x > 0 && *x < 1 << 29),
+        }
+    }
+
+    /// appends parameters as string fields to the base message.
+    /// Parameters that aren't field numbers (like random ones) are skipped
+    pub fn encode<'a, I: Iterator<Item = &'a (String, String)>>(&self, parameters: I) -> Vec<u8> {
+        let mut message = self.base.clone();
+
+        for (k, v) in parameters {
+            if let Some(number) = self.field_number(k) {
+                encode_varint(number << 3 | 2, &mut message);
+                encode_varint(v.len() as u64, &mut message);
+                message.extend_from_slice(v.as_bytes());
+            }
+        }
+
+        if self.grpc_web {
+            frame(&message)
+        } else {
+            message
+        }
+    }
+
+    /// turns the response into text lines so it can be compared as usual
+    pub fn decode(&self, body: &[u8]) -> Option<String> {
+        let message = if self.grpc_web { unframe(body)? } else { body.to_vec() };
+
+        decode_message(&message, 0)
+    }
+}
+
+/// field names and numbers from lines like `string name = 1;`
+fn parse_proto_fields(proto: &str) -> HashMap<String, u64> {
+    lazy_static! {
+        static ref RE_FIELD: Regex =
+            Regex::new(r"(?m)^\s*(?:optional\s+|required\s+|repeated\s+)?[\w.<>, ]+?\s+(\w+)\s*=\s*(\d+)").unwrap();
+    }
+
+    RE_FIELD
+        .captures_iter(proto)
+        .filter_map(|x| Some((x[1].to_string(), x[2].parse().ok()?)))
+        .collect()
+}
+
+fn encode_varint(mut value: u64, buf: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buf.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn decode_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+/// decodes the message without schema: `<field number>: <value>` per line, nested messages are indented.
+/// Returns None in case the data doesn't look like a message
+fn decode_message(data: &[u8], depth: usize) -> Option<String> {
+    // too deep nesting is more likely to be a string that accidentally looks like a message
+    if depth > 8 {
+        return None;
+    }
+
+    let indent = "  ".repeat(depth);
+    let mut lines = Vec::new();
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let key = decode_varint(data, &mut pos)?;
+        let (number, wire_type) = (key >> 3, key & 7);
+
+        if number == 0 {
+            return None;
+        }
+
+        let value = match wire_type {
+            0 => decode_varint(data, &mut pos)?.to_string(),
+            1 => {
+                let bytes = data.get(pos..pos + 8)?;
+                pos += 8;
+                format!("0x{}", encode_hex(bytes))
+            }
+            2 => {
+                let len = decode_varint(data, &mut pos)? as usize;
+                let bytes = data.get(pos..pos.checked_add(len)?)?;
+                pos += len;
+
+                match std::str::from_utf8(bytes) {
+                    Ok(text) if !text.chars().any(|x| x.is_control() && x != '\n' && x != '\t') => format!("{:?}", text),
+                    _ => match decode_message(bytes, depth + 1) {
+                        Some(nested) if !bytes.is_empty() => format!("{{\n{}\n{}}}", nested, indent),
+                        _ => format!("0x{}", encode_hex(bytes)),
+                    },
+                }
+            }
+            5 => {
+                let bytes = data.get(pos..pos + 4)?;
+                pos += 4;
+                format!("0x{}", encode_hex(bytes))
+            }
+            _ => return None,
+        };
+
+        lines.push(format!("{}{}: {}", indent, number, value));
+    }
+
+    Some(lines.join("\n"))
+}
+
+/// gRPC-web frame: 1 byte of flags + 4 bytes of big endian length + the message
+fn frame(message: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(message.len() + 5);
+    framed.push(0);
+    framed.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    framed.extend_from_slice(message);
+    framed
+}
+
+/// returns the first data frame. Trailer frames have the highest bit of flags set
+fn unframe(body: &[u8]) -> Option<Vec<u8>> {
+    let mut pos = 0;
+
+    while pos + 5 <= body.len() {
+        let flags = body[pos];
+        let len = u32::from_be_bytes([body[pos + 1], body[pos + 2], body[pos + 3], body[pos + 4]]) as usize;
+        let message = body.get(pos + 5..pos + 5 + len)?;
+
+        if flags & 0x80 == 0 {
+            return Some(message.to_vec());
+        }
+
+        pos += 5 + len;
+    }
+
+    None
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let hex = hex.replace(' ', "");
+
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        Err("The protobuf body should be hex encoded")?
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| "The protobuf body should be hex encoded".into())
+        })
+        .collect()
+}
@@ -0,0 +1,244 @@
+use std::{
+    error::Error,
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bytes::{Buf, Bytes};
+use futures::future;
+use h3_quinn::OpenStreams;
+use quinn::{ClientConfig, Endpoint};
+use tokio::{net::lookup_host, sync::Mutex};
+
+use crate::{config::structs::Config, error::X8Error};
+
+use super::{
+    raw::{decompress, RawResponse},
+    utils::tls_config,
+};
+
+/// hop-by-hop headers are forbidden in HTTP/3, and the host is sent as :authority
+const CONNECTION_HEADERS: [&str; 6] = ["connection", "host", "keep-alive", "proxy-connection", "transfer-encoding", "upgrade"];
+
+/// sends requests over one QUIC connection per host.
+/// Used with --http 3, the reqwest clients are used in case the host doesn't accept QUIC
+pub struct QuicEngine {
+    endpoint: Endpoint,
+
+    /// --pin-ip address, the host is resolved otherwise
+    addr: Option<SocketAddr>,
+
+    /// the family of the local socket, the first address of the same family is used
+    ipv6: bool,
+
+    timeout: Duration,
+
+    /// the connection is opened by the first request and reopened after errors
+    sender: Mutex<Option<h3::client::SendRequest<OpenStreams, Bytes>>>,
+
+    /// set after the first successful handshake, the host isn't switched to h2/h1.1 after that
+    connected: AtomicBool,
+
+    /// set in case the host doesn't accept QUIC, the requests are sent with reqwest then
+    fallen_back: AtomicBool,
+}
+
+impl fmt::Debug for QuicEngine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("QuicEngine")
+            .field("addr", &self.addr)
+            .field("timeout", &self.timeout)
+            .field("connected", &self.connected)
+            .field("fallen_back", &self.fallen_back)
+            .finish()
+    }
+}
+
+impl QuicEngine {
+    pub fn new(config: &Config, port: u16) -> Result<Self, Box<dyn Error>> {
+        if !config.proxy.is_empty() || !config.proxy_map.is_empty() {
+            Err("HTTP/3 requests can't be sent through proxies")?
+        }
+
+        if config.interface.is_some() {
+            Err("--interface isn't supported with --http 3. Use --source-ip with the address of the interface instead")?
+        }
+
+        let mut tls = tls_config(config)?;
+        tls.alpn_protocols = vec![b"h3".to_vec()];
+
+        let ipv6 = config.source_ip.map_or(false, |x| x.is_ipv6());
+        let local_ip = config.source_ip.unwrap_or(if ipv6 {
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+        } else {
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        });
+
+        let mut endpoint = Endpoint::client(SocketAddr::new(local_ip, 0))?;
+        endpoint.set_default_client_config(ClientConfig::new(Arc::new(tls)));
+
+        Ok(Self {
+            endpoint,
+            addr: config.pin_ip.map(|ip| SocketAddr::new(ip, port)),
+            ipv6,
+            timeout: Duration::from_secs(config.timeout as u64),
+            sender: Mutex::new(None),
+            connected: AtomicBool::new(false),
+            fallen_back: AtomicBool::new(false),
+        })
+    }
+
+    /// whether the host didn't accept QUIC and the requests should be sent with reqwest
+    pub fn fallen_back(&self) -> bool {
+        self.fallen_back.load(Ordering::SeqCst)
+    }
+
+    /// sends the request over the shared connection.
+    /// Returns None in case the host doesn't accept QUIC at all
+    pub async fn send(
+        &self,
+        host: &str,
+        port: u16,
+        method: &str,
+        url: &str,
+        headers: &[(String, String)],
+        body: Vec<u8>,
+    ) -> Result<Option<RawResponse>, X8Error> {
+        let mut sender = match tokio::time::timeout(self.timeout, self.sender(host, port)).await {
+            Ok(Ok(val)) => val,
+            // nothing was received over QUIC yet, so the scan continues over h2/h1.1
+            Ok(Err(_)) | Err(_) if !self.connected.load(Ordering::SeqCst) => {
+                self.fallen_back.store(true, Ordering::SeqCst);
+                return Ok(None);
+            }
+            Ok(Err(err)) => Err(err)?,
+            Err(_) => Err(X8Error::Network(format!("The QUIC handshake with {} timed out", host)))?,
+        };
+
+        match tokio::time::timeout(self.timeout, exchange(&mut sender, method, url, headers, body)).await {
+            Ok(Ok(val)) => Ok(Some(val)),
+            // the connection may be broken, the next request opens a new one
+            Ok(Err(err)) => {
+                self.sender.lock().await.take();
+                Err(err)
+            }
+            Err(_) => Err(X8Error::Network(format!("The HTTP/3 request to {} timed out", host))),
+        }
+    }
+
+    /// the shared connection, a new one is opened in case there's none
+    async fn sender(&self, host: &str, port: u16) -> Result<h3::client::SendRequest<OpenStreams, Bytes>, X8Error> {
+        let mut sender = self.sender.lock().await;
+
+        if let Some(sender) = sender.as_ref() {
+            return Ok(sender.clone());
+        }
+
+        // the requests that waited for the failed handshake don't repeat it
+        if self.fallen_back() {
+            Err(X8Error::Network(format!("{} doesn't accept QUIC", host)))?
+        }
+
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let addr = self.resolve(host, port).await?;
+
+        let connection = self
+            .endpoint
+            .connect(addr, host)
+            .map_err(quic)?
+            .await
+            .map_err(quic)?;
+
+        let (mut driver, new_sender) = h3::client::new(h3_quinn::Connection::new(connection)).await.map_err(quic)?;
+
+        // the connection is closed once the driver ends, and the next request reopens it then
+        tokio::spawn(async move {
+            if let Err(err) = future::poll_fn(|cx| driver.poll_close(cx)).await {
+                log::debug!("The HTTP/3 connection was closed: {}", err);
+            }
+        });
+
+        self.connected.store(true, Ordering::SeqCst);
+        *sender = Some(new_sender.clone());
+
+        Ok(new_sender)
+    }
+
+    /// the pinned address or the first address of the host with the family of the local socket
+    async fn resolve(&self, host: &str, port: u16) -> Result<SocketAddr, X8Error> {
+        if let Some(addr) = self.addr {
+            return Ok(addr);
+        }
+
+        lookup_host((host, port))
+            .await?
+            .find(|x| x.is_ipv6() == self.ipv6)
+            .ok_or_else(|| X8Error::Network(format!("{} doesn't resolve to any address of the --source-ip family", host)))
+    }
+}
+
+/// sends one request over a new stream and reads the whole response
+async fn exchange(
+    sender: &mut h3::client::SendRequest<OpenStreams, Bytes>,
+    method: &str,
+    url: &str,
+    headers: &[(String, String)],
+    body: Vec<u8>,
+) -> Result<RawResponse, X8Error> {
+    let mut request = http_h3::Request::builder().method(method).uri(url);
+
+    for (k, v) in headers.iter().filter(|x| !CONNECTION_HEADERS.contains(&x.0.to_lowercase().as_str())) {
+        request = request.header(k.as_str(), v.as_str());
+    }
+
+    let request = request
+        .body(())
+        .map_err(|err| X8Error::Config(format!("Unable to build the HTTP/3 request: {}", err)))?;
+
+    let mut stream = sender.send_request(request).await.map_err(quic)?;
+
+    if !body.is_empty() {
+        stream.send_data(Bytes::from(body)).await.map_err(quic)?;
+    }
+
+    stream.finish().await.map_err(quic)?;
+
+    let response = stream.recv_response().await.map_err(quic)?;
+
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(k, v)| (k.to_string(), String::from_utf8_lossy(v.as_bytes()).into_owned()))
+        .collect();
+
+    let mut body = Vec::new();
+
+    while let Some(mut chunk) = stream.recv_data().await.map_err(quic)? {
+        while chunk.has_remaining() {
+            let part = chunk.chunk();
+            let len = part.len();
+
+            body.extend_from_slice(part);
+            chunk.advance(len);
+        }
+    }
+
+    // reqwest decodes the bodies as well, so the responses of both are comparable
+    let (headers, body) = decompress(headers, body)?;
+
+    Ok(RawResponse {
+        code: response.status().as_u16(),
+        http_version: http::Version::HTTP_3,
+        headers,
+        body: Bytes::from(body),
+    })
+}
+
+fn quic<E: fmt::Display>(err: E) -> X8Error {
+    X8Error::Network(err.to_string())
+}
@@ -0,0 +1,496 @@
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    error::Error,
+    fmt,
+    io::{self, Read},
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use bytes::Bytes;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use parking_lot::Mutex;
+use rustls::ServerName;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{lookup_host, TcpSocket},
+};
+use tokio_rustls::TlsConnector;
+
+use crate::{config::structs::Config, error::X8Error, MAX_PAGE_SIZE};
+
+use super::utils::tls_config;
+
+/// how long to wait for 100 Continue before sending the body anyway
+const EXPECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// the max amount of idle connections kept per address
+const MAX_IDLE_CONNECTIONS: usize = 32;
+
+/// plain and tls connections behind one type, so both can be pooled
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// sends HTTP/1.1 messages byte by byte over keep-alive connections.
+/// Used for the parts of requests reqwest can't express, e.g. chunk extensions and trailers
+pub struct RawEngine {
+    /// None for plain http
+    tls: Option<TlsConnector>,
+
+    /// --pin-ip address, the host is resolved otherwise
+    addr: Option<SocketAddr>,
+
+    /// --source-ip and --interface, the same as for the reqwest clients
+    source_ip: Option<IpAddr>,
+    interface: Option<String>,
+
+    timeout: Duration,
+
+    /// idle keep-alive connections by the address they're connected to
+    idle: Mutex<HashMap<SocketAddr, Vec<Box<dyn Stream>>>>,
+}
+
+#[derive(Debug)]
+pub struct RawResponse {
+    pub code: u16,
+    pub http_version: http::Version,
+    pub headers: Vec<(String, String)>,
+    pub body: Bytes,
+}
+
+impl fmt::Debug for RawEngine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RawEngine")
+            .field("tls", &self.tls.is_some())
+            .field("addr", &self.addr)
+            .field("source_ip", &self.source_ip)
+            .field("interface", &self.interface)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl RawEngine {
+    pub fn new(config: &Config, scheme: &str, port: u16) -> Result<Self, Box<dyn Error>> {
+        if !config.proxy.is_empty() || !config.proxy_map.is_empty() {
+            Err("Raw HTTP/1.1 requests can't be sent through proxies yet")?
+        }
+
+        let tls = if scheme == "https" {
+            let mut tls = tls_config(config)?;
+            tls.alpn_protocols = vec![b"http/1.1".to_vec()];
+            // the message is written as early data right after the ClientHello when the session is resumed
+            Some(TlsConnector::from(Arc::new(tls)).early_data(config.tls_early_data))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            tls,
+            addr: config.pin_ip.map(|ip| SocketAddr::new(ip, port)),
+            source_ip: config.source_ip,
+            interface: config.interface.clone(),
+            timeout: Duration::from_secs(config.timeout as u64),
+            idle: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// connects to the host (or the sni name) and sends the message as is.
+    /// ip is the address chosen by --ip-failover
+    pub async fn send(
+        &self,
+        host: &str,
+        port: u16,
+        ip: Option<IpAddr>,
+        message: &[u8],
+        head: bool,
+    ) -> Result<RawResponse, X8Error> {
+        match tokio::time::timeout(self.timeout, self.exchange(host, port, ip, message, head)).await {
+            Ok(val) => val,
+            Err(_) => Err(X8Error::Network(format!("The raw request to {} timed out", host))),
+        }
+    }
+
+    async fn exchange(
+        &self,
+        host: &str,
+        port: u16,
+        ip: Option<IpAddr>,
+        message: &[u8],
+        head: bool,
+    ) -> Result<RawResponse, X8Error> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let addr = self.resolve(host, port, ip).await?;
+
+        // the server may have closed the idle connection meanwhile, a new one is opened then
+        let pooled = self.idle.lock().get_mut(&addr).and_then(|x| x.pop());
+
+        if let Some(mut stream) = pooled {
+            if let Ok((response, reusable)) = exchange(&mut stream, message, head).await {
+                self.release(addr, stream, reusable);
+                return Ok(response);
+            }
+        }
+
+        let mut stream = self.connect(host, addr).await?;
+        let (response, reusable) = exchange(&mut stream, message, head).await?;
+        self.release(addr, stream, reusable);
+
+        Ok(response)
+    }
+
+    /// the failover address, the pinned one or the first address of the host with the family of --source-ip
+    async fn resolve(&self, host: &str, port: u16, ip: Option<IpAddr>) -> Result<SocketAddr, X8Error> {
+        if let Some(ip) = ip {
+            return Ok(SocketAddr::new(ip, port));
+        }
+
+        if let Some(addr) = self.addr {
+            return Ok(addr);
+        }
+
+        let addrs: Vec<SocketAddr> = lookup_host((host, port)).await.map_err(network)?.collect();
+
+        addrs
+            .iter()
+            .find(|x| self.source_ip.map_or(true, |ip| ip.is_ipv4() == x.is_ipv4()))
+            .or_else(|| addrs.first())
+            .copied()
+            .ok_or_else(|| X8Error::Network(format!("{} doesn't resolve to any address", host)))
+    }
+
+    /// opens a connection from --source-ip or through --interface, the same way as reqwest clients do
+    async fn connect(&self, host: &str, addr: SocketAddr) -> Result<Box<dyn Stream>, X8Error> {
+        let socket = if addr.is_ipv4() { TcpSocket::new_v4() } else { TcpSocket::new_v6() }.map_err(network)?;
+
+        if let Some(ip) = self.source_ip {
+            socket.bind(SocketAddr::new(ip, 0)).map_err(network)?;
+        }
+
+        if let Some(interface) = self.interface.as_ref() {
+            bind_device(&socket, interface)?;
+        }
+
+        let stream = socket.connect(addr).await.map_err(network)?;
+        stream.set_nodelay(true)?;
+
+        match self.tls.as_ref() {
+            Some(tls) => {
+                let name = ServerName::try_from(host)
+                    .map_err(|err| X8Error::Config(format!("Wrong server name {}: {}", host, err)))?;
+
+                Ok(Box::new(tls.connect(name, stream).await.map_err(network)?))
+            }
+            None => Ok(Box::new(stream)),
+        }
+    }
+
+    /// keeps the connection for the next requests in case the server doesn't close it
+    fn release(&self, addr: SocketAddr, stream: Box<dyn Stream>, reusable: bool) {
+        if !reusable {
+            return;
+        }
+
+        let mut idle = self.idle.lock();
+        let streams = idle.entry(addr).or_default();
+
+        if streams.len() < MAX_IDLE_CONNECTIONS {
+            streams.push(stream);
+        }
+    }
+}
+
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+fn bind_device(socket: &TcpSocket, interface: &str) -> Result<(), X8Error> {
+    socket.bind_device(Some(interface.as_bytes())).map_err(network)
+}
+
+#[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+fn bind_device(_socket: &TcpSocket, _interface: &str) -> Result<(), X8Error> {
+    Err(X8Error::Config("--interface is supported only on Linux".to_string()))
+}
+
+/// sends the message and reads the response.
+/// Returns whether the connection can be used for the next request
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    message: &[u8],
+    head: bool,
+) -> Result<(RawResponse, bool), X8Error> {
+    let mut buf: Vec<u8> = Vec::new();
+
+    let head_end = message.windows(4).position(|x| x == b"\r\n\r\n").map_or(message.len(), |x| x + 4);
+
+    let body_sent = if head_end < message.len() && expects_continue(&message[..head_end]) {
+        stream.write_all(&message[..head_end]).await.map_err(network)?;
+        stream.flush().await.map_err(network)?;
+
+        // servers that answer with the final response right away don't get the body
+        let send_body = match tokio::time::timeout(EXPECT_TIMEOUT, read_head(stream, &mut buf)).await {
+            Ok(end) => {
+                let end = end?;
+                let (code, _, _) = parse_head(&String::from_utf8_lossy(&buf[..end]))?;
+                code == 100
+            }
+            // the same as curl, the body is sent anyway in case the server is silent
+            Err(_) => true,
+        };
+
+        if send_body {
+            stream.write_all(&message[head_end..]).await.map_err(network)?;
+            stream.flush().await.map_err(network)?;
+        }
+
+        send_body
+    } else {
+        stream.write_all(message).await.map_err(network)?;
+        stream.flush().await.map_err(network)?;
+
+        true
+    };
+
+    // interim responses like 100 Continue are skipped
+    let (code, http_version, headers) = loop {
+        let end = read_head(stream, &mut buf).await?;
+        let head = String::from_utf8_lossy(&buf[..end]).into_owned();
+        buf.drain(..end + 4);
+
+        let (code, http_version, headers) = parse_head(&head)?;
+        if !(100..200).contains(&code) || code == 101 {
+            break (code, http_version, headers);
+        }
+    };
+
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|x| x.0.eq_ignore_ascii_case(name))
+            .map(|x| x.1.trim().to_ascii_lowercase())
+    };
+
+    // HTTP/1.0 closes connections unless told otherwise
+    let mut reusable = body_sent
+        && !closes(&String::from_utf8_lossy(&message[..head_end]))
+        && match header("connection") {
+            Some(val) => val != "close",
+            None => http_version == http::Version::HTTP_11,
+        };
+
+    let body = if head || code == 204 || code == 304 || (100..200).contains(&code) {
+        Vec::new()
+    } else if header("transfer-encoding").map_or(false, |x| x.ends_with("chunked")) {
+        read_chunked(stream, buf).await?
+    } else if let Some(length) = header("content-length") {
+        let length: usize = length
+            .parse()
+            .map_err(|_| X8Error::Network(format!("Wrong Content-Length: {}", length)))?;
+
+        read_exact(stream, buf, length).await?
+    } else {
+        // the end of the body is the end of the connection
+        reusable = false;
+        read_to_end(stream, buf).await?
+    };
+
+    let (headers, body) = decompress(headers, body)?;
+
+    Ok((RawResponse { code, http_version, headers, body: Bytes::from(body) }, reusable))
+}
+
+/// whether the request asks to close the connection
+fn closes(head: &str) -> bool {
+    head.split("\r\n").skip(1).any(|x| {
+        x.split_once(':')
+            .map_or(false, |(k, v)| k.trim().eq_ignore_ascii_case("connection") && v.trim().eq_ignore_ascii_case("close"))
+    })
+}
+
+/// decodes gzip and deflate bodies the same way reqwest does, so the responses of both engines are comparable
+pub(super) fn decompress(mut headers: Vec<(String, String)>, body: Vec<u8>) -> Result<(Vec<(String, String)>, Vec<u8>), X8Error> {
+    let encoding = match headers.iter().find(|x| x.0 == "content-encoding") {
+        Some((_, val)) => val.trim().to_ascii_lowercase(),
+        None => return Ok((headers, body)),
+    };
+
+    if body.is_empty() || (encoding != "gzip" && encoding != "deflate") {
+        return Ok((headers, body));
+    }
+
+    let mut decoded = Vec::new();
+    let result = if encoding == "gzip" {
+        GzDecoder::new(&body[..]).read_to_end(&mut decoded)
+    } else {
+        ZlibDecoder::new(&body[..]).read_to_end(&mut decoded)
+    };
+
+    result.map_err(|err| X8Error::Network(format!("Unable to decode the {} body: {}", encoding, err)))?;
+
+    headers.retain(|x| x.0 != "content-encoding" && x.0 != "content-length");
+
+    Ok((headers, decoded))
+}
+
+fn network(err: io::Error) -> X8Error {
+    X8Error::Network(err.to_string())
+}
+
+/// whether the head contains Expect: 100-continue
+fn expects_continue(head: &[u8]) -> bool {
+    String::from_utf8_lossy(head).split("\r\n").skip(1).any(|x| {
+        x.split_once(':')
+            .map_or(false, |(k, v)| k.trim().eq_ignore_ascii_case("expect") && v.trim().eq_ignore_ascii_case("100-continue"))
+    })
+}
+
+/// reads until the end of the headers and returns the position of \r\n\r\n
+pub async fn read_head<S: AsyncRead + Unpin>(stream: &mut S, buf: &mut Vec<u8>) -> Result<usize, X8Error> {
+    loop {
+        if let Some(pos) = buf.windows(4).position(|x| x == b"\r\n\r\n") {
+            return Ok(pos);
+        }
+
+        if buf.len() > MAX_PAGE_SIZE {
+            Err(X8Error::Network("The response headers are too long".to_string()))?
+        }
+
+        if read_more(stream, buf).await? == 0 {
+            Err(X8Error::ConnectionClosed("The connection was closed before the response".to_string()))?
+        }
+    }
+}
+
+fn parse_head(head: &str) -> Result<(u16, http::Version, Vec<(String, String)>), X8Error> {
+    let mut lines = head.split("\r\n");
+    let status_line = lines.next().unwrap_or_default();
+
+    let mut parts = status_line.splitn(3, ' ');
+    let http_version = match parts.next() {
+        Some("HTTP/1.1") => http::Version::HTTP_11,
+        Some("HTTP/1.0") => http::Version::HTTP_10,
+        _ => Err(X8Error::Network(format!("Wrong status line: {}", status_line)))?,
+    };
+
+    let code = parts
+        .next()
+        .and_then(|x| x.parse().ok())
+        .ok_or_else(|| X8Error::Network(format!("Wrong status line: {}", status_line)))?;
+
+    let headers = lines
+        .filter_map(|x| x.split_once(':'))
+        .map(|(k, v)| (k.to_lowercase(), v.trim().to_string()))
+        .collect();
+
+    Ok((code, http_version, headers))
+}
+
+async fn read_more<S: AsyncRead + Unpin>(stream: &mut S, buf: &mut Vec<u8>) -> Result<usize, X8Error> {
+    let mut chunk = [0u8; 8192];
+    let n = stream.read(&mut chunk).await.map_err(network)?;
+    buf.extend_from_slice(&chunk[..n]);
+
+    Ok(n)
+}
+
+async fn read_exact<S: AsyncRead + Unpin>(stream: &mut S, mut buf: Vec<u8>, length: usize) -> Result<Vec<u8>, X8Error> {
+    while buf.len() < length {
+        if read_more(stream, &mut buf).await? == 0 {
+            Err(X8Error::Network("The connection was closed before the end of the body".to_string()))?
+        }
+    }
+
+    buf.truncate(length);
+
+    Ok(buf)
+}
+
+async fn read_to_end<S: AsyncRead + Unpin>(stream: &mut S, mut buf: Vec<u8>) -> Result<Vec<u8>, X8Error> {
+    while buf.len() <= MAX_PAGE_SIZE {
+        // servers often skip close_notify, so an error after some data is treated as the end
+        match read_more(stream, &mut buf).await {
+            Ok(0) => break,
+            Ok(_) => continue,
+            Err(_) if !buf.is_empty() => break,
+            Err(err) => Err(err)?,
+        }
+    }
+
+    Ok(buf)
+}
+
+/// decodes the chunked body. Extensions and trailers of the response are dropped
+pub(super) async fn read_chunked<S: AsyncRead + Unpin>(stream: &mut S, mut buf: Vec<u8>) -> Result<Vec<u8>, X8Error> {
+    let mut body = Vec::new();
+
+    loop {
+        let line_end = loop {
+            if let Some(pos) = buf.windows(2).position(|x| x == b"\r\n") {
+                break pos;
+            }
+
+            if read_more(stream, &mut buf).await? == 0 {
+                Err(X8Error::Network("The connection was closed within the chunked body".to_string()))?
+            }
+        };
+
+        let line = String::from_utf8_lossy(&buf[..line_end]).into_owned();
+        let size = usize::from_str_radix(line.split(';').next().unwrap_or_default().trim(), 16)
+            .map_err(|_| X8Error::Network(format!("Wrong chunk size: {}", line)))?;
+
+        buf.drain(..line_end + 2);
+
+        if size == 0 {
+            // the trailers end with an empty line
+            while !buf.starts_with(b"\r\n") && !buf.windows(4).any(|x| x == b"\r\n\r\n") {
+                if read_more(stream, &mut buf).await? == 0 {
+                    break;
+                }
+            }
+
+            return Ok(body);
+        }
+
+        while buf.len() < size + 2 {
+            if read_more(stream, &mut buf).await? == 0 {
+                Err(X8Error::Network("The connection was closed within the chunked body".to_string()))?
+            }
+        }
+
+        body.extend_from_slice(&buf[..size]);
+        buf.drain(..size + 2);
+
+        if body.len() > MAX_PAGE_SIZE {
+            return Ok(body);
+        }
+    }
+}
+
+/// the body in the chunked encoding: one data chunk with the extensions, the last chunk and the trailers
+pub fn chunked_body(body: &[u8], extensions: &str, trailers: &[(String, String)]) -> Vec<u8> {
+    let extensions = if extensions.is_empty() {
+        String::new()
+    } else {
+        format!(";{}", extensions)
+    };
+
+    let mut chunked = Vec::with_capacity(body.len() + 64);
+
+    if !body.is_empty() {
+        chunked.extend(format!("{:x}{}\r\n", body.len(), extensions).as_bytes());
+        chunked.extend(body);
+        chunked.extend(b"\r\n");
+        chunked.extend(b"0\r\n");
+    } else {
+        chunked.extend(format!("0{}\r\n", extensions).as_bytes());
+    }
+
+    for (k, v) in trailers {
+        chunked.extend(format!("{}: {}\r\n", k, v).as_bytes());
+    }
+
+    chunked.extend(b"\r\n");
+
+    chunked
+}
@@ -1,14 +1,23 @@
-use crate::{config::structs::Config, utils::random_line, VALUE_LENGTH, RANDOM_LENGTH};
+use crate::{config::structs::Config, error::X8Error, runner::{live::ExtraParams, shared::{HostState, HostTarget}, throttle::Throttle}, utils::{error, random_line}, VALUE_LENGTH, RANDOM_LENGTH};
+use bytes::Bytes;
 use itertools::Itertools;
 use lazy_static::lazy_static;
-use percent_encoding::utf8_percent_encode;
+use percent_encoding::{utf8_percent_encode, CONTROLS, NON_ALPHANUMERIC};
+use quick_xml::escape::escape;
+use rand::Rng;
 use regex::Regex;
 use reqwest::Client;
 use std::{
+    borrow::Cow,
     collections::HashMap,
     convert::TryFrom,
     error::Error,
     iter::FromIterator,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 use url::Url;
@@ -19,10 +28,25 @@ const HEADERS_MIDDLE: &str = "\x00@%=%@\x00";
 const HEADERS_JOINER: &str = "\x01@%&%@\x01";
 
 use super::{
+    challenge::{detect_challenge, Clearance},
+    charset,
+    cookies::CookieJar,
+    jwt::{extract_jwt, JwtLocation, JwtSettings},
+    keepalive::{is_connection_drop, KeepAlive},
+    protobuf::ProtobufSettings,
+    raw::{chunked_body, RawEngine},
     response::Response,
-    utils::{DataType, Headers, InjectionPlace, FRAGMENT, create_client, is_binary_content},
+    script::{Script, ScriptRequest, ScriptResponse},
+    utils::{
+        ActiveHours, DataType, DuplicatePosition, Headers, InjectionPlace, IpRange, FRAGMENT, create_resolved_client, create_target_client, is_binary_content, is_token_char, encode_url, malformed_reason,
+        resolve_addrs, run_pre_request_cmd,
+    },
+    watchdog::{Connections, Watchdog},
 };
 
+#[cfg(feature = "http3")]
+use super::quic::QuicEngine;
+
 #[derive(Debug, Clone, Default)]
 pub struct RequestDefaults {
     /// default request data
@@ -71,8 +95,114 @@ pub struct RequestDefaults {
 
     /// check body of responses with binary content type
     pub check_binary: bool,
+
+    /// server name to connect to instead of the host when --sni is used
+    pub sni: Option<String>,
+
+    /// clients bound to different addresses of the host in case --ip-failover is used
+    pub failover_clients: Vec<(IpAddr, Client)>,
+
+    /// the http version the clients were switched to after the negotiated one failed
+    pub http_version: Option<http::Version>,
+
+    /// index of the currently used failover client.
+    /// shared between clones so every request switches to the next address together
+    pub failover_index: Arc<AtomicUsize>,
+
+    /// parameter name -> how to serialize its value (json, array or string)
+    /// set from `name:json` like wordlist entries
+    pub serializations: HashMap<String, String>,
+
+    /// send parameters as protobuf fields instead of the body template
+    pub protobuf: Option<ProtobufSettings>,
+
+    /// send parameters as claims of the token in case --jwt is used
+    pub jwt: Option<JwtSettings>,
+
+    /// user hooks from --script
+    pub script: Option<Script>,
+
+    /// the command that returns headers to add to every request
+    pub pre_request_cmd: Option<String>,
+
+    /// shared with other runners of the same host in case --shared-state is used
+    pub host_state: Option<Arc<HostState>>,
+
+    /// request slots of the host in case --max-host-concurrency is used
+    pub host_slots: Option<HostTarget>,
+
+    /// cancels hanging requests in case --stall-timeout is used. Shared between all the runners
+    pub watchdog: Option<Arc<Watchdog>>,
+
+    /// the delay, concurrency and rate limits from --control-file. Shared between all the runners
+    pub throttle: Option<Arc<Throttle>>,
+
+    /// regions of the page where reflections aren't counted
+    pub reflection_exclusions: Vec<Regex>,
+
+    /// the command that solves anti-bot challenges
+    pub challenge_cmd: Option<String>,
+
+    /// headers from solved anti-bot challenges
+    pub clearance: Arc<Clearance>,
+
+    /// User-Agent values to rotate in case --random-agent or --agents-file is used
+    pub user_agents: Vec<String>,
+
+    /// random addresses to send as the client's one
+    pub rotate_ip: Option<IpRange>,
+
+    /// requests are paused outside of the window
+    pub active_hours: Option<ActiveHours>,
+
+    /// only the first agent is sent until the page is learned, so the baseline stays comparable.
+    /// Shared between clones
+    pub agent_pinned: Arc<AtomicBool>,
+
+    /// closes connections before the server drops them. Shared between clones
+    pub keep_alive: Arc<KeepAlive>,
+
+    /// cookies of the target in case --cookie-jar is used. Shared between clones and clients
+    pub cookie_jar: Option<Arc<CookieJar>>,
+
+    /// send the second half of the parameters within the query in case the backend merges query and body parameters
+    pub query_split: bool,
+
+    /// names from the --extra-params-watch file. Shared between runners
+    pub extra_params: Option<Arc<ExtraParams>>,
+
+    /// send requests that can be parsed differently by servers on the way, e.g. with both Content-Length and Transfer-Encoding
+    pub allow_malformed: bool,
+
+    /// the min body size to add Expect: 100-continue to. None unless --expect-continue is used
+    pub expect_continue: Option<usize>,
+
+    /// the server responded with 417 to the header. Shared between clones
+    pub expect_rejected: Arc<AtomicBool>,
+
+    /// the delimiter between query parameters in case --query-separator is used
+    pub query_separator: Option<String>,
+
+    /// the known parameter that is duplicated for every parameter and where to place the duplicate
+    pub duplicate_names: Option<(DuplicatePosition, (String, String))>,
+
+    /// sends the requests byte by byte instead of reqwest. Used with --engine raw, trailers and chunk extensions
+    pub raw: Option<Arc<RawEngine>>,
+
+    /// sends the requests over QUIC with --http 3
+    #[cfg(feature = "http3")]
+    pub quic: Option<Arc<QuicEngine>>,
+
+    /// the name of the query parameter with a unique value per request. Set when the target is behind a CDN
+    pub cachebuster: Option<String>,
+
+    /// lines that differ only in digits are considered equal while comparing responses
+    pub ignore_numbers: bool,
 }
 
+/// the code, the http version, the headers, the body and the time the headers took
+type Sent = (u16, Option<http::Version>, Vec<(String, String)>, Bytes, Duration);
+
 #[derive(Debug, Clone)]
 pub struct Request<'a> {
     pub defaults: &'a RequestDefaults,
@@ -95,6 +225,15 @@ pub struct Request<'a> {
     /// we can't use defaults.path because there can be {{random}} variable that need to be replaced
     pub path: String,
 
+    /// fields sent after the last chunk in case the injection place is Trailers
+    pub trailers: Vec<(String, String)>,
+
+    /// sent after the size of the chunk in case the injection place is ChunkExtensions, e.g. name=value;name2=value2
+    pub chunk_extensions: String,
+
+    /// the value of the cachebuster parameter. It's removed from the response so it doesn't cause diffs
+    pub cachebuster: String,
+
     /// whether the request was prepared
     /// {{random}} things replaced, prepared_parameters filled
     pub prepared: bool,
@@ -110,6 +249,9 @@ impl<'a> Request<'a> {
             parameters,
             prepared_parameters: Vec::new(), //l.parameters.clone(),
             non_random_parameters: Vec::new(),
+            trailers: Vec::new(),
+            chunk_extensions: String::new(),
+            cachebuster: String::new(),
             prepared: false,
         }
     }
@@ -132,45 +274,102 @@ impl<'a> Request<'a> {
     pub fn url(&self) -> String {
         format!(
             "{}://{}:{}{}",
-            &self.defaults.scheme, &self.defaults.host, &self.defaults.port, &self.path
+            &self.defaults.scheme,
+            self.defaults.sni.as_ref().unwrap_or(&self.defaults.host),
+            &self.defaults.port,
+            &self.path
         )
     }
 
+    /// every parameter paired with a duplicate of the known one in case --duplicate-names is used
+    fn paired<'b>(&'b self, parameters: &'b [(String, String)]) -> Vec<(&'b str, &'b str)> {
+        parameters
+            .iter()
+            .flat_map(|(k, v)| match self.defaults.duplicate_names.as_ref() {
+                Some((DuplicatePosition::Before, (dk, dv))) => vec![(dk.as_str(), dv.as_str()), (k.as_str(), v.as_str())],
+                Some((DuplicatePosition::After, (dk, dv))) => vec![(k.as_str(), v.as_str()), (dk.as_str(), dv.as_str())],
+                None => vec![(k.as_str(), v.as_str())],
+            })
+            .collect()
+    }
+
     pub fn make_query(&self) -> String {
         lazy_static! {
             static ref RE_JSON_WORDS_WITHOUT_QUOTES: Regex =
                 Regex::new(r#"^([1-9]\d*|null|false|true)$"#).unwrap();
         }
 
+        // serialization overrides don't make sense for headers
+        let serializations = if self.defaults.injection_place == InjectionPlace::Path
+            || self.defaults.injection_place == InjectionPlace::Body
+        {
+            Some(&self.defaults.serializations)
+        } else {
+            None
+        };
+
+        let pairs: Vec<(&str, &str)> = self
+            .paired(&self.prepared_parameters)
+            .into_iter()
+            .chain(self.defaults.parameters.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+            .collect();
+
         let query = if self.defaults.is_json {
-            self.prepared_parameters
+            pairs
                 .iter()
-                .chain(self.defaults.parameters.iter())
                 // not very optimal because we know that there's a lot of random parameters
                 // that doesn't need to be checked
-                .map(|(k, v)| {
-                    if RE_JSON_WORDS_WITHOUT_QUOTES.is_match(v) {
-                        self.defaults.template.replace("%k", k).replace("%v", v)
-                    } else {
-                        self.defaults
-                            .template
-                            .replace("%k", k)
-                            .replace("%v", &format!("\"{}\"", v))
-                    }
+                .map(|&(k, v)| {
+                    // serde_json escapes quotes and backslashes within the values
+                    let v = match serializations.and_then(|x| x.get(k)).map(|x| x.as_str()) {
+                        Some("json") => serde_json::json!({ "x8": v }).to_string(),
+                        Some("array") => serde_json::json!([v]).to_string(),
+                        Some("string") => serde_json::Value::from(v).to_string(),
+                        _ => if RE_JSON_WORDS_WITHOUT_QUOTES.is_match(v) {
+                            v.to_string()
+                        } else {
+                            serde_json::Value::from(v).to_string()
+                        },
+                    };
+
+                    self.defaults.template.replace("%k", k).replace("%v", &v)
                 })
                 .collect::<Vec<String>>()
                 .join(&self.defaults.joiner)
         } else {
-            self.prepared_parameters
+            pairs
                 .iter()
-                .chain(self.defaults.parameters.iter())
-                .map(|(k, v)| self.defaults.template.replace("%k", k).replace("%v", v))
+                .map(|&(k, v)| {
+                    // user[x8]=value and user[]=value
+                    let k = match serializations.and_then(|x| x.get(k)).map(|x| x.as_str()) {
+                        Some("json") => format!("{}[x8]", k),
+                        Some("array") => format!("{}[]", k),
+                        _ => k.to_string(),
+                    };
+
+                    // values within xml elements like SOAP ones
+                    let v = if self.defaults.template.starts_with('<') {
+                        escape(v)
+                    } else {
+                        Cow::Borrowed(v)
+                    };
+
+                    self.defaults.template.replace("%k", &k).replace("%v", &v)
+                })
                 .collect::<Vec<String>>()
                 .join(&self.defaults.joiner)
         };
 
+        // claims are placed within the payload of the re-encoded token
+        if let Some(jwt) = self.defaults.jwt.as_ref() {
+            return jwt.token(&query);
+        }
+
         if self.defaults.encode {
             utf8_percent_encode(&query, &FRAGMENT).to_string()
+        // non-ASCII names are sent within the request line percent-encoded
+        } else if self.defaults.injection_place == InjectionPlace::Path {
+            utf8_percent_encode(&query, CONTROLS).to_string()
         } else {
             query
         }
@@ -181,8 +380,9 @@ impl<'a> Request<'a> {
     /// additional param is for reflection counting TODO REMOVE
     ///
     /// in case self.parameters contains parameter with "="
-    /// it gets splitted by =  and the default random value gets replaced with the right part:
-    /// admin=true -> (admin, true) vs admin -> (admin, df32w)
+    /// it gets splitted by the first = and the default random value gets replaced with the right part:
+    /// admin=true -> (admin, true) vs admin -> (admin, df32w).
+    /// The value may contain = as well, e.g. canary urls: next=https://example.com/?a=b
     pub fn prepare(&mut self) {
         if self.prepared {
             return;
@@ -192,14 +392,8 @@ impl<'a> Request<'a> {
         self.non_random_parameters = Vec::from_iter(
             self.parameters
                 .iter()
-                .filter(|x| x.contains('='))
-                .map(|x| x.split('='))
-                .map(|mut x| {
-                    (
-                        x.next().unwrap().to_owned(),
-                        x.next().unwrap_or("").to_owned(),
-                    )
-                }),
+                .filter_map(|x| x.split_once('='))
+                .map(|(k, v)| (k.to_owned(), v.to_owned())),
         );
 
         self.prepared_parameters = Vec::from_iter(
@@ -227,13 +421,36 @@ impl<'a> Request<'a> {
                 self.set_header(k, &v.replace("{{random}}", &random_line(RANDOM_LENGTH)));
             }
         }
+        // the url contains the sni name so the original host should be sent within the Host header
+        if self.defaults.sni.is_some() && !self.defaults.custom_headers.contains_key("Host") {
+            self.set_header("Host", self.defaults.host.as_str());
+        }
+
         self.path = self.path.replace("{{random}}", &random_line(RANDOM_LENGTH));
         self.body = self.body.replace("{{random}}", &random_line(RANDOM_LENGTH));
 
         match self.defaults.injection_place {
             InjectionPlace::Path => self.path = self.path.replace("%s", &self.make_query()),
-            InjectionPlace::Body => {
+            InjectionPlace::Body if self.defaults.query_split && self.defaults.protobuf.is_none() && self.defaults.jwt.is_none() => {
+                // prepared_parameters stay complete because reflections are counted for all of them
+                let query_part = self.prepared_parameters.split_off(self.prepared_parameters.len() / 2);
                 self.body = self.body.replace("%s", &self.make_query());
+                self.prepared_parameters.extend(query_part.iter().cloned());
+
+                let separator = self.defaults.query_separator.as_deref().unwrap_or("&");
+                let query = self
+                    .paired(&query_part)
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect::<Vec<String>>()
+                    .join(separator);
+                let query = utf8_percent_encode(&query, CONTROLS).to_string();
+
+                self.path = if self.path.contains('?') {
+                    format!("{}{}{}", self.path, separator, query)
+                } else {
+                    format!("{}?{}", self.path, query)
+                };
 
                 if !self.defaults.custom_headers.contains_key("Content-Type") {
                     if self.defaults.is_json {
@@ -243,6 +460,29 @@ impl<'a> Request<'a> {
                     }
                 }
             }
+            InjectionPlace::Body => {
+                self.body = self.body.replace("%s", &self.make_query());
+
+                if let Some(protobuf) = self.defaults.protobuf.as_ref() {
+                    if !self.defaults.custom_headers.contains_key("Content-Type") {
+                        if protobuf.grpc_web {
+                            self.set_header("Content-Type", "application/grpc-web+proto");
+                        } else {
+                            self.set_header("Content-Type", "application/x-protobuf");
+                        }
+                    }
+
+                    if protobuf.grpc_web && !self.defaults.custom_headers.contains_key("X-Grpc-Web") {
+                        self.set_header("X-Grpc-Web", "1");
+                    }
+                } else if !self.defaults.custom_headers.contains_key("Content-Type") {
+                    if self.defaults.is_json {
+                        self.set_header("Content-Type", "application/json");
+                    } else {
+                        self.set_header("Content-Type", "application/x-www-form-urlencoded");
+                    }
+                }
+            }
             InjectionPlace::HeaderValue => {
                 // in case someone searches headers while sending a valid body - it's usually important to set Content-Type header as well.
                 if !self.defaults.custom_headers.contains_key("Content-Type") && self.defaults.method != "GET" && self.defaults.method != "HEAD" && !self.body.is_empty() {
@@ -281,12 +521,90 @@ impl<'a> Request<'a> {
 
                 self.set_headers(headers);
             }
+            InjectionPlace::Trailers | InjectionPlace::ChunkExtensions => {
+                if !self.defaults.custom_headers.contains_key("Content-Type") && !self.body.is_empty() {
+                    if self.body.starts_with('{') {
+                        self.set_header("Content-Type", "application/json");
+                    } else {
+                        self.set_header("Content-Type", "application/x-www-form-urlencoded");
+                    }
+                }
+
+                if !self.defaults.custom_headers.contains_key("Transfer-Encoding") {
+                    self.set_header("Transfer-Encoding", "chunked");
+                }
+
+                if self.defaults.injection_place == InjectionPlace::Trailers {
+                    self.trailers = self
+                        .make_query()
+                        .split(&self.defaults.joiner)
+                        .filter(|x| !x.is_empty())
+                        .map(|x| x.split(HEADERS_MIDDLE))
+                        .map(|mut x| (x.next().unwrap().to_owned(), x.next().unwrap().to_owned()))
+                        .collect();
+                } else {
+                    self.chunk_extensions = self.make_query();
+                }
+            }
+        }
+
+        // cached responses wouldn't reflect the changes caused by parameters
+        if let Some(name) = self.defaults.cachebuster.as_ref() {
+            self.cachebuster = random_line(VALUE_LENGTH);
+
+            let separator = self.defaults.query_separator.as_deref().unwrap_or("&");
+            self.path = if self.path.ends_with('?') {
+                format!("{}{}={}", self.path, name, self.cachebuster)
+            } else if self.path.contains('?') {
+                format!("{}{}{}={}", self.path, separator, name, self.cachebuster)
+            } else {
+                format!("{}?{}={}", self.path, name, self.cachebuster)
+            };
         }
     }
 
-    pub async fn send_by(self, clients: &Client) -> Result<Response<'a>, Box<dyn Error>> {
+    /// the first agent while learning and a random one afterwards
+    fn user_agent(&self) -> Option<String> {
+        let agents = &self.defaults.user_agents;
+
+        if agents.is_empty() {
+            None
+        } else if self.defaults.agent_pinned.load(Ordering::SeqCst) {
+            Some(agents[0].to_owned())
+        } else {
+            Some(agents[rand::thread_rng().gen_range(0, agents.len())].to_owned())
+        }
+    }
+
+    pub async fn send_by(self, clients: &Client) -> Result<Response<'a>, X8Error> {
+        let generation = self.defaults.clearance.generation();
+
         match self.clone().request(clients).await {
+            // the header was removed after the rejection
+            Ok(val) if val.code == 417 && self.defaults.expect_rejected.load(Ordering::SeqCst) => {
+                self.clone().request(clients).await
+            }
             Ok(val) => Ok(val),
+            // script errors won't disappear after a retry
+            Err(err @ X8Error::Config(_)) => Err(err),
+            // the server closed the keep-alive connection, a new one is opened right away
+            Err(X8Error::ConnectionClosed(_)) => match self.clone().request(clients).await {
+                Ok(val) => Ok(val),
+                Err(_) => {
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    Ok(self.clone().request(clients).await?)
+                }
+            },
+            // the request is resent once with the clearance headers
+            Err(X8Error::Challenge(kind)) => {
+                self.defaults
+                    .clearance
+                    .solve(generation, self.defaults.challenge_cmd.as_deref(), &self.url(), &kind)
+                    .await
+                    .map_err(X8Error::Challenge)?;
+
+                self.clone().request(clients).await
+            }
             Err(_) => {
                 tokio::time::sleep(Duration::from_secs(10)).await;
                 Ok(self.clone().request(clients).await?)
@@ -294,24 +612,68 @@ impl<'a> Request<'a> {
         }
     }
 
-    // we need to somehow impl Send and Sync for error (for using send() within async recursive func)
-    // therefore we are wrapping the original call to send()
-    // not a good way tho, maybe someone can suggest a better one
-    pub async fn wrapped_send(self) -> Result<Response<'a>, Box<dyn Error + Send + Sync>> {
-        match self.send().await {
-            Err(err) => Err(err.to_string().into()),
-            Ok(val) => Ok(val),
+    /// sends the prepared request with the raw engine or reqwest
+    async fn transmit(&self, client: &Client, body: Vec<u8>) -> Result<Sent, X8Error> {
+        let start = Instant::now();
+
+        match self.defaults.raw.as_ref() {
+            Some(raw) => {
+                let connect_host = self.defaults.sni.as_ref().unwrap_or(&self.defaults.host);
+
+                // the address of the --ip-failover client, if it's one of them
+                let ip = self.defaults.failover_clients.iter().find(|x| std::ptr::eq(&x.1, client)).map(|x| x.0);
+
+                let res = raw
+                    .send(
+                        connect_host,
+                        self.defaults.port,
+                        ip,
+                        &self.raw_message(&body),
+                        self.defaults.method == "HEAD",
+                    )
+                    .await?;
+
+                Ok((res.code, Some(res.http_version), res.headers, res.body, start.elapsed()))
+            }
+            None => self.send_quic(client, body, start).await,
         }
     }
 
-    pub async fn send(self) -> Result<Response<'a>, Box<dyn Error>> {
-        let dc = &self.defaults.client;
-        self.send_by(dc).await
+    /// sends the prepared request over QUIC, or through reqwest in case the host doesn't accept it
+    #[cfg(feature = "http3")]
+    async fn send_quic(&self, client: &Client, body: Vec<u8>, start: Instant) -> Result<Sent, X8Error> {
+        let quic = match self.defaults.quic.as_ref() {
+            Some(quic) if !quic.fallen_back() => quic,
+            _ => return self.send_reqwest(client, body, start).await,
+        };
+
+        let connect_host = self.defaults.sni.as_ref().unwrap_or(&self.defaults.host);
+
+        match quic
+            .send(connect_host, self.defaults.port, &self.defaults.method, &self.url(), &self.headers, body.clone())
+            .await?
+        {
+            Some(res) => Ok((res.code, Some(res.http_version), res.headers, res.body, start.elapsed())),
+            None => {
+                error(
+                    "The host doesn't accept QUIC. Scanning over HTTP/2 or HTTP/1.1",
+                    Some(&self.defaults.host),
+                    None,
+                    None,
+                );
+
+                self.send_reqwest(client, body, Instant::now()).await
+            }
+        }
     }
 
-    async fn request(mut self, client: &Client) -> Result<Response<'a>, reqwest::Error> {
-        self.prepare();
+    #[cfg(not(feature = "http3"))]
+    async fn send_quic(&self, client: &Client, body: Vec<u8>, start: Instant) -> Result<Sent, X8Error> {
+        self.send_reqwest(client, body, start).await
+    }
 
+    /// sends the prepared request through reqwest
+    async fn send_reqwest(&self, client: &Client, body: Vec<u8>, start: Instant) -> Result<Sent, X8Error> {
         let mut request = http::Request::builder()
             .method(self.defaults.method.as_str())
             .uri(self.url());
@@ -320,15 +682,29 @@ impl<'a> Request<'a> {
             request = request.header(k, v)
         }
 
-        let request = request.body(self.body.to_owned()).unwrap();
+        let reqwest_req = reqwest::Request::try_from(request.body(body).unwrap()).unwrap();
 
-        tokio::time::sleep(self.defaults.delay).await;
+        // held until the body is read, the connection is busy until then
+        let _in_flight = self.defaults.keep_alive.start();
 
-        let reqwest_req = reqwest::Request::try_from(request).unwrap();
+        let res = match client.execute(reqwest_req).await {
+            Ok(val) => val,
+            Err(err) if is_connection_drop(&err) => {
+                if let Some(limit) = self.defaults.keep_alive.record_drop() {
+                    error(
+                        format!("The server closes connections after ~{} requests. Recycling them in advance", limit + 1),
+                        Some(&self.defaults.host),
+                        None,
+                        None,
+                    );
+                }
 
-        let start = Instant::now();
+                Err(X8Error::ConnectionClosed(err.to_string()))?
+            }
+            Err(err) => Err(err)?,
+        };
 
-        let res = client.execute(reqwest_req).await?;
+        self.defaults.keep_alive.record_sent(res.version());
 
         let duration = start.elapsed();
 
@@ -352,14 +728,303 @@ impl<'a> Request<'a> {
         let code = res.status().as_u16();
         let http_version = Some(res.version());
 
-        let body_bytes = res.bytes().await?.to_vec();
+        // kept as is without copying into a vector
+        let body = res.bytes().await?;
+
+        Ok((code, http_version, headers, body, duration))
+    }
+
+    /// the whole HTTP/1.1 message for the raw engine
+    fn raw_message(&self, body: &[u8]) -> Vec<u8> {
+        let mut message = format!("{} {} HTTP/1.1\r\n", self.defaults.method, self.path);
+
+        if !self.headers.iter().any(|x| x.0.eq_ignore_ascii_case("host")) {
+            match (self.defaults.scheme.as_str(), self.defaults.port) {
+                ("http", 80) | ("https", 443) => message += &format!("Host: {}\r\n", self.defaults.host),
+                (_, port) => message += &format!("Host: {}:{}\r\n", self.defaults.host, port),
+            }
+        }
+
+        // values that start with whitespace were given so on purpose, e.g. -H 'Name:\tvalue' with --engine raw
+        for (k, v) in &self.headers {
+            if v.starts_with(|x: char| x == ' ' || x == '\t') {
+                message += &format!("{}:{}\r\n", k, v);
+            } else {
+                message += &format!("{}: {}\r\n", k, v);
+            }
+        }
+
+        let chunked = self.defaults.injection_place == InjectionPlace::Trailers
+            || self.defaults.injection_place == InjectionPlace::ChunkExtensions;
+
+        if !chunked && !body.is_empty() && !self.headers.iter().any(|x| x.0.eq_ignore_ascii_case("content-length")) {
+            message += &format!("Content-Length: {}\r\n", body.len());
+        }
+
+        message += "\r\n";
 
-        let text = if is_binary_content(headers.get_value_case_insensitive("content-type")) && !self.defaults.check_binary {
+        let mut message = message.into_bytes();
+
+        if chunked {
+            message.extend(chunked_body(body, &self.chunk_extensions, &self.trailers));
+        } else {
+            message.extend(body);
+        }
+
+        message
+    }
+
+    // X8Error is Send and Sync so it can be used within async recursive funcs as is
+    // left for compatibility
+    pub async fn wrapped_send(self) -> Result<Response<'a>, X8Error> {
+        self.send().await
+    }
+
+    pub async fn send(self) -> Result<Response<'a>, X8Error> {
+        if self.defaults.failover_clients.is_empty() {
+            let dc = &self.defaults.client;
+            return self.send_by(dc).await;
+        }
+
+        self.send_with_failover().await
+    }
+
+    /// sticks to one address until it starts erroring and only then switches to the next one
+    /// so the responses are coming from the same backend as long as possible
+    async fn send_with_failover(self) -> Result<Response<'a>, X8Error> {
+        let clients = &self.defaults.failover_clients;
+        let mut last_err = None;
+
+        for _ in 0..clients.len() {
+            let index = self.defaults.failover_index.load(Ordering::SeqCst);
+
+            match self.clone().send_by(&clients[index].1).await {
+                Ok(val) => return Ok(val),
+                Err(err) => {
+                    let next = (index + 1) % clients.len();
+
+                    // other requests may have already switched the address
+                    if self
+                        .defaults
+                        .failover_index
+                        .compare_exchange(index, next, Ordering::SeqCst, Ordering::SeqCst)
+                        .is_ok()
+                    {
+                        error(
+                            format!(
+                                "{} is failing ({}). Switching to {}",
+                                clients[index].0, err, clients[next].0
+                            ),
+                            Some(&self.defaults.host),
+                            None,
+                            None,
+                        );
+                    }
+
+                    last_err = Some(err);
+                }
+            }
+        }
+
+        Err(last_err.unwrap())
+    }
+
+    async fn request(mut self, client: &Client) -> Result<Response<'a>, X8Error> {
+        self.prepare();
+
+        // the User-Agent is kept as is when parameters are injected into it or it's one of the checked headers
+        let injected_agent = match self.defaults.injection_place {
+            InjectionPlace::HeaderValue => self
+                .defaults
+                .custom_headers
+                .iter()
+                .any(|(k, v)| k.eq_ignore_ascii_case("user-agent") && v.contains("%s")),
+            InjectionPlace::Headers => self
+                .prepared_parameters
+                .iter()
+                .any(|(k, _)| k.eq_ignore_ascii_case("user-agent")),
+            _ => false,
+        };
+
+        if let Some(agent) = self.user_agent().filter(|_| !injected_agent) {
+            self.headers.retain(|x| !x.0.eq_ignore_ascii_case("user-agent"));
+            self.set_header("User-Agent".to_string(), agent);
+        }
+
+        // backends that trust proxy headers apply rate limits per the sent address.
+        // Headers that are already set (e.g. checked ones) are kept
+        if let Some(range) = self.defaults.rotate_ip {
+            let ip = range.random().to_string();
+
+            for name in ["X-Forwarded-For", "X-Real-IP"] {
+                if !self.headers.iter().any(|x| x.0.eq_ignore_ascii_case(name)) {
+                    self.set_header(name.to_string(), ip.clone());
+                }
+            }
+        }
+
+        // user hooks can sign the request or fix checksums
+        if let Some(script) = self.defaults.script.as_ref() {
+            let mut script_request = ScriptRequest {
+                method: self.defaults.method.clone(),
+                url: self.url(),
+                path: self.path.clone(),
+                headers: self.headers.clone(),
+                body: self.body.clone(),
+            };
+
+            script.on_request(&mut script_request).map_err(X8Error::Config)?;
+
+            self.path = script_request.path;
+            self.headers = script_request.headers;
+            self.body = script_request.body;
+        }
+
+        // e.g. the cookie from the solved challenge
+        for (k, v) in self.defaults.clearance.headers() {
+            self.headers.retain(|x| !x.0.eq_ignore_ascii_case(&k));
+            self.set_header(k, v);
+        }
+
+        // the connection is closed right before the server would drop it
+        if self.defaults.keep_alive.should_close() && !self.headers.iter().any(|x| x.0.eq_ignore_ascii_case("connection")) {
+            self.set_header("Connection", "close");
+        }
+
+        let body = match self.defaults.protobuf.as_ref() {
+            Some(protobuf) => protobuf.encode(self.prepared_parameters.iter().chain(self.defaults.parameters.iter())),
+            None => self.body.as_bytes().to_vec(),
+        };
+
+        // picky servers (IIS, some proxies) stall or reject large bodies that weren't announced.
+        // Servers that answer with 417 don't get the header anymore
+        if self.defaults.expect_rejected.load(Ordering::SeqCst) {
+            self.headers.retain(|x| !x.0.eq_ignore_ascii_case("expect"));
+        } else if let Some(min_size) = self.defaults.expect_continue {
+            if body.len() >= min_size && !self.headers.iter().any(|x| x.0.eq_ignore_ascii_case("expect")) {
+                self.set_header("Expect", "100-continue");
+            }
+        }
+
+        // e.g. an external tool adds HMAC signatures.
+        // It runs last, so the signature covers the headers that are sent
+        if let Some(cmd) = self.defaults.pre_request_cmd.as_ref() {
+            let headers = run_pre_request_cmd(cmd, &self.print_sent()).await.map_err(X8Error::Config)?;
+
+            for (k, v) in headers {
+                self.headers.retain(|x| !x.0.eq_ignore_ascii_case(&k));
+                self.set_header(k, v);
+            }
+        }
+
+        // a frontend and a backend may disagree on such requests, so they could affect other users' requests
+        if !self.defaults.allow_malformed {
+            if let Some(reason) = malformed_reason(&self.path, &self.headers, &self.trailers, &self.chunk_extensions) {
+                Err(X8Error::Config(format!(
+                    "Refusing to send a malformed request ({}). Use --allow-malformed to send it anyway",
+                    reason
+                )))?
+            }
+        }
+
+        if let Some(host_state) = self.defaults.host_state.as_ref() {
+            host_state.wait().await;
+        }
+
+        if let Some(active_hours) = self.defaults.active_hours.as_ref() {
+            active_hours.wait().await;
+        }
+
+        // the slot is held until the body is read
+        let _slot = match self.defaults.host_slots.as_ref() {
+            Some(slots) => Some(slots.acquire().await),
+            None => None,
+        };
+
+        // the permit is taken last and held until the body is read,
+        // so the requests to paused or busy hosts don't keep it from the other hosts
+        let _throttle = match self.defaults.throttle.as_ref() {
+            Some(throttle) => Some(throttle.acquire().await),
+            None => {
+                tokio::time::sleep(self.defaults.delay).await;
+                None
+            }
+        };
+
+        let (mut code, http_version, mut headers, body, duration) = match self.defaults.watchdog.as_ref() {
+            Some(watchdog) => {
+                let connections = Connections {
+                    keep_alive: Arc::clone(&self.defaults.keep_alive),
+                    host_slots: self.defaults.host_slots.clone(),
+                };
+
+                watchdog
+                    .guard(format!("{} {}", self.defaults.method, self.url()), connections, || self.transmit(client, body.clone()))
+                    .await?
+            }
+            None => self.transmit(client, body).await?,
+        };
+
+        if code == 417
+            && self.headers.iter().any(|x| x.0.eq_ignore_ascii_case("expect"))
+            && !self.defaults.expect_rejected.swap(true, Ordering::SeqCst)
+        {
+            error(
+                "The server rejects Expect: 100-continue. Sending the bodies without it",
+                Some(&self.defaults.host),
+                None,
+                None,
+            );
+        }
+
+        // every runner of the host slows down
+        if code == 429 {
+            if let Some(host_state) = self.defaults.host_state.as_ref() {
+                let retry_after = headers
+                    .get_value_case_insensitive("retry-after")
+                    .and_then(|x| x.trim().parse().ok())
+                    .unwrap_or(10);
+
+                host_state.pause(Duration::from_secs(retry_after));
+            }
+        }
+
+        // protobuf responses are compared in the decoded form
+        let decoded = self.defaults.protobuf.as_ref().and_then(|x| x.decode(&body));
+
+        let mut text = if let Some(decoded) = decoded {
+            decoded
+        } else if is_binary_content(headers.get_value_case_insensitive("content-type")) && !self.defaults.check_binary {
             String::new()
         } else {
-            String::from_utf8_lossy(&body_bytes).to_string()
+            // non utf-8 pages are transcoded, otherwise the reflections can't be found
+            charset::decode(&headers, &body)
         };
 
+        // e.g. canonical links and redirects contain the whole url
+        if !self.cachebuster.is_empty() {
+            text = text.replace(&self.cachebuster, "");
+
+            for (_, v) in headers.iter_mut() {
+                *v = v.replace(&self.cachebuster, "");
+            }
+        }
+
+        if let Some(kind) = detect_challenge(code, &headers, &text) {
+            Err(X8Error::Challenge(kind.to_string()))?
+        }
+
+        // e.g. to remove dynamic parts that aren't caught automatically
+        if let Some(script) = self.defaults.script.as_ref() {
+            let mut script_response = ScriptResponse { code, headers, body: text };
+
+            script.on_response(&mut script_response).map_err(X8Error::Config)?;
+
+            code = script_response.code;
+            headers = script_response.headers;
+            text = script_response.body;
+        }
+
         let mut response = Response {
             code,
             headers,
@@ -413,7 +1078,12 @@ impl<'a> Request<'a> {
             }
         }
 
-        str_req += &format!("\n{}", self.body);
+        if self.trailers.is_empty() && self.chunk_extensions.is_empty() {
+            str_req += &format!("\n{}", self.body);
+        } else {
+            let body = chunked_body(self.body.as_bytes(), &self.chunk_extensions, &self.trailers);
+            str_req += &format!("\n{}", String::from_utf8_lossy(&body));
+        }
 
         str_req
     }
@@ -425,22 +1095,218 @@ impl<'a> RequestDefaults {
         method: S,
         url: S,
     ) -> Result<Self, Box<dyn Error>> {
-        Self::new(
+        let url = encode_url(&url.into())?;
+        let parsed_url = Url::parse(&url)?;
+        let host = parsed_url.host_str().ok_or("Host missing")?;
+
+        // the name within the url. Differs from the host only when --sni is used
+        let connect_host = match config.sni.as_ref() {
+            Some(sni) if parsed_url.scheme() == "https" => sni.as_str(),
+            _ => host,
+        };
+
+        let cookie_jar = if config.cookie_jar { Some(Arc::new(CookieJar::default())) } else { None };
+
+        let (client, failover_clients) = Self::clients(config, &parsed_url, connect_host, cookie_jar.as_ref())?;
+
+        // place the injection point within the value of the existing parameter
+        let (mut url, mut body) = match config.inject_into.as_ref() {
+            Some(name) => inject_into_value(
+                &url,
+                &config.body,
+                name,
+                config.joiner.as_deref().unwrap_or(";"),
+                config.query_separator.as_deref().unwrap_or("&"),
+            )?,
+            None => (url, config.body.clone()),
+        };
+
+        // the payload of the token becomes the injection point
+        let mut custom_headers = config.custom_headers.clone();
+        let jwt = if config.jwt {
+            Some(extract_jwt(&mut url, &mut body, &mut custom_headers)?)
+        } else {
+            None
+        };
+
+        // the separator joins the query and urlencoded bodies, other formats keep their own joiners
+        let urlencoded = !config.headers_discovery
+            && config.chunked.is_none()
+            && !config.protobuf
+            && jwt.is_none()
+            && config.data_type != Some(DataType::Json)
+            && config.data_type != Some(DataType::ProbablyJson)
+            && !body.trim_start().starts_with('{');
+
+        let joiner = match config.query_separator.as_ref() {
+            Some(separator) if config.joiner.is_none() && urlencoded => Some(separator.clone()),
+            _ => config.joiner.clone(),
+        };
+
+        let mut request_defaults = Self::new(
             method.into().as_str(), //method needs to be set explicitly via .set_method()
-            url.into().as_str(),    //as well as url
-            config.custom_headers.clone(),
+            url.as_str(),           //as well as url
+            custom_headers,
             config.delay,
-            create_client(config, false)?,
+            client,
             config.template.clone(),
-            config.joiner.clone(),
+            joiner,
             config.encode,
             config.data_type.clone(),
             config.invert,
-            config.headers_discovery,
-            &config.body,
+            // tokens within headers are injected the same way as header values.
+            // Trailers and chunk extensions keep the path and the body as they are, the same as headers
+            config.headers_discovery
+                || jwt.as_ref().map_or(false, |x| x.1 == JwtLocation::Header)
+                || config.chunked.is_some(),
+            &body,
             config.disable_custom_parameters,
             config.check_binary
-        )
+        )?;
+
+        if request_defaults.scheme == "https" {
+            request_defaults.sni = config.sni.clone();
+        }
+
+        // the framing of the chunked body is out of reqwest's control
+        if config.raw_engine || config.chunked.is_some() {
+            request_defaults.raw = Some(Arc::new(RawEngine::new(config, &request_defaults.scheme, request_defaults.port)?));
+        }
+
+        // QUIC is always encrypted, plain http targets are scanned over h1.1
+        #[cfg(feature = "http3")]
+        if config.http3 && request_defaults.scheme == "https" {
+            request_defaults.quic = Some(Arc::new(QuicEngine::new(config, request_defaults.port)?));
+        }
+
+        if let Some(place) = config.chunked {
+            let (template, joiner) = match place {
+                InjectionPlace::Trailers => (HEADERS_TEMPLATE, HEADERS_JOINER),
+                _ => ("%k=%v", ";"),
+            };
+
+            request_defaults.injection_place = place;
+            request_defaults.template = config.template.clone().unwrap_or_else(|| template.to_string());
+            request_defaults.joiner = config.joiner.clone().unwrap_or_else(|| joiner.to_string());
+        }
+
+        if let Some((token, location)) = jwt {
+            match (location, request_defaults.injection_place) {
+                (JwtLocation::Header, InjectionPlace::HeaderValue)
+                | (JwtLocation::Url, InjectionPlace::Path)
+                | (JwtLocation::Body, InjectionPlace::Body) => (),
+                _ => Err("The token isn't within the place parameters are sent to. Try --invert")?,
+            }
+
+            // claims are the part of the json payload
+            request_defaults.template = "\"%k\":%v".to_string();
+            request_defaults.joiner = ",".to_string();
+            request_defaults.is_json = true;
+            request_defaults.encode = false;
+            request_defaults.jwt = Some(JwtSettings::new(&token, config.jwt_key.as_deref())?);
+        }
+
+        if let Some(path) = config.script.as_ref() {
+            request_defaults.script = Some(Script::new(path)?);
+        }
+
+        request_defaults.pre_request_cmd = config.pre_request_cmd.clone();
+        request_defaults.reflection_exclusions = config.reflection_exclusions.clone();
+        request_defaults.challenge_cmd = config.challenge_cmd.clone();
+        request_defaults.user_agents = config.user_agents.clone();
+        request_defaults.rotate_ip = config.rotate_ip;
+        request_defaults.active_hours = config.active_hours;
+        request_defaults.allow_malformed = config.allow_malformed;
+        request_defaults.expect_continue = config.expect_continue;
+        request_defaults.query_separator = config.query_separator.clone();
+        request_defaults.duplicate_names = match config.duplicate_names {
+            Some(position) => {
+                let known = known_parameter(&url, &body, config.duplicate_of.as_deref(), &request_defaults)?;
+                Some((position, known))
+            }
+            None => None,
+        };
+        request_defaults.ignore_numbers = config.ignore_numbers;
+
+        if config.protobuf {
+            if request_defaults.injection_place != InjectionPlace::Body {
+                Err("--protobuf works only with the body. Use POST method")?
+            }
+
+            request_defaults.protobuf = Some(ProtobufSettings::new(
+                &config.body,
+                config.proto.as_deref(),
+                config.grpc_web,
+            )?);
+        }
+
+        request_defaults.failover_clients = failover_clients;
+        request_defaults.cookie_jar = cookie_jar;
+
+        Ok(request_defaults)
+    }
+
+    /// the default client and the failover ones.
+    /// In case the addresses need to be controlled -- the clients are bound to them
+    fn clients(
+        config: &Config,
+        parsed_url: &Url,
+        connect_host: &str,
+        cookie_jar: Option<&Arc<CookieJar>>,
+    ) -> Result<(Client, Vec<(IpAddr, Client)>), Box<dyn Error>> {
+        let host = parsed_url.host_str().ok_or("Host missing")?;
+
+        if config.pin_ip.is_some() || config.ip_failover || connect_host != host {
+            let addrs = resolve_addrs(config, parsed_url)?;
+            let client = create_resolved_client(config, connect_host, addrs[0], cookie_jar)?;
+
+            let mut failover_clients = Vec::new();
+            if config.ip_failover {
+                for addr in addrs {
+                    failover_clients.push((addr.ip(), create_resolved_client(config, connect_host, addr, cookie_jar)?));
+                }
+            }
+
+            Ok((client, failover_clients))
+        } else {
+            Ok((create_target_client(config, cookie_jar)?, Vec::new()))
+        }
+    }
+
+    /// recreates the clients to talk to the target over another http version,
+    /// e.g. when the negotiated one keeps failing
+    pub fn set_http_version(&mut self, config: &Config, version: http::Version) -> Result<(), Box<dyn Error>> {
+        self.http_version = Some(version);
+        let config = self.client_config(config);
+
+        // ipv6 addresses are bracketed within urls
+        let host = if self.host.contains(':') && !self.host.starts_with('[') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+
+        let parsed_url = Url::parse(&format!("{}://{}:{}/", self.scheme, host, self.port))?;
+        let connect_host = self.sni.clone().unwrap_or_else(|| self.host.clone());
+
+        let (client, failover_clients) = Self::clients(&config, &parsed_url, &connect_host, self.cookie_jar.as_ref())?;
+
+        self.client = client;
+        self.failover_clients = failover_clients;
+
+        Ok(())
+    }
+
+    /// the config to create new clients with, so they keep the http version the target was switched to
+    pub fn client_config<'b>(&self, config: &'b Config) -> Cow<'b, Config> {
+        match self.http_version {
+            Some(version) if config.http_version != Some(version) => {
+                let mut config = config.clone();
+                config.http_version = Some(version);
+                Cow::Owned(config)
+            }
+            _ => Cow::Borrowed(config),
+        }
     }
 
     pub fn new<S: Into<String> + From<String> + std::fmt::Debug>(
@@ -541,7 +1407,71 @@ impl<'a> RequestDefaults {
 
             parameters: Vec::new(),
 
-            check_binary
+            check_binary,
+
+            sni: None,
+
+            failover_clients: Vec::new(),
+
+            http_version: None,
+
+            failover_index: Arc::new(AtomicUsize::new(0)),
+
+            serializations: HashMap::new(),
+
+            protobuf: None,
+
+            jwt: None,
+
+            script: None,
+
+            pre_request_cmd: None,
+
+            host_state: None,
+
+            host_slots: None,
+            watchdog: None,
+            throttle: None,
+
+            reflection_exclusions: Vec::new(),
+
+            challenge_cmd: None,
+
+            clearance: Arc::new(Clearance::default()),
+
+            user_agents: Vec::new(),
+
+            rotate_ip: None,
+
+            active_hours: None,
+
+            agent_pinned: Arc::new(AtomicBool::new(true)),
+
+            keep_alive: Arc::new(KeepAlive::default()),
+            cookie_jar: None,
+
+            query_split: false,
+
+            extra_params: None,
+
+            allow_malformed: false,
+
+            expect_continue: None,
+
+            expect_rejected: Arc::new(AtomicBool::new(false)),
+
+            query_separator: None,
+
+            duplicate_names: None,
+
+            raw: None,
+
+            #[cfg(feature = "http3")]
+            quic: None,
+
+            cachebuster: None,
+
+            ignore_numbers: false,
         })
     }
 
@@ -568,8 +1498,9 @@ impl<'a> RequestDefaults {
                     }
                 }
                 InjectionPlace::HeaderValue => ("%k=%v", ";", false, None),
+                InjectionPlace::ChunkExtensions => ("%k=%v", ";", false, None),
                 InjectionPlace::Path => ("%k=%v", "&", false, Some(DataType::Urlencoded)),
-                InjectionPlace::Headers => (HEADERS_TEMPLATE, HEADERS_JOINER, false, None),
+                InjectionPlace::Headers | InjectionPlace::Trailers => (HEADERS_TEMPLATE, HEADERS_JOINER, false, None),
             }
         }
     }
@@ -640,4 +1571,160 @@ impl<'a> RequestDefaults {
 
         format!("{}://{}{}{}", self.scheme, self.host, port, self.path)
     }
+
+    /// escapes the parameter name so it can't break the request.
+    /// Returns None in case the name can't be sent within the injection place.
+    /// The value of `name=value` entries is kept as it is
+    pub fn escape_name(&self, param: &str) -> Option<String> {
+        let (name, value) = match param.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (param, None),
+        };
+
+        let name = match self.injection_place {
+            InjectionPlace::Headers
+            | InjectionPlace::HeaderValue
+            | InjectionPlace::Trailers
+            | InjectionPlace::ChunkExtensions => {
+                if !name.bytes().all(is_token_char) {
+                    return None;
+                }
+                name.to_string()
+            }
+            // protobuf fields are matched by their names from --proto-file
+            _ if self.protobuf.is_some() => name.to_string(),
+            // jwt claims are json as well
+            _ if self.is_json || self.jwt.is_some() => name.replace('\\', "\\\\").replace('"', "\\\""),
+            _ if self.template.starts_with('<') => {
+                if name.chars().any(|x| "<>&\"'/".contains(x) || x.is_whitespace()) {
+                    return None;
+                }
+                name.to_string()
+            }
+            _ => {
+                let name: String = name
+                    .chars()
+                    .map(|x| match x {
+                        '&' => "%26".to_string(),
+                        '#' => "%23".to_string(),
+                        '+' => "%2B".to_string(),
+                        ' ' => "%20".to_string(),
+                        _ => x.to_string(),
+                    })
+                    .collect();
+
+                // e.g. a;b would be sent as two parameters with --query-separator ';'
+                match self.query_separator.as_deref() {
+                    Some(separator) if !separator.is_empty() && separator != "&" => {
+                        name.replace(separator, &utf8_percent_encode(separator, NON_ALPHANUMERIC).to_string())
+                    }
+                    _ => name,
+                }
+            }
+        };
+
+        Some(match value {
+            Some(value) => format!("{}={}", name, value),
+            None => name,
+        })
+    }
+}
+
+/// appends the injection point to the value of the parameter within either the query or the urlencoded body:
+/// filter=a -> filter=a;%s, sort= -> sort=%s.
+/// The pairs are split by the separator, & or the one from --query-separator
+fn inject_into_value(url: &str, body: &str, name: &str, joiner: &str, separator: &str) -> Result<(String, String), Box<dyn Error>> {
+    fn inject(query: &str, name: &str, joiner: &str, separator: &str) -> Option<String> {
+        let prefix = format!("{}=", name);
+        let mut found = false;
+
+        let query = query
+            .split(separator)
+            .map(|x| {
+                if !found && x.starts_with(&prefix) {
+                    found = true;
+
+                    if x.len() == prefix.len() {
+                        format!("{}%s", x)
+                    } else {
+                        format!("{}{}%s", x, joiner)
+                    }
+                } else {
+                    x.to_string()
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(separator);
+
+        if found {
+            Some(query)
+        } else {
+            None
+        }
+    }
+
+    // the fragment isn't sent anyway
+    let url_without_fragment = url.split('#').next().unwrap();
+
+    if let Some((path, query)) = url_without_fragment.split_once('?') {
+        if let Some(query) = inject(query, name, joiner, separator) {
+            return Ok((format!("{}?{}", path, query), body.to_string()));
+        }
+    }
+
+    if let Some(body) = inject(body, name, joiner, separator) {
+        return Ok((url.to_string(), body));
+    }
+
+    Err(format!("Unable to find {} parameter within the url or the body", name))?
+}
+
+/// the known parameter for --duplicate-names: the one with the name or the first one
+/// within the query or the urlencoded and json bodies. The injection point itself is skipped
+fn known_parameter(
+    url: &str,
+    body: &str,
+    name: Option<&str>,
+    request_defaults: &RequestDefaults,
+) -> Result<(String, String), Box<dyn Error>> {
+    lazy_static! {
+        static ref RE_JSON_PAIR: Regex = Regex::new(r#""([^"]+)"\s*:\s*(?:"([^"]*)"|([^\s,}\]"]+))"#).unwrap();
+    }
+
+    let separator = request_defaults.query_separator.as_deref().unwrap_or("&");
+
+    let pairs: Vec<(String, String)> = match request_defaults.injection_place {
+        InjectionPlace::Path => url
+            .split('#')
+            .next()
+            .unwrap()
+            .split_once('?')
+            .map(|(_, query)| query)
+            .unwrap_or_default()
+            .split(separator)
+            .filter_map(|x| x.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        InjectionPlace::Body if request_defaults.is_json => RE_JSON_PAIR
+            .captures_iter(body)
+            .map(|x| (x[1].to_string(), x.get(2).or_else(|| x.get(3)).unwrap().as_str().to_string()))
+            .collect(),
+        InjectionPlace::Body => body
+            .split(separator)
+            .filter_map(|x| x.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        _ => Err("--duplicate-names works only with the query and the body")?,
+    };
+
+    pairs
+        .into_iter()
+        .filter(|(k, v)| !k.is_empty() && !k.contains("%s") && !v.contains("%s"))
+        .find(|(k, _)| name.map_or(true, |name| k == name))
+        .ok_or_else(|| match name {
+            Some(name) => format!("Unable to find {} parameter to duplicate within the url or the body", name),
+            None => "--duplicate-names requires a known parameter within the url or the body".to_string(),
+        })
+        .map_err(|x| x.into())
 }
+
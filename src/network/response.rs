@@ -1,4 +1,4 @@
-use std::{collections::HashMap, error::Error, iter::FromIterator, io::{self, Write}};
+use std::{borrow::Cow, collections::HashMap, error::Error, iter::FromIterator, io::{self, Write}};
 
 use colored::Colorize;
 use indicatif::ProgressBar;
@@ -6,13 +6,29 @@ use itertools::Itertools;
 use lazy_static::lazy_static;
 use regex::Regex;
 
-use crate::{config::structs::Config, diff::diff, runner::utils::ReasonKind, utils::{color_id, is_id_important}};
+use crate::{
+    config::structs::Config,
+    diff::{changed_lines, diff_lines},
+    runner::utils::{Enrichment, ReasonKind},
+    utils::{color_id, is_id_important},
+};
 
 use super::{
     request::Request,
-    utils::{save_request, Headers},
+    utils::{save_request, save_snapshot, Headers},
 };
 
+/// headers that usually differ between backends behind the same load balancer
+const BACKEND_HEADERS: &[&str] = &[
+    "server",
+    "via",
+    "x-backend-server",
+    "x-powered-by",
+    "x-served-by",
+    "x-server",
+    "x-upstream",
+];
+
 #[derive(Debug, Clone, Default)]
 pub struct Response<'a> {
     /// time from the sent request to response headers
@@ -24,7 +40,8 @@ pub struct Response<'a> {
     /// headers with order preserved
     pub headers: Vec<(String, String)>,
 
-    /// headers + body
+    /// headers + body.
+    /// The only copy of the body that is kept, see body_text()
     pub text: String,
 
     /// hashmap<parameter, amount of reflections> that fills later with possible reflected parameters
@@ -53,9 +70,18 @@ pub enum Status {
 
 impl<'a> Response<'a> {
     /// count how many times we can see the string in the response
-    pub fn count(&self, string: &str) -> usize {
+    /// reflections within the exclusion regions aren't counted
+    pub fn count(&self, string: &str, exclusions: &[Regex]) -> usize {
         let re = Regex::new(&format!("(?i){}", string)).unwrap();
-        re.find_iter(&self.text).count()
+
+        let regions: Vec<(usize, usize)> = exclusions
+            .iter()
+            .flat_map(|x| x.find_iter(&self.text).map(|x| (x.start(), x.end())))
+            .collect();
+
+        re.find_iter(&self.text)
+            .filter(|x| !regions.iter().any(|(start, end)| x.start() >= *start && x.end() <= *end))
+            .count()
     }
 
     /// calls check_diffs & returns code and found diffs
@@ -71,8 +97,26 @@ impl<'a> Response<'a> {
             is_code_diff = true
         }
 
+        // lines borrow from both responses instead of copying them into print()
+        let (status, initial_status) = (self.status_line(), initial_response.status_line());
+        let mut lines = self.lines(&status);
+        let mut initial_lines = initial_response.lines(&initial_status);
+
+        // counters, timestamps and prices change by themselves on dashboards and listings.
+        // The initial response doesn't keep its request, so the option is taken from this one
+        let (masked, initial_masked);
+        if self.request.as_ref().map_or(false, |x| x.defaults.ignore_numbers) {
+            masked = mask_numbers(&lines);
+            initial_masked = mask_numbers(&initial_lines);
+
+            lines = masked.iter().map(|x| x.as_ref()).collect();
+            initial_lines = initial_masked.iter().map(|x| x.as_ref()).collect();
+        }
+
         // just push every found diff to the vector of diffs
-        for diff in diff(&self.print(), &initial_response.print())? {
+        for hunk in diff_lines(&lines, &initial_lines)? {
+            let diff = hunk.to_string();
+
             if !diffs.contains(&diff) && !old_diffs.contains(&diff) {
                 diffs.push(diff);
             // sometimes returns a few same diffs. They should be considered as well
@@ -151,10 +195,12 @@ impl<'a> Response<'a> {
             Vec::from_iter(self.request.as_ref().unwrap().prepared_parameters.iter())
         };
 
+        let exclusions = &self.request.as_ref().unwrap().defaults.reflection_exclusions;
+
         for (k, v) in prepated_parameters.iter() {
             // maybe it's better to remove count from the initial response
             // sure it's increases accuracy a bit, but the performance impact is high
-            let new_count = self.count(v) - initial_response.count(v);
+            let new_count = self.count(v, exclusions) - initial_response.count(v, exclusions);
 
             if self
                 .request
@@ -221,6 +267,110 @@ impl<'a> Response<'a> {
         (None, true)
     }
 
+    /// returns the reflected parameters in case they're a clear minority of the chunk.
+    /// A parameter that changes the page usually changes the amount of reflections of every parameter instead
+    pub fn unambiguous_reflected_parameters(&self) -> Option<Vec<&str>> {
+        let amount = self.request.as_ref().unwrap().prepared_parameters.len();
+
+        if self.reflected_parameters.len() < 2 || self.reflected_parameters.len() * 4 > amount {
+            return None;
+        }
+
+        let mut names: Vec<&str> = self.reflected_parameters.keys().map(|x| x.as_str()).collect();
+        names.sort_unstable();
+
+        Some(names)
+    }
+
+    /// finds known error messages that aren't present on the initial page
+    pub fn error_signatures(&self, initial_response: &Response, custom_signatures: &[Regex]) -> Vec<Enrichment> {
+        lazy_static! {
+            static ref RE_ERROR_SIGNATURES: Vec<(&'static str, Regex)> = [
+                ("sql", r"You have an error in your SQL syntax"),
+                ("sql", r"Warning.{1,80}mysqli?_"),
+                ("sql", r"PostgreSQL.{1,80}ERROR"),
+                ("sql", r"syntax error at or near"),
+                ("sql", r"ORA-\d{5}"),
+                ("sql", r"Unclosed quotation mark after the character string"),
+                ("sql", r"SQLSTATE\["),
+                ("sql", r"SQLITE_ERROR|sqlite3\.OperationalError"),
+                ("template", r"jinja2\.exceptions|TemplateSyntaxError"),
+                ("template", r"Twig[_\\]Error"),
+                ("template", r"freemarker\.core\.|org\.apache\.velocity"),
+                ("template", r"Liquid (syntax )?error"),
+                ("stack-trace", r"Traceback \(most recent call last\)"),
+                ("stack-trace", r"at [\w.$]+\([\w]+\.java:\d+\)"),
+                ("stack-trace", r"System\.[\w.]+Exception"),
+                ("stack-trace", r"Fatal error:.{1,200} on line \d+"),
+                ("stack-trace", r"\.rb:\d+:in `"),
+            ]
+            .iter()
+            .map(|(kind, re)| (*kind, Regex::new(re).unwrap()))
+            .collect();
+        }
+
+        RE_ERROR_SIGNATURES
+            .iter()
+            .map(|(kind, re)| (*kind, re))
+            .chain(custom_signatures.iter().map(|re| ("custom", re)))
+            .filter(|(_, re)| !re.is_match(&initial_response.text))
+            .filter_map(|(kind, re)| {
+                re.find(&self.text).map(|x| Enrichment::ErrorSignature {
+                    kind: kind.to_string(),
+                    matched: x.as_str().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// identifies the backend that returned the response by its headers
+    pub fn backend(&self) -> String {
+        BACKEND_HEADERS
+            .iter()
+            .filter_map(|x| self.headers.get_value_case_insensitive(x))
+            .collect::<Vec<String>>()
+            .join("|")
+    }
+
+    /// describes the technology behind the response like "nginx+php"
+    /// based on product names from Server and X-Powered-By headers without versions
+    pub fn fingerprint(&self) -> String {
+        let mut products = ["server", "x-powered-by"]
+            .iter()
+            .filter_map(|x| self.headers.get_value_case_insensitive(x))
+            .flat_map(|x| {
+                x.split(|c: char| c.is_whitespace() || c == ',')
+                    .map(|x| x.split('/').next().unwrap().to_lowercase())
+                    .filter(|x| !x.is_empty() && !x.starts_with('('))
+                    .collect::<Vec<String>>()
+            })
+            .collect::<Vec<String>>();
+
+        products.sort();
+        products.dedup();
+
+        if products.is_empty() {
+            "unknown".to_string()
+        } else {
+            products.join("+")
+        }
+    }
+
+    /// the body part of the text after the headers added by add_headers()
+    pub fn body_text(&self) -> &str {
+        let mut rest = self.text.as_str();
+
+        // a line per header and an empty line
+        for _ in 0..=self.headers.len() {
+            match rest.split_once('\n') {
+                Some((_, val)) => rest = val,
+                None => return "",
+            }
+        }
+
+        rest
+    }
+
     /// adds headers to response text
     pub fn add_headers(&mut self) {
         let mut text = String::new();
@@ -249,7 +399,7 @@ impl<'a> Response<'a> {
             String::new()
         };
 
-        let mut message = match reason_kind {
+        let mut message = match &reason_kind {
             ReasonKind::Code => format!(
                 "{}{}: code {} -> {}",
                 &id_if_important,
@@ -277,15 +427,42 @@ impl<'a> Response<'a> {
                 "changes reflections".bright_cyan(),
                 parameter
             ),
+            ReasonKind::Custom(name) => format!(
+                "{}{}: {} ({})",
+                &id_if_important,
+                &parameter,
+                name.bright_magenta(),
+                diff.unwrap_or_default()
+            ),
         };
 
+        // the amount of changed lines to show with --show-diffs
+        const MAX_DIFF_LINES: usize = 20;
+
+        if config.show_diffs && reason_kind == ReasonKind::Text {
+            let lines = changed_lines(&initial_response.print(), &self.print())?;
+
+            for line in lines.iter().take(MAX_DIFF_LINES) {
+                message += "\n    ";
+                message += &if line.starts_with('-') {
+                    line.red().to_string()
+                } else {
+                    line.green().to_string()
+                };
+            }
+
+            if lines.len() > MAX_DIFF_LINES {
+                message += &format!("\n    ... {} more lines", lines.len() - MAX_DIFF_LINES);
+            }
+        }
+
         if config.verbose > 0 {
             if !config.save_responses.is_empty() {
                 message += &format!(" [saved to {}]", save_request(config, self, parameter)?);
             }
 
             if config.disable_progress_bar {
-                writeln!(io::stdout(), "{}", message).ok();
+                writeln!(io::stderr(), "{}", message).ok();
             } else {
                 progress_bar.println(message);
             }
@@ -296,6 +473,15 @@ impl<'a> Response<'a> {
         Ok(())
     }
 
+    /// stores the request and response in case --snapshots is used
+    /// returns the snapshot location
+    pub fn save_snapshot(&self, config: &Config) -> Result<Option<String>, Box<dyn Error>> {
+        match config.snapshots.as_ref() {
+            Some(dir) => Ok(Some(save_snapshot(dir, self)?)),
+            None => Ok(None),
+        }
+    }
+
     fn kind(&self) -> Status {
         if self.code <= 199 {
             Status::Other
@@ -324,10 +510,29 @@ impl<'a> Response<'a> {
     }
 
     /// get possible parameters from the page itself
-    pub fn get_possible_parameters(&self) -> Vec<String> {
+    /// separator is the delimiter between query parameters, e.g. & or ; for legacy Java servers
+    pub fn get_possible_parameters(&self, separator: &str) -> Vec<String> {
+        lazy_static! {
+            static ref RE_QUERIES: Regex = Regex::new(r#"\?([^\s"'<>#]+)"#).unwrap();
+            static ref RE_QUERY_NAME: Regex = Regex::new(r"^[A-Za-z_][\w.\[\]-]{0,40}$").unwrap();
+        }
+
         let mut found: Vec<String> = Vec::new();
         let body = &self.text;
 
+        // names from the query strings of the links, e.g. href="/search?q=1;page=2"
+        for cap in RE_QUERIES.captures_iter(body) {
+            let query = cap[1].replace("&amp;", "&");
+
+            for pair in query.split(|x| x == '&' || separator.contains(x)) {
+                if let Some((name, _)) = pair.split_once('=') {
+                    if RE_QUERY_NAME.is_match(name) {
+                        found.push(name.to_string());
+                    }
+                }
+            }
+        }
+
         let re_special_chars = Regex::new(r#"[\W]"#).unwrap();
 
         let re_name = Regex::new(r#"(?i)name=("|')?"#).unwrap();
@@ -357,9 +562,67 @@ impl<'a> Response<'a> {
         found
     }
 
+    /// finds parameter names that get_possible_parameters() doesn't look for:
+    /// json keys, ids of form fields, data-* attributes and names from error messages like "missing parameter 'foo'"
+    pub fn mine_parameters(&self) -> Vec<String> {
+        lazy_static! {
+            static ref RE_NAME: Regex = Regex::new(r"^[A-Za-z_][\w.-]{0,40}$").unwrap();
+            static ref RE_FIELD_IDS: Regex =
+                Regex::new(r#"(?i)<(?:input|select|textarea|button)\s[^>]*?\bid=["']?([\w-]+)"#).unwrap();
+            static ref RE_DATA_ATTRIBUTES: Regex = Regex::new(r#"(?i)\sdata-([a-z][\w-]*)="#).unwrap();
+            static ref RE_ERROR_HINTS: Regex = Regex::new(
+                r#"(?i)\b(?:missing|required|unknown|invalid|unexpected|unrecognized)\s+(?:query\s+)?(?:parameter|param|argument|field|key)s?\s*[:=]?\s*[`'"]?([A-Za-z_][\w.-]{0,40})"#
+            ).unwrap();
+        }
+
+        /// the limit of json keys per response, big api responses would flood the wordlist otherwise
+        const MAX_JSON_KEYS: usize = 256;
+
+        fn json_keys(value: &serde_json::Value, keys: &mut Vec<String>) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    for (key, value) in map {
+                        if keys.len() >= MAX_JSON_KEYS {
+                            return;
+                        }
+                        keys.push(key.to_owned());
+                        json_keys(value, keys);
+                    }
+                }
+                serde_json::Value::Array(values) => values.iter().for_each(|x| json_keys(x, keys)),
+                _ => (),
+            }
+        }
+
+        let mut found = Vec::new();
+
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(self.body_text()) {
+            json_keys(&value, &mut found);
+        }
+
+        for re in [&*RE_FIELD_IDS, &*RE_DATA_ATTRIBUTES, &*RE_ERROR_HINTS] {
+            found.extend(re.captures_iter(&self.text).map(|x| x[1].to_string()));
+        }
+
+        found.retain(|x| RE_NAME.is_match(x));
+        found.sort();
+        found.dedup();
+        found
+    }
+
     /// print the whole response
     pub fn print(&self) -> String {
-        let http_version = match self.http_version {
+        format!("{}\n{}", self.status_line(), self.text)
+    }
+
+    /// lines of print() without building the whole string
+    fn lines<'b>(&'b self, status: &'b str) -> Vec<&'b str> {
+        std::iter::once(status).chain(self.text.lines()).collect()
+    }
+
+    /// the http version the response came over, e.g. HTTP/1.1
+    pub fn http_version_name(&self) -> &'static str {
+        match self.http_version {
             Some(val) => match val {
                 http::Version::HTTP_09 => "HTTP/0.9",
                 http::Version::HTTP_10 => "HTTP/1.0",
@@ -369,9 +632,11 @@ impl<'a> Response<'a> {
                 _ => "HTTP/x",
             },
             None => "HTTP/x",
-        };
+        }
+    }
 
-        format!("{} {} \n{}", http_version, self.code, self.text)
+    fn status_line(&self) -> String {
+        format!("{} {} ", self.http_version_name(), self.code)
     }
 
     /// print the request and response
@@ -379,3 +644,17 @@ impl<'a> Response<'a> {
         self.request.as_ref().unwrap().print_sent() + "\n\n" + &self.print()
     }
 }
+
+/// replaces every number with 0, so lines that differ only in digits are equal.
+/// The status line is kept because the code is compared separately
+fn mask_numbers<'b>(lines: &[&'b str]) -> Vec<Cow<'b, str>> {
+    lazy_static! {
+        static ref RE_NUMBERS: Regex = Regex::new(r"\d+").unwrap();
+    }
+
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, x)| if i == 0 { Cow::Borrowed(*x) } else { RE_NUMBERS.replace_all(x, "0") })
+        .collect()
+}
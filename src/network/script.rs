@@ -0,0 +1,166 @@
+use std::{
+    error::Error,
+    fmt,
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use ring::{digest, hmac};
+
+/// user hooks from --script
+/// on_request(req) and on_response(resp) receive maps and return the modified ones
+#[derive(Clone)]
+pub struct Script {
+    engine: Arc<Engine>,
+    ast: Arc<AST>,
+    on_request: bool,
+    on_response: bool,
+}
+
+impl fmt::Debug for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Script")
+            .field("on_request", &self.on_request)
+            .field("on_response", &self.on_response)
+            .finish()
+    }
+}
+
+/// the request as seen by on_request
+#[derive(Debug, Clone, Default)]
+pub struct ScriptRequest {
+    pub method: String,
+    pub url: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// the response as seen by on_response
+#[derive(Debug, Clone, Default)]
+pub struct ScriptResponse {
+    pub code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Script {
+    pub fn new(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut engine = Engine::new();
+
+        // helpers for signatures and checksums
+        engine.register_fn("sha256", |data: &str| hex(digest::digest(&digest::SHA256, data.as_bytes()).as_ref()));
+        engine.register_fn("hmac_sha256", |key: &str, data: &str| {
+            hex(hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key.as_bytes()), data.as_bytes()).as_ref())
+        });
+        engine.register_fn("timestamp", || {
+            SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs() as i64).unwrap_or(0)
+        });
+
+        let ast = engine
+            .compile_file(PathBuf::from(path))
+            .map_err(|err| format!("Unable to compile the script: {}", err))?;
+
+        let on_request = ast.iter_functions().any(|x| x.name == "on_request");
+        let on_response = ast.iter_functions().any(|x| x.name == "on_response");
+
+        if !on_request && !on_response {
+            Err("The script should define on_request(req) or on_response(resp)")?
+        }
+
+        Ok(Self {
+            engine: Arc::new(engine),
+            ast: Arc::new(ast),
+            on_request,
+            on_response,
+        })
+    }
+
+    /// changes path, headers and body of the request
+    pub fn on_request(&self, request: &mut ScriptRequest) -> Result<(), String> {
+        if !self.on_request {
+            return Ok(());
+        }
+
+        let mut map = Map::new();
+        map.insert("method".into(), request.method.clone().into());
+        map.insert("url".into(), request.url.clone().into());
+        map.insert("path".into(), request.path.clone().into());
+        map.insert("headers".into(), headers_to_map(&request.headers).into());
+        map.insert("body".into(), request.body.clone().into());
+
+        let mut map = self.call("on_request", map)?;
+
+        if let Some(path) = take_string(&mut map, "path") {
+            request.path = path;
+        }
+        if let Some(headers) = map.remove("headers").and_then(|x| x.try_cast::<Map>()) {
+            request.headers = map_to_headers(headers);
+        }
+        if let Some(body) = take_string(&mut map, "body") {
+            request.body = body;
+        }
+
+        Ok(())
+    }
+
+    /// changes code, headers and body of the response before it's compared
+    pub fn on_response(&self, response: &mut ScriptResponse) -> Result<(), String> {
+        if !self.on_response {
+            return Ok(());
+        }
+
+        let mut map = Map::new();
+        map.insert("code".into(), (response.code as i64).into());
+        map.insert("headers".into(), headers_to_map(&response.headers).into());
+        map.insert("body".into(), response.body.clone().into());
+
+        let mut map = self.call("on_response", map)?;
+
+        if let Some(code) = map.remove("code").and_then(|x| x.as_int().ok()) {
+            response.code = code as u16;
+        }
+        if let Some(headers) = map.remove("headers").and_then(|x| x.try_cast::<Map>()) {
+            response.headers = map_to_headers(headers);
+        }
+        if let Some(body) = take_string(&mut map, "body") {
+            response.body = body;
+        }
+
+        Ok(())
+    }
+
+    fn call(&self, name: &str, map: Map) -> Result<Map, String> {
+        let result: Dynamic = self
+            .engine
+            .call_fn(&mut Scope::new(), &self.ast, name, (map,))
+            .map_err(|err| format!("{} failed: {}", name, err))?;
+
+        result
+            .try_cast::<Map>()
+            .ok_or_else(|| format!("{} should return the modified map", name))
+    }
+}
+
+fn headers_to_map(headers: &[(String, String)]) -> Map {
+    headers
+        .iter()
+        .map(|(k, v)| (k.as_str().into(), v.clone().into()))
+        .collect()
+}
+
+fn map_to_headers(map: Map) -> Vec<(String, String)> {
+    map.into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn take_string(map: &mut Map, key: &str) -> Option<String> {
+    map.remove(key).and_then(|x| x.into_string().ok())
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|x| format!("{:02x}", x)).collect()
+}
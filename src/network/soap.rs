@@ -0,0 +1,358 @@
+use std::{error::Error, fs};
+
+use lazy_static::lazy_static;
+use quick_xml::{
+    escape::escape,
+    events::{BytesStart, Event},
+    Reader,
+};
+use regex::Regex;
+
+use crate::config::structs::Config;
+
+use super::utils::create_client;
+
+lazy_static! {
+    /// faults of servers that don't serve the SOAPAction or the operation element
+    static ref RE_UNKNOWN_OPERATION: Regex = Regex::new(
+        r"(?i)did not recognize the value of http header soapaction|no such operation|cannot find dispatch method|no endpoint mapping found|unexpected wrapper element|unknown (soap ?action|operation)|operation \S+ (is )?not (found|supported|recognized)"
+    ).unwrap();
+}
+
+const SOAP11_ENVELOPE: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+const SOAP12_ENVELOPE: &str = "http://www.w3.org/2003/05/soap-envelope";
+
+/// an operation from the WSDL binding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operation {
+    pub name: String,
+
+    /// SOAPAction header value
+    pub action: String,
+
+    /// the element within soap:Body
+    pub element: String,
+
+    /// namespace of the element
+    pub namespace: String,
+
+    /// whether the binding uses SOAP 1.2
+    pub soap12: bool,
+}
+
+impl Operation {
+    /// the request body with the injection point within the operation element
+    pub fn envelope(&self) -> String {
+        format!(
+            "<soapenv:Envelope xmlns:soapenv=\"{}\" xmlns:x8=\"{}\"><soapenv:Header/><soapenv:Body><x8:{}>%s</x8:{}></soapenv:Body></soapenv:Envelope>",
+            if self.soap12 { SOAP12_ENVELOPE } else { SOAP11_ENVELOPE },
+            escape(&self.namespace),
+            self.element,
+            self.element,
+        )
+    }
+
+    /// config to check the operation with.
+    /// Parameters become child elements of the operation element
+    pub fn config(&self, config: &Config) -> Config {
+        let mut config = config.clone();
+
+        config.methods = vec!["POST".to_string()];
+        config.body = self.envelope();
+        config.template = Some("<x8:%k>%v</x8:%k>".to_string());
+        config.joiner = Some(String::new());
+        config.data_type = None;
+        config.soap_operation = Some(self.name.clone());
+
+        config.custom_headers.retain(|(k, _)| {
+            !k.eq_ignore_ascii_case("content-type") && !k.eq_ignore_ascii_case("soapaction")
+        });
+
+        // SOAP 1.2 moves the action into Content-Type
+        if self.soap12 {
+            config.custom_headers.push((
+                "Content-Type".to_string(),
+                format!("application/soap+xml; charset=utf-8; action=\"{}\"", self.action),
+            ));
+        } else {
+            config.custom_headers.push(("Content-Type".to_string(), "text/xml; charset=utf-8".to_string()));
+            config.custom_headers.push(("SOAPAction".to_string(), format!("\"{}\"", self.action)));
+        }
+
+        config
+    }
+}
+
+/// an element of the parsed xml document
+#[derive(Debug, Default)]
+struct Node {
+    /// the name without the prefix
+    name: String,
+
+    /// the prefix of the name, e.g. soap12 for soap12:operation
+    prefix: String,
+
+    /// (the full key, unescaped value)
+    attributes: Vec<(String, String)>,
+
+    children: Vec<Node>,
+
+    /// the unescaped text and CDATA within the element
+    text: String,
+}
+
+impl Node {
+    fn parse(xml: &str) -> Result<Node, Box<dyn Error>> {
+        let mut reader = Reader::from_str(xml);
+        reader.trim_text(true);
+
+        // the document node is the first one
+        let mut stack = vec![Node::default()];
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) => stack.push(Node::from_start(&e)?),
+                Event::Empty(e) => {
+                    let node = Node::from_start(&e)?;
+                    stack.last_mut().unwrap().children.push(node);
+                }
+                Event::End(_) => {
+                    if stack.len() < 2 {
+                        Err("Unexpected closing tag within the xml")?
+                    }
+
+                    let node = stack.pop().unwrap();
+                    stack.last_mut().unwrap().children.push(node);
+                }
+                Event::Text(e) => stack.last_mut().unwrap().text.push_str(&e.unescape()?),
+                Event::CData(e) => stack.last_mut().unwrap().text.push_str(&String::from_utf8_lossy(&e)),
+                Event::Eof => break,
+                _ => (),
+            }
+        }
+
+        if stack.len() != 1 {
+            Err("Unclosed tag within the xml")?
+        }
+
+        Ok(stack.pop().unwrap())
+    }
+
+    fn from_start(e: &BytesStart) -> Result<Node, Box<dyn Error>> {
+        let name = e.name();
+
+        let mut attributes = Vec::new();
+        for attribute in e.attributes() {
+            let attribute = attribute?;
+            attributes.push((
+                String::from_utf8_lossy(attribute.key.as_ref()).to_string(),
+                attribute.unescape_value()?.to_string(),
+            ));
+        }
+
+        Ok(Node {
+            name: String::from_utf8_lossy(name.local_name().as_ref()).to_string(),
+            prefix: name
+                .prefix()
+                .map(|x| String::from_utf8_lossy(x.as_ref()).to_string())
+                .unwrap_or_default(),
+            attributes,
+            ..Default::default()
+        })
+    }
+
+    fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find(|x| x.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Node> {
+        self.children.iter().filter(move |x| x.name == name)
+    }
+
+    /// every element with the name within the subtree, the node itself included
+    fn descendants<'a>(&'a self, name: &str, found: &mut Vec<&'a Node>) {
+        if self.name == name {
+            found.push(self);
+        }
+
+        for child in self.children.iter() {
+            child.descendants(name, found);
+        }
+    }
+
+    fn find_all(&self, name: &str) -> Vec<&Node> {
+        let mut found = Vec::new();
+        self.descendants(name, &mut found);
+        found
+    }
+
+    /// the namespace declared for the prefix anywhere within the document
+    fn namespace_of(&self, prefix: &str) -> Option<&str> {
+        let key = format!("xmlns:{}", prefix);
+
+        self.attribute(&key)
+            .or_else(|| self.children.iter().find_map(|x| x.namespace_of(prefix)))
+    }
+}
+
+/// tns:GetUser -> GetUser
+fn local_name(qname: &str) -> &str {
+    qname.rsplit_once(':').map_or(qname, |(_, name)| name)
+}
+
+/// enumerates operations of every SOAP binding within the WSDL
+pub fn parse_wsdl(wsdl: &str) -> Result<Vec<Operation>, Box<dyn Error>> {
+    let document = Node::parse(wsdl)?;
+
+    // elements are usually declared within the schema, and its namespace can differ from the WSDL one
+    let namespace = match document
+        .find_all("schema")
+        .into_iter()
+        .chain(document.find_all("definitions"))
+        .find_map(|x| x.attribute("targetNamespace"))
+    {
+        Some(val) => val.to_string(),
+        None => Err("Unable to find targetNamespace within the WSDL")?,
+    };
+
+    let mut operations: Vec<Operation> = Vec::new();
+
+    // soap:binding is nested within wsdl:binding and has no operations
+    for binding in document.find_all("binding") {
+        for operation in binding.children("operation") {
+            // operations without soap:operation belong to http bindings
+            let soap_operation = match operation.child("operation") {
+                Some(val) => val,
+                None => continue,
+            };
+
+            let name = match operation.attribute("name") {
+                Some(val) => val.to_string(),
+                None => continue,
+            };
+
+            // both SOAP 1.1 and 1.2 bindings are usually present
+            if operations.iter().any(|x| x.name == name) {
+                continue;
+            }
+
+            let soap12 = document
+                .namespace_of(&soap_operation.prefix)
+                .map_or(false, |x| x.contains("soap12"));
+
+            operations.push(Operation {
+                action: soap_operation.attribute("soapAction").unwrap_or_default().to_string(),
+                element: input_element(&document, &name).unwrap_or_else(|| name.clone()),
+                namespace: namespace.clone(),
+                soap12,
+                name,
+            });
+        }
+    }
+
+    if operations.is_empty() {
+        Err("No SOAP operations were found within the WSDL")?;
+    }
+
+    Ok(operations)
+}
+
+/// document/literal operations send the element of the input message's part
+fn input_element(document: &Node, operation: &str) -> Option<String> {
+    let message = document
+        .find_all("portType")
+        .into_iter()
+        .flat_map(|x| x.children("operation"))
+        .find(|x| x.attribute("name") == Some(operation))?
+        .child("input")?
+        .attribute("message")
+        .map(local_name)?;
+
+    document
+        .find_all("message")
+        .into_iter()
+        .find(|x| x.attribute("name") == Some(message))?
+        .children("part")
+        .find_map(|x| x.attribute("element"))
+        .map(|x| local_name(x).to_string())
+}
+
+/// what the fault says about the operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultKind {
+    /// Client or Sender. Usually the required elements are missing, so parameters can change it
+    Client,
+
+    /// Server or Receiver
+    Server,
+
+    /// the service doesn't serve the SOAPAction or the operation element
+    UnknownOperation,
+
+    /// the envelope's SOAP version isn't supported
+    VersionMismatch,
+
+    /// a header with mustUnderstand isn't supported
+    MustUnderstand,
+
+    Other,
+}
+
+/// a SOAP 1.1 or 1.2 fault
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fault {
+    pub kind: FaultKind,
+    pub message: String,
+}
+
+impl Fault {
+    /// whether the operation can't be checked at all
+    pub fn is_fatal(&self) -> bool {
+        matches!(self.kind, FaultKind::UnknownOperation | FaultKind::VersionMismatch)
+    }
+}
+
+/// the fault in case the body is a SOAP fault
+pub fn fault(body: &str) -> Option<Fault> {
+    let document = Node::parse(body).ok()?;
+    let fault = document.find_all("Fault").into_iter().next()?;
+
+    // 1.1: <faultcode>soap:Client</faultcode><faultstring>..</faultstring>
+    // 1.2: <Code><Value>soap:Sender</Value></Code><Reason><Text>..</Text></Reason>
+    let code = fault
+        .child("faultcode")
+        .or_else(|| fault.child("Code").and_then(|x| x.child("Value")))
+        .map(|x| x.text.trim())
+        .unwrap_or_default();
+
+    let message = fault
+        .child("faultstring")
+        .or_else(|| fault.child("Reason").and_then(|x| x.child("Text")))
+        .map(|x| x.text.trim().to_string())
+        .unwrap_or_default();
+
+    // faultcodes can be dotted like Client.Authentication
+    let kind = match local_name(code).split('.').next().unwrap_or_default() {
+        "VersionMismatch" => FaultKind::VersionMismatch,
+        "MustUnderstand" => FaultKind::MustUnderstand,
+        _ if RE_UNKNOWN_OPERATION.is_match(&message) => FaultKind::UnknownOperation,
+        "Client" | "Sender" => FaultKind::Client,
+        "Server" | "Receiver" => FaultKind::Server,
+        _ => FaultKind::Other,
+    };
+
+    Some(Fault { kind, message })
+}
+
+/// reads the WSDL either from the file or from the url
+pub async fn read_wsdl(config: &Config, wsdl: &str) -> Result<String, Box<dyn Error>> {
+    if wsdl.starts_with("http://") || wsdl.starts_with("https://") {
+        Ok(create_client(config, false)?.get(wsdl).send().await?.text().await?)
+    } else {
+        Ok(fs::read_to_string(wsdl)?)
+    }
+}
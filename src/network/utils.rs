@@ -1,16 +1,43 @@
-use std::{time::Duration, error::Error};
+use std::{
+    error::Error,
+    net::{Ipv4Addr, SocketAddr, ToSocketAddrs},
+    path::Path,
+    process::Stdio,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
 
+use chrono::{Local, Timelike};
 use lazy_static::lazy_static;
-use percent_encoding::{AsciiSet, CONTROLS};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use regex::Regex;
-use reqwest::Client;
+use reqwest::{Client, ClientBuilder};
+use rustls::{
+    client::{
+        ClientSessionMemoryCache, NoClientSessionStorage, ServerCertVerified, ServerCertVerifier, StoresClientSessions,
+        WebPkiVerifier,
+    },
+    Certificate, ServerName,
+};
 use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use url::Url;
 
-use crate::{config::structs::Config, utils::random_line};
+use crate::{config::structs::Config, utils::{error, random_line}};
 
-use super::response::Response;
+use super::{
+    cookies::CookieJar,
+    proxy::{NoProxy, ProxyRules},
+    response::Response,
+};
 
 lazy_static! {
+    /// resumable tls sessions of every client with the preconfigured tls
+    static ref TLS_SESSIONS: Arc<ClientSessionMemoryCache> = ClientSessionMemoryCache::new(1024);
+
     /// characters to encode in case --encode option provided
     pub static ref FRAGMENT: AsciiSet = CONTROLS
         .add(b' ')
@@ -50,12 +77,149 @@ pub enum InjectionPlace {
     Body,
     Headers,
     HeaderValue,
+    /// trailer fields after the last chunk of the chunked body
+    Trailers,
+    /// extensions of the chunk size lines, e.g. 5;name=value
+    ChunkExtensions,
+}
+
+/// where the instance with the constant value goes in case every parameter is sent twice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePosition {
+    Before,
+    After,
 }
 
 impl Default for InjectionPlace {
     fn default() -> Self { InjectionPlace::Path }
 }
 
+/// addresses to send within X-Forwarded-For and X-Real-IP in case --rotate-ip is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    base: u32,
+    mask: u32,
+}
+
+impl IpRange {
+    /// parses CIDR like 10.0.0.0/8. Empty value means any address
+    pub fn parse(cidr: &str) -> Result<Self, Box<dyn Error>> {
+        if cidr.is_empty() {
+            return Ok(Self { base: 0, mask: 0 });
+        }
+
+        let (ip, prefix) = match cidr.split_once('/') {
+            Some((ip, prefix)) => (ip, prefix.parse::<u32>()?),
+            None => (cidr, 32),
+        };
+
+        if prefix > 32 {
+            Err("Wrong CIDR prefix")?
+        }
+
+        let mask = if prefix == 0 { 0 } else { u32::MAX << (32 - prefix) };
+
+        Ok(Self {
+            base: u32::from(ip.parse::<Ipv4Addr>()?) & mask,
+            mask,
+        })
+    }
+
+    pub fn random(&self) -> Ipv4Addr {
+        Ipv4Addr::from(self.base | (rand::random::<u32>() & !self.mask))
+    }
+
+    pub fn contains(&self, ip: Ipv4Addr) -> bool {
+        u32::from(ip) & self.mask == self.base
+    }
+}
+
+/// local time window when requests are allowed in case --active-hours is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveHours {
+    /// minutes since midnight
+    start: u32,
+    end: u32,
+}
+
+impl ActiveHours {
+    /// parses windows like 22:00-06:00
+    pub fn parse(window: &str) -> Result<Self, Box<dyn Error>> {
+        let parse_time = |time: &str| -> Result<u32, Box<dyn Error>> {
+            let (hours, minutes) = time.trim().split_once(':').ok_or("The time should be like 22:00")?;
+            let (hours, minutes): (u32, u32) = (hours.parse()?, minutes.parse()?);
+
+            if hours > 23 || minutes > 59 {
+                Err("Wrong time within --active-hours")?
+            }
+
+            Ok(hours * 60 + minutes)
+        };
+
+        let (start, end) = window.split_once('-').ok_or("--active-hours should be like 22:00-06:00")?;
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+
+        if start == end {
+            Err("The start and the end of --active-hours should differ")?
+        }
+
+        Ok(Self { start, end })
+    }
+
+    /// seconds until the window opens. 0 within the window
+    pub fn seconds_until_open(&self) -> u64 {
+        let now = Local::now();
+        let minute = now.hour() * 60 + now.minute();
+
+        let is_open = if self.start <= self.end {
+            self.start <= minute && minute < self.end
+        } else {
+            minute >= self.start || minute < self.end
+        };
+
+        if is_open {
+            0
+        } else {
+            let minutes = (self.start + 24 * 60 - minute) % (24 * 60);
+            (minutes as u64 * 60).saturating_sub(now.second() as u64)
+        }
+    }
+
+    /// sleeps until the window opens
+    pub async fn wait(&self) {
+        let seconds = self.seconds_until_open();
+
+        if seconds > 0 {
+            // requests that are waiting together are reported once
+            if !ACTIVE_HOURS_PAUSED.swap(true, Ordering::SeqCst) {
+                error(format!("Outside of --active-hours. Pausing for {} minutes", seconds / 60 + 1), None, None, None);
+            }
+
+            tokio::time::sleep(Duration::from_secs(seconds)).await;
+
+            ACTIVE_HOURS_PAUSED.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+static ACTIVE_HOURS_PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// which certificate checks to perform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertVerification {
+    /// accept any certificate
+    Skip,
+
+    /// verify the chain, but accept certificates issued for any name
+    SkipHostname,
+
+    Full,
+}
+
+impl Default for CertVerification {
+    fn default() -> Self { CertVerification::Skip }
+}
+
 pub trait Headers {
     fn contains_key(&self, key: &str) -> bool;
     fn get_index_case_insensitive(&self, key: &str) -> Option<usize>;
@@ -126,19 +290,114 @@ pub(super) fn save_request(
     Ok(filename)
 }
 
+/// stores request and response to the directory under the sha256 of their content
+/// returns file location
+pub(super) fn save_snapshot(dir: &str, response: &Response) -> Result<String, Box<dyn Error>> {
+    let output = response.print_all();
+
+    let hash: String = ring::digest::digest(&ring::digest::SHA256, output.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect();
+
+    let filename = format!("{}/{}", dir, hash);
+
+    // the same content is already stored
+    if !Path::new(&filename).exists() {
+        std::fs::write(&filename, output)?;
+    }
+
+    Ok(filename)
+}
+
 pub fn create_client(config: &Config, replay: bool) -> Result<Client, Box<dyn Error>> {
+    Ok(client_builder(config, replay, None)?.build()?)
+}
+
+/// creates a client for the target that keeps cookies within the target's jar
+pub fn create_target_client(config: &Config, jar: Option<&Arc<CookieJar>>) -> Result<Client, Box<dyn Error>> {
+    Ok(client_builder(config, false, jar)?.build()?)
+}
+
+/// returns the addresses to connect to: either the --pin-ip one or all the addresses the host resolves to
+pub fn resolve_addrs(config: &Config, url: &Url) -> Result<Vec<SocketAddr>, Box<dyn Error>> {
+    let port = url.port_or_known_default().ok_or("Wrong scheme")?;
+
+    if let Some(ip) = config.pin_ip {
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+
+    let mut addrs: Vec<SocketAddr> = Vec::new();
+
+    for addr in (url.host_str().ok_or("Host missing")?, port).to_socket_addrs()? {
+        if !addrs.contains(&addr) {
+            addrs.push(addr);
+        }
+    }
+
+    if addrs.is_empty() {
+        Err("Unable to resolve the target's host")?
+    }
+
+    Ok(addrs)
+}
+
+/// creates a client that always connects to the provided address when requesting the domain
+pub fn create_resolved_client(
+    config: &Config,
+    domain: &str,
+    addr: SocketAddr,
+    jar: Option<&Arc<CookieJar>>,
+) -> Result<Client, Box<dyn Error>> {
+    Ok(client_builder(config, false, jar)?.resolve(domain, addr).build()?)
+}
+
+fn client_builder(config: &Config, replay: bool, jar: Option<&Arc<CookieJar>>) -> Result<ClientBuilder, Box<dyn Error>> {
     let mut client = Client::builder()
-        .danger_accept_invalid_certs(true)
         .timeout(Duration::from_secs(config.timeout as u64))
         .http1_title_case_headers()
-        .cookie_store(true)
-        .http09_responses()
-        .use_rustls_tls();
+        .http09_responses();
+
+    // the clients of the target share the cookies set by it
+    client = match jar {
+        Some(jar) if !replay => client.cookie_provider(Arc::clone(jar)),
+        _ => client.cookie_store(true),
+    };
+
+    // rustls doesn't allow to change cipher suites or to skip only a part of certificate checks
+    // so we have to pass our own tls config in these cases
+    if !config.ciphers.is_empty()
+        || config.insecure == CertVerification::SkipHostname
+        || config.no_tls_tickets
+    {
+        client = client.use_preconfigured_tls(tls_config(config)?);
+    } else {
+        client = client
+            .danger_accept_invalid_certs(config.insecure == CertVerification::Skip)
+            .use_rustls_tls();
+
+        if let Some(version) = config.tls_min {
+            client = client.min_tls_version(version);
+        }
+
+        if let Some(version) = config.tls_max {
+            client = client.max_tls_version(version);
+        }
+    }
 
     if config.disable_trustdns {
         client = client.no_trust_dns();
     }
 
+    if let Some(ip) = config.source_ip {
+        client = client.local_address(ip);
+    }
+
+    if let Some(interface) = config.interface.as_ref() {
+        client = bind_interface(client, interface)?;
+    }
+
     if replay {
         client = client.proxy(match reqwest::Proxy::all(&config.replay_proxy) {
             Ok(val) => val,
@@ -146,10 +405,14 @@ pub fn create_client(config: &Config, replay: bool) -> Result<Client, Box<dyn Er
                 Err(format!("Unable to parse replay_proxy: {}", err))?
             }
         });
-    } else {
-        if !config.proxy.is_empty() {
-            client = client.proxy(reqwest::Proxy::all(&config.proxy)?);
-        }
+    } else if !config.proxy.is_empty() || !config.proxy_map.is_empty() {
+        let rules = ProxyRules::new(
+            config.proxy_map.clone(),
+            Some(config.proxy.clone()).filter(|x| !x.is_empty()),
+            NoProxy::from_env(),
+        );
+
+        client = client.proxy(reqwest::Proxy::custom(move |url| rules.route(url.host_str()?)));
     }
 
     if !config.follow_redirects {
@@ -164,7 +427,135 @@ pub fn create_client(config: &Config, replay: bool) -> Result<Client, Box<dyn Er
         }
     }
 
-    Ok(client.build()?)
+    Ok(client)
+}
+
+/// sends the traffic through the network interface in case --interface is used
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+fn bind_interface(client: ClientBuilder, interface: &str) -> Result<ClientBuilder, Box<dyn Error>> {
+    Ok(client.interface(interface))
+}
+
+#[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+fn bind_interface(_client: ClientBuilder, _interface: &str) -> Result<ClientBuilder, Box<dyn Error>> {
+    Err("--interface is supported only on Linux. Use --source-ip with the address of the interface instead")?
+}
+
+/// builds rustls config with user supplied cipher suites, tls versions and certificate checks
+pub fn tls_config(config: &Config) -> Result<rustls::ClientConfig, Box<dyn Error>> {
+    let cipher_suites: Vec<rustls::SupportedCipherSuite> = if config.ciphers.is_empty() {
+        rustls::DEFAULT_CIPHER_SUITES.to_vec()
+    } else {
+        rustls::ALL_CIPHER_SUITES
+            .iter()
+            .filter(|x| {
+                config
+                    .ciphers
+                    .iter()
+                    .any(|cipher| cipher.eq_ignore_ascii_case(&format!("{:?}", x.suite())))
+            })
+            .copied()
+            .collect()
+    };
+
+    if cipher_suites.is_empty() {
+        Err("None of the provided --ciphers are supported")?
+    }
+
+    let versions: Vec<&'static rustls::SupportedProtocolVersion> = [
+        (&rustls::version::TLS12, reqwest::tls::Version::TLS_1_2),
+        (&rustls::version::TLS13, reqwest::tls::Version::TLS_1_3),
+    ]
+    .iter()
+    .filter(|(_, version)| {
+        config.tls_min.map_or(true, |min| *version >= min)
+            && config.tls_max.map_or(true, |max| *version <= max)
+    })
+    .map(|(rustls_version, _)| *rustls_version)
+    .collect();
+
+    if versions.is_empty() {
+        Err("Only TLS 1.2 and TLS 1.3 are supported")?
+    }
+
+    let mut root_store = rustls::RootCertStore::empty();
+    root_store.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+            ta.subject,
+            ta.spki,
+            ta.name_constraints,
+        )
+    }));
+
+    let verifier: Arc<dyn ServerCertVerifier> = match config.insecure {
+        CertVerification::Skip => Arc::new(SkipVerification),
+        CertVerification::SkipHostname => Arc::new(SkipHostnameVerification(WebPkiVerifier::new(root_store, None))),
+        CertVerification::Full => Arc::new(WebPkiVerifier::new(root_store, None)),
+    };
+
+    let mut tls = rustls::ClientConfig::builder()
+        .with_cipher_suites(&cipher_suites)
+        .with_safe_default_kx_groups()
+        .with_protocol_versions(&versions)?
+        .with_custom_certificate_verifier(verifier)
+        .with_no_client_auth();
+
+    // sessions are shared between the clients of different urls, so connections to the same host are resumed
+    if config.no_tls_tickets {
+        tls.session_storage = Arc::new(NoClientSessionStorage {});
+        tls.enable_tickets = false;
+    } else {
+        tls.session_storage = Arc::clone(&TLS_SESSIONS) as Arc<dyn StoresClientSessions>;
+    }
+
+    tls.enable_early_data = config.tls_early_data;
+
+    // reqwest doesn't set alpn protocols for preconfigured tls
+    tls.alpn_protocols = match config.http_version {
+        Some(http::Version::HTTP_11) => vec![b"http/1.1".to_vec()],
+        Some(http::Version::HTTP_2) => vec![b"h2".to_vec()],
+        _ => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+    };
+
+    Ok(tls)
+}
+
+struct SkipVerification;
+
+impl ServerCertVerifier for SkipVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// verifies the chain, but ignores errors about a wrong name
+struct SkipHostnameVerification(WebPkiVerifier);
+
+impl ServerCertVerifier for SkipHostnameVerification {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        match self.0.verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now) {
+            Err(rustls::Error::InvalidCertificateData(err)) if err.contains("CertNotValidForName") => {
+                Ok(ServerCertVerified::assertion())
+            }
+            res => res,
+        }
+    }
 }
 
 /// check whether the content is binary
@@ -183,4 +574,195 @@ pub fn is_binary_content(content_type: Option<String>) -> bool {
     }
 
     content_type.is_some() && RE_BINARY_MIME.is_match(&content_type.unwrap())
-}
\ No newline at end of file
+}
+
+/// converts internationalized domain names to punycode
+/// and percent-encodes non-ASCII characters within the path and the query,
+/// so the url can be sent within the request line as it is
+pub fn encode_url(url: &str) -> Result<String, Box<dyn Error>> {
+    let (scheme, rest) = match url.split_once("://") {
+        Some(val) if !url.is_ascii() => val,
+        _ => return Ok(url.to_string()),
+    };
+
+    let (authority, path) = rest.split_at(rest.find(|x| x == '/' || x == '?' || x == '#').unwrap_or(rest.len()));
+
+    let (userinfo, host_port) = match authority.rsplit_once('@') {
+        Some((userinfo, host_port)) => (format!("{}@", utf8_percent_encode(userinfo, CONTROLS)), host_port),
+        None => (String::new(), authority),
+    };
+
+    // ipv6 addresses are always ascii
+    let (host, port) = match host_port.rsplit_once(':') {
+        Some((host, port)) if !host.starts_with('[') && port.bytes().all(|x| x.is_ascii_digit()) => {
+            (host, format!(":{}", port))
+        }
+        _ => (host_port, String::new()),
+    };
+
+    let host = if host.is_ascii() {
+        host.to_string()
+    } else {
+        idna::domain_to_ascii(host).map_err(|err| format!("Unable to convert {} to punycode: {:?}", host, err))?
+    };
+
+    Ok(format!("{}://{}{}{}{}", scheme, userinfo, host, port, utf8_percent_encode(path, CONTROLS)))
+}
+
+/// tchar from RFC 7230. Header and cookie names can contain only these characters
+pub fn is_token_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c)
+}
+
+/// returns why the request can be parsed differently by servers on the way to the backend,
+/// e.g. a frontend and a backend that disagree on where the request ends.
+/// None in case the request is well-formed.
+/// Trailers and chunk extensions are checked the same way as headers
+pub fn malformed_reason(
+    target: &str,
+    headers: &[(String, String)],
+    trailers: &[(String, String)],
+    chunk_extensions: &str,
+) -> Option<String> {
+    let has_bare_lf = |x: &str| x.match_indices('\n').any(|(i, _)| i == 0 || x.as_bytes()[i - 1] != b'\r');
+
+    if has_bare_lf(target) {
+        return Some("bare LF within the request target".to_string());
+    }
+
+    if target.bytes().any(|x| x.is_ascii_control()) {
+        return Some("control characters within the request target".to_string());
+    }
+
+    for (kind, fields) in [("header", headers), ("trailer", trailers)] {
+        for (name, value) in fields {
+            if name.is_empty() || !name.bytes().all(is_token_char) {
+                return Some(format!("invalid {} name {:?}", kind, name));
+            }
+
+            if has_bare_lf(value) {
+                return Some(format!("bare LF within the {} {}", name, kind));
+            }
+
+            if value.bytes().any(|x| x.is_ascii_control() && x != b'\t') {
+                return Some(format!("control characters within the {} {}", name, kind));
+            }
+        }
+    }
+
+    // servers that honor framing fields within trailers would end the request elsewhere
+    if let Some((name, _)) = trailers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length") || name.eq_ignore_ascii_case("transfer-encoding"))
+    {
+        return Some(format!("{} within the trailers", name));
+    }
+
+    // the chunk size line ends with CRLF, so anything that breaks the line moves the chunk boundary
+    if chunk_extensions.bytes().any(|x| x.is_ascii_control()) {
+        return Some("control characters within the chunk extensions".to_string());
+    }
+
+    for extension in chunk_extensions.split(';').filter(|x| !x.is_empty()) {
+        let name = extension.split_once('=').map_or(extension, |(name, _)| name).trim();
+
+        if name.is_empty() || !name.bytes().all(is_token_char) {
+            return Some(format!("invalid chunk extension name {:?}", name));
+        }
+    }
+
+    let values = |header: &str| -> Vec<&str> {
+        headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case(header))
+            .map(|(_, value)| value.trim())
+            .collect()
+    };
+
+    let content_length = values("content-length");
+    let transfer_encoding = values("transfer-encoding");
+
+    if !content_length.is_empty() && !transfer_encoding.is_empty() {
+        return Some("both Content-Length and Transfer-Encoding headers".to_string());
+    }
+
+    if content_length.len() > 1 || content_length.iter().any(|x| x.is_empty() || !x.bytes().all(|x| x.is_ascii_digit())) {
+        return Some("ambiguous Content-Length".to_string());
+    }
+
+    if transfer_encoding.len() > 1 || transfer_encoding.iter().any(|x| !x.eq_ignore_ascii_case("chunked")) {
+        return Some("ambiguous Transfer-Encoding".to_string());
+    }
+
+    None
+}
+
+/// passes the raw request to the command's stdin
+/// and returns headers from its stdout (one `Name: value` per line)
+pub async fn run_pre_request_cmd(cmd: &str, request: &str) -> Result<Vec<(String, String)>, String> {
+    run_headers_cmd("--pre-request-cmd", cmd, request).await
+}
+
+/// runs the command with the input on stdin and parses headers from its stdout
+pub async fn run_headers_cmd(flag: &str, cmd: &str, input: &str) -> Result<Vec<(String, String)>, String> {
+    #[cfg(windows)]
+    let mut command = tokio::process::Command::new("cmd");
+    #[cfg(windows)]
+    command.arg("/C").arg(cmd);
+
+    #[cfg(not(windows))]
+    let mut command = tokio::process::Command::new("sh");
+    #[cfg(not(windows))]
+    command.arg("-c").arg(cmd);
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| format!("Unable to run {}: {}", flag, err))?;
+
+    // stdin is written within another task while stdout and stderr are read,
+    // otherwise the command can block on the full stdout pipe while x8 blocks on the full stdin one.
+    // The stdin is dropped after writing so the command receives EOF
+    let writer = child.stdin.take().map(|mut stdin| {
+        let input = input.as_bytes().to_vec();
+        tokio::spawn(async move { stdin.write_all(&input).await })
+    });
+
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|err| format!("{} failed: {}", flag, err))?;
+
+    if let Some(writer) = writer {
+        match writer.await {
+            Ok(Ok(())) => (),
+            // the command may exit without reading the whole input
+            Ok(Err(err)) if err.kind() == std::io::ErrorKind::BrokenPipe => (),
+            Ok(Err(err)) => Err(format!("Unable to write to {}: {}", flag, err))?,
+            Err(err) => Err(format!("Unable to write to {}: {}", flag, err))?,
+        }
+    }
+
+    if !output.status.success() {
+        Err(format!(
+            "{} exited with {}: {}",
+            flag,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))?
+    }
+
+    let mut headers = Vec::new();
+
+    for line in String::from_utf8_lossy(&output.stdout).lines().filter(|x| !x.trim().is_empty()) {
+        match line.split_once(':') {
+            Some((k, v)) => headers.push((k.trim().to_string(), v.trim().to_string())),
+            None => Err(format!("{} returned a wrong header: {}", flag, line))?,
+        }
+    }
+
+    Ok(headers)
+}
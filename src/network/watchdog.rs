@@ -0,0 +1,212 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use tokio::sync::Notify;
+
+use crate::{error::X8Error, runner::shared::HostTarget, utils::error};
+
+use super::keepalive::KeepAlive;
+
+/// the amount of the last finished requests within the stall report
+const MAX_EVENTS: usize = 20;
+
+/// what to do with the requests in flight when nothing arrives within --stall-timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StallAction {
+    /// cancel the requests in flight and send them once more
+    Retry,
+
+    /// cancel the requests in flight and fail every further request
+    Abort,
+}
+
+/// the connections of the target. Their state is read when the report is built
+#[derive(Debug, Clone)]
+pub struct Connections {
+    pub keep_alive: Arc<KeepAlive>,
+
+    /// request slots of the host in case --max-host-concurrency is used
+    pub host_slots: Option<HostTarget>,
+}
+
+impl Connections {
+    fn state(&self) -> String {
+        match self.host_slots.as_ref() {
+            Some(slots) => format!("{}, {} free slots", self.keep_alive.state(), slots.free()),
+            None => self.keep_alive.state(),
+        }
+    }
+}
+
+/// a request that is waiting for the response
+#[derive(Debug)]
+struct InFlight {
+    /// METHOD url
+    description: String,
+
+    connections: Connections,
+
+    started: Instant,
+}
+
+/// notices scans that stopped receiving responses, reports what they're waiting for
+/// and cancels the hanging requests. Shared between all the runners
+#[derive(Debug)]
+pub struct Watchdog {
+    timeout: Duration,
+    action: StallAction,
+
+    /// the last moment a request finished, successfully or not
+    last_activity: Mutex<Instant>,
+
+    in_flight: Mutex<HashMap<usize, InFlight>>,
+    next_id: AtomicUsize,
+
+    /// the last finished requests, the newest at the back
+    events: Mutex<VecDeque<String>>,
+
+    /// wakes the requests in flight up on a stall
+    stall: Notify,
+
+    aborted: AtomicBool,
+}
+
+impl Watchdog {
+    pub fn new(timeout: Duration, action: StallAction) -> Self {
+        Self {
+            timeout,
+            action,
+            last_activity: Mutex::new(Instant::now()),
+            in_flight: Mutex::new(HashMap::new()),
+            next_id: AtomicUsize::new(0),
+            events: Mutex::new(VecDeque::with_capacity(MAX_EVENTS)),
+            stall: Notify::new(),
+            aborted: AtomicBool::new(false),
+        }
+    }
+
+    /// checks for stalls until the scan drops its references to the watchdog
+    pub async fn watch(self: Arc<Self>) {
+        let interval = (self.timeout / 4).max(Duration::from_secs(1));
+
+        while Arc::strong_count(&self) > 1 {
+            tokio::time::sleep(interval).await;
+
+            // requests that wait for a slot, a 429 pause or --active-hours aren't in flight yet
+            if self.last_activity.lock().elapsed() < self.timeout || self.in_flight.lock().is_empty() {
+                continue;
+            }
+
+            error(self.report(), None, None, None);
+
+            if self.action == StallAction::Abort {
+                self.aborted.store(true, Ordering::SeqCst);
+            }
+
+            *self.last_activity.lock() = Instant::now();
+            self.stall.notify_waiters();
+        }
+    }
+
+    /// sends the request, unless it's cancelled by a stall.
+    /// With StallAction::Retry a cancelled request is sent once more
+    pub async fn guard<T, F, Fut>(&self, description: String, connections: Connections, send: F) -> Result<T, X8Error>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, X8Error>>,
+    {
+        for attempt in 0..2 {
+            if self.aborted.load(Ordering::SeqCst) {
+                Err(X8Error::Network(format!(
+                    "The scan was aborted because no responses arrived for {}s",
+                    self.timeout.as_secs()
+                )))?
+            }
+
+            let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+            let started = Instant::now();
+
+            self.in_flight.lock().insert(
+                id,
+                InFlight {
+                    description: description.clone(),
+                    connections: connections.clone(),
+                    started,
+                },
+            );
+
+            let result = tokio::select! {
+                res = send() => Some(res),
+                _ = self.stall.notified() => None,
+            };
+
+            self.in_flight.lock().remove(&id);
+
+            let event = match result.as_ref() {
+                Some(Ok(_)) => format!("{} finished in {}ms", description, started.elapsed().as_millis()),
+                Some(Err(err)) => format!("{} failed after {}ms: {}", description, started.elapsed().as_millis(), err),
+                None => format!("{} was cancelled after {}ms", description, started.elapsed().as_millis()),
+            };
+            self.record(event);
+
+            match result {
+                Some(val) => return val,
+                None if self.action == StallAction::Retry && attempt == 0 => continue,
+                None => (),
+            }
+        }
+
+        Err(X8Error::Network(format!("No response within {}s", self.timeout.as_secs())))
+    }
+
+    fn record(&self, event: String) {
+        *self.last_activity.lock() = Instant::now();
+
+        let mut events = self.events.lock();
+        if events.len() == MAX_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// the requests in flight, their connections and the last events
+    pub(super) fn report(&self) -> String {
+        let mut report = format!(
+            "No responses for {}s. {} requests in flight:",
+            self.timeout.as_secs(),
+            self.in_flight.lock().len()
+        );
+
+        let mut in_flight: Vec<(String, String, Instant)> = self
+            .in_flight
+            .lock()
+            .values()
+            .map(|x| (x.description.clone(), x.connections.state(), x.started))
+            .collect();
+        in_flight.sort_by_key(|x| x.2);
+
+        for (description, connection, started) in in_flight {
+            report += &format!("\n    {} for {}s ({})", description, started.elapsed().as_secs(), connection);
+        }
+
+        report += "\n  The last events:";
+        for event in self.events.lock().iter() {
+            report += &format!("\n    {}", event);
+        }
+
+        report += match self.action {
+            StallAction::Retry => "\n  Resending the requests",
+            StallAction::Abort => "\n  Aborting the scan",
+        };
+
+        report
+    }
+}
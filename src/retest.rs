@@ -0,0 +1,190 @@
+use std::{
+    error::Error,
+    fs,
+    io::{self, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::{App, Arg};
+use colored::Colorize;
+use indicatif::ProgressBar;
+use serde_json::{json, Value};
+
+use crate::{
+    config::{args::report_config, structs::Config},
+    error::{EXIT_FOUND, EXIT_OK},
+    network::request::{Request, RequestDefaults},
+    runner::{output::read_outputs, runner::Runner, utils::Confirmation},
+};
+
+/// rechecks the found parameters of one output against the live target.
+/// Returns the names that are still found
+async fn recheck(output: &Value, args: Vec<String>) -> Result<Vec<String>, Box<dyn Error>> {
+    let method = output["method"].as_str().ok_or("The method is missing")?;
+    let url = output["url"].as_str().ok_or("The url is missing")?;
+
+    let config = report_config(args, method, url)?;
+    let mut request_defaults = RequestDefaults::from_config(&config, method, url)?;
+
+    let mut confirmations = Vec::new();
+    let mut params = Vec::new();
+
+    for param in output["found_params"].as_array().into_iter().flatten() {
+        let name = match param["name"].as_str() {
+            Some(val) => val.to_string(),
+            None => continue,
+        };
+
+        match serde_json::from_value::<Confirmation>(param["confirmation"].clone()) {
+            Ok(confirmation) => confirmations.push((name, confirmation)),
+            // reports of older versions don't contain the requests.
+            // The parameters are sent with the values they were found with
+            Err(_) => params.push(match param["value"].as_str() {
+                Some(value) => format!("{}={}", name, value),
+                None => name,
+            }),
+        }
+    }
+
+    let mut found = resend(&request_defaults, &confirmations).await?;
+
+    if !params.is_empty() {
+        found.append(&mut rescan(&config, &mut request_defaults, params).await?);
+    }
+
+    Ok(found)
+}
+
+/// sends the requests that confirmed the parameters and compares them with the page without parameters.
+/// Returns the names of the parameters that still change the page the same way
+async fn resend(
+    request_defaults: &RequestDefaults,
+    confirmations: &[(String, Confirmation)],
+) -> Result<Vec<String>, Box<dyn Error>> {
+    if confirmations.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let initial_response = Request::new(request_defaults, Vec::new()).send().await?;
+
+    // the lines that change by themselves aren't counted
+    let (_, dynamic_diffs) = Request::new(request_defaults, Vec::new())
+        .send()
+        .await?
+        .compare(&initial_response, &[])?;
+
+    let mut found = Vec::new();
+
+    for (name, confirmation) in confirmations {
+        let response = confirmation.request(request_defaults).send().await?;
+        let (is_code_diff, diffs) = response.compare(&initial_response, &dynamic_diffs)?;
+
+        if response.code == confirmation.status && (is_code_diff || !diffs.is_empty()) {
+            found.push(name.to_owned());
+        }
+    }
+
+    Ok(found)
+}
+
+/// learns the page again and checks only the parameters
+async fn rescan(
+    config: &Config,
+    request_defaults: &mut RequestDefaults,
+    mut params: Vec<String>,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let progress_bar = ProgressBar::hidden();
+
+    let found = Runner::new(config, request_defaults, &progress_bar, 0)
+        .await?
+        .run(&mut params)
+        .await?
+        .found_params;
+
+    Ok(found.into_iter().map(|x| x.name).collect())
+}
+
+/// the arguments of x8 verify-report. Also used to generate shell completions
+pub fn verify_report_app() -> App<'static, 'static> {
+    App::new("verify-report")
+        .bin_name("x8 verify-report")
+        .about("Recheck the findings of a json or jsonl output against the live targets")
+        .arg(
+            Arg::with_name("report")
+                .help("The json or jsonl output of the previous run")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .help("The file to write the report with the recheck results to")
+                .takes_value(true),
+        )
+}
+
+/// x8 verify-report report.json [-o updated.json].
+/// args are the command line starting with the program name and "verify-report". Returns the exit code
+pub async fn verify_report(args: Vec<String>) -> Result<i32, Box<dyn Error>> {
+    let args = verify_report_app().get_matches_from(args.into_iter().skip(1));
+
+    let mut outputs = read_outputs(args.value_of("report").unwrap())?;
+
+    // jsonl outputs contain the config only within the first line of every url
+    let default_args = outputs.iter().find_map(|x| x["config"]["args"].as_array().cloned());
+
+    let (mut present, mut gone, mut failed) = (0, 0, 0);
+    let checked_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |x| x.as_millis()) as u64;
+
+    for output in outputs.iter_mut() {
+        if output["found_params"].as_array().map_or(true, |x| x.is_empty()) {
+            continue;
+        }
+
+        let report_args = match output["config"]["args"].as_array().cloned().or_else(|| default_args.clone()) {
+            Some(val) => val.iter().filter_map(|x| x.as_str()).map(|x| x.to_string()).collect(),
+            None => Err("The report doesn't contain the scan config. Only json and jsonl outputs contain it")?,
+        };
+
+        let method = output["method"].as_str().unwrap_or_default().to_string();
+        let url = output["url"].as_str().unwrap_or_default().to_string();
+
+        let result = recheck(output, report_args).await;
+
+        for param in output["found_params"].as_array_mut().unwrap() {
+            let name = param["name"].as_str().unwrap_or_default().to_string();
+
+            let status = match result.as_ref() {
+                Ok(found) if found.contains(&name) => {
+                    present += 1;
+                    writeln!(io::stdout(), "{} {} {} {}", "[still-present]".green(), method, url, name).ok();
+                    "still-present"
+                }
+                Ok(_) => {
+                    gone += 1;
+                    writeln!(io::stdout(), "{} {} {} {}", "[gone]".red(), method, url, name).ok();
+                    "gone"
+                }
+                Err(err) => {
+                    failed += 1;
+                    writeln!(io::stdout(), "{} {} {} {}: {}", "[unchecked]".yellow(), method, url, name, err).ok();
+                    "unchecked"
+                }
+            };
+
+            param["recheck"] = json!({"status": status, "checked_at": checked_at});
+        }
+    }
+
+    writeln!(io::stdout(), "\n{} still present, {} gone, {} unchecked", present, gone, failed).ok();
+
+    if let Some(path) = args.value_of("output") {
+        fs::write(path, serde_json::to_string_pretty(&outputs)?)?;
+    }
+
+    if gone > 0 {
+        Ok(EXIT_FOUND)
+    } else {
+        Ok(EXIT_OK)
+    }
+}
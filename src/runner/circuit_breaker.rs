@@ -0,0 +1,103 @@
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use tokio::sync::Mutex;
+
+use crate::error::X8Error;
+
+/// the maximum amount of attempts to reach the target while the circuit is open
+const MAX_RECOVERY_ATTEMPTS: u32 = 10;
+
+/// the maximum delay between the attempts
+const MAX_BACKOFF_SECS: u64 = 60;
+
+/// opens after `threshold` consecutive chunks failed because of connection errors and pauses the scan until the target recovers.
+/// Other errors, like an unstable page or an exhausted error budget, mean that the target is up, so they aren't counted
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: usize,
+    consecutive_errors: AtomicUsize,
+    open: AtomicBool,
+
+    /// locked by the future which is checking whether the target has recovered
+    /// other futures are waiting for it to be unlocked
+    recovery: Mutex<()>,
+
+    /// the amount of chunks that were retested after the recovery
+    pub retested: AtomicUsize,
+}
+
+impl CircuitBreaker {
+    /// threshold == 0 disables the breaker
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            consecutive_errors: AtomicUsize::new(0),
+            open: AtomicBool::new(false),
+            recovery: Mutex::new(()),
+            retested: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.load(Ordering::SeqCst)
+    }
+
+    /// waits until the circuit is closed
+    pub async fn wait(&self) {
+        if self.is_open() {
+            drop(self.recovery.lock().await);
+        }
+    }
+
+    pub fn success(&self) {
+        self.consecutive_errors.store(0, Ordering::SeqCst);
+    }
+
+    /// returns true in case the circuit is open and the failed chunk should be retested after the recovery
+    pub fn failure(&self, err: &X8Error) -> bool {
+        if self.threshold == 0 || !matches!(err, X8Error::Network(_) | X8Error::ConnectionClosed(_)) {
+            return false;
+        }
+
+        let errors = self.consecutive_errors.fetch_add(1, Ordering::SeqCst) + 1;
+        if errors >= self.threshold {
+            self.open.store(true, Ordering::SeqCst);
+        }
+
+        self.is_open()
+    }
+
+    /// retries `probe` with exponential backoff until it succeeds.
+    /// Only one future is probing at a time, the rest are waiting for the result.
+    pub async fn recover<F, Fut>(&self, probe: F) -> Result<(), String>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = bool>,
+    {
+        let _guard = self.recovery.lock().await;
+
+        // another future has already recovered the circuit
+        if !self.is_open() {
+            return Ok(());
+        }
+
+        for attempt in 0..MAX_RECOVERY_ATTEMPTS {
+            let delay = std::cmp::min(2u64.pow(attempt), MAX_BACKOFF_SECS);
+            tokio::time::sleep(Duration::from_secs(delay)).await;
+
+            if probe().await {
+                self.consecutive_errors.store(0, Ordering::SeqCst);
+                self.open.store(false, Ordering::SeqCst);
+                return Ok(());
+            }
+        }
+
+        Err(format!(
+            "The target didn't recover after {} attempts",
+            MAX_RECOVERY_ATTEMPTS
+        ))
+    }
+}
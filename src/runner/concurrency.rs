@@ -0,0 +1,132 @@
+use std::{cmp, collections::VecDeque};
+
+use parking_lot::Mutex;
+use tokio::sync::Notify;
+
+/// how many finished chunks to collect before adjusting the limit
+const WINDOW_SIZE: usize = 10;
+
+/// p95 is calculated over the latencies of the last chunks rather than over one window,
+/// otherwise it would be just the slowest chunk.
+/// Until MIN_SAMPLES chunks finished only errors change the limit
+const LATENCY_HISTORY: usize = 40;
+const MIN_SAMPLES: usize = 20;
+
+/// AIMD-style controller of the amount of concurrent chunks.
+/// Increases the limit by 1 while there are no errors and p95 latency stays close to the first window's one.
+/// Halves the limit otherwise.
+#[derive(Debug)]
+pub struct ConcurrencyController {
+    state: Mutex<ControllerState>,
+    notify: Notify,
+    max: usize,
+}
+
+#[derive(Debug)]
+struct ControllerState {
+    limit: usize,
+    active: usize,
+
+    /// the amount of finished chunks and errors within the current window
+    finished: usize,
+    errors: usize,
+
+    /// latencies of the last LATENCY_HISTORY chunks
+    latencies: VecDeque<u128>,
+
+    /// p95 latency of the first MIN_SAMPLES chunks
+    baseline_p95: Option<u128>,
+}
+
+impl ConcurrencyController {
+    pub fn new(initial: usize, max: usize) -> Self {
+        Self {
+            state: Mutex::new(ControllerState {
+                limit: cmp::max(1, cmp::min(initial, max)),
+                active: 0,
+                finished: 0,
+                errors: 0,
+                latencies: VecDeque::with_capacity(LATENCY_HISTORY),
+                baseline_p95: None,
+            }),
+            notify: Notify::new(),
+            max,
+        }
+    }
+
+    /// waits until the amount of active chunks is below the limit
+    pub async fn acquire(&self) {
+        loop {
+            // the future should be created before the check to not miss notifications
+            let notified = self.notify.notified();
+
+            {
+                let mut state = self.state.lock();
+                if state.active < state.limit {
+                    state.active += 1;
+                    return;
+                }
+            }
+
+            notified.await;
+        }
+    }
+
+    /// marks the chunk as finished and adjusts the limit at the end of every window
+    pub fn release(&self, latency: u128, is_error: bool) {
+        let mut state = self.state.lock();
+
+        state.active -= 1;
+        state.finished += 1;
+        if is_error {
+            state.errors += 1;
+        }
+
+        if state.latencies.len() == LATENCY_HISTORY {
+            state.latencies.pop_front();
+        }
+        state.latencies.push_back(latency);
+
+        if state.finished >= WINDOW_SIZE {
+            let p95 = if state.latencies.len() >= MIN_SAMPLES {
+                let mut sorted: Vec<u128> = state.latencies.iter().copied().collect();
+                sorted.sort_unstable();
+                Some(sorted[sorted.len() * 95 / 100])
+            } else {
+                None
+            };
+
+            let baseline_p95 = match p95 {
+                Some(p95) => Some(*state.baseline_p95.get_or_insert(p95)),
+                None => None,
+            };
+
+            let old_limit = state.limit;
+
+            state.limit = match (p95, baseline_p95) {
+                _ if state.errors > 0 => cmp::max(1, state.limit / 2),
+                (Some(p95), Some(baseline_p95)) if p95 > baseline_p95 * 2 => cmp::max(1, state.limit / 2),
+                (Some(_), Some(_)) => cmp::min(self.max, state.limit + 1),
+                // not enough samples to tell whether the target slows down
+                _ => state.limit,
+            };
+
+            if state.limit != old_limit {
+                log::debug!(
+                    "Concurrency {} -> {} (p95 {:?}ms, baseline {:?}ms, {} errors)",
+                    old_limit, state.limit, p95, baseline_p95, state.errors
+                );
+            }
+
+            state.finished = 0;
+            state.errors = 0;
+        }
+
+        drop(state);
+        self.notify.notify_waiters();
+    }
+
+    pub fn limit(&self) -> usize {
+        self.state.lock().limit
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{config::structs::Config, error::X8Error, network::response::Response};
+
+use super::utils::{ReasonKind, Stable};
+
+/// what the response to a chunk of parameters tells about them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Detection {
+    /// the response is the same as the initial one
+    Nothing,
+
+    /// the parameter is found right away, without narrowing the chunk.
+    /// repeat tells whether the rest of the chunk needs to be rechecked
+    Found {
+        name: String,
+        kind: ReasonKind,
+        diffs: Vec<String>,
+        repeat: bool,
+    },
+
+    /// several parameters are found right away.
+    /// The rest of the chunk doesn't need to be rechecked
+    FoundSeveral { names: Vec<String>, kind: ReasonKind },
+
+    /// one of the parameters changed the response.
+    /// The chunk is splitted until there's one parameter left
+    Changed { kind: ReasonKind, diffs: Vec<String> },
+
+    /// the response can't be trusted. The chunk is rechecked in halves
+    Recheck,
+}
+
+/// everything a detector knows about the response
+pub struct DetectionInput<'a> {
+    pub config: &'a Config,
+
+    /// whether body or/and reflections are stable
+    pub stable: &'a Stable,
+
+    pub initial_response: &'a Response<'a>,
+
+    /// reflected parameters are already filled in case reflections are stable
+    pub response: &'a Response<'a>,
+
+    /// parameters of the chunk. Empty for responses to random parameters
+    pub params: &'a [String],
+
+    /// diffs that appear with random parameters as well
+    pub known_diffs: &'a [String],
+}
+
+/// detection modules. They're run for every response in the registration order
+/// until one of them reports Changed or Recheck
+pub trait Detector: Send + Sync {
+    /// identifies the detector, e.g. to remove it from the runner
+    fn name(&self) -> &'static str;
+
+    fn detect(&self, input: &DetectionInput) -> Result<Detection, X8Error>;
+
+    /// whether a change needs to be confirmed with a request with random parameters.
+    /// Diffs that appear within that response as well are added to the known ones
+    fn needs_confirmation(&self) -> bool {
+        true
+    }
+}
+
+/// finds parameters by the different amount of reflections
+pub struct ReflectionDetector;
+
+impl Detector for ReflectionDetector {
+    fn name(&self) -> &'static str {
+        "reflection"
+    }
+
+    fn detect(&self, input: &DetectionInput) -> Result<Detection, X8Error> {
+        if !input.stable.reflections {
+            return Ok(Detection::Nothing);
+        }
+
+        Ok(match input.response.proceed_reflected_parameters() {
+            (Some(name), repeat) => Detection::Found {
+                name: name.to_string(),
+                // explained in response.proceed_reflected_parameters() method
+                // params.len() == 1 and not 2 because the random parameter appends later
+                kind: if input.params.len() == 1 {
+                    ReasonKind::NotReflected
+                } else {
+                    ReasonKind::Reflected
+                },
+                diffs: Vec::new(),
+                repeat,
+            },
+            (None, true) => match input.response.unambiguous_reflected_parameters() {
+                // every reflected parameter is attributed by its own value, no need to narrow the chunk
+                Some(names) => Detection::FoundSeveral {
+                    names: names.into_iter().map(|x| x.to_string()).collect(),
+                    kind: ReasonKind::Reflected,
+                },
+                None => Detection::Recheck,
+            },
+            (None, false) => Detection::Nothing,
+        })
+    }
+}
+
+/// finds parameters that change the response code
+pub struct CodeDetector;
+
+impl Detector for CodeDetector {
+    fn name(&self) -> &'static str {
+        "code"
+    }
+
+    fn detect(&self, input: &DetectionInput) -> Result<Detection, X8Error> {
+        // the code is compared with the exact code of the page, even when several codes are expected
+        if is_reflected_only(input) || input.initial_response.code == input.response.code {
+            return Ok(Detection::Nothing);
+        }
+
+        Ok(Detection::Changed {
+            kind: ReasonKind::Code,
+            diffs: vec![format!("{} -> {}", input.initial_response.code, input.response.code)],
+        })
+    }
+
+    // the amount of changed codes is tracked instead
+    fn needs_confirmation(&self) -> bool {
+        false
+    }
+}
+
+/// finds parameters that change the body or headers
+pub struct BodyDiffDetector;
+
+impl Detector for BodyDiffDetector {
+    fn name(&self) -> &'static str {
+        "body"
+    }
+
+    fn detect(&self, input: &DetectionInput) -> Result<Detection, X8Error> {
+        if is_reflected_only(input) || !input.stable.body {
+            return Ok(Detection::Nothing);
+        }
+
+        let (_, diffs) = input.response.compare(input.initial_response, input.known_diffs)?;
+
+        if diffs.is_empty() {
+            Ok(Detection::Nothing)
+        } else {
+            Ok(Detection::Changed { kind: ReasonKind::Text, diffs })
+        }
+    }
+}
+
+/// reflection, code and body diff detectors
+pub fn default_detectors() -> Vec<Box<dyn Detector>> {
+    vec![Box::new(ReflectionDetector), Box::new(CodeDetector), Box::new(BodyDiffDetector)]
+}
+
+/// --reflected-only falls back to other detectors only in case reflections aren't stable
+fn is_reflected_only(input: &DetectionInput) -> bool {
+    input.config.reflected_only && input.stable.reflections
+}
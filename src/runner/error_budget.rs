@@ -0,0 +1,112 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::error::X8Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// requests with random parameters to learn the page's behavior
+    Learning,
+    /// the first request of every chunk
+    Discovery,
+    /// splitting changed chunks into smaller parts
+    Narrowing,
+    /// rechecking found parameters
+    Verification,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 4] = [
+        Phase::Learning,
+        Phase::Discovery,
+        Phase::Narrowing,
+        Phase::Verification,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_name(name: &str) -> Option<Phase> {
+        Phase::ALL.iter().copied().find(|x| x.to_string() == name)
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Phase::Learning => "learning",
+            Phase::Discovery => "discovery",
+            Phase::Narrowing => "narrowing",
+            Phase::Verification => "verification",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// counts failed requests per phase and aborts the phase once its limit is exceeded
+#[derive(Debug)]
+pub struct ErrorBudget {
+    limits: [usize; 4],
+    errors: [AtomicUsize; 4],
+}
+
+impl ErrorBudget {
+    pub fn new(limits: [usize; 4]) -> Self {
+        Self {
+            limits,
+            errors: Default::default(),
+        }
+    }
+
+    pub fn errors(&self, phase: Phase) -> usize {
+        self.errors[phase.index()].load(Ordering::SeqCst)
+    }
+
+    pub fn is_exhausted(&self, phase: Phase) -> bool {
+        self.errors(phase) > self.limits[phase.index()]
+    }
+
+    /// records the error and returns Err in case the phase's budget is exhausted
+    pub fn record(&self, phase: Phase, err: &dyn fmt::Display) -> Result<(), X8Error> {
+        // the error was already counted by the nested call
+        if !self.is_exhausted(phase) {
+            self.errors[phase.index()].fetch_add(1, Ordering::SeqCst);
+        }
+
+        self.check(phase, err)
+    }
+
+    /// returns Err in case the phase's budget is exhausted
+    pub fn check(&self, phase: Phase, err: &dyn fmt::Display) -> Result<(), X8Error> {
+        if self.is_exhausted(phase) {
+            Err(X8Error::ErrorBudget(format!(
+                "Too many errors during the {} phase ({} > {}). The last one: {}",
+                phase,
+                self.errors(phase),
+                self.limits[phase.index()],
+                err
+            )))?
+        }
+
+        Ok(())
+    }
+
+    /// returns something like "discovery: 3/10, narrowing: 1/10" or None in case there were no errors
+    pub fn summary(&self) -> Option<String> {
+        let summary = Phase::ALL
+            .iter()
+            .filter(|x| self.errors(**x) > 0)
+            .map(|x| format!("{}: {}/{}", x, self.errors(*x), self.limits[x.index()]))
+            .collect::<Vec<String>>();
+
+        if summary.is_empty() {
+            None
+        } else {
+            Some(summary.join(", "))
+        }
+    }
+}
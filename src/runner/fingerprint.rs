@@ -0,0 +1,266 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::{network::response::Response, utils::base64_encode};
+
+use super::runner::Runner;
+
+/// (technology, header name, regex for the header value)
+/// set-cookie rules match cookie names
+const HEADER_RULES: &[(&str, &str, &str)] = &[
+    ("php", "x-powered-by", r"(?i)php"),
+    ("express", "x-powered-by", r"(?i)express"),
+    ("nextjs", "x-powered-by", r"(?i)next\.js"),
+    ("asp.net", "x-powered-by", r"(?i)asp\.net"),
+    ("asp.net", "x-aspnet-version", r"."),
+    ("drupal", "x-generator", r"(?i)drupal"),
+    ("drupal", "x-drupal-cache", r"."),
+    ("wordpress", "link", r"(?i)api\.w\.org"),
+    ("wordpress", "set-cookie", r"^(wordpress_|wp-settings)"),
+    ("django", "set-cookie", r"^(csrftoken|django_language)="),
+    ("laravel", "set-cookie", r"^laravel_session="),
+    ("java", "set-cookie", r"^JSESSIONID="),
+    ("php", "set-cookie", r"^PHPSESSID="),
+    ("asp.net", "set-cookie", r"^ASP\.NET_SessionId="),
+    ("codeigniter", "set-cookie", r"^ci_session="),
+];
+
+/// (technology, regex for the body)
+const BODY_RULES: &[(&str, &str)] = &[
+    ("wordpress", r"/wp-(content|includes)/"),
+    ("django", r"csrfmiddlewaretoken"),
+    ("asp.net", r"__VIEWSTATE"),
+    ("spring", r"Whitelabel Error Page"),
+    ("drupal", r#"(?i)<meta[^>]+name="generator"[^>]+content="drupal"#),
+    ("joomla", r#"(?i)<meta[^>]+name="generator"[^>]+content="joomla"#),
+    ("laravel", r"laravel_session|Laravel"),
+];
+
+/// (cdn or cache, header name, regex for the header value)
+const CDN_RULES: &[(&str, &str, &str)] = &[
+    ("cloudflare", "cf-ray", r"."),
+    ("cloudfront", "x-amz-cf-id", r"."),
+    ("fastly", "x-fastly-request-id", r"."),
+    ("fastly", "x-served-by", r"(?i)^cache-"),
+    ("akamai", "x-akamai-transformed", r"."),
+    ("akamai", "server", r"(?i)akamai"),
+    ("varnish", "x-varnish", r"."),
+    ("varnish", "via", r"(?i)varnish"),
+    ("cache", "x-cache", r"(?i)\b(hit|miss)"),
+    ("cache", "age", r"^\d+$"),
+];
+
+/// (technology, path, substring that should be within the response)
+const PATH_RULES: &[(&str, &str, &str)] = &[
+    ("wordpress", "/wp-login.php", "wp-submit"),
+    ("spring", "/actuator/health", "\"status\""),
+    ("django", "/admin/login/", "Django"),
+];
+
+/// (technology, shodan-like favicon hash)
+const FAVICON_HASHES: &[(&str, i32)] = &[("spring", 116323821), ("jenkins", 81586312)];
+
+/// parameters that are specific to the technology
+pub const TECHNOLOGY_PARAMS: &[(&str, &[&str])] = &[
+    ("wordpress", &[
+        "p", "page_id", "s", "cat", "author", "preview", "attachment_id", "post_type", "feed",
+        "rest_route", "orderby", "order", "paged", "m", "tag", "year", "monthnum", "name", "static",
+    ]),
+    ("django", &[
+        "next", "format", "page", "page_size", "ordering", "search", "q", "lang", "_popup",
+        "_changelist_filters", "o", "all",
+    ]),
+    ("spring", &[
+        "_method", "format", "lang", "locale", "page", "size", "sort", "callback", "debug", "trace",
+        "fields", "projection", "view", "theme",
+    ]),
+    ("laravel", &[
+        "_token", "_method", "page", "per_page", "sort", "include", "filter", "XDEBUG_SESSION_START",
+    ]),
+    ("php", &["XDEBUG_SESSION_START", "XDEBUG_PROFILE", "debug", "source", "phpinfo"]),
+    ("asp.net", &[
+        "__VIEWSTATE", "__EVENTVALIDATION", "__EVENTTARGET", "__EVENTARGUMENT", "ReturnUrl",
+        "aspxerrorpath", "trace",
+    ]),
+    ("drupal", &["q", "destination", "_format", "_wrapper_format", "page", "render"]),
+    ("joomla", &["option", "view", "task", "tmpl", "Itemid", "format", "lang"]),
+];
+
+impl<'a> Runner<'a> {
+    /// detects the technologies behind the target by the initial response, a few common paths and the favicon
+    pub(super) async fn fingerprint(&self) -> Vec<String> {
+        let mut technologies = detect_from_response(&self.initial_response);
+
+        let base = format!(
+            "{}://{}:{}",
+            self.request_defaults.scheme, self.request_defaults.host, self.request_defaults.port
+        );
+
+        for (technology, path, marker) in PATH_RULES {
+            if technologies.iter().any(|x| x == technology) {
+                continue;
+            }
+
+            if let Some(response) = self.fetch(&format!("{}{}", base, path)).await {
+                if response.status().is_success() && response.text().await.map_or(false, |x| x.contains(marker)) {
+                    technologies.push(technology.to_string());
+                }
+            }
+        }
+
+        if let Some(response) = self.fetch(&format!("{}/favicon.ico", base)).await {
+            if response.status().is_success() {
+                if let Ok(favicon) = response.bytes().await {
+                    let hash = favicon_hash(&favicon);
+
+                    for (technology, _) in FAVICON_HASHES.iter().filter(|(_, x)| *x == hash) {
+                        technologies.push(technology.to_string());
+                    }
+                }
+            }
+        }
+
+        technologies.sort();
+        technologies.dedup();
+
+        technologies
+    }
+
+    /// requests another path of the target the same way as the scan requests: after --delay and with the custom headers
+    async fn fetch(&self, url: &str) -> Option<reqwest::Response> {
+        tokio::time::sleep(self.request_defaults.delay).await;
+
+        let mut request = self.request_defaults.client.get(url);
+
+        // the header with the injection point is sent only within the scan requests
+        for (key, value) in self.request_defaults.custom_headers.iter().filter(|(_, v)| !v.contains("%s")) {
+            request = request.header(key.as_str(), value.as_str());
+        }
+
+        request.send().await.ok()
+    }
+}
+
+/// detects technologies only by headers, cookies and the body
+pub fn detect_from_response(response: &Response) -> Vec<String> {
+    lazy_static! {
+        static ref RE_HEADER_RULES: Vec<(&'static str, &'static str, Regex)> = HEADER_RULES
+            .iter()
+            .map(|(technology, header, re)| (*technology, *header, Regex::new(re).unwrap()))
+            .collect();
+        static ref RE_BODY_RULES: Vec<(&'static str, Regex)> = BODY_RULES
+            .iter()
+            .map(|(technology, re)| (*technology, Regex::new(re).unwrap()))
+            .collect();
+    }
+
+    let mut technologies = Vec::new();
+
+    for (technology, header, re) in RE_HEADER_RULES.iter() {
+        if response
+            .headers
+            .iter()
+            .any(|(k, v)| k.to_lowercase() == *header && re.is_match(v))
+        {
+            technologies.push(technology.to_string());
+        }
+    }
+
+    for (technology, re) in RE_BODY_RULES.iter() {
+        if re.is_match(&response.text) {
+            technologies.push(technology.to_string());
+        }
+    }
+
+    technologies.sort();
+    technologies.dedup();
+
+    technologies
+}
+
+/// detects CDNs and caching proxies in front of the target by the headers of the response
+pub fn detect_cdn(response: &Response) -> Option<&'static str> {
+    lazy_static! {
+        static ref RE_CDN_RULES: Vec<(&'static str, &'static str, Regex)> = CDN_RULES
+            .iter()
+            .map(|(cdn, header, re)| (*cdn, *header, Regex::new(re).unwrap()))
+            .collect();
+    }
+
+    RE_CDN_RULES
+        .iter()
+        .find(|(_, header, re)| {
+            response
+                .headers
+                .iter()
+                .any(|(k, v)| k.to_lowercase() == *header && re.is_match(v))
+        })
+        .map(|(cdn, _, _)| *cdn)
+}
+
+/// returns specialized parameters for the technologies
+pub fn technology_params(technologies: &[String]) -> Vec<String> {
+    let mut params = Vec::new();
+
+    for (_, names) in TECHNOLOGY_PARAMS
+        .iter()
+        .filter(|(technology, _)| technologies.iter().any(|x| x == technology))
+    {
+        for name in names.iter() {
+            if !params.iter().any(|x| x == name) {
+                params.push(name.to_string());
+            }
+        }
+    }
+
+    params
+}
+
+/// the same hash as shodan uses: murmur3 of the base64 encoded favicon with new lines every 76 chars
+fn favicon_hash(favicon: &[u8]) -> i32 {
+    let encoded = base64_encode(favicon, false);
+
+    let mut data = String::with_capacity(encoded.len() + encoded.len() / 76 + 1);
+    for chunk in encoded.as_bytes().chunks(76) {
+        data.push_str(std::str::from_utf8(chunk).unwrap());
+        data.push('\n');
+    }
+
+    murmur3_32(data.as_bytes()) as i32
+}
+
+/// murmur3 x86 32bit with 0 seed
+fn murmur3_32(data: &[u8]) -> u32 {
+    const C1: u32 = 0xcc9e2d51;
+    const C2: u32 = 0x1b873593;
+
+    let mut hash: u32 = 0;
+    let chunks = data.chunks_exact(4);
+    let tail = chunks.remainder();
+
+    for chunk in chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+        hash ^= k;
+        hash = hash.rotate_left(13).wrapping_mul(5).wrapping_add(0xe6546b64);
+    }
+
+    if !tail.is_empty() {
+        let mut k: u32 = 0;
+        for (i, byte) in tail.iter().enumerate() {
+            k ^= (*byte as u32) << (8 * i);
+        }
+
+        hash ^= k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+    }
+
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85ebca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2ae35);
+    hash ^= hash >> 16;
+
+    hash
+}
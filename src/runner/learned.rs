@@ -0,0 +1,76 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+use serde_json::json;
+
+/// parameters found during previous scans per technology fingerprint
+/// stored as jsonl with one {"fingerprint": "..", "name": ".."} line per found parameter
+#[derive(Debug, Default)]
+pub struct LearnedDb {
+    /// fingerprint -> (name -> how many times it was found)
+    entries: HashMap<String, HashMap<String, usize>>,
+}
+
+impl LearnedDb {
+    pub fn read(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut db = LearnedDb::default();
+
+        if !Path::new(path).exists() {
+            return Ok(db);
+        }
+
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let entry: serde_json::Value = serde_json::from_str(&line)?;
+
+            if let (Some(fingerprint), Some(name)) = (entry["fingerprint"].as_str(), entry["name"].as_str()) {
+                *db.entries
+                    .entry(fingerprint.to_string())
+                    .or_default()
+                    .entry(name.to_string())
+                    .or_default() += 1;
+            }
+        }
+
+        Ok(db)
+    }
+
+    /// returns the parameters found for the fingerprint, the most frequent first
+    pub fn names(&self, fingerprint: &str) -> Vec<String> {
+        let mut names: Vec<(&String, &usize)> = match self.entries.get(fingerprint) {
+            Some(val) => val.iter().collect(),
+            None => return Vec::new(),
+        };
+
+        names.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+        names.into_iter().map(|(name, _)| name.to_owned()).collect()
+    }
+
+    pub fn append(path: &str, fingerprint: &str, names: &[String]) -> Result<(), Box<dyn Error>> {
+        if names.is_empty() {
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        let mut lines = String::new();
+        for name in names {
+            lines += &format!("{}\n", json!({"fingerprint": fingerprint, "name": name}));
+        }
+
+        // a single write to not mix lines from parallel runners
+        file.write_all(lines.as_bytes())?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Weak},
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, BufReader},
+};
+
+/// how often the --extra-params-watch file is checked for new lines
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// parameters added while the scan is running, e.g. names found within responses.
+/// They're checked after the current chunks, every name only once
+#[derive(Debug, Default)]
+pub struct LiveQueue {
+    known: Mutex<HashSet<String>>,
+    pending: Mutex<Vec<String>>,
+}
+
+impl LiveQueue {
+    /// remembers the parameters that are already going to be checked
+    pub fn extend_known(&self, params: &[String]) {
+        self.known.lock().extend(params.iter().cloned());
+    }
+
+    pub fn is_known(&self, param: &str) -> bool {
+        self.known.lock().contains(param)
+    }
+
+    /// queues the parameters that weren't seen before. Returns the amount of new ones
+    pub fn push(&self, params: Vec<String>) -> usize {
+        let mut known = self.known.lock();
+        let new: Vec<String> = params.into_iter().filter(|x| known.insert(x.to_owned())).collect();
+        let amount = new.len();
+
+        self.pending.lock().extend(new);
+
+        amount
+    }
+
+    /// returns the queued parameters
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.pending.lock())
+    }
+}
+
+/// names appended to the --extra-params-watch file while the scan is running.
+/// Shared between runners, every runner checks the names it hasn't checked yet
+#[derive(Debug, Default)]
+pub struct ExtraParams {
+    names: Mutex<Vec<String>>,
+}
+
+impl ExtraParams {
+    /// starts reading the file or named pipe in the background until the returned value is dropped
+    pub fn watch(path: String) -> Arc<Self> {
+        let extra_params = Arc::new(Self::default());
+        let weak = Arc::downgrade(&extra_params);
+
+        tokio::spawn(async move {
+            // the file may be created after the scan has started
+            while weak.strong_count() > 0 {
+                if let Ok(file) = File::open(&path).await {
+                    read_lines(BufReader::new(file), &weak).await;
+                }
+
+                tokio::time::sleep(WATCH_INTERVAL).await;
+            }
+        });
+
+        extra_params
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.names.lock().clone()
+    }
+}
+
+/// follows the file like tail -f. Pipes return EOF until the next writer appears, so they're followed the same way
+async fn read_lines(mut reader: BufReader<File>, extra_params: &Weak<ExtraParams>) {
+    let mut line = String::new();
+
+    loop {
+        let extra_params = match extra_params.upgrade() {
+            Some(val) => val,
+            None => return,
+        };
+
+        match reader.read_line(&mut line).await {
+            Ok(0) => {
+                drop(extra_params);
+                tokio::time::sleep(WATCH_INTERVAL).await;
+            }
+            // the rest of the line is still being written
+            Ok(_) if !line.ends_with('\n') => (),
+            Ok(_) => {
+                let name = line.trim();
+                if !name.is_empty() {
+                    extra_params.names.lock().push(name.to_string());
+                }
+                line.clear();
+            }
+            Err(_) => return,
+        }
+    }
+}
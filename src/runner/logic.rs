@@ -1,12 +1,23 @@
-use std::{cmp, collections::HashMap, error::Error, sync::Arc};
+use std::{
+    cmp,
+    collections::HashMap,
+    sync::{atomic::Ordering, Arc},
+    time::Instant,
+};
 
 use async_recursion::async_recursion;
 use futures::stream::StreamExt;
 use parking_lot::Mutex;
 
 use crate::{
-    network::request::Request,
-    runner::utils::{FoundParameter, ReasonKind}, utils::progress_style_check_requests,
+    error::X8Error,
+    network::{request::Request, response::Response, utils::InjectionPlace},
+    runner::{
+        detectors::{Detection, DetectionInput, Detector},
+        error_budget::Phase,
+        utils::{Confirmation, FoundParameter, ReasonKind, Step},
+    },
+    utils::{self, progress_style_check_requests},
 };
 
 use super::runner::Runner;
@@ -20,23 +31,27 @@ impl<'a> Runner<'a> {
         shared_green_lines: Arc<Mutex<&'a mut HashMap<String, usize>>>,
         shared_found_params: Arc<Mutex<&'a mut Vec<FoundParameter>>>,
         mut params: Vec<String>,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), X8Error> {
+        let _urgent = self.priority.urgent();
+
         let second_params_part = params.split_off(params.len() / 2);
 
-        self.check_parameters_recursion(
-            Arc::clone(&shared_diffs),
-            Arc::clone(&shared_green_lines),
-            Arc::clone(&shared_found_params),
-            params,
-        )
-        .await?;
-        self.check_parameters_recursion(
-            shared_diffs,
-            shared_green_lines,
-            shared_found_params,
-            second_params_part,
-        )
-        .await
+        // an error in one part shouldn't prevent checking the other one until the budget is exhausted
+        for part in [params, second_params_part] {
+            if let Err(err) = self
+                .check_parameters_recursion(
+                    Arc::clone(&shared_diffs),
+                    Arc::clone(&shared_green_lines),
+                    Arc::clone(&shared_found_params),
+                    part,
+                )
+                .await
+            {
+                self.error_budget.record(Phase::Narrowing, &err)?;
+            }
+        }
+
+        Ok(())
     }
 
     #[async_recursion(?Send)]
@@ -46,7 +61,14 @@ impl<'a> Runner<'a> {
         shared_green_lines: Arc<Mutex<&'a mut HashMap<String, usize>>>,
         shared_found_params: Arc<Mutex<&'a mut Vec<FoundParameter>>>,
         mut params: Vec<String>,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), X8Error> {
+        // the chunk is known to be too large for the server
+        if params.len() > self.size_limit.load(Ordering::SeqCst) {
+            return self
+                .repeat(shared_diffs, shared_green_lines, shared_found_params, params)
+                .await;
+        }
+
         let request = Request::new(&self.request_defaults, params.clone());
         let mut response = match request.clone().wrapped_send().await {
             Ok(val) => val,
@@ -58,240 +80,399 @@ impl<'a> Runner<'a> {
                 //instead we return an empty response from the original request
                 Ok(_) => request.empty_response(),
                 //looks like either server or network is down
-                Err(err) => Err(format!("Unable to reach server ({})", err))?,
+                Err(err) => Err(X8Error::Network(format!("Unable to reach server ({})", err)))?,
             },
         };
 
+        // 413 Payload Too Large, 414 URI Too Long and 431 Request Header Fields Too Large are about the size of the chunk
+        // rather than about parameters within it
+        if [413, 414, 431].contains(&response.code) && response.code != self.initial_response.code {
+            // a single parameter that doesn't fit is a length limit as well, not a finding
+            if params.len() <= 1 {
+                utils::info(
+                    self.config,
+                    self.id,
+                    self.progress_bar,
+                    "~",
+                    format!("{} response for {}. The parameter is too long to be checked", response.code, params.join("")),
+                );
+                return Ok(());
+            }
+
+            let limit = params.len() / 2;
+
+            if self.size_limit.fetch_min(limit, Ordering::SeqCst) > limit {
+                utils::info(
+                    self.config,
+                    self.id,
+                    self.progress_bar,
+                    "~",
+                    format!("{} response. Sending up to {} parameters per request", response.code, limit),
+                );
+            }
+
+            return self
+                .repeat(shared_diffs, shared_green_lines, shared_found_params, params)
+                .await;
+        }
+
+        // reflections are counted once for all the detectors
         if self.stable.reflections {
             response.fill_reflected_parameters(&self.initial_response);
+        }
 
-            let (reflected_parameter, repeat) = response.proceed_reflected_parameters();
+        // names from changed pages, like "missing parameter 'foo'", are checked after the current chunks
+        if self.mines_responses()
+            && (response.code != self.initial_response.code || response.text.len() != self.initial_response.text.len())
+        {
+            self.live_queue.push(response.mine_parameters());
+        }
 
-            if let Some(reflected_parameter) = reflected_parameter {
+        let mut reflected: Vec<(String, usize)> =
+            response.reflected_parameters.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        reflected.sort();
+
+        // kept only in case the chunk changed the page
+        let mut step = Some(Step {
+            request: self.steps.next_request(),
+            params: params.len(),
+            status: response.code,
+            size: response.text.len(),
+            reflected,
+        });
+
+        for detector in self.detectors.iter() {
+            let detection = {
+                let diffs = shared_diffs.lock();
+                detector.detect(&DetectionInput {
+                    config: self.config,
+                    stable: &self.stable,
+                    initial_response: &self.initial_response,
+                    response: &response,
+                    params: &params,
+                    known_diffs: &diffs,
+                })?
+            };
+
+            if !matches!(detection, Detection::Nothing) {
+                if let Some(step) = step.take() {
+                    self.steps.record(step, &params);
+                }
+            }
 
-                let mut found_params = shared_found_params.lock();
-                if !found_params.iter().any(|x| x.name == reflected_parameter) {
-                    let mut kind = ReasonKind::Reflected;
-                    // explained in response.proceed_reflected_parameters() method
-                    // chunk.len() == 1 and not 2 because the random parameter appends later
-                    if params.len() == 1 {
-                        kind = ReasonKind::NotReflected;
+            match detection {
+                Detection::Nothing => (),
+
+                Detection::Recheck => {
+                    return self
+                        .repeat(shared_diffs, shared_green_lines, shared_found_params, params)
+                        .await;
+                }
+
+                Detection::Found { name, kind, diffs, repeat } => {
+                    if self.add_found_param(&shared_found_params, &response, &name, kind, &diffs)? {
+                        // remove found parameter from the list, the entry can be name=value as well
+                        params.retain(|x| *x != name && x.split('=').next().unwrap_or_default() != name);
                     }
 
-                    found_params.push(FoundParameter::new(
-                        reflected_parameter,
-                        &vec![],
-                        response.code,
-                        response.text.len(),
-                        kind.clone(),
-                    ));
-                    drop(found_params);
-
-                    // remove found parameter from the list
-                    params.remove(
-                        params
-                            .iter()
-                            .position(|x| *x == reflected_parameter)
-                            .unwrap(),
-                    );
+                    if repeat {
+                        return self
+                            .repeat(shared_diffs, shared_green_lines, shared_found_params, params)
+                            .await;
+                    }
+                }
+
+                Detection::FoundSeveral { names, kind } => {
+                    // narrowing would need about log2(chunk size) requests per parameter
+                    let saved = names.len() * (usize::BITS - params.len().leading_zeros()) as usize;
+                    self.saved_requests.fetch_add(saved, Ordering::SeqCst);
+
+                    for name in names.iter() {
+                        if self.add_found_param(&shared_found_params, &response, name, kind.clone(), &[])? {
+                            params.retain(|x| x != name && x.split('=').next().unwrap_or_default() != name.as_str());
+                        }
+                    }
+                }
+
+                Detection::Changed { kind, diffs } => {
+                    // resending, confirming and narrowing the chunk go before new discovery chunks
+                    let _urgent = self.priority.urgent();
+
+                    if kind == ReasonKind::Code {
+                        self.check_code_stability(&shared_green_lines, response.code, params.len()).await?;
+                    }
+
+                    // slow responses may contain parts like slow-path banners or generation times
+                    // so the change is ignored in case it disappears within a resent request
+                    if kind == ReasonKind::Text
+                        && self.time_baseline.is_slow(response.time)
+                        && !self.changes_again(detector.as_ref(), &shared_diffs, &params).await?
+                    {
+                        return Ok(());
+                    }
+
+                    // the first diff that doesn't appear with random parameters
+                    let diff = if detector.needs_confirmation() {
+                        match self
+                            .confirm_change(detector.as_ref(), &shared_diffs, &shared_found_params, &diffs, params.len())
+                            .await?
+                        {
+                            Some(val) => Some(val),
+                            None => return Ok(()),
+                        }
+                    } else {
+                        None
+                    };
+
+                    // we don't know what parameter caused the change yet
+                    // so we are repeating
+                    if params.len() != 1 {
+                        return self
+                            .repeat(shared_diffs, shared_green_lines, shared_found_params, params)
+                            .await;
+                    }
+
+                    let mut found_params = shared_found_params.lock();
+
+                    // repeating --strict checks. We need to do it twice because we're usually running in parallel
+                    // and some parameters may be found after the first check
+                    if found_params.iter().any(|x| x.name == params[0])
+                        || (self.config.strict
+                            && detector.needs_confirmation()
+                            && found_params.iter().any(|x| x.diffs == diffs.join("|")))
+                    {
+                        return Ok(());
+                    }
 
                     response.write_and_save(
                         self.id,
                         self.config,
                         &self.initial_response,
-                        kind,
-                        reflected_parameter,
-                        None,
+                        kind.clone(),
+                        &params[0],
+                        diff.as_deref(),
                         self.progress_bar,
                     )?;
-                }
-            }
 
-            if repeat {
-                return self
-                    .repeat(
-                        shared_diffs,
-                        shared_green_lines,
-                        shared_found_params,
-                        params.clone(),
-                    )
-                    .await;
-            }
+                    let mut found_param = FoundParameter::new(
+                        &params[0],
+                        &diffs,
+                        response.code,
+                        response.text.len(),
+                        kind,
+                    );
+                    found_param.enrichments =
+                        response.error_signatures(&self.initial_response, &self.config.error_signatures);
+                    found_param.snapshot = self.save_snapshot(&response);
+                    found_param.confirmation = Confirmation::new(&response);
+                    found_param.times.push(response.time);
 
-            if self.config.reflected_only {
-                return Ok(());
-            }
-        }
+                    found_params.push(found_param);
 
-        if self.initial_response.code != response.code {
-            // increases the specific response code counter
-            // helps to notice whether the page's completely changed
-            // like, for example, when the IP got banned by the server
-            {
-                let mut green_lines = shared_green_lines.lock();
-                match green_lines.get(&response.code.to_string()) {
-                    Some(val) => {
-                        let n_val = *val;
-                        green_lines.insert(response.code.to_string(), n_val + 1);
-                        if n_val > 50 {
-                            drop(green_lines);
-
-                            let check_response =
-                                Request::new_random(&self.request_defaults, params.len())
-                                    .wrapped_send()
-                                    .await
-                                    .unwrap_or_default();
-
-                            if check_response.code != self.initial_response.code {
-                                return Err(format!(
-                                    "{} The page became unstable (code)",
-                                    self.request_defaults.url()
-                                ))?;
-                            } else {
-                                let mut green_lines = shared_green_lines.lock();
-                                green_lines.insert(response.code.to_string(), 0);
-                            }
-                        }
-                    }
-                    _ => {
-                        green_lines.insert(response.code.to_string(), 0);
-                    }
+                    return Ok(());
                 }
             }
+        }
 
-            // there's only 1 parameter left that's changing the page's code
-            if params.len() == 1 {
-                response.write_and_save(
-                    self.id,
-                    self.config,
-                    &self.initial_response,
-                    ReasonKind::Code,
-                    &params[0],
+        Ok(())
+    }
+
+    /// stores the evidence in case --snapshots is used.
+    /// A failed write is reported, but doesn't stop the scan
+    fn save_snapshot(&self, response: &Response) -> Option<String> {
+        match response.save_snapshot(self.config) {
+            Ok(val) => val,
+            Err(err) => {
+                utils::error(
+                    format!("Unable to save the snapshot: {}", err),
                     None,
-                    self.progress_bar,
-                )?;
-
-                let mut found_params = shared_found_params.lock();
-                found_params.push(FoundParameter::new(
-                    &params[0],
-                    &vec![format!(
-                        "{} -> {}",
-                        &self.initial_response.code, response.code
-                    )],
-                    response.code,
-                    response.text.len(),
-                    ReasonKind::Code,
-                ));
-            // there's more than 1 parameter left - split the list and repeat
-            } else {
-                return self
-                    .repeat(
-                        shared_diffs,
-                        shared_green_lines,
-                        shared_found_params,
-                        params.clone(),
-                    )
-                    .await;
+                    Some(self.progress_bar),
+                    Some(self.config),
+                );
+                None
             }
-        } else if self.stable.body {
-            // check whether the new_diff has at least 1 unique diff compared to stored diffs
-            let (_, new_diffs) = {
-                let diffs = shared_diffs.lock();
-                response.compare(&self.initial_response, &diffs)?
-            };
+        }
+    }
 
-            // and then make a new request to check whether it's a permament diff or not
-            if !new_diffs.is_empty() {
-                if self.config.strict {
-                    let found_params = shared_found_params.lock();
-                    if found_params.iter().any(|x| x.diffs == new_diffs.join("|")) {
-                        return Ok(());
-                    }
-                }
+    /// header names rarely appear within pages
+    pub(super) fn mines_responses(&self) -> bool {
+        !self.config.no_mining && self.request_defaults.injection_place != InjectionPlace::Headers
+    }
 
-                // just request the page with random parameters and store it's diffs
-                // maybe I am overcheking this, but still to be sure..
-                let tmp_resp = Request::new_random(&self.request_defaults, params.len())
-                    .send()
-                    .await?;
+    /// saves the parameter found without narrowing. Returns false in case it was already found
+    fn add_found_param(
+        &self,
+        shared_found_params: &Arc<Mutex<&'a mut Vec<FoundParameter>>>,
+        response: &Response,
+        name: &str,
+        kind: ReasonKind,
+        diffs: &[String],
+    ) -> Result<bool, X8Error> {
+        let mut found_params = shared_found_params.lock();
+        if found_params.iter().any(|x| x.name == name) {
+            return Ok(false);
+        }
 
-                let (_, tmp_diffs) = {
-                    let diffs = shared_diffs.lock();
-                    tmp_resp.compare(&self.initial_response, &diffs)?
-                };
+        let mut found_param = FoundParameter::new(name, diffs, response.code, response.text.len(), kind.clone());
+        found_param.snapshot = self.save_snapshot(response);
+        found_param.confirmation = Confirmation::new(response);
+        found_param.times.push(response.time);
+
+        found_params.push(found_param);
+        drop(found_params);
+
+        response.write_and_save(
+            self.id,
+            self.config,
+            &self.initial_response,
+            kind,
+            name,
+            diffs.first().map(|x| x.as_str()),
+            self.progress_bar,
+        )?;
+
+        Ok(true)
+    }
 
-                let mut diffs = shared_diffs.lock();
-                for diff in tmp_diffs {
-                    diffs.push(diff);
+    /// increases the specific response code counter
+    /// helps to notice whether the page's completely changed
+    /// like, for example, when the IP got banned by the server
+    async fn check_code_stability(
+        &self,
+        shared_green_lines: &Arc<Mutex<&'a mut HashMap<String, usize>>>,
+        code: u16,
+        amount: usize,
+    ) -> Result<(), X8Error> {
+        let mut green_lines = shared_green_lines.lock();
+        match green_lines.get(&code.to_string()) {
+            Some(val) => {
+                let n_val = *val;
+                green_lines.insert(code.to_string(), n_val + 1);
+                if n_val > 50 {
+                    drop(green_lines);
+
+                    let check_response = Request::new_random(&self.request_defaults, amount)
+                        .wrapped_send()
+                        .await
+                        .unwrap_or_default();
+
+                    if check_response.code != self.initial_response.code {
+                        return Err(X8Error::UnstableTarget(format!(
+                            "{} The page became unstable (code)",
+                            self.request_defaults.url()
+                        )));
+                    } else {
+                        let mut green_lines = shared_green_lines.lock();
+                        green_lines.insert(code.to_string(), 0);
+                    }
                 }
             }
+            _ => {
+                green_lines.insert(code.to_string(), 0);
+            }
+        }
 
+        Ok(())
+    }
+
+    /// makes a request with random parameters and stores the diffs the detector finds within its response.
+    /// Returns the first diff that isn't among them (a permanent one)
+    async fn confirm_change(
+        &self,
+        detector: &dyn Detector,
+        shared_diffs: &Arc<Mutex<&'a mut Vec<String>>>,
+        shared_found_params: &Arc<Mutex<&'a mut Vec<FoundParameter>>>,
+        diffs: &[String],
+        amount: usize,
+    ) -> Result<Option<String>, X8Error> {
+        let _urgent = self.priority.urgent();
+
+        if self.config.strict {
+            let found_params = shared_found_params.lock();
+            if found_params.iter().any(|x| x.diffs == diffs.join("|")) {
+                return Ok(None);
+            }
+        }
+
+        // just request the page with random parameters and store it's diffs
+        // maybe I am overcheking this, but still to be sure..
+        let tmp_resp = Request::new_random(&self.request_defaults, amount)
+            .send()
+            .await?;
+
+        let tmp_detection = {
             let diffs = shared_diffs.lock();
+            detector.detect(&DetectionInput {
+                config: self.config,
+                stable: &self.stable,
+                initial_response: &self.initial_response,
+                response: &tmp_resp,
+                params: &[],
+                known_diffs: &diffs,
+            })?
+        };
 
-            // check whether the page still(after making a random request and storing it's diffs) has an unique diffs
-            for diff in new_diffs.iter() {
-                if !diffs.contains(diff) {
-                    let mut found_params = shared_found_params.lock();
+        let mut known_diffs = shared_diffs.lock();
+        if let Detection::Changed { diffs, .. } | Detection::Found { diffs, .. } = tmp_detection {
+            known_diffs.extend(diffs);
+        }
 
-                    // there's only one parameter left that changing the page
-                    if params.len() == 1 && !found_params.iter().any(|x| x.name == params[0]) {
-                        // repeating --strict checks. We need to do it twice because we're usually running in parallel
-                        // and some parameters may be found after the first check
-                        if self.config.strict && found_params.iter().any(|x| x.diffs == new_diffs.join("|")) {
-                            return Ok(());
-                        }
+        // check whether the page still(after making a random request and storing it's diffs) has an unique diffs
+        Ok(diffs.iter().find(|x| !known_diffs.contains(x)).cloned())
+    }
 
-                        response.write_and_save(
-                            self.id,
-                            self.config,
-                            &self.initial_response,
-                            ReasonKind::Text,
-                            &params[0],
-                            Some(diff),
-                            self.progress_bar,
-                        )?;
-
-                        found_params.push(FoundParameter::new(
-                            &params[0],
-                            &new_diffs,
-                            response.code,
-                            response.text.len(),
-                            ReasonKind::Text,
-                        ));
-                        break;
-                    // we don't know what parameter caused the difference in response yet
-                    // so we are repeating
-                    } else {
-                        drop(diffs);
-                        drop(found_params);
-                        return self
-                            .repeat(
-                                shared_diffs,
-                                shared_green_lines,
-                                shared_found_params,
-                                params.clone(),
-                            )
-                            .await;
-                    }
-                }
-            }
+    /// resends the parameters and tells whether the detector still reports a change
+    async fn changes_again(
+        &self,
+        detector: &dyn Detector,
+        shared_diffs: &Arc<Mutex<&'a mut Vec<String>>>,
+        params: &[String],
+    ) -> Result<bool, X8Error> {
+        let mut response = Request::new(&self.request_defaults, params.to_vec()).send().await?;
+
+        if self.stable.reflections {
+            response.fill_reflected_parameters(&self.initial_response);
         }
 
-        Ok(())
+        let diffs = shared_diffs.lock();
+        let detection = detector.detect(&DetectionInput {
+            config: self.config,
+            stable: &self.stable,
+            initial_response: &self.initial_response,
+            response: &response,
+            params,
+            known_diffs: &diffs,
+        })?;
+
+        Ok(matches!(detection, Detection::Changed { .. }))
     }
 
-    /// check parameters in a loop chunk by chunk
+    /// sends the base request to check whether the target is up again
+    async fn target_is_reachable(&self) -> bool {
+        Request::new(&self.request_defaults, vec![])
+            .send()
+            .await
+            .is_ok()
+    }
+
+    /// check parameters in a loop chunk by chunk.
+    /// Chunks that changed the page are narrowed right away within the same slot of the concurrency,
+    /// and new chunks wait for them (see PriorityGate)
+    /// with_progress_bar is false for checks that run alongside the main one
     pub async fn check_parameters(
         &self,
         params: &Vec<String>,
-    ) -> Result<(Vec<String>, Vec<FoundParameter>), Box<dyn Error>> {
-        let max = cmp::min(self.max, params.len());
+        with_progress_bar: bool,
+    ) -> Result<(Vec<String>, Vec<FoundParameter>), X8Error> {
+        let max = cmp::min(cmp::min(self.max, self.size_limit.load(Ordering::SeqCst)), params.len());
 
         // the amount of requests needed for process all the parameters
         let all = params.len() / max;
 
         // change and reset the progress bar
-        self.prepare_progress_bar(progress_style_check_requests(self.config), all + 1);
+        if with_progress_bar {
+            self.prepare_progress_bar(progress_style_check_requests(self.config), all + 1);
+        }
 
         // wrap the variables to share them between futures
         let mut diffs = self.diffs.clone();
@@ -308,21 +489,94 @@ impl<'a> Runner<'a> {
             let shared_found_params = Arc::clone(&shared_found_params);
 
             async move {
-                self.progress_bar.inc(1);
+                // there's no sense to continue in case the limit of errors is reached
+                if self.error_budget.is_exhausted(Phase::Discovery)
+                    || self.error_budget.is_exhausted(Phase::Narrowing)
+                {
+                    return Ok(());
+                }
 
-                self.check_parameters_recursion(
-                    shared_diffs,
-                    shared_green_lines,
-                    shared_found_params,
-                    chunk.to_vec(),
-                )
-                .await
+                // candidates that are being narrowed go first
+                self.priority.wait().await;
+
+                if let Some(controller) = self.concurrency_controller.as_ref() {
+                    controller.acquire().await;
+                }
+
+                if with_progress_bar {
+                    self.progress_bar.inc(1);
+                }
+
+                let start = Instant::now();
+
+                let result = loop {
+                    self.circuit_breaker.wait().await;
+
+                    let result = self.check_parameters_recursion(
+                        Arc::clone(&shared_diffs),
+                        Arc::clone(&shared_green_lines),
+                        Arc::clone(&shared_found_params),
+                        chunk.to_vec(),
+                    )
+                    .await;
+
+                    let err = match result.as_ref() {
+                        Ok(()) => {
+                            self.circuit_breaker.success();
+                            break result;
+                        }
+                        Err(err) => err,
+                    };
+
+                    if !self.circuit_breaker.failure(err) {
+                        break result;
+                    }
+
+                    // the target looks down: pause the scan and retest the chunk once it has recovered
+                    if let Err(err) = self.circuit_breaker.recover(|| self.target_is_reachable()).await {
+                        break Err(X8Error::Network(err));
+                    }
+
+                    self.circuit_breaker.retested.fetch_add(1, Ordering::SeqCst);
+                };
+
+                if let Some(controller) = self.concurrency_controller.as_ref() {
+                    controller.release(start.elapsed().as_millis(), result.is_err());
+                }
+
+                match result {
+                    // already counted within the phase it happened in, e.g. while narrowing the chunk
+                    Err(err @ X8Error::ErrorBudget(_)) => Err(err),
+                    Err(err) => self.error_budget.record(Phase::Discovery, &err),
+                    Ok(()) => Ok(()),
+                }
             }
         }))
-        .buffer_unordered(self.config.concurrency)
-        .collect::<Vec<Result<(), Box<dyn Error>>>>()
+        // the controller limits the amount of concurrent chunks itself
+        .buffer_unordered(if self.concurrency_controller.is_some() {
+            self.config.max_concurrency
+        } else {
+            self.config.concurrency
+        })
+        .collect::<Vec<Result<(), X8Error>>>()
         .await;
 
+        // the errors themselves were already collected within the budget
+        for phase in [Phase::Discovery, Phase::Narrowing] {
+            self.error_budget.check(phase, &"see the errors above")?;
+        }
+
+        let retested = self.circuit_breaker.retested.swap(0, Ordering::SeqCst);
+        if retested > 0 {
+            utils::info(
+                self.config,
+                self.id,
+                self.progress_bar,
+                "retested",
+                format!("{} chunks after the target recovered", retested),
+            );
+        }
+
         Ok((diffs, found_params))
     }
 }
@@ -1,4 +1,17 @@
+pub mod circuit_breaker;
+pub mod concurrency;
+pub mod detectors;
+pub mod error_budget;
+pub mod fingerprint;
+pub mod learned;
+pub mod live;
 pub mod logic;
 pub mod output;
+pub mod priority;
+pub mod probes;
 pub mod runner;
+pub mod shared;
+pub mod throttle;
 pub mod utils;
+
+mod tests;
@@ -1,8 +1,21 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    error::Error,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
 use serde::Serialize;
+use serde_json::json;
 use colored::Colorize;
+use url::Url;
 
 use crate::{
-    config::structs::Config,
+    config::{
+        structs::Config,
+        utils::{redact_args, redact_headers},
+    },
     network::{
         request::{Request, RequestDefaults},
         response::Response,
@@ -10,7 +23,7 @@ use crate::{
     },
 };
 
-use super::utils::FoundParameter;
+use super::utils::{FoundParameter, ReasonKind, Stable};
 
 #[derive(Debug, Serialize)]
 pub struct RunnerOutput {
@@ -20,16 +33,27 @@ pub struct RunnerOutput {
     /// request url without injection point
     pub url: String,
 
+    /// SOAP operation in case --wsdl is used
+    pub operation: Option<String>,
+
     /// initial response code
     pub status: u16,
 
     /// initial response size (body + headers)
     pub size: usize,
 
+    /// the http version the target was checked over
+    pub protocol: String,
+
     pub found_params: Vec<FoundParameter>,
 
     pub injection_place: InjectionPlace,
 
+    /// detected frameworks and CMSes
+    pub technologies: Vec<String>,
+
+    /// the settings the url was checked with
+    pub config: Option<ScanConfig>,
 
     /// prepared query with found parameters
     #[serde(skip_serializing)]
@@ -40,6 +64,73 @@ pub struct RunnerOutput {
     pub request: String,
 }
 
+/// the effective settings of the run after parsing the arguments and learning the page.
+/// args are enough to rerun the scan with --from-report, the rest shows what was detected automatically
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanConfig {
+    pub version: String,
+
+    /// the command line without the program name. Secrets are redacted
+    pub args: Vec<String>,
+
+    /// parameters per request
+    pub max: usize,
+    pub template: String,
+    pub joiner: String,
+    pub encode: bool,
+    pub is_json: bool,
+    pub body: String,
+    pub headers: Vec<(String, String)>,
+
+    /// in milliseconds
+    pub delay: u128,
+
+    pub stable_body: bool,
+    pub stable_reflections: bool,
+
+    /// the amount of reflections of a non existing parameter
+    pub reflections: usize,
+
+    /// (k, n) in case only a part of the wordlist was checked
+    pub shard: Option<(usize, usize)>,
+}
+
+impl ScanConfig {
+    pub fn new(config: &Config, request_defaults: &RequestDefaults, max: usize, stable: &Stable) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            args: redact_args(&config.args),
+            max,
+            template: request_defaults.template.clone(),
+            joiner: request_defaults.joiner.clone(),
+            encode: request_defaults.encode,
+            is_json: request_defaults.is_json,
+            body: request_defaults.body.clone(),
+            headers: redact_headers(&request_defaults.custom_headers),
+            delay: request_defaults.delay.as_millis(),
+            stable_body: stable.body,
+            stable_reflections: stable.reflections,
+            reflections: request_defaults.amount_of_reflections,
+            shard: config.shard,
+        }
+    }
+}
+
+/// host, method, name, reason kind and status of the found parameter
+type DedupKey = (String, String, String, ReasonKind, u16);
+
+/// collapses the same parameter found at several urls of the host with the same behavior into one finding
+/// in case more than 1 url is checked without --no-dedup
+#[derive(Debug, Default)]
+pub struct Dedup {
+    /// the url of the first finding and the urls of the collapsed ones
+    seen: HashMap<DedupKey, (String, Vec<String>)>,
+}
+
+/// found parameters from the previous run per (method, url)
+#[derive(Debug, Default)]
+pub struct Baseline(HashMap<(String, String), HashSet<String>>);
+
 pub trait ParseOutputs {
     fn parse_output(&self, config: &Config) -> String;
 }
@@ -58,10 +149,14 @@ impl RunnerOutput {
             } else {
                 request_defaults.url_without_default_port()
             },
+            operation: None,
             status: initial_response.code,
             size: initial_response.text.len(),
+            protocol: initial_response.http_version_name().to_string(),
             found_params,
             injection_place: request_defaults.injection_place,
+            technologies: Vec::new(),
+            config: None,
             query: String::new(),
             request: String::new(),
         }
@@ -99,6 +194,28 @@ impl RunnerOutput {
         }
     }
 
+    /// removes the parameters that were already found during the baseline run
+    pub fn remove_known(&mut self, baseline: &Baseline) {
+        if let Some(known) = baseline.0.get(&(self.method.clone(), target_url(&self.url, self.operation.as_deref()))) {
+            self.found_params.retain(|x| !known.contains(&x.name));
+        }
+    }
+
+    /// saves json and the plain output to the url|method pair's directory within --output-dir
+    pub fn save_to_dir(&self, config: &Config, dir: &Path) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(dir)?;
+
+        fs::write(
+            dir.join("output.txt"),
+            strip_ansi_escapes::strip(self.parse(config).as_bytes())?,
+        )?;
+
+        // written the last because its presence means that the url|method pair is checked
+        fs::write(dir.join("result.json"), serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
     /// parses the runner output struct to one specified in config format
     pub fn parse(&self, config: &Config) -> String {
         match config.output_format.as_str() {
@@ -121,11 +238,14 @@ impl RunnerOutput {
 
             "request" => self.request.clone(),
 
+            "jsonl" => serde_json::to_string(&self).unwrap(),
+
             _ => {
                 format!(
-                    "{} {} % {}",
+                    "{} {}{} % {}",
                     &self.method.blue(),
                     &self.url,
+                    self.operation.as_ref().map(|x| format!(" ({})", x)).unwrap_or_default(),
                     self.found_params
                         .iter()
                         .map(|x| x.get_colored())
@@ -151,3 +271,191 @@ impl ParseOutputs for Vec<RunnerOutput> {
         }
     }
 }
+
+impl Dedup {
+    /// removes the parameters that were already found at other urls of the host.
+    /// Returns the amount of removed parameters
+    pub fn remove_repeated(&mut self, output: &mut RunnerOutput) -> usize {
+        let before = output.found_params.len();
+        let host = host_of(&output.url);
+        let url = target_url(&output.url, output.operation.as_deref());
+        let method = output.method.clone();
+
+        let seen = &mut self.seen;
+        output.found_params.retain(|x| {
+            let key = (host.clone(), method.clone(), x.name.clone(), x.reason_kind.clone(), x.status);
+
+            match seen.get_mut(&key) {
+                Some((_, urls)) => {
+                    urls.push(url.clone());
+                    false
+                }
+                None => {
+                    seen.insert(key, (url.clone(), Vec::new()));
+                    true
+                }
+            }
+        });
+
+        before - output.found_params.len()
+    }
+
+    /// collapses the findings within outputs that weren't printed yet
+    pub fn collapse(&mut self, outputs: &mut [RunnerOutput]) {
+        for output in outputs.iter_mut() {
+            self.remove_repeated(output);
+        }
+
+        for output in outputs.iter_mut() {
+            let host = host_of(&output.url);
+
+            for param in output.found_params.iter_mut() {
+                let key = (host.clone(), output.method.clone(), param.name.clone(), param.reason_kind.clone(), param.status);
+
+                if let Some((_, urls)) = self.seen.get(&key) {
+                    param.also_found_at = urls.clone();
+                }
+            }
+        }
+    }
+
+    /// lines about the collapsed findings for the outputs that were printed in real time
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .seen
+            .iter()
+            .filter(|(_, (_, urls))| !urls.is_empty())
+            .map(|((_, method, name, _, _), (first, urls))| {
+                format!("{} {} % {} is also found at {} urls: {}", method.blue(), first, name, urls.len(), urls.join(" "))
+            })
+            .collect();
+
+        lines.sort();
+        lines
+    }
+}
+
+impl Baseline {
+    /// reads either json or jsonl output of the previous run
+    pub fn read(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut baseline = Baseline::default();
+
+        for output in read_outputs(path)? {
+            let (method, url) = match (output["method"].as_str(), output["url"].as_str()) {
+                (Some(method), Some(url)) => (method.to_string(), target_url(url, output["operation"].as_str())),
+                _ => Err("Unable to parse the baseline: method or url is missing")?,
+            };
+
+            let names = output["found_params"]
+                .as_array()
+                .map(|x| {
+                    x.iter()
+                        .filter_map(|x| x["name"].as_str())
+                        .map(|x| x.to_string())
+                        .collect::<Vec<String>>()
+                })
+                .unwrap_or_default();
+
+            baseline.0.entry((method, url)).or_default().extend(names);
+        }
+
+        Ok(baseline)
+    }
+}
+
+/// reads either json or jsonl output
+pub fn read_outputs(path: &str) -> Result<Vec<serde_json::Value>, Box<dyn Error>> {
+    let content = fs::read_to_string(path)?;
+
+    Ok(match serde_json::from_str::<serde_json::Value>(&content) {
+        Ok(serde_json::Value::Array(outputs)) => outputs,
+        _ => content
+            .lines()
+            .filter(|x| !x.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<serde_json::Value>, serde_json::Error>>()
+            .map_err(|err| format!("Unable to parse {}: {}", path, err))?,
+    })
+}
+
+/// returns the arguments the previous run was started with
+pub fn report_args(path: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let args = read_outputs(path)?
+        .iter()
+        .find_map(|x| x["config"]["args"].as_array().cloned())
+        .ok_or("The report doesn't contain the scan config. Only json and jsonl outputs contain it")?;
+
+    Ok(args.iter().filter_map(|x| x.as_str()).map(|x| x.to_string()).collect())
+}
+
+fn host_of(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(val) => val.host_str().unwrap_or("unknown").to_string(),
+        Err(_) => "unknown".to_string(),
+    }
+}
+
+/// operations of the same SOAP endpoint are different targets
+pub fn target_url(url: &str, operation: Option<&str>) -> String {
+    match operation {
+        Some(operation) => format!("{}#{}", url, operation),
+        None => url.to_string(),
+    }
+}
+
+/// returns <output dir>/<host>/<hash of method and url>
+pub fn target_dir(output_dir: &str, method: &str, url: &str) -> PathBuf {
+    let host = host_of(url);
+
+    let mut hasher = DefaultHasher::new();
+    method.hash(&mut hasher);
+    url.hash(&mut hasher);
+
+    Path::new(output_dir)
+        .join(host)
+        .join(format!("{:016x}", hasher.finish()))
+}
+
+/// writes index.json with the summary of every checked url|method pair within --output-dir
+/// including the ones from previous runs
+pub fn write_index(output_dir: &str) -> Result<(), Box<dyn Error>> {
+    let mut index = Vec::new();
+
+    for host_dir in fs::read_dir(output_dir)? {
+        let host_dir = host_dir?.path();
+
+        if !host_dir.is_dir() {
+            continue;
+        }
+
+        for target_dir in fs::read_dir(&host_dir)? {
+            let target_dir = target_dir?.path();
+            let result = target_dir.join("result.json");
+
+            if !result.exists() {
+                continue;
+            }
+
+            let output: serde_json::Value = serde_json::from_str(&fs::read_to_string(&result)?)?;
+            let found_params: Vec<&serde_json::Value> = output["found_params"]
+                .as_array()
+                .map(|x| x.iter().map(|x| &x["name"]).collect())
+                .unwrap_or_default();
+
+            index.push(json!({
+                "method": output["method"],
+                "url": output["url"],
+                "status": output["status"],
+                "found_params": found_params,
+                "dir": target_dir.strip_prefix(output_dir).unwrap_or(&target_dir),
+            }));
+        }
+    }
+
+    fs::write(
+        Path::new(output_dir).join("index.json"),
+        serde_json::to_string_pretty(&index)?,
+    )?;
+
+    Ok(())
+}
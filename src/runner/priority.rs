@@ -0,0 +1,46 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tokio::sync::Notify;
+
+/// lets narrowing and confirmation requests go before new discovery chunks.
+/// Discovery chunks wait until there's no urgent work in progress,
+/// so candidate findings are confirmed while the rest of the wordlist is waiting
+#[derive(Debug, Default)]
+pub struct PriorityGate {
+    urgent: AtomicUsize,
+    notify: Notify,
+}
+
+/// marks urgent work until dropped
+pub struct UrgentGuard<'a> {
+    gate: &'a PriorityGate,
+}
+
+impl PriorityGate {
+    pub fn urgent(&self) -> UrgentGuard<'_> {
+        self.urgent.fetch_add(1, Ordering::SeqCst);
+        UrgentGuard { gate: self }
+    }
+
+    /// waits until the urgent work is done
+    pub async fn wait(&self) {
+        loop {
+            // created before the check so the notification between them isn't missed
+            let notified = self.notify.notified();
+
+            if self.urgent.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+}
+
+impl Drop for UrgentGuard<'_> {
+    fn drop(&mut self) {
+        if self.gate.urgent.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.gate.notify.notify_waiters();
+        }
+    }
+}
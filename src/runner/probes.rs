@@ -0,0 +1,302 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::{
+    error::X8Error,
+    network::{request::Request, response::Response, utils::Headers},
+    utils::{self, random_line},
+};
+
+use super::{
+    runner::Runner,
+    utils::{Enrichment, FoundParameter, ParserFingerprint, ReasonKind, MIN_LATENCY_SAMPLES},
+};
+
+/// follow-up probes for found parameters
+impl<'a> Runner<'a> {
+    /// checks whether parameters that influence redirects can redirect to an external url
+    pub(super) async fn probe_redirects(
+        &self,
+        found_params: &mut Vec<FoundParameter>,
+        canary: &str,
+    ) -> Result<(), X8Error> {
+        for param in found_params.iter_mut() {
+            // firstly check whether the value gets into the redirect location at all
+            let token = random_line(10);
+            let response = self.send_with_value(&param.name, &token).await?;
+
+            if !redirect_location(&response).map_or(false, |x| x.contains(&token)) {
+                continue;
+            }
+
+            let response = self.send_with_value(&param.name, canary).await?;
+
+            if let Some(location) = redirect_location(&response) {
+                if location.starts_with(canary)
+                    || location.starts_with(&canary.replacen("https:", "", 1).replacen("http:", "", 1))
+                {
+                    utils::info(
+                        self.config,
+                        self.id,
+                        self.progress_bar,
+                        "redirect",
+                        format!("{} redirects to {}", param.name, location),
+                    );
+
+                    param.enrichments.push(Enrichment::OpenRedirect(location));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// sends canary urls to parameters that accept urls and polls the callback server in case --ssrf-poll is used
+    pub(super) async fn probe_ssrf(
+        &self,
+        found_params: &mut Vec<FoundParameter>,
+        canary: &str,
+    ) -> Result<(), X8Error> {
+        // (index of the parameter, unique id within the canary url)
+        let mut sent = Vec::new();
+
+        for (index, param) in found_params.iter().enumerate() {
+            // parameters that error on urls are unlikely to fetch them
+            let response = self
+                .send_with_value(&param.name, &format!("http://{}.com/", random_line(10)))
+                .await?;
+
+            if response.code >= 400 {
+                continue;
+            }
+
+            let id = random_line(10);
+            self.send_with_value(&param.name, &canary.replace("{{id}}", &id))
+                .await?;
+
+            sent.push((index, id));
+        }
+
+        let callbacks = match self.config.ssrf_poll.as_ref() {
+            Some(poll) if !sent.is_empty() => {
+                // give the callback server some time to receive the requests
+                tokio::time::sleep(self.config.ssrf_poll_delay).await;
+
+                // the same proxy and tls settings as for the target
+                self.request_defaults.client.get(poll).send().await?.text().await?
+            }
+            _ => String::new(),
+        };
+
+        for (index, id) in sent {
+            let canary = canary.replace("{{id}}", &id);
+            let param = &mut found_params[index];
+
+            if callbacks.contains(&id) {
+                utils::info(
+                    self.config,
+                    self.id,
+                    self.progress_bar,
+                    "ssrf",
+                    format!("{} made a request to {}", param.name, canary),
+                );
+
+                param.enrichments.push(Enrichment::Ssrf(canary));
+            } else {
+                param.enrichments.push(Enrichment::SsrfCandidate(canary));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// resends the parameters that were confirmed by too few requests for the latency percentiles to mean anything
+    pub(super) async fn sample_latency(&self, found_params: &mut Vec<FoundParameter>) -> Result<(), X8Error> {
+        if self.time_baseline.samples < MIN_LATENCY_SAMPLES {
+            return Ok(());
+        }
+
+        for param in found_params.iter_mut() {
+            while param.times.len() < MIN_LATENCY_SAMPLES {
+                let param_with_value = match param.value.as_ref() {
+                    Some(value) => format!("{}={}", param.name, value),
+                    None => param.name.clone(),
+                };
+
+                let response = self.send_params(vec![param_with_value]).await?;
+                param.times.push(response.time);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// checks whether reflected parameters are reflected in the decoded form or just echoed as sent
+    pub(super) async fn probe_reflections(&self, found_params: &mut Vec<FoundParameter>) -> Result<(), X8Error> {
+        for param in found_params.iter_mut() {
+            if param.reason_kind != ReasonKind::Reflected {
+                continue;
+            }
+
+            let (start, end) = (random_line(6), random_line(6));
+            let response = self
+                .send_with_value(&param.name, &format!("{}%22{}", start, end))
+                .await?;
+
+            let text = response.text.to_lowercase();
+            let (start, end) = (start.to_lowercase(), end.to_lowercase());
+
+            // the decoded quote may be html-encoded on the page
+            let enrichment = if ["\"", "&quot;", "&#34;", "&#x22;"]
+                .iter()
+                .any(|quote| text.contains(&format!("{}{}{}", start, quote, end)))
+            {
+                Enrichment::DecodedReflection
+            } else if text.contains(&format!("{}%22{}", start, end)) {
+                utils::info(
+                    self.config,
+                    self.id,
+                    self.progress_bar,
+                    "~",
+                    format!("{} is reflected only as it was sent", param.name),
+                );
+
+                Enrichment::VerbatimReflection
+            } else {
+                continue;
+            };
+
+            param.enrichments.push(enrichment);
+        }
+
+        Ok(())
+    }
+
+    /// sends edge-case encodings of reflected parameters to see which parser the backend uses
+    pub(super) async fn probe_parsers(&self, found_params: &mut Vec<FoundParameter>) -> Result<(), X8Error> {
+        for param in found_params.iter_mut() {
+            if param.reason_kind != ReasonKind::Reflected {
+                continue;
+            }
+
+            // the same parameter twice
+            let (first, second) = (random_line(8).to_lowercase(), random_line(8).to_lowercase());
+            let text = self
+                .send_params(vec![format!("{}={}", param.name, first), format!("{}={}", param.name, second)])
+                .await?
+                .text
+                .to_lowercase();
+
+            let duplicates = if text.contains(&format!("{},{}", first, second)) || text.contains(&format!("{}, {}", first, second)) {
+                "joined"
+            } else {
+                match (text.contains(&first), text.contains(&second)) {
+                    (true, true) => "both",
+                    (true, false) => "first",
+                    (false, true) => "last",
+                    (false, false) => "none",
+                }
+            };
+
+            // the name with the percent-encoded first character
+            let token = random_line(8).to_lowercase();
+            let split = param.name.chars().next().map_or(0, |x| x.len_utf8());
+            let encoded_name = param.name[..split].bytes().map(|x| format!("%{:02X}", x)).collect::<String>() + &param.name[split..];
+            let encoded_names = self.reflects(&encoded_name, &token, &token).await?;
+
+            // %2541 -> %41 -> A
+            let (start, end) = (random_line(6).to_lowercase(), random_line(6).to_lowercase());
+            let double_decoding = self
+                .reflects(&param.name, &format!("{}%2541{}", start, end), &format!("{}a{}", start, end))
+                .await?;
+
+            // IIS-style %u escapes
+            let unicode_escapes = self
+                .reflects(&param.name, &format!("{}%u0041{}", start, end), &format!("{}a{}", start, end))
+                .await?;
+
+            // the overlong encoding of a dot
+            let response = self.send_with_value(&param.name, &format!("{}%C0%AE{}", start, end)).await?;
+            let text = response.text.to_lowercase();
+
+            let overlong_utf8 = if text.contains(&format!("{}.{}", start, end)) {
+                "decoded"
+            } else if text.contains(&format!("{}\u{FFFD}", start)) {
+                "replaced"
+            } else if text.contains(&format!("{}{}", start, end)) {
+                "stripped"
+            } else if text.contains(&format!("{}%c0%ae{}", start, end)) {
+                "verbatim"
+            } else if response.code >= 400 {
+                "rejected"
+            } else {
+                "none"
+            }
+            .to_string();
+
+            let fingerprint = ParserFingerprint {
+                duplicates: duplicates.to_string(),
+                encoded_names,
+                double_decoding,
+                unicode_escapes,
+                overlong_utf8,
+                guess: None,
+            }
+            .with_guess();
+
+            utils::info(
+                self.config,
+                self.id,
+                self.progress_bar,
+                "parser",
+                format!(
+                    "{}: duplicates {}, overlong utf-8 {}{}",
+                    param.name,
+                    fingerprint.duplicates,
+                    fingerprint.overlong_utf8,
+                    fingerprint.guess.as_ref().map_or(String::new(), |x| format!(" ({})", x))
+                ),
+            );
+
+            param.enrichments.push(Enrichment::ParserFingerprint(fingerprint));
+        }
+
+        Ok(())
+    }
+
+    /// sends the parameter with the specific value
+    async fn send_with_value(&self, name: &str, value: &str) -> Result<Response<'_>, X8Error> {
+        self.send_params(vec![format!("{}={}", name, value)]).await
+    }
+
+    /// sends the parameters as they are, e.g. the same name several times
+    async fn send_params(&self, params: Vec<String>) -> Result<Response<'_>, X8Error> {
+        Request::new(&self.request_defaults, params).send().await
+    }
+
+    /// whether the expected string is on the page after sending the parameter with the value
+    async fn reflects(&self, name: &str, value: &str, expected: &str) -> Result<bool, X8Error> {
+        Ok(self
+            .send_with_value(name, value)
+            .await?
+            .text
+            .to_lowercase()
+            .contains(expected))
+    }
+}
+
+/// returns the location from either the Location header or the meta refresh tag
+fn redirect_location(response: &Response) -> Option<String> {
+    lazy_static! {
+        static ref RE_META_REFRESH: Regex =
+            Regex::new(r#"(?i)<meta[^>]+http-equiv=["']?refresh[^>]+url=([^"'>\s]+)"#).unwrap();
+    }
+
+    if let Some(location) = response.headers.get_value_case_insensitive("location") {
+        return Some(location);
+    }
+
+    RE_META_REFRESH
+        .captures(&response.text)
+        .map(|x| x[1].to_string())
+}
@@ -1,24 +1,52 @@
-use std::{error::Error, io::{self, Write}};
+use std::{cmp, collections::HashMap, io::{self, Write}, sync::atomic::{AtomicUsize, Ordering}};
 
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
+use parking_lot::Mutex;
+use rand::Rng;
+use url::Url;
 
 use crate::{
     config::structs::Config,
+    error::X8Error,
     network::{
         request::{Request, RequestDefaults},
         response::Response,
-        utils::{create_client, InjectionPlace},
+        soap,
+        utils::{create_client, create_resolved_client, resolve_addrs, Headers, InjectionPlace},
     },
-    utils::{self, color_id, random_line, progress_style_learn_requests, is_id_important},
-    DEFAULT_PROGRESS_URL_MAX_LEN, MAX_PAGE_SIZE,
+    utils::{self, color_id, random_line, progress_style_learn_requests, is_id_important, sort_by_weight},
+    wordlist::WordlistParts,
+    DEFAULT_PROGRESS_URL_MAX_LEN, MAX_PAGE_SIZE, VALUE_LENGTH,
 };
 
 use super::{
-    output::RunnerOutput,
-    utils::{fold_url, replay, verify, FoundParameter, Parameters, Stable},
+    circuit_breaker::CircuitBreaker,
+    concurrency::ConcurrencyController,
+    detectors::{default_detectors, Detector},
+    error_budget::{ErrorBudget, Phase},
+    fingerprint::{detect_cdn, technology_params},
+    learned::LearnedDb,
+    live::LiveQueue,
+    output::{RunnerOutput, ScanConfig},
+    priority::PriorityGate,
+    utils::{
+        fold_url, replay, verify, FoundParameter, Latency, Parameters, Stable, StepLog, TimeBaseline,
+    },
 };
 
+/// the amount of random parameters within the initial request to calibrate reflections with
+const REFLECTION_CANARIES: usize = 3;
+
+/// the amount of parameters per request within the body in case --reflected-only is used
+const REFLECTED_ONLY_MAX: usize = 4096;
+
+/// the amount of failed initial requests over one http version before switching to the other one
+const DOWNGRADE_AFTER: usize = 2;
+
+/// pauses in ms before additional learning requests to catch time dependent parts of the page
+const LEARN_DELAYS: [u64; 2] = [0, 1000];
+
 pub struct Runner<'a> {
     /// unique id of the runner to distinguish output between different urls
     pub id: usize,
@@ -43,8 +71,80 @@ pub struct Runner<'a> {
     /// page's diffs for the current url|method pair
     pub diffs: Vec<String>,
 
+    /// different backends that were seen while learning the page
+    pub backends: Vec<String>,
+
     /// progress bar object to print progress bar & found parameters
     pub progress_bar: &'a ProgressBar,
+
+    /// adjusts the amount of concurrent requests in case --adaptive-concurrency is used
+    pub concurrency_controller: Option<ConcurrencyController>,
+
+    /// pauses the scan after several consecutive connection errors
+    pub circuit_breaker: CircuitBreaker,
+
+    /// failed requests per phase
+    pub error_budget: ErrorBudget,
+
+    /// detected frameworks and CMSes, filled in case --fingerprint is used
+    pub technologies: Vec<String>,
+
+    /// values for `name=value1,value2` wordlist entries.
+    /// Checked together with custom parameters
+    pub value_overrides: HashMap<String, Vec<String>>,
+
+    /// detection modules that are run for every response
+    pub detectors: Vec<Box<dyn Detector>>,
+
+    /// the max amount of parameters per request after 414 or 431 responses
+    pub size_limit: AtomicUsize,
+
+    /// response times while learning the page
+    pub time_baseline: TimeBaseline,
+
+    /// narrowing and confirmation go before new discovery chunks
+    pub priority: PriorityGate,
+
+    /// narrowing requests that weren't needed because reflections attributed the parameters right away
+    pub saved_requests: AtomicUsize,
+
+    /// the chunks that changed the page during discovery and narrowing, used to explain the findings
+    pub steps: StepLog,
+
+    /// parameters to check after the current ones
+    pub live_queue: LiveQueue,
+
+    /// the rest of the wordlist in case --wordlist-parts is used
+    pub wordlist_parts: Option<Mutex<WordlistParts>>,
+}
+
+/// the result of Runner::learn()
+#[derive(Debug, Clone, Default)]
+pub struct Learned {
+    /// ordered parameters to check within the budget
+    pub params: Vec<String>,
+}
+
+/// the result of Runner::discover()
+#[derive(Debug, Clone, Default)]
+pub struct Discovered {
+    /// diffs that appear with random parameters as well
+    pub diffs: Vec<String>,
+
+    /// parameters found within the chunks, narrowed down or found right away like reflected ones
+    pub found_params: Vec<FoundParameter>,
+
+    /// parameters with non random values like admin=true
+    pub custom_params: Vec<FoundParameter>,
+}
+
+/// the result of Runner::narrow()
+#[derive(Debug, Clone, Default)]
+pub struct Narrowed {
+    pub diffs: Vec<String>,
+
+    /// not processed parameters. May contain duplicates and false positives
+    pub found_params: Vec<FoundParameter>,
 }
 
 impl<'a> Runner<'a> {
@@ -54,30 +154,184 @@ impl<'a> Runner<'a> {
         request_defaults: &'a mut RequestDefaults,
         progress_bar: &'a ProgressBar,
         id: usize,
-    ) -> Result<Runner<'a>, Box<dyn Error>> {
+    ) -> Result<Runner<'a>, X8Error> {
+        if config.warmup > 0 {
+            Runner::warmup(config, request_defaults, progress_bar, id).await;
+        }
+
         // make first request and collect some information like code, reflections, possible parameters
+        let (mut initial_response, mut possible_params, mut amount_of_reflections) =
+            Runner::downgrade(config, request_defaults, progress_bar, id).await?;
+
+        // the initial response is requested once more so the baseline isn't a cached page either
+        if let (Some(name), Some(cdn)) = (config.cachebuster.as_ref(), detect_cdn(&initial_response)) {
+            request_defaults.cachebuster = Some(name.to_owned());
+
+            utils::info(
+                config,
+                id,
+                progress_bar,
+                "cdn",
+                format!("{} detected, sending {} with a unique value in every request", cdn, name),
+            );
+
+            (initial_response, possible_params, amount_of_reflections) = Runner::initial_request(request_defaults).await?;
+        }
+
+        request_defaults.amount_of_reflections = amount_of_reflections;
+
+        Ok(Runner {
+            config,
+            request_defaults: request_defaults.clone(),
+            possible_params,
+            max: 0, //to be filled later, in stability-checker()
+            stable: Default::default(),
+            initial_response,
+            diffs: Vec::new(),
+            backends: Vec::new(),
+            progress_bar,
+            id,
+            concurrency_controller: if config.adaptive_concurrency {
+                Some(ConcurrencyController::new(config.concurrency, config.max_concurrency))
+            } else {
+                None
+            },
+            circuit_breaker: CircuitBreaker::new(config.circuit_breaker),
+            error_budget: ErrorBudget::new(config.error_budget),
+            technologies: Vec::new(),
+            value_overrides: HashMap::new(),
+            detectors: default_detectors(),
+            size_limit: AtomicUsize::new(usize::MAX),
+            time_baseline: Default::default(),
+            priority: Default::default(),
+            saved_requests: AtomicUsize::new(0),
+            steps: Default::default(),
+            live_queue: Default::default(),
+            wordlist_parts: match config.wordlist_parts {
+                Some(size) => Some(Mutex::new(WordlistParts::open(&config.wordlist, size)?)),
+                None => None,
+            },
+        })
+    }
+
+    /// adds the detector after the already registered ones
+    pub fn register_detector(&mut self, detector: Box<dyn Detector>) {
+        self.detectors.push(detector);
+    }
+
+    /// removes detectors with the name, e.g. to replace a built-in one
+    pub fn remove_detector(&mut self, name: &str) {
+        self.detectors.retain(|x| x.name() != name);
+    }
+
+    /// makes the initial request and switches to the other http version in case the current one
+    /// keeps failing with TLS, ALPN or h2 errors. h2 is sometimes broken behind proxies and h1 is sometimes refused by h2-only edges.
+    /// Only https targets are switched, cleartext h2 would require prior knowledge
+    async fn downgrade<'b>(
+        config: &Config,
+        request_defaults: &mut RequestDefaults,
+        progress_bar: &ProgressBar,
+        id: usize,
+    ) -> Result<(Response<'b>, Vec<String>, usize), X8Error> {
+        // None is the negotiated version
+        let mut failures: HashMap<Option<http::Version>, usize> = HashMap::new();
+
+        loop {
+            let err = match Runner::initial_request(request_defaults).await {
+                Ok(val) => return Ok(val),
+                // dead hosts and dns failures fail the same way over any version
+                Err(err) if !is_protocol_error(&err) || request_defaults.scheme != "https" => return Err(err),
+                Err(err) => err,
+            };
+
+            let current = request_defaults.http_version.or(config.http_version);
+            let failed = failures.entry(current).or_default();
+            *failed += 1;
+
+            if *failed < DOWNGRADE_AFTER {
+                continue;
+            }
+
+            let version = match current {
+                Some(http::Version::HTTP_11) => http::Version::HTTP_2,
+                _ => http::Version::HTTP_11,
+            };
+
+            if failures.get(&Some(version)).map_or(false, |x| *x >= DOWNGRADE_AFTER) {
+                return Err(err);
+            }
+
+            request_defaults.set_http_version(config, version)?;
+
+            utils::info(
+                config,
+                id,
+                progress_bar,
+                "~",
+                format!("{}. Retrying over {:?}", err, version),
+            );
+        }
+    }
+
+    /// sends throwaway requests without parameters so cold caches and scaling backends
+    /// don't end up within the initial response, learned diffs and the time baseline
+    async fn warmup(config: &Config, request_defaults: &RequestDefaults, progress_bar: &ProgressBar, id: usize) {
+        let mut failed = 0;
+
+        for _ in 0..config.warmup {
+            if Request::new(request_defaults, Vec::new()).send().await.is_err() {
+                failed += 1;
+            }
+        }
+
+        utils::info(
+            config,
+            id,
+            progress_bar,
+            "warmup",
+            format!("{} requests sent, {} failed", config.warmup, failed),
+        );
+    }
+
+    /// makes the initial request with a random parameter
+    /// returns the response, possible parameters from it and the amount of reflections of the random parameter
+    async fn initial_request<'b>(
+        request_defaults: &RequestDefaults,
+    ) -> Result<(Response<'b>, Vec<String>, usize), X8Error> {
         // we are making another request defaults because the original one will be changed right after
         let mut temp_request_defaults = request_defaults.clone();
 
-        // we need a random_parameter with a long value in order to increase accuracy while determining the default amount of reflections
-        let mut random_parameter = vec![(random_line(10), random_line(10))];
+        // we need random parameters with long values in order to increase accuracy while determining the default amount of reflections
+        // several of them are used because some pages echo the parameters a different amount of times
+        let canaries: Vec<(String, String)> = (0..REFLECTION_CANARIES)
+            .map(|_| (random_line(10), random_line(10)))
+            .collect();
 
         temp_request_defaults
             .parameters
-            .append(&mut random_parameter);
+            .extend(canaries.iter().cloned());
 
         let initial_response = Request::new(&temp_request_defaults, vec![]).send().await?;
 
         // add possible parameters to the list of parameters in case the injection place is not headers
         let possible_params = if request_defaults.injection_place != InjectionPlace::Headers {
-            initial_response.get_possible_parameters()
+            initial_response.get_possible_parameters(request_defaults.query_separator.as_deref().unwrap_or("&"))
         } else {
             Vec::new()
         };
 
-        // find how many times was the random parameter reflected
-        request_defaults.amount_of_reflections =
-            initial_response.count(&temp_request_defaults.parameters.first().unwrap().1);
+        // find how many times were the random parameters reflected
+        // the most common amount is used so a canary that happens to be echoed once more doesn't break the calibration
+        let counts: Vec<usize> = canaries
+            .iter()
+            .map(|(_, value)| initial_response.count(value, &temp_request_defaults.reflection_exclusions))
+            .collect();
+
+        let amount_of_reflections = counts
+            .iter()
+            .max_by_key(|x| (counts.iter().filter(|y| y == x).count(), cmp::Reverse(**x)))
+            .copied()
+            .unwrap_or(0);
 
         // some "magic" to be able to return initial_response
         // otherwise throws lifetime errors
@@ -92,26 +346,128 @@ impl<'a> Runner<'a> {
             http_version: initial_response.http_version,
         };
 
-        Ok(Runner {
-            config,
-            request_defaults: request_defaults.clone(),
-            possible_params,
-            max: 0, //to be filled later, in stability-checker()
-            stable: Default::default(),
-            initial_response,
-            diffs: Vec::new(),
-            progress_bar,
-            id,
-        })
+        Ok((initial_response, possible_params, amount_of_reflections))
     }
 
     /// actually runs the runner
-    pub async fn run(mut self, params: &mut Vec<String>) -> Result<RunnerOutput, Box<dyn Error>> {
+    /// the phases can be run separately as well: learn() -> discover() -> narrow() -> verify()
+    pub async fn run(mut self, params: &mut Vec<String>) -> Result<RunnerOutput, X8Error> {
+        let learned = self.learn(params).await?;
+        let discovered = self.discover(&learned).await?;
+        let narrowed = self.narrow(discovered).await?;
+        let mut found_params = self.verify(narrowed).await?;
+
+        // names added while narrowing and verifying, e.g. appended to the --extra-params-watch file
+        // or mined from the last changed responses, are checked before finishing
+        loop {
+            let mut late = Discovered::default();
+
+            if !self.check_pending(&mut late.diffs, &mut late.found_params).await? {
+                break;
+            }
+
+            late.found_params.retain(|x| !found_params.contains_name(&x.name));
+
+            if !late.found_params.is_empty() {
+                let narrowed = self.narrow(late).await?;
+                found_params.append(&mut self.verify(narrowed).await?);
+                self.sort_found(&mut found_params);
+            }
+        }
+
+        let mut output = RunnerOutput::new(
+            &self.request_defaults,
+            &self.initial_response,
+            found_params,
+        );
+        output.technologies = self.technologies;
+        output.operation = self.config.soap_operation.clone();
+        output.config = Some(ScanConfig::new(self.config, &self.request_defaults, self.max, &self.stable));
+
+        Ok(output)
+    }
+
+    /// learns how the page behaves and prepares the list of parameters to check.
+    /// params are reordered and extended with parameters found on the page
+    pub async fn learn(&mut self, params: &mut Vec<String>) -> Result<Learned, X8Error> {
         self.write_banner_url();
 
+        if !self.config.expect_codes.is_empty() && !self.config.expect_codes.contains(&self.initial_response.code) {
+            Err(X8Error::UnstableTarget(format!(
+                "The initial response code {} isn't among the expected ones",
+                self.initial_response.code
+            )))?
+        }
+
+        // operations usually respond with a client fault until the required elements are sent.
+        // Parameters that turn the fault into a successful response are found by the code or body changes
+        if let Some(operation) = self.config.soap_operation.as_ref() {
+            if let Some(fault) = soap::fault(self.initial_response.body_text()) {
+                if fault.is_fatal() {
+                    Err(X8Error::UnstableTarget(format!(
+                        "The service rejected the {} operation ({:?}): {}",
+                        operation, fault.kind, fault.message
+                    )))?
+                }
+
+                utils::info(
+                    self.config,
+                    self.id,
+                    self.progress_bar,
+                    "fault",
+                    format!("{:?}: {}", fault.kind, fault.message),
+                );
+            }
+        }
+
+        // some edge configs expose different backends over h3
+        if let Some(alt_svc) = self.initial_response.headers.get_value_case_insensitive("alt-svc") {
+            let over_h3 = self.initial_response.http_version == Some(http::Version::HTTP_3);
+
+            if !over_h3 && alt_svc.split(',').any(|x| x.trim_start().starts_with("h3")) {
+                utils::info(
+                    self.config,
+                    self.id,
+                    self.progress_bar,
+                    "alt-svc",
+                    format!("The target advertises HTTP/3 ({}), parameters handled only over h3 are found with --http 3", alt_svc),
+                );
+            }
+        }
+
+        // the first part is learned together with the page, the rest are read during discovery
+        if params.is_empty() {
+            if let Some(part) = self.wordlist_parts.as_ref().and_then(|x| x.lock().next()) {
+                *params = sort_by_weight(part);
+            }
+        }
+
+        self.extract_overrides(params);
+
         // makes a few request to check page's behavior
         self.stability_checker().await?;
 
+        // the cookies the page was learned with
+        if self.config.freeze_cookies {
+            if let Some(jar) = self.request_defaults.cookie_jar.as_ref() {
+                jar.freeze();
+            }
+        }
+
+        // the baseline is learned with one agent
+        self.request_defaults.agent_pinned.store(false, Ordering::SeqCst);
+
+        // the noise is usually the same on every page of the host
+        if let Some(host_state) = self.request_defaults.host_state.clone() {
+            host_state.add_diffs(&self.diffs);
+
+            for diff in host_state.diffs() {
+                if !self.diffs.contains(&diff) {
+                    self.diffs.push(diff);
+                }
+            }
+        }
+
         if self.config.max.is_none() {
             utils::info(
                 self.config,
@@ -122,6 +478,58 @@ impl<'a> Runner<'a> {
             );
         }
 
+        // technology specific parameters go first
+        if self.config.fingerprint {
+            self.technologies = self.fingerprint().await;
+
+            if !self.technologies.is_empty() {
+                utils::info(
+                    self.config,
+                    self.id,
+                    self.progress_bar,
+                    "tech",
+                    self.technologies.join(", "),
+                );
+
+                let specialized = technology_params(&self.technologies);
+                params.retain(|x| !specialized.contains(x));
+                params.splice(0..0, specialized);
+            }
+        }
+
+        // parameters found on similar targets before go first
+        if let Some(path) = self.config.learned_db.as_ref() {
+            let learned = LearnedDb::read(path)?.names(&self.learned_fingerprint());
+
+            if !learned.is_empty() {
+                utils::info(
+                    self.config,
+                    self.id,
+                    self.progress_bar,
+                    "learned",
+                    format!("{} parameters for {}", learned.len(), self.learned_fingerprint()),
+                );
+
+                params.retain(|x| !learned.contains(x));
+                params.splice(0..0, learned);
+            }
+        }
+
+        // parameters found on other pages of the host go first
+        if let Some(host_state) = self.request_defaults.host_state.as_ref() {
+            let found = host_state.found_params();
+
+            if !found.is_empty() {
+                params.retain(|x| !found.contains(x));
+                params.splice(0..0, found);
+            }
+        }
+
+        if self.mines_responses() {
+            let mined = self.initial_response.mine_parameters();
+            self.possible_params.extend(mined);
+        }
+
         // add only unique possible params to the vec of all params (the tool works properly only with unique parameters)
         // less efficient than making it within the sorted vec but I want to preserve the order
         for param in self.possible_params.iter() {
@@ -130,24 +538,84 @@ impl<'a> Runner<'a> {
             }
         }
 
+        self.escape_params(params);
+
+        // check only a part of the parameters in case the amount of requests is limited
+        let params = match self.apply_budget(params) {
+            Some(val) => val,
+            None => params.clone(),
+        };
+
+        Ok(Learned { params })
+    }
+
+    /// checks the parameters chunk by chunk and narrows down the chunks that changed the page.
+    /// Parameters with non random values are checked completely
+    pub async fn discover(&self, learned: &Learned) -> Result<Discovered, X8Error> {
+        self.live_queue.extend_known(&learned.params);
+
         // try to find existing parameters from the list
-        let (diffs, mut found_params) = if !params.is_empty() {
-            self.check_parameters(params).await?
-        } else {
+        let main_check = async {
+            if !learned.params.is_empty() {
+                self.check_parameters(&learned.params, true).await
+            } else {
+                utils::info(
+                    self.config,
+                    self.id,
+                    self.progress_bar,
+                    "info",
+                    "No parameters were provided",
+                );
+                Ok((self.diffs.clone(), Vec::new()))
+            }
+        };
+
+        // parameters with non random values are checked alongside the main check
+        let (main_result, custom_result) = futures::join!(main_check, self.check_non_random_parameters());
+
+        let (mut diffs, mut found_params) = main_result?;
+
+        // parameters added during the check, e.g. names found within the responses or appended to the --extra-params-watch file,
+        // are checked right after it. The next parts of the wordlist go after them
+        while self.check_pending(&mut diffs, &mut found_params).await? {}
+
+        Ok(Discovered {
+            diffs,
+            found_params,
+            custom_params: custom_result?,
+        })
+    }
+
+    /// collects the parameters narrowed down during discovery together with the custom ones
+    pub async fn narrow(&self, discovered: Discovered) -> Result<Narrowed, X8Error> {
+        let diffs = discovered.diffs;
+        let mut found_params = discovered.found_params;
+
+        // goes after the main results so process() prefers parameters with random values
+        found_params.extend(discovered.custom_params);
+
+        if let Some(host_state) = self.request_defaults.host_state.as_ref() {
+            host_state.add_diffs(&diffs);
+        }
+
+        let saved_requests = self.saved_requests.load(Ordering::SeqCst);
+        if saved_requests > 0 {
             utils::info(
                 self.config,
                 self.id,
                 self.progress_bar,
-                "info",
-                "No parameters were provided",
+                "~",
+                format!("Reflections saved ~{} narrowing requests", saved_requests),
             );
-            (Vec::new(), Vec::new())
-        };
+        }
 
-        self.check_non_random_parameters(&mut found_params).await?;
+        Ok(Narrowed { diffs, found_params })
+    }
 
+    /// removes duplicates and false positives, runs follow-up probes and scores the parameters
+    pub async fn verify(&self, narrowed: Narrowed) -> Result<Vec<FoundParameter>, X8Error> {
         // remove duplicates
-        let mut found_params = found_params.process(self.request_defaults.injection_place);
+        let mut found_params = narrowed.found_params.process(self.request_defaults.injection_place);
 
         // verify found parameters
         if self.config.verify {
@@ -155,8 +623,9 @@ impl<'a> Runner<'a> {
                 &self.initial_response,
                 &self.request_defaults,
                 &found_params,
-                &diffs,
+                &narrowed.diffs,
                 &self.stable,
+                &self.error_budget,
             )
             .await
             {
@@ -210,20 +679,281 @@ impl<'a> Runner<'a> {
             }
         }
 
-        Ok(RunnerOutput::new(
-            &self.request_defaults,
-            &self.initial_response,
-            found_params,
-        ))
+        // headers and json bodies aren't url-decoded
+        if self.request_defaults.injection_place == InjectionPlace::Path
+            || (self.request_defaults.injection_place == InjectionPlace::Body && !self.request_defaults.is_json)
+        {
+            if let Err(err) = self.probe_reflections(&mut found_params).await {
+                utils::info(self.config, self.id, self.progress_bar, "~", format!("Unable to probe reflections: {}", err));
+            }
+        }
+
+        if self.config.parser_probes
+            && (self.request_defaults.injection_place == InjectionPlace::Path
+                || (self.request_defaults.injection_place == InjectionPlace::Body && !self.request_defaults.is_json))
+        {
+            if let Err(err) = self.probe_parsers(&mut found_params).await {
+                utils::info(self.config, self.id, self.progress_bar, "~", format!("Unable to probe parsers: {}", err));
+            }
+        }
+
+        if let Some(canary) = self.config.redirect_canary.as_ref() {
+            if let Err(err) = self.probe_redirects(&mut found_params, canary).await {
+                utils::info(self.config, self.id, self.progress_bar, "~", format!("Unable to probe redirects: {}", err));
+            }
+        }
+
+        if let Some(canary) = self.config.ssrf_canary.as_ref() {
+            if let Err(err) = self.probe_ssrf(&mut found_params, canary).await {
+                utils::info(self.config, self.id, self.progress_bar, "~", format!("Unable to probe ssrf: {}", err));
+            }
+        }
+
+        if let Err(err) = self.sample_latency(&mut found_params).await {
+            utils::info(self.config, self.id, self.progress_bar, "~", format!("Unable to measure latency: {}", err));
+        }
+
+        // the most interesting parameters go first
+        for param in found_params.iter_mut() {
+            param.calculate_score(&self.initial_response);
+            param.fill_tags();
+            param.latency = Latency::new(&param.times, &self.time_baseline);
+            param.provenance = Some(self.steps.provenance(
+                &param.name,
+                &self.initial_response,
+                self.request_defaults.amount_of_reflections,
+            ));
+        }
+
+        if let Some(summary) = self.error_budget.summary() {
+            utils::info(self.config, self.id, self.progress_bar, "errors", summary);
+        }
+
+        found_params.retain(|x| x.score >= self.config.min_score);
+
+        if let Some(path) = self.config.learned_db.as_ref() {
+            let names = found_params.iter().map(|x| x.name.clone()).collect::<Vec<String>>();
+
+            if let Err(err) = LearnedDb::append(path, &self.learned_fingerprint(), &names) {
+                utils::info(self.config, self.id, self.progress_bar, "~", format!("Unable to update the learned db: {}", err));
+            }
+        }
+
+        if let Some(host_state) = self.request_defaults.host_state.as_ref() {
+            host_state.add_found_params(&found_params.iter().map(|x| x.name.clone()).collect::<Vec<String>>());
+        }
+
+        self.sort_found(&mut found_params);
+
+        Ok(found_params)
     }
 
-    /// check parameters with non random values
-    async fn check_non_random_parameters(
+    /// orders the found parameters according to --sort-by
+    fn sort_found(&self, found_params: &mut [FoundParameter]) {
+        if self.config.sort_by == "discovery" {
+            found_params.sort_by_key(|x| x.discovered_at);
+        } else {
+            found_params.sort_by(|a, b| b.score.cmp(&a.score));
+        }
+    }
+
+    /// the key for --learned-db: detected technologies or product names from headers
+    fn learned_fingerprint(&self) -> String {
+        if self.technologies.is_empty() {
+            self.initial_response.fingerprint()
+        } else {
+            self.technologies.join("+")
+        }
+    }
+
+    /// handles wordlist entries with overrides:
+    /// `name=value1,value2` are moved to self.value_overrides,
+    /// `name:json`, `name:array` and `name:string` are replaced with the name and their serialization is saved to request defaults
+    fn extract_overrides(&mut self, params: &mut Vec<String>) {
+        let mut rest = Vec::with_capacity(params.len());
+
+        for param in params.drain(..) {
+            if let Some((name, values)) = param.split_once('=') {
+                if values.contains(',') && !name.is_empty() {
+                    // values are popped from the end
+                    let values = values.split(',').rev().map(|x| x.to_string()).collect();
+                    self.value_overrides.insert(name.to_string(), values);
+                    continue;
+                }
+            }
+
+            if let Some((name, kind)) = param.rsplit_once(':') {
+                if ["json", "array", "string"].contains(&kind) && !name.is_empty() {
+                    self.request_defaults
+                        .serializations
+                        .insert(name.to_string(), kind.to_string());
+                    rest.push(name.to_string());
+                    continue;
+                }
+            }
+
+            rest.push(param);
+        }
+
+        *params = rest;
+    }
+
+    /// checks the names waiting within the live queue or the next part of the wordlist.
+    /// Returns false when there is nothing left to check
+    async fn check_pending(
         &self,
+        diffs: &mut Vec<String>,
         found_params: &mut Vec<FoundParameter>,
-    ) -> Result<(), Box<dyn Error>> {
-        if !self.request_defaults.disable_custom_parameters {
-            let mut custom_parameters = self.config.custom_parameters.clone();
+    ) -> Result<bool, X8Error> {
+        if let Some(extra_params) = self.request_defaults.extra_params.as_ref() {
+            let names = extra_params
+                .names()
+                .iter()
+                .filter_map(|x| self.request_defaults.escape_name(x))
+                .collect();
+            self.live_queue.push(names);
+        }
+
+        let (live, is_part) = match self.live_queue.take() {
+            live if !live.is_empty() => (live, false),
+            _ => match self.next_wordlist_part() {
+                Some(part) => (part, true),
+                None => return Ok(false),
+            },
+        };
+
+        utils::info(
+            self.config,
+            self.id,
+            self.progress_bar,
+            if is_part { "part" } else { "live" },
+            format!("checking {} more parameters", live.len()),
+        );
+
+        let (live_diffs, mut live_found) = self.check_parameters(&live, is_part).await?;
+
+        for diff in live_diffs {
+            if !diffs.contains(&diff) {
+                diffs.push(diff);
+            }
+        }
+        found_params.append(&mut live_found);
+
+        Ok(true)
+    }
+
+    /// the next part of the wordlist without the names that were already checked
+    fn next_wordlist_part(&self) -> Option<Vec<String>> {
+        let parts = self.wordlist_parts.as_ref()?;
+
+        loop {
+            let mut part = sort_by_weight(parts.lock().next()?);
+            self.escape_params(&mut part);
+            part.retain(|x| !self.live_queue.is_known(x));
+
+            if !part.is_empty() {
+                return Some(part);
+            }
+        }
+    }
+
+    /// escapes names that would break the request and removes the ones that can't be sent at all
+    fn escape_params(&self, params: &mut Vec<String>) {
+        let mut escaped = 0;
+        let mut skipped = Vec::new();
+        let mut rest = Vec::with_capacity(params.len());
+
+        for param in params.drain(..) {
+            match self.request_defaults.escape_name(&param) {
+                Some(val) => {
+                    if val != param {
+                        escaped += 1;
+                    }
+                    rest.push(val);
+                }
+                None => skipped.push(param),
+            }
+        }
+
+        *params = rest;
+
+        if escaped > 0 {
+            utils::info(
+                self.config,
+                self.id,
+                self.progress_bar,
+                "~",
+                format!("{} parameter names were escaped", escaped),
+            );
+        }
+
+        if !skipped.is_empty() {
+            utils::info(
+                self.config,
+                self.id,
+                self.progress_bar,
+                "~",
+                format!(
+                    "{} parameters can't be sent within {:?} and were skipped: {}",
+                    skipped.len(),
+                    self.request_defaults.injection_place,
+                    skipped[..skipped.len().min(5)].join(", ")
+                ),
+            );
+        }
+    }
+
+    /// limits the parameters to the amount that can be checked within --max-requests
+    /// returns None in case all of them fit
+    fn apply_budget(&self, params: &[String]) -> Option<Vec<String>> {
+        let max_requests = self.config.max_requests?;
+        let capacity = max_requests * self.max;
+
+        if params.len() <= capacity {
+            return None;
+        }
+
+        let mut budgeted_params = params.to_vec();
+
+        if self.config.sample {
+            rand::thread_rng().shuffle(&mut budgeted_params);
+        }
+
+        budgeted_params.truncate(capacity);
+
+        utils::info(
+            self.config,
+            self.id,
+            self.progress_bar,
+            "budget",
+            format!(
+                "Checking {} of {} parameters within {} requests (estimated coverage - {:.1}%)",
+                capacity,
+                params.len(),
+                max_requests,
+                capacity as f64 * 100.0 / params.len() as f64
+            ),
+        );
+
+        Some(budgeted_params)
+    }
+
+    /// check parameters with non random values
+    async fn check_non_random_parameters(&self) -> Result<Vec<FoundParameter>, X8Error> {
+        let mut found_params = Vec::new();
+
+        // the values from the wordlist are checked even when custom parameters are disabled
+        let mut custom_parameters = if self.request_defaults.disable_custom_parameters {
+            HashMap::new()
+        } else {
+            self.config.custom_parameters.clone()
+        };
+
+        for (k, v) in self.value_overrides.iter() {
+            custom_parameters.insert(k.to_owned(), v.to_owned());
+        }
+
+        if !custom_parameters.is_empty() {
             let mut params = Vec::new();
 
             // in a loop check common parameters like debug, admin, .. with common values true, 1, false..
@@ -248,35 +978,67 @@ impl<'a> Runner<'a> {
                     break;
                 }
 
-                found_params.append(&mut self.check_parameters(&params).await?.1);
+                found_params.append(&mut self.check_parameters(&params, false).await?.1);
                 params.clear();
             }
         }
 
-        Ok(())
+        Ok(found_params)
     }
 
     /// makes several requests in order to learn how the page behaves
     /// tries to increase the max amount of parameters per request in case the default value not changed
-    async fn stability_checker(&mut self) -> Result<(), Box<dyn Error>> {
+    async fn stability_checker(&mut self) -> Result<(), X8Error> {
         // guess or get from the user the amount of parameters to send per request
         let default_max = match self.config.max {
             Some(var) => var as isize,
             None => match self.request_defaults.injection_place {
+                // reflections are attributed by their amount, so the chunks can be much bigger
+                InjectionPlace::Body if self.config.reflected_only => -(REFLECTED_ONLY_MAX as isize),
                 InjectionPlace::Body => -512,
                 InjectionPlace::Path => self.try_to_guess_the_right_max_for_query().await?,
-                InjectionPlace::Headers => -64,
+                InjectionPlace::Headers => self.try_to_guess_the_right_max_for_headers().await?,
                 InjectionPlace::HeaderValue => -64,
+                InjectionPlace::Trailers | InjectionPlace::ChunkExtensions => -64,
             },
         };
 
         self.max = default_max.unsigned_abs();
 
+        // every parameter takes the place of two with --duplicate-names.
+        // The guessed amounts are learned with the duplicates already
+        let guessed = self.config.max.is_none() && self.request_defaults.injection_place == InjectionPlace::Path;
+
+        if self.request_defaults.duplicate_names.is_some() && !guessed {
+            self.max = cmp::max(self.max / 2, 1);
+        }
+
         // make a few requests and collect all persistent diffs, check for stability
-        self.empty_reqs().await?;
+        let learned = self.empty_reqs().await;
+
+        // the page may look unstable only because the responses are coming from different backends
+        if self.backends.len() > 1 && (learned.is_err() || !self.stable.body) && self.pin_backend().await? {
+            self.empty_reqs().await?;
+        } else {
+            learned?;
+        }
+
+        if self.backends.len() > 1 {
+            utils::info(
+                self.config,
+                self.id,
+                self.progress_bar,
+                "~",
+                format!("The responses are coming from different backends: {}", self.backends.join(", ")),
+            );
+        }
 
         if self.config.reflected_only && !self.stable.reflections {
-            Err("Reflections are not stable")?;
+            Err(X8Error::UnstableTarget("Reflections are not stable".to_string()))?;
+        }
+
+        if self.request_defaults.injection_place == InjectionPlace::Body && self.config.query_split {
+            self.probe_query_split().await?;
         }
 
         // check whether it is possible to use 192 or 256 params in a single request instead of 128 default
@@ -289,12 +1051,17 @@ impl<'a> Runner<'a> {
 
     /// makes first requests and checks page behavior
     /// fills self.diffs and self.stable
-    pub async fn empty_reqs(&mut self) -> Result<(), Box<dyn Error>> {
+    pub async fn empty_reqs(&mut self) -> Result<(), X8Error> {
         let mut stable = Stable {
             body: true,
             reflections: true,
         };
-        let mut diffs: Vec<String> = Vec::new();
+        // (diff, the amount of learning responses it appeared in)
+        let mut frequencies: Vec<(String, usize)> = Vec::new();
+        let mut samples = 0;
+        let mut times: Vec<u128> = Vec::new();
+
+        self.backends = vec![self.initial_response.backend()];
 
         // set up progress bar
         self.prepare_progress_bar(progress_style_learn_requests(self.config), self.config.learn_requests_count);
@@ -303,12 +1070,26 @@ impl<'a> Runner<'a> {
             // to increase stability
             tokio::time::sleep(tokio::time::Duration::from_millis(150)).await;
 
-            let response = Request::new_random(&self.request_defaults, self.max)
+            let response = match Request::new_random(&self.request_defaults, self.max)
                 .send()
-                .await?;
+                .await
+            {
+                Ok(val) => val,
+                Err(err) => {
+                    self.error_budget.record(Phase::Learning, &err)?;
+                    continue;
+                }
+            };
 
             self.progress_bar.inc(1);
 
+            times.push(response.time);
+
+            let backend = response.backend();
+            if !self.backends.contains(&backend) {
+                self.backends.push(backend);
+            }
+
             // do not check pages >25MB because usually its just a binary file or sth
             if response.text.len() > MAX_PAGE_SIZE && !self.config.force {
                 Err("The page's size > 25MB. Use --force flag to disable this error")?;
@@ -318,13 +1099,86 @@ impl<'a> Runner<'a> {
                 stable.reflections = false;
             }
 
-            let (is_code_diff, mut new_diffs) = response.compare(&self.initial_response, &diffs)?;
+            // the body isn't compared with --reflected-only, only the code needs to be stable
+            let (is_code_diff, new_diffs) = if self.config.reflected_only {
+                (response.code != self.initial_response.code, Vec::new())
+            } else {
+                response.compare(&self.initial_response, &[])?
+            };
 
             if is_code_diff {
-                Err("The page is not stable (code)")?
+                // the page started asking for credentials
+                if [401, 403].contains(&response.code)
+                    && ![401, 403].contains(&self.initial_response.code)
+                    && !self.config.expect_codes.contains(&response.code)
+                {
+                    Err(X8Error::AuthExpired(format!("the response code changed to {}", response.code)))?
+                }
+
+                // some servers change the code only because of the amount of parameters
+                if self.config.max.is_none()
+                    && self.max > 1
+                    && Request::new_random(&self.request_defaults, 0).send().await?.code == self.initial_response.code
+                {
+                    let boundary = self.find_count_boundary(0, self.max).await?;
+
+                    if boundary > 0 {
+                        self.report_count_boundary(boundary);
+                        continue;
+                    }
+                }
+
+                Err(X8Error::UnstableTarget("The page is not stable (code)".to_string()))?
+            }
+
+            samples += 1;
+            count_diffs(&mut frequencies, new_diffs);
+        }
+
+        // the body stability doesn't matter for reflections
+        if self.config.reflected_only {
+            self.time_baseline = TimeBaseline::new(&times);
+            (self.diffs, self.stable) = (Vec::new(), stable);
+            return Ok(());
+        }
+
+        // parts like "generated at" or "X seconds ago" depend on when the request is sent
+        // so the page is sampled right away and after a pause as well
+        for delay in LEARN_DELAYS {
+            tokio::time::sleep(tokio::time::Duration::from_millis(delay)).await;
+
+            if let Ok(response) = Request::new_random(&self.request_defaults, self.max).send().await {
+                times.push(response.time);
+
+                let (is_code_diff, new_diffs) = response.compare(&self.initial_response, &[])?;
+                if !is_code_diff {
+                    samples += 1;
+                    count_diffs(&mut frequencies, new_diffs);
+                }
             }
+        }
+
+        self.time_baseline = TimeBaseline::new(&times);
+
+        // rare diffs are rather flukes of single responses than noise, so they aren't ignored afterwards
+        let diffs: Vec<String> = frequencies
+            .iter()
+            .filter(|(_, count)| count * 100 >= self.config.persistent_diffs * samples)
+            .map(|(diff, _)| diff.to_owned())
+            .collect();
 
-            diffs.append(&mut new_diffs);
+        if self.config.verbose > 1 {
+            for (diff, count) in frequencies.iter() {
+                let kind = if diffs.contains(diff) { "persistent" } else { "rare" };
+
+                utils::info(
+                    self.config,
+                    self.id,
+                    self.progress_bar,
+                    kind,
+                    format!("{}/{} {}", count, samples, diff),
+                );
+            }
         }
 
         // check the last time
@@ -353,9 +1207,112 @@ impl<'a> Runner<'a> {
         Ok(())
     }
 
+    /// binds the client to a single address of the host so all the responses come from the same backend
+    /// remakes the initial response. Returns false in case there's nothing to pin
+    async fn pin_backend(&mut self) -> Result<bool, X8Error> {
+        // the client is already bound to a single address
+        if self.config.pin_ip.is_some() || !self.request_defaults.failover_clients.is_empty() {
+            return Ok(false);
+        }
+
+        let url = Url::parse(&format!(
+            "{}://{}:{}/",
+            self.request_defaults.scheme, self.request_defaults.host, self.request_defaults.port
+        ))?;
+
+        let addrs = resolve_addrs(self.config, &url)?;
+
+        if addrs.len() < 2 {
+            return Ok(false);
+        }
+
+        utils::info(
+            self.config,
+            self.id,
+            self.progress_bar,
+            "~",
+            format!(
+                "The responses are coming from different backends ({}). Pinning to {}",
+                self.backends.join(", "),
+                addrs[0].ip()
+            ),
+        );
+
+        let connect_host = self.request_defaults.sni.as_ref().unwrap_or(&self.request_defaults.host).to_owned();
+        self.request_defaults.client =
+            create_resolved_client(
+                &self.request_defaults.client_config(self.config),
+                &connect_host,
+                addrs[0],
+                self.request_defaults.cookie_jar.as_ref(),
+            )?;
+
+        let (initial_response, _, amount_of_reflections) =
+            Runner::initial_request(&self.request_defaults).await?;
+
+        self.initial_response = initial_response;
+        self.request_defaults.amount_of_reflections = amount_of_reflections;
+
+        Ok(true)
+    }
+
+    /// checks whether the backend merges query and body parameters, like PHP's $_REQUEST does.
+    /// In this case every chunk is split between the query and the body, so it can be twice as big
+    async fn probe_query_split(&mut self) -> Result<(), X8Error> {
+        // the merging is visible only by reflections of the query parameter
+        if self.config.max.is_some()
+            || !self.stable.reflections
+            || self.request_defaults.amount_of_reflections == 0
+            || self.request_defaults.protobuf.is_some()
+            || self.request_defaults.jwt.is_some()
+        {
+            return Ok(());
+        }
+
+        // the same parameters with the same values are sent within the body and split between the body and the query
+        let params: Vec<String> = (0..2)
+            .map(|_| format!("{}={}", random_line(VALUE_LENGTH), random_line(VALUE_LENGTH)))
+            .collect();
+
+        let body_response = Request::new(&self.request_defaults, params.clone()).send().await?;
+
+        let mut split_defaults = self.request_defaults.clone();
+        split_defaults.query_split = true;
+
+        // one parameter goes to the body, another one to the query
+        let mut split_response = Request::new(&split_defaults, params).send().await?;
+        split_response.fill_reflected_parameters(&self.initial_response);
+
+        // a page that only echoes the url reflects the query parameter elsewhere, so the responses differ
+        let (is_code_diff, new_diffs) = split_response.compare(&body_response, &self.diffs)?;
+
+        let merged = split_response.code == self.initial_response.code
+            && split_response.reflected_parameters.is_empty()
+            && !is_code_diff
+            && new_diffs.is_empty();
+
+        if merged {
+            self.request_defaults.query_split = true;
+            self.max *= 2;
+
+            utils::info(
+                self.config,
+                self.id,
+                self.progress_bar,
+                "~",
+                format!(
+                    "The backend merges query and body parameters. Sending {} parameters per request between them",
+                    self.max
+                ),
+            );
+        }
+
+        Ok(())
+    }
+
     /// checks whether the increasing of the amount of parameters changes the page
     /// changes self.max in case the page is stable with more parameters per request
-    pub async fn try_to_increase_max(&mut self) -> Result<(), Box<dyn Error>> {
+    pub async fn try_to_increase_max(&mut self) -> Result<(), X8Error> {
 
         let delta = self.max / 2;
 
@@ -367,12 +1324,20 @@ impl<'a> Runner<'a> {
             response.compare(&self.initial_response, &self.diffs)?;
         let mut is_the_body_the_same = true;
 
-        if !new_diffs.is_empty() {
+        // the body isn't learned with --reflected-only
+        if !new_diffs.is_empty() && !self.config.reflected_only {
             is_the_body_the_same = false;
         }
 
+        // the page changes only because of the amount of parameters
+        if is_code_different {
+            let boundary = self.find_count_boundary(self.max, self.max + delta).await?;
+            self.report_count_boundary(boundary);
+            return Ok(());
+        }
+
         // in case the page isn't different from previous one - try to increase max amount of parameters by 128
-        if !is_code_different && (!self.stable.body || is_the_body_the_same) {
+        if !self.stable.body || is_the_body_the_same {
             let response = Request::new_random(&self.request_defaults, self.max + delta*2)
                 .send()
                 .await?;
@@ -380,11 +1345,14 @@ impl<'a> Runner<'a> {
             let (is_code_different, new_diffs) =
                 response.compare(&self.initial_response, &self.diffs)?;
 
-            if !new_diffs.is_empty() {
+            if !new_diffs.is_empty() && !self.config.reflected_only {
                 is_the_body_the_same = false;
             }
 
-            if !is_code_different && (!self.stable.body || is_the_body_the_same) {
+            if is_code_different {
+                let boundary = self.find_count_boundary(self.max + delta, self.max + delta*2).await?;
+                self.report_count_boundary(boundary);
+            } else if !self.stable.body || is_the_body_the_same {
                 self.max += delta*2
             } else {
                 self.max += delta
@@ -394,9 +1362,43 @@ impl<'a> Runner<'a> {
         Ok(())
     }
 
+    /// searches for the largest amount of random parameters that doesn't change the response code
+    /// accepted is known to keep the code while rejected is known to change it
+    async fn find_count_boundary(&self, mut accepted: usize, mut rejected: usize) -> Result<usize, X8Error> {
+        while rejected - accepted > 1 {
+            let middle = (accepted + rejected) / 2;
+
+            let code = Request::new_random(&self.request_defaults, middle)
+                .send()
+                .await?
+                .code;
+
+            if code == self.initial_response.code {
+                accepted = middle
+            } else {
+                rejected = middle
+            }
+        }
+
+        Ok(accepted)
+    }
+
+    /// keeps chunks under the amount of parameters that changes the page
+    fn report_count_boundary(&mut self, boundary: usize) {
+        utils::info(
+            self.config,
+            self.id,
+            self.progress_bar,
+            "~",
+            format!("The page changes when more than {} parameters are sent", boundary),
+        );
+
+        self.max = boundary;
+    }
+
     /// tries to detect the right amount of parameters that can be send per request in query
     /// TODO maybe detect based on reflection as well
-    pub async fn try_to_guess_the_right_max_for_query(&mut self) -> Result<isize, Box<dyn Error>> {
+    pub async fn try_to_guess_the_right_max_for_query(&mut self) -> Result<isize, X8Error> {
 
         let mut max = 128;
 
@@ -418,7 +1420,7 @@ impl<'a> Runner<'a> {
             }
 
             if Request::new_random(&self.request_defaults, 0).send().await?.code != self.initial_response.code {
-                Err("The page became unstable (code)")?
+                Err(X8Error::UnstableTarget("The page became unstable (code)".to_string()))?
             };
 
             max /= 2;
@@ -440,6 +1442,46 @@ impl<'a> Runner<'a> {
         Ok(max as isize *-1)
     }
 
+    /// tries to detect the amount of headers that can be send per request
+    /// servers limit both the amount and the total size of headers so the limit is probed instead of using small chunks
+    pub async fn try_to_guess_the_right_max_for_headers(&mut self) -> Result<isize, X8Error> {
+
+        // some servers reject every request with unknown headers so there's nothing to check
+        let response = Request::new_random(&self.request_defaults, 1).send().await?;
+
+        if response.code != self.initial_response.code {
+            Err(X8Error::UnstableTarget(format!(
+                "The server rejects unknown headers ({} -> {})",
+                self.initial_response.code, response.code
+            )))?
+        }
+
+        let mut max = 256;
+
+        loop {
+            let code = match Request::new_random(&self.request_defaults, max)
+                .send()
+                .await {
+                    Ok(val) => val.code,
+                    // the connection may be closed in case there are too many headers
+                    Err(_) => 0,
+            };
+
+            // the choosen max is okay
+            if self.initial_response.code == code {
+                break
+            }
+
+            max /= 2;
+
+            if max < 8 {
+                Err("Unable to guess the max amount of headers per request. Try to use --max command line argument.")?
+            }
+        }
+
+        Ok(max as isize *-1)
+    }
+
     pub fn prepare_progress_bar(&self, sty: ProgressStyle, length: usize) {
         self.progress_bar.reset();
         self.progress_bar.set_prefix(self.make_progress_prefix());
@@ -482,10 +1524,11 @@ impl<'a> Runner<'a> {
         };
 
         let msg = format!(
-            "{}{} {} ({}) [{}] {{{}}}",
+            "{}{} {}{} ({}) [{}] {{{}}}",
             id,
             self.request_defaults.method.blue(),
             self.request_defaults.url_without_default_port().green(),
+            self.config.soap_operation.as_ref().map(|x| format!(" {}", x.cyan())).unwrap_or_default(),
             self.initial_response.code(),
             self.initial_response.text.len().to_string().green(),
             self.request_defaults
@@ -495,9 +1538,33 @@ impl<'a> Runner<'a> {
         );
 
         if self.config.disable_progress_bar {
-            writeln!(io::stdout(), "{}", msg).ok();
+            writeln!(io::stderr(), "{}", msg).ok();
         } else {
             self.progress_bar.println(msg);
         }
     }
 }
+
+/// counts in how many learning responses every diff appeared
+fn count_diffs(frequencies: &mut Vec<(String, usize)>, diffs: Vec<String>) {
+    for diff in diffs {
+        match frequencies.iter_mut().find(|x| x.0 == diff) {
+            Some(val) => val.1 += 1,
+            None => frequencies.push((diff, 1)),
+        }
+    }
+}
+
+/// errors the other http version can avoid: failed TLS handshakes, ALPN and h2 protocol errors
+fn is_protocol_error(err: &X8Error) -> bool {
+    let message = match err {
+        X8Error::Network(val) | X8Error::ConnectionClosed(val) => val.to_lowercase(),
+        _ => return false,
+    };
+
+    // a version switch doesn't fix certificates
+    !message.contains("certificate")
+        && ["http2", "h2 ", "alpn", "tls", "handshake", "corrupt message", "frame", "goaway", "protocol error"]
+            .iter()
+            .any(|x| message.contains(x))
+}
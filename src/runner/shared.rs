@@ -0,0 +1,224 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
+
+/// state shared between runners in case --shared-state is used
+#[derive(Debug, Default)]
+pub struct SharedInfo {
+    hosts: Mutex<HashMap<String, Arc<HostState>>>,
+}
+
+impl SharedInfo {
+    /// returns the state of the host, creates it on the first call
+    pub fn host(&self, host: &str) -> Arc<HostState> {
+        Arc::clone(self.hosts.lock().entry(host.to_string()).or_default())
+    }
+}
+
+/// what runners learned about the host
+#[derive(Debug, Default)]
+pub struct HostState {
+    /// diffs that appear with random parameters on the host's pages
+    diffs: Mutex<Vec<String>>,
+
+    /// parameters found on the host's pages
+    found_params: Mutex<Vec<String>>,
+
+    /// requests to the host are paused until this moment after 429 responses
+    paused_until: Mutex<Option<Instant>>,
+}
+
+impl HostState {
+    pub fn diffs(&self) -> Vec<String> {
+        self.diffs.lock().clone()
+    }
+
+    pub fn add_diffs(&self, diffs: &[String]) {
+        let mut known = self.diffs.lock();
+        for diff in diffs {
+            if !known.contains(diff) {
+                known.push(diff.to_owned());
+            }
+        }
+    }
+
+    pub fn found_params(&self) -> Vec<String> {
+        self.found_params.lock().clone()
+    }
+
+    pub fn add_found_params(&self, names: &[String]) {
+        let mut known = self.found_params.lock();
+        for name in names {
+            if !known.contains(name) {
+                known.push(name.to_owned());
+            }
+        }
+    }
+
+    /// waits in case the host asked to slow down
+    pub async fn wait(&self) {
+        loop {
+            let paused_until = *self.paused_until.lock();
+
+            match paused_until {
+                Some(until) if until > Instant::now() => tokio::time::sleep_until(until.into()).await,
+                _ => break,
+            }
+        }
+    }
+
+    /// pauses requests of every runner to the host
+    pub fn pause(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+
+        let mut paused_until = self.paused_until.lock();
+        if paused_until.map_or(true, |x| x < until) {
+            *paused_until = Some(until);
+        }
+    }
+}
+
+/// limits the amount of concurrent requests per host in case --max-host-concurrency is used.
+/// Free slots are given to the urls of the host in turns, so a url with many waiting requests doesn't starve the others
+#[derive(Debug)]
+pub struct HostLimiter {
+    per_host: usize,
+    hosts: Mutex<HashMap<String, Arc<HostSlots>>>,
+    /// every url|method pair is a separate target
+    last_target: AtomicUsize,
+}
+
+impl HostLimiter {
+    pub fn new(per_host: usize) -> Self {
+        Self {
+            per_host,
+            hosts: Mutex::new(HashMap::new()),
+            last_target: AtomicUsize::new(0),
+        }
+    }
+
+    /// returns request slots of the host for a new target, creates them on the first call
+    pub fn target(&self, host: &str) -> HostTarget {
+        let slots = Arc::clone(
+            self.hosts
+                .lock()
+                .entry(host.to_string())
+                .or_insert_with(|| Arc::new(HostSlots::new(self.per_host))),
+        );
+
+        HostTarget {
+            slots,
+            id: self.last_target.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+/// request slots of the host as seen by one target
+#[derive(Debug, Clone)]
+pub struct HostTarget {
+    slots: Arc<HostSlots>,
+    id: usize,
+}
+
+impl HostTarget {
+    /// waits for the turn of the target. The slot is released once dropped
+    pub async fn acquire(&self) -> HostSlot {
+        let receiver = {
+            let mut inner = self.slots.inner.lock();
+
+            if inner.free > 0 && inner.waiting.is_empty() {
+                inner.free -= 1;
+                return HostSlot { slots: Some(Arc::clone(&self.slots)) };
+            }
+
+            let (sender, receiver) = oneshot::channel();
+
+            match inner.waiting.iter_mut().find(|(id, _)| *id == self.id) {
+                Some((_, senders)) => senders.push_back(sender),
+                None => inner.waiting.push_back((self.id, VecDeque::from([sender]))),
+            }
+
+            receiver
+        };
+
+        // the sender is never dropped without sending, the slot is given to the next target instead
+        receiver.await.unwrap_or(HostSlot { slots: None })
+    }
+
+    pub fn free(&self) -> usize {
+        self.slots.inner.lock().free
+    }
+}
+
+#[derive(Debug)]
+struct HostSlots {
+    inner: Mutex<SlotsInner>,
+}
+
+#[derive(Debug)]
+struct SlotsInner {
+    free: usize,
+    /// targets with waiting requests in the order of their turns
+    waiting: VecDeque<(usize, VecDeque<oneshot::Sender<HostSlot>>)>,
+}
+
+impl HostSlots {
+    fn new(per_host: usize) -> Self {
+        Self {
+            inner: Mutex::new(SlotsInner {
+                free: per_host,
+                waiting: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// gives the slot to the first waiting request of the next target
+    fn release(self: Arc<Self>) {
+        let mut slot = HostSlot { slots: Some(Arc::clone(&self)) };
+        let mut inner = self.inner.lock();
+
+        while let Some((id, mut senders)) = inner.waiting.pop_front() {
+            let sender = match senders.pop_front() {
+                Some(val) => val,
+                None => continue,
+            };
+
+            // the target goes to the end of the queue
+            if !senders.is_empty() {
+                inner.waiting.push_back((id, senders));
+            }
+
+            // the request could be cancelled while waiting
+            match sender.send(slot) {
+                Ok(()) => return,
+                Err(val) => slot = val,
+            }
+        }
+
+        inner.free += 1;
+        // the slot is returned to the free ones without releasing it once more
+        slot.slots = None;
+    }
+}
+
+/// a request slot of the host
+#[derive(Debug)]
+pub struct HostSlot {
+    slots: Option<Arc<HostSlots>>,
+}
+
+impl Drop for HostSlot {
+    fn drop(&mut self) {
+        if let Some(slots) = self.slots.take() {
+            slots.release();
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    error::Error,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use parking_lot::Mutex;
+use tokio::sync::Notify;
+
+use crate::utils::error;
+
+/// how often the --control-file is checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// requests per second. The interval between requests is 1µs then
+const MAX_RATE: u64 = 1_000_000;
+
+/// the delay, concurrency and rate limits that can be changed while the scan is running.
+/// Shared between runners
+#[derive(Debug)]
+pub struct Throttle {
+    /// milliseconds to sleep before every request
+    delay: AtomicU64,
+
+    /// the max amount of requests in flight across all runners. 0 - unlimited
+    concurrency: AtomicUsize,
+    active: Mutex<usize>,
+    released: Notify,
+
+    /// requests per second across all runners. 0 - unlimited, MAX_RATE at most
+    rate: AtomicU64,
+    next_start: Mutex<Instant>,
+
+    /// used when the control file doesn't contain the value
+    default_delay: Duration,
+}
+
+/// holds the slot of the request in flight
+#[derive(Debug)]
+pub struct ThrottlePermit<'a>(&'a Throttle);
+
+impl Drop for ThrottlePermit<'_> {
+    fn drop(&mut self) {
+        *self.0.active.lock() -= 1;
+        self.0.released.notify_waiters();
+    }
+}
+
+impl Throttle {
+    /// starts applying the control file in the background until the returned value is dropped.
+    /// The file is reread on changes and on SIGHUP
+    pub fn watch(path: String, default_delay: Duration) -> Arc<Self> {
+        let throttle = Arc::new(Self::new(default_delay));
+        let weak = Arc::downgrade(&throttle);
+
+        tokio::spawn(async move {
+            let mut hangup = hangup();
+            let mut modified: Option<SystemTime> = None;
+
+            while let Some(throttle) = weak.upgrade() {
+                let current = tokio::fs::metadata(&path).await.and_then(|x| x.modified()).ok();
+
+                if current.is_some() && current != modified {
+                    modified = current;
+
+                    let applied = match tokio::fs::read_to_string(&path).await {
+                        Ok(content) => throttle.apply(&content),
+                        Err(err) => Err(err.into()),
+                    };
+
+                    match applied {
+                        Ok(state) => error(format!("Applied {}: {}", path, state), None, None, None),
+                        Err(err) => error(format!("Unable to apply {}: {}", path, err), None, None, None),
+                    }
+                }
+
+                drop(throttle);
+
+                // the file is reread on SIGHUP even if it wasn't modified
+                if next_check(&mut hangup).await {
+                    modified = None;
+                }
+            }
+        });
+
+        throttle
+    }
+
+    /// without limits until the control file is applied
+    pub fn new(default_delay: Duration) -> Self {
+        Self {
+            delay: AtomicU64::new(default_delay.as_millis() as u64),
+            concurrency: AtomicUsize::new(0),
+            active: Mutex::new(0),
+            released: Notify::new(),
+            rate: AtomicU64::new(0),
+            next_start: Mutex::new(Instant::now()),
+            default_delay,
+        }
+    }
+
+    /// applies `key = value` lines: delay (ms), concurrency and rate (requests per second).
+    /// Missing keys are reset to the values the scan was started with
+    pub(super) fn apply(&self, content: &str) -> Result<String, Box<dyn Error>> {
+        let (mut delay, mut concurrency, mut rate) = (self.default_delay.as_millis() as u64, 0, 0);
+
+        for line in content.lines().map(|x| x.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or_else(|| format!("Unable to parse the line: {}", line))?;
+            let value = value.trim();
+
+            match key.trim() {
+                "delay" => delay = value.trim_end_matches("ms").parse()?,
+                "concurrency" => concurrency = value.parse()?,
+                "rate" => rate = value.parse()?,
+                key => Err(format!("Unknown key {}. Supported keys: delay, concurrency, rate", key))?,
+            }
+        }
+
+        if rate > MAX_RATE {
+            Err(format!("The rate can't be bigger than {} requests per second", MAX_RATE))?
+        }
+
+        self.delay.store(delay, Ordering::SeqCst);
+        self.concurrency.store(concurrency, Ordering::SeqCst);
+        self.rate.store(rate, Ordering::SeqCst);
+
+        // the waiting requests may fit into the new limit
+        self.released.notify_waiters();
+
+        Ok(self.state())
+    }
+
+    /// e.g. "delay 200ms, concurrency 4, rate unlimited"
+    pub fn state(&self) -> String {
+        let limit = |x: u64| if x == 0 { "unlimited".to_string() } else { x.to_string() };
+
+        format!(
+            "delay {}ms, concurrency {}, rate {}",
+            self.delay.load(Ordering::SeqCst),
+            limit(self.concurrency.load(Ordering::SeqCst) as u64),
+            limit(self.rate.load(Ordering::SeqCst)),
+        )
+    }
+
+    /// sleeps for the delay, waits for the rate limit and for a free slot.
+    /// The slot is released when the permit is dropped
+    pub async fn acquire(&self) -> ThrottlePermit<'_> {
+        tokio::time::sleep(Duration::from_millis(self.delay.load(Ordering::SeqCst))).await;
+
+        let rate = self.rate.load(Ordering::SeqCst);
+        if rate > 0 {
+            let start = {
+                let mut next_start = self.next_start.lock();
+                let start = (*next_start).max(Instant::now());
+                *next_start = start + Duration::from_nanos(1_000_000_000 / rate);
+                start
+            };
+
+            tokio::time::sleep_until(start.into()).await;
+        }
+
+        loop {
+            // the future should be created before the check to not miss notifications
+            let released = self.released.notified();
+
+            {
+                let mut active = self.active.lock();
+                let concurrency = self.concurrency.load(Ordering::SeqCst);
+
+                if concurrency == 0 || *active < concurrency {
+                    *active += 1;
+                    return ThrottlePermit(self);
+                }
+            }
+
+            released.await;
+        }
+    }
+}
+
+#[cfg(unix)]
+type Hangup = Option<tokio::signal::unix::Signal>;
+
+#[cfg(not(unix))]
+type Hangup = ();
+
+#[cfg(unix)]
+fn hangup() -> Hangup {
+    tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()).ok()
+}
+
+#[cfg(not(unix))]
+fn hangup() -> Hangup {}
+
+/// waits for the next check of the control file. Returns true in case it was triggered by SIGHUP
+#[cfg(unix)]
+async fn next_check(hangup: &mut Hangup) -> bool {
+    match hangup.as_mut() {
+        Some(hangup) => tokio::select! {
+            _ = tokio::time::sleep(WATCH_INTERVAL) => false,
+            _ = hangup.recv() => true,
+        },
+        None => {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+            false
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn next_check(_: &mut Hangup) -> bool {
+    tokio::time::sleep(WATCH_INTERVAL).await;
+    false
+}
@@ -1,35 +1,333 @@
 use std::{
-    error::Error,
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use lazy_static::lazy_static;
+use parking_lot::Mutex;
 use regex::Regex;
 use reqwest::Client;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use colored::Colorize;
 
 use crate::{
     config::structs::Config,
+    error::X8Error,
     network::{
         request::{Request, RequestDefaults},
         response::Response,
-        utils::InjectionPlace,
+        utils::{Headers, InjectionPlace},
     },
     utils::random_line, VALUE_LENGTH,
 };
 
+use super::error_budget::{ErrorBudget, Phase};
+
+/// parts of parameter names that usually lead to interesting functionality
+const INTERESTING_NAMES: &[&str] = &[
+    "admin", "callback", "cmd", "config", "debug", "dest", "dev", "exec", "file", "internal",
+    "next", "path", "proxy", "query", "redirect", "return", "sql", "template", "test", "token",
+    "url",
+];
+
 #[derive(Debug, Default)]
 pub struct Stable {
     pub body: bool,
     pub reflections: bool,
 }
 
+/// percentiles over fewer response times are just the slowest or the fastest ones
+pub const MIN_LATENCY_SAMPLES: usize = 5;
+
+/// response times of requests with random parameters
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TimeBaseline {
+    pub mean: u128,
+    pub deviation: u128,
+    pub p50: u128,
+    pub p95: u128,
+    pub samples: usize,
+}
+
+impl TimeBaseline {
+    pub fn new(times: &[u128]) -> Self {
+        if times.is_empty() {
+            return Self::default();
+        }
+
+        let mean = times.iter().sum::<u128>() / times.len() as u128;
+        let variance = times.iter().map(|x| x.abs_diff(mean).pow(2)).sum::<u128>() / times.len() as u128;
+
+        Self {
+            mean,
+            deviation: (variance as f64).sqrt() as u128,
+            p50: percentile(times, 50),
+            p95: percentile(times, 95),
+            samples: times.len(),
+        }
+    }
+
+    /// whether the response took much longer than usual, so the page may contain slow-path parts
+    pub fn is_slow(&self, time: u128) -> bool {
+        self.mean > 0 && time > self.mean * 2 && time > self.mean + self.deviation * 3
+    }
+}
+
+/// nearest-rank percentile of the times
+fn percentile(times: &[u128], p: usize) -> u128 {
+    let mut sorted = times.to_vec();
+    sorted.sort_unstable();
+
+    sorted[((sorted.len() * p + 99) / 100).max(1) - 1]
+}
+
+/// response times of the requests that confirmed the parameter compared to the ones with random parameters.
+/// A much higher latency may mean that the parameter triggers an expensive code path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Latency {
+    pub p50: u128,
+    pub p95: u128,
+    pub baseline_p50: u128,
+    pub baseline_p95: u128,
+
+    /// the amount of requests with the parameter
+    pub samples: usize,
+}
+
+impl Latency {
+    /// None in case there are less than MIN_LATENCY_SAMPLES times on either side
+    pub fn new(times: &[u128], baseline: &TimeBaseline) -> Option<Self> {
+        if times.len() < MIN_LATENCY_SAMPLES || baseline.samples < MIN_LATENCY_SAMPLES || baseline.mean == 0 {
+            return None;
+        }
+
+        Some(Self {
+            p50: percentile(times, 50),
+            p95: percentile(times, 95),
+            baseline_p50: baseline.p50,
+            baseline_p95: baseline.p95,
+            samples: times.len(),
+        })
+    }
+}
+
+/// a checked chunk of parameters
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Step {
+    /// the number of the request among the ones that checked parameters during the run
+    pub request: usize,
+
+    /// the amount of parameters within the chunk
+    pub params: usize,
+    pub status: u16,
+    pub size: usize,
+
+    /// parameters reflected a different amount of times than a non existing one
+    pub reflected: Vec<(String, usize)>,
+}
+
+/// the request that confirmed the parameter, so verify-report can resend it.
+/// Headers from -H aren't kept, they're taken from the config of the report instead
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Confirmation {
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub trailers: Vec<(String, String)>,
+    pub chunk_extensions: String,
+
+    /// the status of the response to the request
+    pub status: u16,
+}
+
+impl Confirmation {
+    /// None in case the response has no request, e.g. the initial one
+    pub fn new(response: &Response) -> Option<Self> {
+        let request = response.request.as_ref()?;
+
+        Some(Self {
+            path: request.path.clone(),
+            headers: request
+                .headers
+                .iter()
+                .filter(|(k, v)| request.defaults.custom_headers.get_value(k).as_ref() != Some(v))
+                .cloned()
+                .collect(),
+            body: request.body.clone(),
+            trailers: request.trailers.clone(),
+            chunk_extensions: request.chunk_extensions.clone(),
+            status: response.code,
+        })
+    }
+
+    /// the same request with the -H headers of the current config
+    pub fn request<'a>(&self, defaults: &'a RequestDefaults) -> Request<'a> {
+        let mut request = Request::new(defaults, Vec::new());
+        request.path = self.path.clone();
+        request.headers = self.headers.clone();
+        request.body = self.body.clone();
+        request.trailers = self.trailers.clone();
+        request.chunk_extensions = self.chunk_extensions.clone();
+
+        for (k, v) in defaults.custom_headers.iter() {
+            if !request.headers.contains_key(k) {
+                request.set_header(k.to_owned(), v.replace("{{random}}", &random_line(VALUE_LENGTH)));
+            }
+        }
+
+        // parameters are already within the path, the headers or the body
+        request.prepared = true;
+        request
+    }
+}
+
+/// the chunks that contained the parameter in the order they were sent,
+/// along with the values of the initial response they were compared with
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Provenance {
+    pub steps: Vec<Step>,
+    pub initial_status: u16,
+    pub initial_size: usize,
+
+    /// the amount of reflections of a non existing parameter
+    pub initial_reflections: usize,
+}
+
+/// the chunks checked during discovery and narrowing.
+/// Only the chunks that changed the page are kept, the found parameters come from them
+#[derive(Debug, Default)]
+pub struct StepLog {
+    last_request: AtomicUsize,
+    steps: Mutex<Vec<Step>>,
+
+    /// lowercased parameter names -> positions of their chunks within steps
+    by_name: Mutex<HashMap<String, Vec<usize>>>,
+}
+
+impl StepLog {
+    /// the number of the next checked chunk
+    pub fn next_request(&self) -> usize {
+        self.last_request.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// keeps the step for the parameters of the chunk. Entries can be name=value
+    pub fn record(&self, step: Step, params: &[String]) {
+        let position = {
+            let mut steps = self.steps.lock();
+            steps.push(step);
+            steps.len() - 1
+        };
+
+        let mut by_name = self.by_name.lock();
+        for param in params {
+            let name = param.split('=').next().unwrap_or_default().to_lowercase();
+            let positions = by_name.entry(name).or_default();
+
+            if positions.last() != Some(&position) {
+                positions.push(position);
+            }
+        }
+    }
+
+    /// only the reflections of the parameter itself are kept within the steps
+    pub fn provenance(&self, name: &str, initial_response: &Response, initial_reflections: usize) -> Provenance {
+        let steps = self.steps.lock();
+        let positions = self.by_name.lock().get(&name.to_lowercase()).cloned().unwrap_or_default();
+
+        Provenance {
+            steps: positions
+                .iter()
+                .map(|x| {
+                    let mut step = steps[*x].clone();
+                    step.reflected.retain(|(k, _)| k.eq_ignore_ascii_case(name));
+                    step
+                })
+                .collect(),
+            initial_status: initial_response.code,
+            initial_size: initial_response.text.len(),
+            initial_reflections,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 pub enum ReasonKind {
     Code,
     Text,
     Reflected,
     NotReflected,
+
+    /// found by a registered detector. Contains its name
+    Custom(String),
+}
+
+/// additional information about the parameter from follow-up probes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Enrichment {
+    /// the parameter redirects to an arbitrary url. Contains the redirect location
+    OpenRedirect(String),
+
+    /// the parameter accepts urls. Contains the sent canary url
+    SsrfCandidate(String),
+
+    /// the callback server received a request to the canary url
+    Ssrf(String),
+
+    /// the page with the parameter contains an error message
+    /// kind is either sql, template, stack-trace or custom
+    ErrorSignature { kind: String, matched: String },
+
+    /// the url-decoded value is reflected, so the server processes the parameter
+    DecodedReflection,
+
+    /// the value is reflected exactly as sent, e.g. within the echoed query string
+    VerbatimReflection,
+
+    /// how the backend parses edge-case encodings of the parameter
+    ParserFingerprint(ParserFingerprint),
+}
+
+/// reactions of the backend's parser to edge-case encodings of a reflected parameter
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParserFingerprint {
+    /// which value is reflected when the parameter is sent twice: first, last, both, joined or none
+    pub duplicates: String,
+
+    /// whether percent-encoded names are decoded
+    pub encoded_names: bool,
+
+    /// whether values are decoded twice
+    pub double_decoding: bool,
+
+    /// whether IIS-style %uXXXX escapes are decoded
+    pub unicode_escapes: bool,
+
+    /// what happens with the overlong encoding of a dot: decoded, replaced, stripped, verbatim, rejected or none
+    pub overlong_utf8: String,
+
+    /// the parsers that behave this way
+    pub guess: Option<String>,
+}
+
+impl ParserFingerprint {
+    /// fills the guess based on the well-known behaviors of parsers
+    pub fn with_guess(mut self) -> Self {
+        self.guess = if self.unicode_escapes || self.duplicates == "joined" {
+            Some("asp.net")
+        } else if self.duplicates == "last" {
+            Some("php, ruby on rails, node.js querystring")
+        } else if self.duplicates == "first" {
+            Some("java servlets, python, go")
+        } else if self.duplicates == "both" {
+            Some("node.js qs, arrays")
+        } else {
+            None
+        }
+        .map(|x| x.to_string());
+
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -43,6 +341,37 @@ pub struct FoundParameter {
     pub status: u16,
     pub size: usize,
     pub reason_kind: ReasonKind,
+
+    /// how interesting the parameter is. Filled at the end of the run
+    pub score: usize,
+
+    /// categories of the parameter like auth or redirect. Filled at the end of the run
+    pub tags: Vec<String>,
+
+    /// confirmed behavior from follow-up probes
+    pub enrichments: Vec<Enrichment>,
+
+    /// path to the stored request and response that confirmed the parameter
+    pub snapshot: Option<String>,
+
+    /// unix time in milliseconds
+    pub discovered_at: u128,
+
+    /// other urls of the host where the parameter behaves the same.
+    /// Filled in case findings are collapsed
+    pub also_found_at: Vec<String>,
+
+    /// response times of the requests with the parameter in milliseconds
+    #[serde(skip)]
+    pub times: Vec<u128>,
+
+    /// None in case there's no baseline to compare with. Filled at the end of the run
+    pub latency: Option<Latency>,
+
+    /// the requests that led to the parameter. Filled at the end of the run
+    pub provenance: Option<Provenance>,
+
+    pub confirmation: Option<Confirmation>,
 }
 
 impl FoundParameter {
@@ -55,14 +384,9 @@ impl FoundParameter {
     ) -> Self {
         let name = name.into();
 
-        let (name, value) = if name.contains('=') {
-            let mut name = name.split('=');
-            (
-                name.next().unwrap().to_string(),
-                Some(name.next().unwrap().to_string()),
-            )
-        } else {
-            (name, None)
+        let (name, value) = match name.split_once('=') {
+            Some((name, value)) => (name.to_string(), Some(value.to_string())),
+            None => (name, None),
         };
 
         Self {
@@ -72,9 +396,85 @@ impl FoundParameter {
             status,
             size,
             reason_kind,
+            score: 0,
+            tags: Vec::new(),
+            enrichments: Vec::new(),
+            snapshot: None,
+            discovered_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |x| x.as_millis()),
+            also_found_at: Vec::new(),
+            times: Vec::new(),
+            latency: None,
+            provenance: None,
+            confirmation: None,
+        }
+    }
+
+    /// tags the parameter with categories based on its name and value
+    pub fn fill_tags(&mut self) {
+        lazy_static! {
+            static ref RE_TAGS: Vec<(&'static str, Regex)> = vec![
+                ("auth", Regex::new(r"(?i)(auth|token|session|passw|secret|jwt|sso|login|apikey|api_key)").unwrap()),
+                ("debug", Regex::new(r"(?i)(debug|test|dev|verbose|trace|log)").unwrap()),
+                ("redirect", Regex::new(r"(?i)(redirect|return|next|url|dest|continue|goto|callback)").unwrap()),
+                ("file-path", Regex::new(r"(?i)(file|path|dir|folder|template|include|load)").unwrap()),
+                ("id", Regex::new(r"(?i)(^id$|[_-]id$|uid|guid|^user$|account)").unwrap()),
+                ("pagination", Regex::new(r"(?i)(page|limit|offset|cursor|sort|order|start)").unwrap()),
+                ("feature-flag", Regex::new(r"(?i)(enable|disable|feature|flag|beta|experiment|toggle|show|hide)").unwrap()),
+            ];
+            static ref RE_FLAG_VALUE: Regex = Regex::new(r"(?i)^(true|false|1|0|on|off|yes|no)$").unwrap();
+        }
+
+        self.tags = RE_TAGS
+            .iter()
+            .filter(|(_, re)| re.is_match(&self.name))
+            .map(|(tag, _)| tag.to_string())
+            .collect();
+
+        // parameters that accept only a specific boolean-like value usually switch a feature
+        let tag = "feature-flag".to_string();
+        if !self.tags.contains(&tag) && self.value.as_ref().map_or(false, |x| RE_FLAG_VALUE.is_match(x)) {
+            self.tags.push(tag);
         }
     }
 
+    /// rates the parameter: reflection in html > code change to 5xx > other code changes > body diffs.
+    /// Boosted by interesting names like debug or redirect
+    pub fn calculate_score(&mut self, initial_response: &Response) {
+        let is_html = initial_response
+            .headers
+            .get_value_case_insensitive("content-type")
+            .map_or(false, |x| x.contains("html"));
+
+        let mut score = match self.reason_kind {
+            ReasonKind::Reflected if is_html => 50,
+            ReasonKind::Reflected => 30,
+            ReasonKind::Code if self.status >= 500 => 40,
+            ReasonKind::Code => 25,
+            ReasonKind::NotReflected => 20,
+            ReasonKind::Custom(_) => 15,
+            ReasonKind::Text => 10,
+        };
+
+        let name = self.name.to_lowercase();
+        if INTERESTING_NAMES.iter().any(|x| name.contains(x)) {
+            score += 20;
+        }
+
+        // the parameter requires a specific value like debug=1
+        if self.value.is_some() {
+            score += 10;
+        }
+
+        // the page just echoes the raw query string
+        if self.enrichments.contains(&Enrichment::VerbatimReflection) {
+            score = score.saturating_sub(20);
+        }
+
+        self.score = score;
+    }
+
     /// just returns (Key, Value) pair
     pub fn get(&self) -> (String, String) {
         (
@@ -90,6 +490,7 @@ impl FoundParameter {
             ReasonKind::Text => self.name.bright_yellow(),
             ReasonKind::Reflected => self.name.bright_blue(),
             ReasonKind::NotReflected => self.name.bright_cyan(),
+            ReasonKind::Custom(_) => self.name.bright_magenta(),
         };
 
         if self.value.is_some() {
@@ -192,7 +593,7 @@ pub(super) async fn replay<'a>(
     request_defaults: &RequestDefaults,
     replay_client: &Client,
     found_params: &Vec<FoundParameter>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), X8Error> {
 
     // get cookies
     Request::new(request_defaults, vec![])
@@ -229,14 +630,24 @@ pub(super) async fn verify<'a>(
     found_params: &Vec<FoundParameter>,
     diffs: &Vec<String>,
     stable: &Stable,
-) -> Result<Vec<FoundParameter>, Box<dyn Error>> {
+    error_budget: &ErrorBudget,
+) -> Result<Vec<FoundParameter>, X8Error> {
     let mut filtered_params = Vec::with_capacity(found_params.len());
 
     for param in found_params {
         let param_value = param.get();
-        let mut response = Request::new(request_defaults, vec![format!("{}={}", param_value.0, param_value.1)])
+        let mut response = match Request::new(request_defaults, vec![format!("{}={}", param_value.0, param_value.1)])
             .send()
-            .await?;
+            .await
+        {
+            Ok(val) => val,
+            Err(err) => {
+                error_budget.record(Phase::Verification, &err)?;
+                // keep the parameter because it wasn't possible to disprove it
+                filtered_params.push(param.clone());
+                continue;
+            }
+        };
 
         let (is_code_diff, new_diffs) = response.compare(initial_response, diffs)?;
         let mut is_the_body_the_same = true;
@@ -248,7 +659,10 @@ pub(super) async fn verify<'a>(
         response.fill_reflected_parameters(initial_response);
 
         if is_code_diff || !response.reflected_parameters.is_empty() || stable.body && !is_the_body_the_same {
-            filtered_params.push(param.clone());
+            let mut param = param.clone();
+            param.times.push(response.time);
+            param.confirmation = Confirmation::new(&response).or(param.confirmation);
+            filtered_params.push(param);
         }
     }
 
@@ -361,7 +775,7 @@ pub(super) async fn _smart_verify(
     found_params: &Vec<FoundParameter>,
     diffs: &Vec<String>,
     stable: &Stable,
-) -> Result<Vec<FoundParameter>, Box<dyn Error>> {
+) -> Result<Vec<FoundParameter>, X8Error> {
     let mut filtered_params = Vec::with_capacity(found_params.len());
 
     for param in found_params {
@@ -0,0 +1,491 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+    iter::FromIterator,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+    },
+};
+
+use colored::Colorize;
+use futures::StreamExt;
+use indicatif::ProgressBar;
+use parking_lot::Mutex;
+use tokio::{
+    fs::{self, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+use crate::{
+    config::structs::Config,
+    error::{exit_code_of, EXIT_CONFIG, EXIT_FOUND, EXIT_OK, EXIT_OTHER},
+    job::{JobError, JobResult},
+    network::{
+        request::{Request, RequestDefaults},
+        utils::Headers,
+        watchdog::Watchdog,
+    },
+    runner::{
+        live::ExtraParams,
+        output::{target_dir, target_url, write_index, Baseline, Dedup, ParseOutputs, RunnerOutput},
+        runner::Runner,
+        shared::{HostLimiter, SharedInfo},
+        throttle::Throttle,
+        utils::{Parameters, ReasonKind},
+    },
+    utils::{self, init_progress, ScanState},
+    watch::{Failed, Findings},
+};
+
+/// checks all the urls with the given parameters
+/// fills findings with the found parameters of every checked url in case they're needed
+/// and failed with the urls that couldn't be checked.
+/// The outputs and the errors are collected instead of being printed in case collected is given, e.g. for jobs
+/// returns the exit code
+pub async fn scan(
+    config: &Config,
+    params: &Vec<String>,
+    baseline: Option<&Baseline>,
+    findings: Option<&Mutex<Findings>>,
+    failed: Option<&Mutex<Failed>>,
+    collected: Option<&Mutex<JobResult>>,
+) -> Result<i32, Box<dyn Error>> {
+    // -W 0 is a special option to run everything in parallel
+    let workers = if config.workers == 0 {
+        config.urls.len()*config.methods.len()
+    } else {
+        config.workers
+    };
+
+    // open output file. Jobs return the outputs within their result instead
+    let mut output_file = if !config.output_file.is_empty() && collected.is_none() {
+        let mut file = OpenOptions::new();
+
+        let file = if config.append {
+            file.write(true).append(true)
+        } else {
+            file.write(true).truncate(true)
+        };
+
+        let file = match file.open(&config.output_file).await {
+            Ok(file) => file,
+            Err(_) => fs::File::create(&config.output_file).await?,
+        };
+
+        Some(file)
+    } else {
+        None
+    };
+
+    let shared_output_file = Arc::new(Mutex::new(&mut output_file));
+
+    // the highest exit code among all the urls
+    let exit_code = AtomicI32::new(EXIT_OK);
+
+    // learned diffs, found parameters and rate limits per host
+    let shared_info = SharedInfo::default();
+    let host_limiter = config.max_host_concurrency.map(HostLimiter::new);
+
+    // one watchdog for all the urls, so a single slow target doesn't look like a stall
+    let watchdog = config.stall_timeout.map(|x| Arc::new(Watchdog::new(x, config.stall_action)));
+    if let Some(watchdog) = watchdog.as_ref() {
+        tokio::spawn(Arc::clone(watchdog).watch());
+    }
+
+    // the limits from the control file are applied to all the urls at once
+    let throttle = config.control_file.clone().map(|x| Throttle::watch(x, config.delay));
+
+    // stops reading the file once the scan is finished
+    let extra_params = config.extra_params_watch.clone().map(ExtraParams::watch);
+
+    // jsonl lines are independent, so they're never collapsed
+    let dedup = if config.urls.len() > 1 && !config.no_dedup && config.output_format != "jsonl" {
+        Some(Mutex::new(Dedup::default()))
+    } else {
+        None
+    };
+
+    // paused scans are continued from the first unfinished pair
+    let scan_state = match config.state_file.as_ref() {
+        Some(path) => Some(ScanState::open(path).map_err(|x| format!("Unable to open the state file: {}", x))?),
+        None => None,
+    };
+
+    let runner_outputs =
+        futures::stream::iter(init_progress(config).iter().enumerate().skip(1).map(
+            |(id, (progress_bar, url_set))| {
+
+                let shared_output_file = Arc::clone(&shared_output_file);
+
+                // each url set should have each own list of parameters
+                let params = params.clone();
+
+                let exit_code = &exit_code;
+                let shared_info = &shared_info;
+                let host_limiter = &host_limiter;
+                let watchdog = &watchdog;
+                let throttle = &throttle;
+                let extra_params = &extra_params;
+                let dedup = &dedup;
+                let scan_state = &scan_state;
+
+                //let output_file = output_file.as_ref().unwrap().try_clone();
+
+                async move {
+                    let mut runner_outputs = Vec::new();
+
+                    // for now url set are used only in case --one-worker-per-host option is provided
+                    // otherwise it's just url sets of 1 url
+                    for url in url_set {
+                        for method in &config.methods.clone() {
+                            // each method should have each own list of parameters (we're changing this list through the run)
+                            let mut params = params.clone();
+
+                            let pair_url = target_url(url, config.soap_operation.as_deref());
+
+                            let target_dir = config
+                                .output_dir
+                                .as_ref()
+                                .map(|dir| target_dir(dir, method, &pair_url));
+
+                            let state_key = format!("{} {}", method, pair_url);
+
+                            if scan_state.as_ref().map_or(false, |x| x.is_finished(&state_key)) {
+                                utils::info(
+                                    config,
+                                    id,
+                                    progress_bar,
+                                    "resume",
+                                    format!("{} {} is already finished within the state file. Skipping", method, url),
+                                );
+                                continue;
+                            }
+
+                            // the url|method pair was already checked during one of the previous runs
+                            if let Some(dir) = target_dir.as_ref() {
+                                if dir.join("result.json").exists() {
+                                    utils::info(
+                                        config,
+                                        id,
+                                        progress_bar,
+                                        "resume",
+                                        format!("{} {} is already checked. Skipping", method, url),
+                                    );
+                                    continue;
+                                }
+                            }
+
+                            let mut request_defaults = match RequestDefaults::from_config(
+                                config,
+                                method.as_str(),
+                                url.as_str(),
+                            ) {
+                                Ok(val) => val,
+                                Err(err) => {
+                                    exit_code.fetch_max(exit_code_of(err.as_ref(), EXIT_CONFIG), Ordering::SeqCst);
+
+                                    if let Some(failed) = failed {
+                                        failed.lock().insert((method.clone(), pair_url.clone()));
+                                    }
+
+                                    if let Some(collected) = collected {
+                                        collected.lock().errors.push(JobError::new(method, url, err.as_ref()));
+                                    }
+
+                                    utils::error(err, Some(url), Some(progress_bar), Some(config));
+                                    continue;
+                                }
+                            };
+
+                            if config.shared_state {
+                                request_defaults.host_state = Some(shared_info.host(&request_defaults.host));
+                            }
+
+                            if let Some(host_limiter) = host_limiter {
+                                request_defaults.host_slots = Some(host_limiter.target(&request_defaults.host));
+                            }
+
+                            request_defaults.watchdog = watchdog.clone();
+                            request_defaults.throttle = throttle.clone();
+                            request_defaults.extra_params = extra_params.clone();
+
+                            let log = match target_dir.as_ref().map(|dir| utils::open_target_log(dir)) {
+                                Some(Ok(log)) => Some(log),
+                                Some(Err(err)) => {
+                                    utils::error(
+                                        format!("Unable to create the log file: {}", err),
+                                        Some(url),
+                                        Some(progress_bar),
+                                        Some(config),
+                                    );
+                                    None
+                                }
+                                None => None,
+                            };
+
+                            let result = utils::with_target_log(log.clone(), async {
+                                // get cookies
+                                if let Err(err) = Request::new(&request_defaults, Vec::new()).send().await {
+                                    return Err(Box::<dyn Error>::from(err));
+                                }
+
+                                run(
+                                    config,
+                                    &mut request_defaults,
+                                    &mut params,
+                                    &progress_bar,
+                                    id,
+                                )
+                                .await
+                            })
+                            .await;
+
+                            match result
+                            {
+                                Ok(mut val) => {
+                                    if let Some(scan_state) = scan_state {
+                                        scan_state.finish(&state_key);
+                                    }
+
+                                    if let Some(findings) = findings {
+                                        findings.lock().insert(
+                                            (val.method.clone(), target_url(&val.url, val.operation.as_deref())),
+                                            val.found_params.iter().map(|x| x.name.clone()).collect(),
+                                        );
+                                    }
+
+                                    // the full results are saved so they can be used as the next baseline
+                                    if let Some(dir) = target_dir.as_ref() {
+                                        if let Err(err) = val.save_to_dir(config, dir) {
+                                            utils::error(err, Some(url), Some(progress_bar), Some(config));
+                                        }
+                                    }
+
+                                    // report only the delta
+                                    if let Some(baseline) = baseline {
+                                        val.remove_known(baseline);
+
+                                        if val.found_params.is_empty() {
+                                            continue;
+                                        }
+
+                                        exit_code.fetch_max(EXIT_FOUND, Ordering::SeqCst);
+                                    }
+
+                                    if config.fail_on_found && !val.found_params.is_empty() {
+                                        exit_code.fetch_max(EXIT_FOUND, Ordering::SeqCst);
+                                    }
+
+                                    // if output format is not json we can print output and write to file in real time
+                                    if config.output_format != "json" && collected.is_none() {
+                                        // urls without findings are still printed, only the collapsed ones are skipped
+                                        if let Some(dedup) = dedup {
+                                            if dedup.lock().remove_repeated(&mut val) > 0 && val.found_params.is_empty() {
+                                                continue;
+                                            }
+                                        }
+
+                                        let mut output_file = shared_output_file.lock();
+                                        let output = val.parse(config);
+
+                                        if output_file.is_some() && !(config.remove_empty && val.found_params.is_empty()) {
+
+                                            match output_file.as_mut().unwrap().write_all(
+                                                &strip_ansi_escapes::strip(&(output.normal().clear().to_string()+"\n").as_bytes()).unwrap()
+                                            ).await {
+                                                Ok(()) => output_file.as_mut().unwrap().flush().await.unwrap(),
+                                                Err(err) => utils::error(err, Some(url), Some(progress_bar), Some(config)),
+                                            };
+                                        }
+
+                                        let msg = if config.verbose > 0 && config.output_format != "jsonl" {
+                                            format!("\n{}\n\n", output)
+                                        } else {
+                                            format!("{}", output)
+                                        };
+
+                                        // findings go to stdout even when the progress bars are drawn on stderr
+                                        if !(config.quiet && val.found_params.is_empty()) {
+                                            if config.disable_progress_bar {
+                                                writeln!(io::stdout(), "{}", msg).ok();
+                                            } else {
+                                                progress_bar.suspend(|| writeln!(io::stdout(), "{}", msg).ok());
+                                            }
+                                        }
+
+                                    } else {
+                                        runner_outputs.push(val)
+                                    }
+                                },
+                                Err(err) => {
+                                    // the errors about the target are X8Error, the rest are unexpected ones
+                                    exit_code.fetch_max(exit_code_of(err.as_ref(), EXIT_OTHER), Ordering::SeqCst);
+
+                                    if let Some(failed) = failed {
+                                        failed.lock().insert((method.clone(), pair_url.clone()));
+                                    }
+
+                                    if let Some(log) = log.as_ref() {
+                                        utils::write_target_log(log, &err.to_string());
+                                    }
+
+                                    if let Some(collected) = collected {
+                                        collected.lock().errors.push(JobError::new(method, url, err.as_ref()));
+                                    }
+
+                                    utils::error(err, Some(url), Some(progress_bar), Some(config))
+                                }
+                            }
+                        }
+                    }
+                    runner_outputs
+                }
+            },
+        ))
+        .buffer_unordered(workers)
+        .collect::<Vec<Vec<RunnerOutput>>>()
+        .await;
+
+    // works only in case json output is used or the outputs are collected.
+    // otherwise runner_outputs is an empty vector
+    // and all the printing work is done within the futures above
+    let mut runner_outputs = runner_outputs.into_iter().flatten().collect::<Vec<RunnerOutput>>();
+
+    if let Some(dedup) = dedup.as_ref() {
+        dedup.lock().collapse(&mut runner_outputs);
+    }
+
+    if let Some(collected) = collected {
+        collected.lock().outputs.extend(runner_outputs);
+    } else if !runner_outputs.is_empty() {
+        let output = runner_outputs
+            .into_iter()
+            .filter(|x| !(config.remove_empty && x.found_params.is_empty()))
+            .collect::<Vec<RunnerOutput>>()
+            .parse_output(config);
+
+        if output_file.is_some() {
+            output_file.as_mut().unwrap().write_all(output.as_bytes()).await?;
+            output_file.as_mut().unwrap().flush().await?;
+        }
+
+        if config.quiet {
+            write!(io::stdout(), "{}", output).ok();
+        } else {
+            write!(io::stdout(), "\n{}", output).ok();
+        }
+    }
+
+    // the findings were printed in real time, so the collapsed ones are listed at the end
+    if let Some(dedup) = dedup.filter(|_| config.output_format != "json" && collected.is_none()) {
+        let summary = dedup.into_inner().summary();
+
+        if !summary.is_empty() {
+            let summary = summary.join("\n") + "\n";
+
+            if output_file.is_some() {
+                output_file.as_mut().unwrap().write_all(&strip_ansi_escapes::strip(summary.as_bytes())?).await?;
+                output_file.as_mut().unwrap().flush().await?;
+            }
+
+            // stdout is kept for the findings
+            if !config.quiet {
+                write!(io::stderr(), "\n{}", summary).ok();
+            }
+        }
+    }
+
+    if let Some(dir) = config.output_dir.as_ref() {
+        write_index(dir)?;
+    }
+
+    Ok(exit_code.into_inner())
+}
+
+/// checks the url|method pair and repeats the check with the found parameters up to --recursion-depth times
+async fn run(
+    config: &Config,
+    request_defaults: &mut RequestDefaults,
+    params: &mut Vec<String>,
+    progress_bar: &ProgressBar,
+    id: usize,
+) -> Result<RunnerOutput, Box<dyn Error>> {
+    let mut runner_output = Runner::new(config, request_defaults, progress_bar, id)
+        .await?
+        .run(params)
+        .await?;
+
+    // the whole block related to the recursive searching
+    if !runner_output.found_params.is_empty() {
+        for depth in 1..config.recursion_depth + 1 {
+            // remove already found parameters from the list to prevent duplicates
+            params.retain(|x| !runner_output.found_params.contains_name(x));
+
+            // custom parameters work badly with recursion enabled
+            request_defaults.disable_custom_parameters = true;
+
+            // so we are keeping parameters that don't change pages' code
+            // or change it to 200
+            // we cant simply overwrite request_defaults.parameters because there's user-supplied parameters as well.
+            request_defaults.parameters.append(&mut Vec::from_iter(
+                runner_output
+                    .found_params
+                    .iter()
+                    .filter(|x| {
+                        !request_defaults.parameters.contains_key(&x.name)
+                            && (x.reason_kind != ReasonKind::Code || x.status == 200)
+                    })
+                    .map(|x| (x.get())),
+            ));
+
+            utils::info(
+                config,
+                id,
+                progress_bar,
+                "recursion",
+                format!(
+                    "({}) repeating with {}",
+                    depth,
+                    request_defaults
+                        .parameters
+                        .iter()
+                        .map(|x| x.0.as_str())
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                ),
+            );
+
+            let mut new_found_params = Runner::new(config, request_defaults, progress_bar, id)
+                .await?
+                .run(params)
+                .await?
+                .found_params;
+
+            // no new params where found - just quit the loop
+            if !new_found_params
+                .iter()
+                .any(|x| !runner_output.found_params.contains_name(&x.name))
+            {
+                break;
+            }
+
+            runner_output.found_params.append(&mut new_found_params);
+        }
+    }
+
+    // we probably changed request_defaults.parameters within the loop above
+    // so we are removing all of the added parameters in there
+    // leaving only user-supplied ones
+    // (to not cause double parameters in some output types)
+    request_defaults.parameters = request_defaults
+        .parameters
+        .iter()
+        .filter(|x| !runner_output.found_params.contains_name(&x.0))
+        .map(|x| x.to_owned())
+        .collect();
+
+    runner_output.prepare(config, request_defaults);
+
+    Ok(runner_output)
+}
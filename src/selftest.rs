@@ -0,0 +1,182 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use indicatif::ProgressBar;
+use percent_encoding::percent_decode_str;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{
+    config::{
+        args::get_config_from,
+        utils::{strip_multi_args, TARGET_ARGS},
+    },
+    error::{EXIT_FOUND, EXIT_OK},
+    network::request::RequestDefaults,
+    runner::runner::Runner,
+    utils::random_line,
+};
+
+/// parameters the test server reacts to. Their behavior is within page()
+const PLANTED: &[&str] = &["admin", "callback", "debug", "verbose"];
+
+/// the amount of non existing parameters to check along with the planted ones
+const FILLER: usize = 2000;
+
+/// requests with larger headers are dropped
+const MAX_REQUEST_SIZE: usize = 256 * 1024;
+
+/// returns the code and body of the test page
+fn page(query: &str) -> (u16, String) {
+    let mut code = 200;
+    let mut body = String::from("<html><head><title>x8 self-test</title></head><body><h1>Welcome</h1>");
+
+    for pair in query.split('&') {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode_str(value).decode_utf8_lossy();
+
+        match name {
+            // reflected
+            "debug" => body.push_str(&format!("<p>debug: {}</p>", value)),
+            "callback" => body.push_str(&format!("<script>{}({{}})</script>", value)),
+            // changes the code
+            "admin" => code = 403,
+            // changes the body
+            "verbose" => body.push_str("<p>Verbose output is enabled</p>"),
+            _ => (),
+        }
+    }
+
+    body.push_str("</body></html>");
+
+    (code, body)
+}
+
+/// answers a single request and closes the connection
+async fn handle(mut stream: TcpStream, requests: Arc<AtomicUsize>) -> io::Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0; 4096];
+
+    while !buf.windows(4).any(|x| x == b"\r\n\r\n") {
+        let len = stream.read(&mut chunk).await?;
+
+        if len == 0 || buf.len() > MAX_REQUEST_SIZE {
+            return Ok(());
+        }
+
+        buf.extend_from_slice(&chunk[..len]);
+    }
+
+    requests.fetch_add(1, Ordering::SeqCst);
+
+    let head = String::from_utf8_lossy(&buf);
+    let target = head.split(' ').nth(1).unwrap_or("/");
+    let (code, body) = page(target.split_once('?').map_or("", |x| x.1));
+
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        if code == 200 { "OK" } else { "Forbidden" },
+        body.len(),
+        body
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// runs the discovery against an embedded server with planted parameters and prints how many of them were found.
+/// args are the command line starting with the program name and "self-test", the rest of them are used as options.
+/// Returns the exit code
+pub async fn self_test(args: Vec<String>) -> Result<i32, Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/", listener.local_addr()?);
+    let requests = Arc::new(AtomicUsize::new(0));
+
+    {
+        let requests = Arc::clone(&requests);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(handle(stream, Arc::clone(&requests)));
+            }
+        });
+    }
+
+    let options: Vec<String> = args.iter().skip(2).cloned().collect();
+    let stripped = strip_multi_args(options.clone(), TARGET_ARGS);
+
+    // the target is always the embedded server
+    if stripped.len() != options.len() {
+        writeln!(io::stderr(), "The target options are ignored, the embedded server is checked instead").ok();
+    }
+
+    let mut cmd_args = vec![args[0].clone(), "-u".to_string(), url.clone(), "--disable-progress-bar".to_string()];
+    cmd_args.extend(stripped);
+
+    let config = get_config_from(cmd_args)?;
+
+    // the planted parameters are spread across the wordlist
+    let mut params: Vec<String> = (0..FILLER).map(|_| random_line(8)).collect();
+    for (i, name) in PLANTED.iter().enumerate() {
+        params.insert(i * FILLER / PLANTED.len(), name.to_string());
+    }
+    let amount = params.len();
+
+    let mut request_defaults = RequestDefaults::from_config(&config, "GET", &url)?;
+    let progress_bar = ProgressBar::hidden();
+    let started = Instant::now();
+
+    let found_params = Runner::new(&config, &mut request_defaults, &progress_bar, 1)
+        .await?
+        .run(&mut params)
+        .await?
+        .found_params;
+
+    let names: Vec<&str> = found_params.iter().map(|x| x.name.as_str()).collect();
+    let missed: Vec<&str> = PLANTED.iter().filter(|x| !names.contains(*x)).copied().collect();
+    let false_positives: Vec<&str> = names.iter().filter(|x| !PLANTED.contains(*x)).copied().collect();
+    let detected = PLANTED.len() - missed.len();
+
+    writeln!(
+        io::stdout(),
+        "\nDetection rate: {}/{} ({}%)",
+        detected,
+        PLANTED.len(),
+        detected * 100 / PLANTED.len()
+    )
+    .ok();
+
+    if !missed.is_empty() {
+        writeln!(io::stdout(), "Missed: {}", missed.join(", ")).ok();
+    }
+
+    writeln!(io::stdout(), "False positives: {}", false_positives.len()).ok();
+
+    if !false_positives.is_empty() {
+        writeln!(io::stdout(), "Reported: {}", false_positives.join(", ")).ok();
+    }
+
+    writeln!(
+        io::stdout(),
+        "Requests: {} for {} parameters within {:.1}s",
+        requests.load(Ordering::SeqCst),
+        amount,
+        started.elapsed().as_secs_f64()
+    )
+    .ok();
+
+    if missed.is_empty() && false_positives.is_empty() {
+        Ok(EXIT_OK)
+    } else {
+        Ok(EXIT_FOUND)
+    }
+}
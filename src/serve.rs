@@ -0,0 +1,366 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    io::{self, Write},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use clap::{App, Arg};
+use parking_lot::Mutex;
+use ring::digest::{digest, SHA256};
+use serde::Serialize;
+use serde_json::json;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Semaphore,
+};
+
+use crate::job::Job;
+
+/// requests with larger heads or bodies are dropped
+const MAX_REQUEST_SIZE: usize = 1024 * 1024;
+
+/// connections that don't send the whole request within that time are closed
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// finished jobs and their findings are forgotten after that time
+const FINISHED_JOB_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// the max amount of finished jobs kept in memory, the oldest ones are forgotten first
+const MAX_FINISHED_JOBS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+struct JobState {
+    status: JobStatus,
+
+    /// why the job failed
+    error: Option<String>,
+
+    /// serialized JobResult
+    findings: Option<String>,
+
+    finished_at: Option<Instant>,
+}
+
+/// submitted jobs and the limits they run with. Shared between connections
+struct Service {
+    /// jobs by their ids. Finished ones are removed after FINISHED_JOB_TTL
+    jobs: Mutex<BTreeMap<usize, JobState>>,
+
+    next_id: Mutex<usize>,
+
+    /// the amount of jobs that can run at the same time
+    slots: Semaphore,
+
+    /// the max amount of jobs waiting for a slot
+    max_queued: usize,
+
+    /// required within the Authorization: Bearer header in case it's set
+    token: Option<String>,
+}
+
+/// the arguments of x8 serve. Also used to generate shell completions
+pub fn serve_app() -> App<'static, 'static> {
+    App::new("serve")
+        .bin_name("x8 serve")
+        .about("Accept scan jobs over http")
+        .arg(
+            Arg::with_name("listen")
+                .long("listen")
+                .help("The address to listen on. :8089 listens on every interface")
+                .default_value("127.0.0.1:8089")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-jobs")
+                .long("max-jobs")
+                .help("The amount of jobs that run at the same time")
+                .default_value("1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-queued")
+                .long("max-queued")
+                .help("The max amount of jobs waiting for their turn. New jobs are rejected after that")
+                .default_value("100")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("token")
+                .long("token")
+                .help("Require this token within the Authorization: Bearer header.\nRequired unless the service listens on a loopback address")
+                .takes_value(true),
+        )
+}
+
+/// runs x8 as a service that accepts scan jobs over http.
+/// args are the command line starting with the program name and "serve"
+pub async fn serve(args: Vec<String>) -> Result<i32, Box<dyn Error>> {
+    let args = serve_app().get_matches_from(args.into_iter().skip(1));
+
+    let listen = args.value_of("listen").unwrap();
+    // :8089 -> 0.0.0.0:8089
+    let listen = if listen.starts_with(':') {
+        format!("0.0.0.0{}", listen)
+    } else {
+        listen.to_string()
+    };
+
+    let service = Arc::new(Service {
+        jobs: Mutex::new(BTreeMap::new()),
+        next_id: Mutex::new(0),
+        slots: Semaphore::new(args.value_of("max-jobs").unwrap().parse()?),
+        max_queued: args.value_of("max-queued").unwrap().parse()?,
+        token: args.value_of("token").map(|x| x.to_string()),
+    });
+
+    let listener = TcpListener::bind(&listen).await?;
+
+    // anyone who can reach the service can send requests from its host
+    if service.token.is_none() && !listener.local_addr()?.ip().is_loopback() {
+        Err("--token is required unless the service listens on a loopback address")?
+    }
+
+    writeln!(io::stderr(), "[#] Accepting jobs on http://{}/jobs", listener.local_addr()?).ok();
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(handle(stream, Arc::clone(&service)));
+    }
+}
+
+/// answers a single request and closes the connection
+async fn handle(mut stream: TcpStream, service: Arc<Service>) -> io::Result<()> {
+    let (code, body) = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream, &service)).await {
+        Ok(Ok(Some(val))) => val,
+        Ok(Err(err)) => return Err(err),
+        _ => return Ok(()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        reason_phrase(code),
+        body.len(),
+        body
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// reads the request and returns the code and json body of the response.
+/// None in case the request is too big or the connection was closed
+async fn read_request(stream: &mut TcpStream, service: &Arc<Service>) -> io::Result<Option<(u16, String)>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0; 4096];
+
+    let head_len = loop {
+        if let Some(pos) = buf.windows(4).position(|x| x == b"\r\n\r\n") {
+            break pos + 4;
+        }
+
+        let len = stream.read(&mut chunk).await?;
+
+        if len == 0 || buf.len() > MAX_REQUEST_SIZE {
+            return Ok(None);
+        }
+
+        buf.extend_from_slice(&chunk[..len]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_len]).to_string();
+    let mut request_line = head.split("\r\n").next().unwrap_or_default().split(' ');
+    let (method, path) = (request_line.next().unwrap_or_default(), request_line.next().unwrap_or("/"));
+
+    let header = |name: &str| {
+        head.split("\r\n")
+            .skip(1)
+            .filter_map(|x| x.split_once(':'))
+            .find(|(k, _)| k.trim().eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim().to_string())
+    };
+
+    let content_length: usize = header("content-length").and_then(|x| x.parse().ok()).unwrap_or(0);
+    if content_length > MAX_REQUEST_SIZE {
+        return Ok(None);
+    }
+
+    while buf.len() < head_len + content_length {
+        let len = stream.read(&mut chunk).await?;
+
+        if len == 0 {
+            return Ok(None);
+        }
+
+        buf.extend_from_slice(&chunk[..len]);
+    }
+
+    let body = &buf[head_len..head_len + content_length];
+
+    Ok(Some(match service.token.as_ref() {
+        Some(token) if !token_matches(token, header("authorization").as_deref()) => {
+            (401, json!({"error": "Wrong or missing token"}).to_string())
+        }
+        _ => route(service, method, path, body),
+    }))
+}
+
+/// whether the Authorization header is "Bearer <token>".
+/// Both are hashed to compare the same amount of bytes within the same time, whatever the header is
+pub fn token_matches(token: &str, authorization: Option<&str>) -> bool {
+    let expected = digest(&SHA256, format!("Bearer {}", token).as_bytes());
+    let received = digest(&SHA256, authorization.unwrap_or_default().as_bytes());
+
+    expected
+        .as_ref()
+        .iter()
+        .zip(received.as_ref())
+        .fold(0, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+/// returns the code and json body of the response
+fn route(service: &Arc<Service>, method: &str, path: &str, body: &[u8]) -> (u16, String) {
+    let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+    forget_finished(service);
+
+    match (method, parts.as_slice()) {
+        ("POST", ["jobs"]) => submit(service, body),
+
+        ("GET", ["jobs"]) => {
+            let jobs = service.jobs.lock();
+            let list: Vec<_> = jobs
+                .iter()
+                .map(|(id, x)| json!({"id": id, "status": x.status}))
+                .collect();
+
+            (200, json!(list).to_string())
+        }
+
+        ("GET", ["jobs", id]) => match job_state(service, id, |id, x| json!({"id": id, "status": x.status, "error": x.error})) {
+            Some(val) => (200, val.to_string()),
+            None => (404, json!({"error": "Unknown job"}).to_string()),
+        },
+
+        ("GET", ["jobs", id, "findings"]) => match job_state(service, id, |_, x| (x.status, x.findings.clone(), x.error.clone())) {
+            Some((JobStatus::Done, Some(findings), _)) => (200, findings),
+            Some((JobStatus::Failed, _, error)) => (409, json!({"error": error}).to_string()),
+            Some((status, _, _)) => (409, json!({"error": "The job isn't finished yet", "status": status}).to_string()),
+            None => (404, json!({"error": "Unknown job"}).to_string()),
+        },
+
+        _ => (404, json!({"error": "Unknown endpoint"}).to_string()),
+    }
+}
+
+/// queues the job from the body
+fn submit(service: &Arc<Service>, body: &[u8]) -> (u16, String) {
+    let job: Job = match serde_json::from_slice(body) {
+        Ok(val) => val,
+        Err(err) => return (400, json!({"error": format!("Unable to parse the job: {}", err)}).to_string()),
+    };
+
+    if let Err(err) = job.check_remote() {
+        return (400, json!({"error": err.to_string()}).to_string());
+    }
+
+    let id = {
+        let mut jobs = service.jobs.lock();
+
+        if jobs.values().filter(|x| x.status == JobStatus::Queued).count() >= service.max_queued {
+            return (503, json!({"error": "Too many queued jobs"}).to_string());
+        }
+
+        let mut next_id = service.next_id.lock();
+        let id = *next_id;
+        *next_id += 1;
+
+        jobs.insert(
+            id,
+            JobState {
+                status: JobStatus::Queued,
+                error: None,
+                findings: None,
+                finished_at: None,
+            },
+        );
+
+        id
+    };
+
+    tokio::spawn(run_job(Arc::clone(service), id, job));
+
+    (202, json!({"id": id, "status": JobStatus::Queued}).to_string())
+}
+
+/// waits for a free slot and runs the job
+async fn run_job(service: Arc<Service>, id: usize, job: Job) {
+    let _slot = service.slots.acquire().await;
+    if let Some(state) = service.jobs.lock().get_mut(&id) {
+        state.status = JobStatus::Running;
+    }
+
+    let result = job.run_on_thread().await;
+
+    let mut jobs = service.jobs.lock();
+    let state = match jobs.get_mut(&id) {
+        Some(val) => val,
+        None => return,
+    };
+    state.finished_at = Some(Instant::now());
+
+    match result {
+        Ok(findings) => {
+            state.status = JobStatus::Done;
+            state.findings = Some(findings.to_string());
+        }
+        Err(err) => {
+            state.status = JobStatus::Failed;
+            state.error = Some(err);
+        }
+    }
+}
+
+fn job_state<T>(service: &Service, id: &str, f: impl Fn(usize, &JobState) -> T) -> Option<T> {
+    let id: usize = id.parse().ok()?;
+    service.jobs.lock().get(&id).map(|x| f(id, x))
+}
+
+/// removes finished jobs older than FINISHED_JOB_TTL and the oldest ones above MAX_FINISHED_JOBS
+fn forget_finished(service: &Service) {
+    let mut jobs = service.jobs.lock();
+    jobs.retain(|_, x| x.finished_at.map_or(true, |finished_at| finished_at.elapsed() < FINISHED_JOB_TTL));
+
+    let mut finished: Vec<(Instant, usize)> =
+        jobs.iter().filter_map(|(id, x)| Some((x.finished_at?, *id))).collect();
+
+    if finished.len() > MAX_FINISHED_JOBS {
+        finished.sort();
+        for (_, id) in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+            jobs.remove(id);
+        }
+    }
+}
+
+fn reason_phrase(code: u16) -> &'static str {
+    match code {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        409 => "Conflict",
+        _ => "Service Unavailable",
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    io::{self, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::{App, Arg};
+use colored::Colorize;
+use serde_json::{json, Value};
+
+use crate::{
+    config::args::report_config,
+    error::EXIT_OK,
+    network::request::{Request, RequestDefaults},
+    runner::output::read_outputs,
+    utils::random_line,
+};
+
+const HELP: &str = "list                           show the findings
+show <n>                       show the details of the finding
+send <n> [value]               resend the finding with the value (random by default) and show the diffs
+mark <n> <confirmed|false-positive|clear> [note]
+                               mark the finding, marks are saved into the report
+quit";
+
+/// the report along with the positions of its findings
+struct Triage {
+    path: String,
+    outputs: Vec<Value>,
+
+    /// the report is saved in the same format it was read in
+    jsonl: bool,
+
+    /// (index of the output, index of the parameter)
+    findings: Vec<(usize, usize)>,
+
+    /// the arguments of the scan in case outputs don't contain them
+    default_args: Option<Vec<Value>>,
+
+    /// request defaults per output
+    defaults: HashMap<usize, RequestDefaults>,
+}
+
+/// the arguments of x8 triage. Also used to generate shell completions
+pub fn triage_app() -> App<'static, 'static> {
+    App::new("triage")
+        .bin_name("x8 triage")
+        .about("Resend, inspect and mark the findings of a json or jsonl output")
+        .arg(
+            Arg::with_name("report")
+                .help("The json or jsonl output")
+                .required(true),
+        )
+}
+
+/// x8 triage report.json.
+/// args are the command line starting with the program name and "triage". Returns the exit code
+pub async fn triage(args: Vec<String>) -> Result<i32, Box<dyn Error>> {
+    let args = triage_app().get_matches_from(args.into_iter().skip(1));
+
+    triage_report(args.value_of("report").unwrap()).await
+}
+
+/// reads commands from stdin until quit or EOF
+pub async fn triage_report(path: &str) -> Result<i32, Box<dyn Error>> {
+    let outputs = read_outputs(path)?;
+
+    let findings: Vec<(usize, usize)> = outputs
+        .iter()
+        .enumerate()
+        .flat_map(|(i, x)| (0..x["found_params"].as_array().map_or(0, |x| x.len())).map(move |j| (i, j)))
+        .collect();
+
+    if findings.is_empty() {
+        writeln!(io::stdout(), "The report doesn't contain findings").ok();
+        return Ok(EXIT_OK);
+    }
+
+    let mut triage = Triage {
+        path: path.to_string(),
+        jsonl: !fs::read_to_string(path)?.trim_start().starts_with('['),
+        default_args: outputs.iter().find_map(|x| x["config"]["args"].as_array().cloned()),
+        outputs,
+        findings,
+        defaults: HashMap::new(),
+    };
+
+    triage.list();
+    writeln!(io::stdout(), "\nType help to see the commands").ok();
+
+    loop {
+        write!(io::stdout(), "x8> ").ok();
+        io::stdout().flush().ok();
+
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            break;
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        let result = match words.first().copied() {
+            None => Ok(()),
+            Some("list") | Some("ls") => {
+                triage.list();
+                Ok(())
+            }
+            Some("show") => triage.show(&words[1..]),
+            Some("send") => triage.send(&words[1..]).await,
+            Some("mark") => triage.mark(&words[1..]),
+            Some("quit") | Some("exit") | Some("q") => break,
+            _ => {
+                writeln!(io::stdout(), "{}", HELP).ok();
+                Ok(())
+            }
+        };
+
+        if let Err(err) = result {
+            writeln!(io::stdout(), "{} {}", "[#]".red(), err).ok();
+        }
+    }
+
+    Ok(EXIT_OK)
+}
+
+impl Triage {
+    fn list(&self) {
+        for (n, (i, j)) in self.findings.iter().enumerate() {
+            let output = &self.outputs[*i];
+            let param = &output["found_params"][*j];
+
+            let mark = match param["triage"]["mark"].as_str() {
+                Some("confirmed") => " [confirmed]".green().to_string(),
+                Some(val) => format!(" [{}]", val).red().to_string(),
+                None => String::new(),
+            };
+
+            writeln!(
+                io::stdout(),
+                "{:>3}. {} {} {} ({}, {}){}",
+                n + 1,
+                output["method"].as_str().unwrap_or_default(),
+                output["url"].as_str().unwrap_or_default(),
+                param["name"].as_str().unwrap_or_default().bold(),
+                param["reason_kind"],
+                param["status"],
+                mark
+            )
+            .ok();
+        }
+    }
+
+    /// the output and the parameter of the 1-based finding number
+    fn finding(&self, n: Option<&&str>) -> Result<(usize, usize), Box<dyn Error>> {
+        let n: usize = n.ok_or("The finding number is missing")?.parse()?;
+
+        match n.checked_sub(1).and_then(|x| self.findings.get(x)) {
+            Some(val) => Ok(*val),
+            None => Err(format!("There are only {} findings", self.findings.len()))?,
+        }
+    }
+
+    fn show(&self, words: &[&str]) -> Result<(), Box<dyn Error>> {
+        let (i, j) = self.finding(words.first())?;
+        writeln!(io::stdout(), "{}", serde_json::to_string_pretty(&self.outputs[i]["found_params"][j])?).ok();
+
+        Ok(())
+    }
+
+    /// resends the finding along with a request without parameters and prints the diffs between them
+    async fn send(&mut self, words: &[&str]) -> Result<(), Box<dyn Error>> {
+        let (i, j) = self.finding(words.first())?;
+        let output = &self.outputs[i];
+
+        let name = output["found_params"][j]["name"].as_str().unwrap_or_default().to_string();
+        let value = if words.len() > 1 {
+            words[1..].join(" ")
+        } else {
+            random_line(8)
+        };
+
+        if !self.defaults.contains_key(&i) {
+            let method = output["method"].as_str().ok_or("The method is missing")?;
+            let url = output["url"].as_str().ok_or("The url is missing")?;
+
+            let args = match output["config"]["args"].as_array().or(self.default_args.as_ref()) {
+                Some(val) => val.iter().filter_map(|x| x.as_str()).map(|x| x.to_string()).collect(),
+                None => Err("The report doesn't contain the scan config")?,
+            };
+
+            let config = report_config(args, method, url)?;
+            self.defaults.insert(i, RequestDefaults::from_config(&config, method, url)?);
+        }
+
+        let defaults = &self.defaults[&i];
+
+        let initial_response = Request::new(defaults, Vec::new()).send().await?;
+        let response = Request::new(defaults, vec![format!("{}={}", name, value)]).send().await?;
+
+        writeln!(io::stdout(), "without {}: {} {} bytes", name, initial_response.code, initial_response.text.len()).ok();
+        writeln!(
+            io::stdout(),
+            "{}={}: {} {} bytes, the value is reflected {} times",
+            name,
+            value,
+            response.code,
+            response.text.len(),
+            response.count(&value, &[])
+        )
+        .ok();
+
+        let (_, diffs) = response.compare(&initial_response, &[])?;
+
+        for diff in diffs {
+            writeln!(io::stdout(), "{}", diff).ok();
+        }
+
+        Ok(())
+    }
+
+    fn mark(&mut self, words: &[&str]) -> Result<(), Box<dyn Error>> {
+        let (i, j) = self.finding(words.first())?;
+        let param = &mut self.outputs[i]["found_params"][j];
+
+        match words.get(1).copied() {
+            Some("clear") => {
+                if let Some(param) = param.as_object_mut() {
+                    param.remove("triage");
+                }
+            }
+            Some(mark) if mark == "confirmed" || mark == "false-positive" => {
+                param["triage"] = json!({
+                    "mark": mark,
+                    "note": words.get(2..).map(|x| x.join(" ")).filter(|x| !x.is_empty()),
+                    "marked_at": SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |x| x.as_millis()) as u64,
+                });
+            }
+            _ => Err("Use: mark <n> <confirmed|false-positive|clear> [note]")?,
+        }
+
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), Box<dyn Error>> {
+        let content = if self.jsonl {
+            self.outputs
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<String>>()
+                .join("\n")
+                + "\n"
+        } else {
+            serde_json::to_string_pretty(&self.outputs)?
+        };
+
+        fs::write(&self.path, content)?;
+
+        Ok(())
+    }
+}
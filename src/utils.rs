@@ -1,12 +1,16 @@
 use std::{
+    collections::HashSet,
     fs::File,
+    future::Future,
     io::{self, BufRead, Write},
     path::Path,
+    sync::Arc,
 };
 
 use colored::*;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle, ProgressDrawTarget};
 use linked_hash_map::LinkedHashMap;
+use parking_lot::Mutex;
 use rand::Rng;
 use url::Url;
 
@@ -37,6 +41,110 @@ pub fn progress_style_check_requests(config: &Config) -> ProgressStyle {
 }
 
 
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+pub const BASE64URL: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// the standard base64 with padding, or base64url without padding as within JWTs
+pub fn base64_encode(data: &[u8], url_safe: bool) -> String {
+    let charset = if url_safe { BASE64URL } else { BASE64 };
+
+    let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+
+    for chunk in data.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+
+        encoded.push(charset[(n >> 18) as usize & 63] as char);
+        encoded.push(charset[(n >> 12) as usize & 63] as char);
+
+        if chunk.len() > 1 {
+            encoded.push(charset[(n >> 6) as usize & 63] as char);
+        } else if !url_safe {
+            encoded.push('=');
+        }
+
+        if chunk.len() > 2 {
+            encoded.push(charset[n as usize & 63] as char);
+        } else if !url_safe {
+            encoded.push('=');
+        }
+    }
+
+    encoded
+}
+
+/// log.txt of the url|method pair within --output-dir
+pub type TargetLog = Arc<Mutex<File>>;
+
+tokio::task_local! {
+    /// the log of the pair that is being checked within the current task
+    static TARGET_LOG: TargetLog;
+}
+
+/// creates <dir>/log.txt, overwriting the one from an unfinished previous run
+pub fn open_target_log(dir: &Path) -> io::Result<TargetLog> {
+    std::fs::create_dir_all(dir)?;
+    Ok(Arc::new(Mutex::new(File::create(dir.join("log.txt"))?)))
+}
+
+/// every info and error message printed while the future runs is also written to the log
+pub async fn with_target_log<F: Future>(log: Option<TargetLog>, future: F) -> F::Output {
+    match log {
+        Some(log) => TARGET_LOG.scope(log, future).await,
+        None => future.await,
+    }
+}
+
+/// writes the message without colors
+pub fn write_target_log(log: &TargetLog, message: &str) {
+    if let Ok(message) = strip_ansi_escapes::strip(message.as_bytes()) {
+        let mut log = log.lock();
+        log.write_all(&message).ok();
+        log.write_all(b"\n").ok();
+    }
+}
+
+/// the finished url|method pairs in case --state-file is used
+pub struct ScanState {
+    finished: Mutex<HashSet<String>>,
+    file: Mutex<File>,
+}
+
+impl ScanState {
+    /// reads the pairs finished by the previous runs and appends the new ones to the same file
+    pub fn open(path: &str) -> io::Result<Self> {
+        let finished = match std::fs::read_to_string(path) {
+            Ok(val) => val.lines().filter(|x| !x.is_empty()).map(|x| x.to_string()).collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self {
+            finished: Mutex::new(finished),
+            file: Mutex::new(std::fs::OpenOptions::new().create(true).append(true).open(path)?),
+        })
+    }
+
+    pub fn is_finished(&self, key: &str) -> bool {
+        self.finished.lock().contains(key)
+    }
+
+    /// the line is flushed at once, so the pair isn't checked again even after the process is killed
+    pub fn finish(&self, key: &str) {
+        if self.finished.lock().insert(key.to_string()) {
+            let mut file = self.file.lock();
+            writeln!(file, "{}", key).ok();
+            file.flush().ok();
+        }
+    }
+}
+
+/// the messages are logged regardless of --verbose
+fn log_message(message: &str) {
+    TARGET_LOG.try_with(|log| write_target_log(log, message)).ok();
+}
+
 /// prints informative messages/non critical errors
 pub fn info<S: Into<String>, T: std::fmt::Display>(
     config: &Config,
@@ -45,6 +153,9 @@ pub fn info<S: Into<String>, T: std::fmt::Display>(
     word: S,
     msg: T,
 ) {
+    let word = word.into();
+    log_message(&format!("[{}] {}", word, msg));
+
     if config.verbose > 0 {
 
         let id = if is_id_important(config) {
@@ -56,13 +167,14 @@ pub fn info<S: Into<String>, T: std::fmt::Display>(
         let message = format!(
             "{}[{}] {}",
             id,
-            word.into().yellow(),
+            word.yellow(),
             msg
         );
 
-        // in case progress bars are hidden -- the messages from progress_bar.println arent' displayed, so we need to use writeln instead
+        // in case progress bars are hidden -- the messages from progress_bar.println arent' displayed, so we need to use writeln instead.
+        // Only findings are printed to stdout
         if config.disable_progress_bar {
-            writeln!(io::stdout(), "{}", message).ok();
+            writeln!(io::stderr(), "{}", message).ok();
         } else {
             progress_bar.println(message);
         }
@@ -77,8 +189,10 @@ pub fn error<T: std::fmt::Display>(msg: T, url: Option<&str>, progress_bar: Opti
         format!("{} [{}] {}", "[#]".red(), url.unwrap(), msg)
     };
 
+    log_message(&message);
+
     if progress_bar.is_none() || (config.is_some() && config.unwrap().disable_progress_bar) {
-        writeln!(io::stdout(), "{}", message).ok();
+        writeln!(io::stderr(), "{}", message).ok();
     } else {
         progress_bar.unwrap().println(message);
     }
@@ -138,6 +252,34 @@ pub fn read_stdin_lines() -> Vec<String> {
     stdin.lock().lines().filter_map(|x| x.ok()).collect()
 }
 
+/// supports weighted wordlists with `name weight` lines
+/// removes the weights and puts the names with higher weights first.
+/// Names without weights are treated as 0, the order of names with the same weight is preserved
+pub fn sort_by_weight(lines: Vec<String>) -> Vec<String> {
+    let mut weighted: Vec<(String, f64)> = lines
+        .iter()
+        .map(|line| {
+            let (name, weight) = split_weight(line);
+            (name.to_string(), weight.unwrap_or(0.))
+        })
+        .collect();
+
+    weighted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    weighted.into_iter().map(|(name, _)| name).collect()
+}
+
+/// splits `name weight` lines. The line is returned as is in case there's no valid weight
+pub fn split_weight(line: &str) -> (&str, Option<f64>) {
+    match line.trim_end().rsplit_once(char::is_whitespace) {
+        Some((name, weight)) if !name.trim().is_empty() => match weight.parse::<f64>() {
+            Ok(weight) => (name.trim(), Some(weight)),
+            Err(_) => (line, None),
+        },
+        _ => (line, None),
+    }
+}
+
 /// generate random word of RANDOM_CHARSET chars
 pub fn random_line(size: usize) -> String {
     (0..size)
@@ -0,0 +1,107 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::json;
+
+/// found parameter names per (method, url)
+pub type Findings = BTreeMap<(String, String), Vec<String>>;
+
+/// (method, url) pairs that couldn't be checked
+pub type Failed = BTreeSet<(String, String)>;
+
+/// reads the latest findings for every url from the history file
+pub fn read_history(path: &str) -> Result<Findings, Box<dyn Error>> {
+    let mut findings = Findings::new();
+
+    if !Path::new(path).exists() {
+        return Ok(findings);
+    }
+
+    for line in BufReader::new(File::open(path)?).lines() {
+        let entry: serde_json::Value = serde_json::from_str(&line?)?;
+
+        let (method, url) = match (entry["method"].as_str(), entry["url"].as_str()) {
+            (Some(method), Some(url)) => (method.to_string(), url.to_string()),
+            _ => continue,
+        };
+
+        let names = entry["found_params"]
+            .as_array()
+            .map(|x| {
+                x.iter()
+                    .filter_map(|x| x.as_str())
+                    .map(|x| x.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // the later entries overwrite the earlier ones
+        findings.insert((method, url), names);
+    }
+
+    Ok(findings)
+}
+
+/// appends the findings of the current run to the history file
+pub fn append_history(path: &str, findings: &Findings) -> Result<(), Box<dyn Error>> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    let time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+    for ((method, url), names) in findings {
+        writeln!(
+            file,
+            "{}",
+            json!({
+                "time": time,
+                "method": method,
+                "url": url,
+                "found_params": names,
+            })
+        )?;
+    }
+
+    Ok(())
+}
+
+/// returns the lines like "+ GET https://example.com/ debug" for new and "- .." for disappeared parameters
+/// only the urls that were checked during the current run are compared
+pub fn changes(previous: &Findings, current: &Findings) -> Vec<String> {
+    let mut changes = Vec::new();
+    let empty = Vec::new();
+
+    for ((method, url), names) in current {
+        let previous_names = previous.get(&(method.clone(), url.clone())).unwrap_or(&empty);
+
+        for name in names.iter().filter(|x| !previous_names.contains(x)) {
+            changes.push(format!("+ {} {} {}", method, url, name));
+        }
+
+        for name in previous_names.iter().filter(|x| !names.contains(x)) {
+            changes.push(format!("- {} {} {}", method, url, name));
+        }
+    }
+
+    changes
+}
+
+/// sends the changes to the webhook as {"text": "<changes separated by new lines>", "changes": [..]}
+pub async fn notify(webhook: &str, changes: &[String]) -> Result<(), Box<dyn Error>> {
+    reqwest::Client::new()
+        .post(webhook)
+        .json(&json!({
+            "text": format!("x8: the parameters have changed\n{}", changes.join("\n")),
+            "changes": changes,
+        }))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
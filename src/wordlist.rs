@@ -0,0 +1,263 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{config::structs::Config, network::utils::is_token_char, utils::split_weight};
+
+/// the wordlist without repeated and invalid names in case --wordlist-parts is used.
+/// The file is removed once dropped
+pub struct PreparedWordlist {
+    pub path: PathBuf,
+}
+
+impl Drop for PreparedWordlist {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+/// normalizes and shards the names of the wordlist on disk and writes them to a temporary file,
+/// so every url reads the same prepared names part by part.
+/// Repeated names are found by the first 16 bytes of their sha256, so only those are kept in memory.
+/// Value overrides and serialization hints are written first to get into the first part
+pub fn prepare_wordlist(config: &Config) -> io::Result<(PreparedWordlist, Dropped)> {
+    let prepared = PreparedWordlist {
+        path: std::env::temp_dir().join(format!("x8-wordlist-{}.txt", std::process::id())),
+    };
+
+    let mut writer = BufWriter::new(File::create(&prepared.path)?);
+    let mut seen: HashSet<[u8; 16]> = HashSet::new();
+    let mut dropped = Dropped::default();
+
+    for hints in [true, false] {
+        for line in BufReader::new(File::open(&config.wordlist)?).lines() {
+            // lines that aren't valid utf-8
+            let line = match line {
+                Ok(val) => val,
+                Err(_) => continue,
+            };
+
+            let (name, weight) = split_weight(&line);
+
+            if is_hint(name) != hints {
+                continue;
+            }
+
+            let name = match normalize_name(name, config.lowercase, config.headers_discovery, &mut dropped) {
+                Some(val) => val,
+                None => continue,
+            };
+
+            if let Some((k, n)) = config.shard {
+                if !in_shard(&name, k, n) {
+                    continue;
+                }
+            }
+
+            let mut key = [0; 16];
+            key.copy_from_slice(&ring::digest::digest(&ring::digest::SHA256, name.as_bytes()).as_ref()[..16]);
+
+            if !seen.insert(key) {
+                dropped.duplicates += 1;
+                continue;
+            }
+
+            match weight {
+                Some(weight) => writeln!(writer, "{} {}", name, weight)?,
+                None => writeln!(writer, "{}", name)?,
+            }
+        }
+    }
+
+    writer.flush()?;
+
+    Ok((prepared, dropped))
+}
+
+/// `name=value1,value2` overrides and `name:json` serialization hints
+fn is_hint(name: &str) -> bool {
+    match (name.split_once('='), name.rsplit_once(':')) {
+        (Some((name, values)), _) if values.contains(',') && !name.is_empty() => true,
+        (_, Some((name, kind))) => ["json", "array", "string"].contains(&kind) && !name.is_empty(),
+        _ => false,
+    }
+}
+
+/// reads the prepared wordlist from disk in parts in case --wordlist-parts is used.
+/// Only the current part is kept in memory
+pub struct WordlistParts {
+    lines: io::Lines<BufReader<File>>,
+    part_size: usize,
+}
+
+impl WordlistParts {
+    pub fn open<P: AsRef<Path>>(filename: P, part_size: usize) -> io::Result<Self> {
+        Ok(Self {
+            lines: BufReader::new(File::open(filename)?).lines(),
+            part_size,
+        })
+    }
+}
+
+impl Iterator for WordlistParts {
+    type Item = Vec<String>;
+
+    fn next(&mut self) -> Option<Vec<String>> {
+        let mut part = Vec::with_capacity(self.part_size.min(1 << 16));
+
+        while part.len() < self.part_size {
+            match self.lines.next() {
+                Some(Ok(val)) => part.push(val),
+                Some(Err(_)) => continue,
+                None => break,
+            }
+        }
+
+        if part.is_empty() {
+            None
+        } else {
+            Some(part)
+        }
+    }
+}
+
+/// the amount of lines within the file without loading it
+pub fn count_lines<P: AsRef<Path>>(filename: P) -> io::Result<usize> {
+    let mut reader = BufReader::new(File::open(filename)?);
+    let mut count = 0;
+
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+
+        count += buf.iter().filter(|x| **x == b'\n').count();
+
+        let len = buf.len();
+        reader.consume(len);
+    }
+
+    Ok(count)
+}
+
+/// why wordlist entries were dropped while normalizing
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Dropped {
+    pub empty: usize,
+    pub duplicates: usize,
+
+    /// header names with characters outside of the token charset
+    pub invalid_headers: usize,
+
+    /// Content-Length and Host headers
+    pub unsafe_headers: usize,
+}
+
+impl Dropped {
+    pub fn total(&self) -> usize {
+        self.empty + self.duplicates + self.invalid_headers + self.unsafe_headers
+    }
+
+    pub fn add(&mut self, other: Dropped) {
+        self.empty += other.empty;
+        self.duplicates += other.duplicates;
+        self.invalid_headers += other.invalid_headers;
+        self.unsafe_headers += other.unsafe_headers;
+    }
+}
+
+impl fmt::Display for Dropped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reasons: Vec<String> = [
+            (self.duplicates, "repeated"),
+            (self.empty, "empty"),
+            (self.invalid_headers, "invalid header names"),
+            (self.unsafe_headers, "content-length/host"),
+        ]
+        .iter()
+        .filter(|(amount, _)| *amount > 0)
+        .map(|(amount, reason)| format!("{} {}", amount, reason))
+        .collect();
+
+        write!(f, "{} entries were dropped from the wordlist: {}", self.total(), reasons.join(", "))
+    }
+}
+
+/// keeps the names of the k-th of n parts. The parts are disjoint and depend only on the names,
+/// so instances on different machines split the same wordlist the same way
+pub fn shard(params: Vec<String>, k: usize, n: usize) -> Vec<String> {
+    params.into_iter().filter(|x| in_shard(x, k, n)).collect()
+}
+
+fn in_shard(name: &str, k: usize, n: usize) -> bool {
+    stable_hash(name) % n as u64 == (k - 1) as u64
+}
+
+/// FNV-1a. Unlike DefaultHasher, it's guaranteed to be the same across builds
+fn stable_hash(item: &str) -> u64 {
+    item.bytes()
+        .fold(0xcbf29ce484222325, |hash, x| (hash ^ x as u64).wrapping_mul(0x100000001b3))
+}
+
+/// trims the names, removes control characters and repeated names.
+/// With headers = true names that can't be sent as header names are removed as well
+pub fn normalize(params: Vec<String>, lowercase: bool, headers: bool) -> (Vec<String>, Dropped) {
+    let mut dropped = Dropped::default();
+    let mut seen = HashSet::with_capacity(params.len());
+    let mut normalized = Vec::with_capacity(params.len());
+
+    for param in params {
+        let param = match normalize_name(&param, lowercase, headers, &mut dropped) {
+            Some(val) => val,
+            None => continue,
+        };
+
+        if !seen.insert(param.clone()) {
+            dropped.duplicates += 1;
+            continue;
+        }
+
+        normalized.push(param);
+    }
+
+    (normalized, dropped)
+}
+
+/// trims the name and removes control characters. Returns None and counts the reason in case the name is dropped
+fn normalize_name(param: &str, lowercase: bool, headers: bool, dropped: &mut Dropped) -> Option<String> {
+    let mut param: String = param.trim().chars().filter(|x| !x.is_control()).collect();
+
+    if param.is_empty() {
+        dropped.empty += 1;
+        return None;
+    }
+
+    // only the name is lowercased, the values of "name=value1,value2" overrides are kept
+    if lowercase {
+        let name_len = param.find('=').unwrap_or(param.len());
+        param = param[..name_len].to_lowercase() + &param[name_len..];
+    }
+
+    if headers {
+        let name = param.split('=').next().unwrap_or_default();
+
+        // such headers usually cause server to timeout
+        // especially when http/2 is used
+        if name.eq_ignore_ascii_case("content-length") || name.eq_ignore_ascii_case("host") {
+            dropped.unsafe_headers += 1;
+            return None;
+        }
+
+        if name.is_empty() || !name.bytes().all(is_token_char) {
+            dropped.invalid_headers += 1;
+            return None;
+        }
+    }
+
+    Some(param)
+}
@@ -0,0 +1,208 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+    sync::Arc,
+};
+
+use clap::{App, Arg};
+use serde_json::json;
+use tokio::sync::Semaphore;
+
+use crate::job::Job;
+
+/// the arguments of x8 worker. Also used to generate shell completions
+pub fn worker_app() -> App<'static, 'static> {
+    App::new("worker")
+        .bin_name("x8 worker")
+        .about("Pull scan jobs from a queue and push the results back")
+        .arg(
+            Arg::with_name("queue")
+                .long("queue")
+                .help("redis://host:port or nats://host:port")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .long("jobs")
+                .help("The redis list or the nats subject with the jobs")
+                .default_value("x8-jobs")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("processing")
+                .long("processing")
+                .help("The redis list that keeps the jobs while they're running, one per worker.\nJobs left there after a crash are moved back to --jobs on start")
+                .default_value("x8-processing")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("results")
+                .long("results")
+                .help("The redis list or the nats subject to push the results to")
+                .default_value("x8-results")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-jobs")
+                .long("max-jobs")
+                .help("The amount of jobs that run at the same time")
+                .default_value("1")
+                .takes_value(true),
+        )
+}
+
+/// pulls scan jobs from a redis list or a nats subject and pushes the results back.
+/// args are the command line starting with the program name and "worker"
+pub async fn worker(args: Vec<String>) -> Result<i32, Box<dyn Error>> {
+    let args = worker_app().get_matches_from(args.into_iter().skip(1));
+
+    let queue = args.value_of("queue").unwrap();
+    let jobs = args.value_of("jobs").unwrap();
+    let processing = args.value_of("processing").unwrap();
+    let results = args.value_of("results").unwrap();
+    let slots = Arc::new(Semaphore::new(args.value_of("max-jobs").unwrap().parse()?));
+
+    writeln!(io::stderr(), "[#] Pulling jobs from {} of {}", jobs, queue).ok();
+
+    match queue.split_once("://").map(|x| x.0) {
+        Some("redis") | Some("rediss") => redis_worker(queue, jobs, processing, results, slots).await,
+        Some("nats") | Some("tls") => nats_worker(queue, jobs, results, slots).await,
+        _ => Err("--queue should be a redis:// or nats:// url")?,
+    }
+}
+
+/// runs the job from the message and returns the message with its result
+async fn process(payload: &[u8]) -> String {
+    let (id, result) = match serde_json::from_slice::<Job>(payload) {
+        Ok(job) => match job.check_remote() {
+            Ok(()) => (job.id.clone(), job.run_on_thread().await),
+            Err(err) => (job.id.clone(), Err(err.to_string())),
+        },
+        Err(err) => (None, Err(format!("Unable to parse the job: {}", err))),
+    };
+
+    match result {
+        Ok(result) => json!({"id": id, "result": result}),
+        Err(err) => json!({"id": id, "error": err}),
+    }
+    .to_string()
+}
+
+#[cfg(feature = "redis-queue")]
+async fn redis_worker(
+    url: &str,
+    jobs: &str,
+    processing: &str,
+    results: &str,
+    slots: Arc<Semaphore>,
+) -> Result<i32, Box<dyn Error>> {
+    let client = redis::Client::open(url)?;
+
+    // BLMOVE blocks the connection, so the results are pushed through another one
+    let mut conn = client.get_async_connection().await?;
+    let publisher = client.get_multiplexed_async_connection().await?;
+
+    // the jobs of the previous run of this worker that weren't finished
+    loop {
+        let payload: Option<Vec<u8>> = redis::cmd("LMOVE")
+            .arg(processing)
+            .arg(jobs)
+            .arg("RIGHT")
+            .arg("LEFT")
+            .query_async(&mut conn)
+            .await?;
+
+        if payload.is_none() {
+            break;
+        }
+    }
+
+    loop {
+        // the next job is taken only when there's a free slot, so other workers can take it meanwhile.
+        // It stays within the processing list until the result is pushed, so it isn't lost if the worker dies
+        let slot = Arc::clone(&slots).acquire_owned().await?;
+        let payload: Vec<u8> = redis::cmd("BLMOVE")
+            .arg(jobs)
+            .arg(processing)
+            .arg("LEFT")
+            .arg("RIGHT")
+            .arg(0)
+            .query_async(&mut conn)
+            .await?;
+
+        let mut publisher = publisher.clone();
+        let (processing, results) = (processing.to_string(), results.to_string());
+
+        tokio::spawn(async move {
+            let message = process(&payload).await;
+
+            if let Err(err) = redis::cmd("RPUSH")
+                .arg(&results)
+                .arg(message)
+                .query_async::<_, ()>(&mut publisher)
+                .await
+            {
+                crate::utils::error(format!("Unable to push the result: {}", err), None, None, None);
+            } else if let Err(err) = redis::cmd("LREM")
+                .arg(&processing)
+                .arg(1)
+                .arg(&payload)
+                .query_async::<_, ()>(&mut publisher)
+                .await
+            {
+                crate::utils::error(format!("Unable to remove the finished job: {}", err), None, None, None);
+            }
+
+            drop(slot);
+        });
+    }
+}
+
+#[cfg(not(feature = "redis-queue"))]
+async fn redis_worker(
+    _url: &str,
+    _jobs: &str,
+    _processing: &str,
+    _results: &str,
+    _slots: Arc<Semaphore>,
+) -> Result<i32, Box<dyn Error>> {
+    Err("x8 was built without the redis-queue feature. Build it with --features queue")?
+}
+
+#[cfg(feature = "nats-queue")]
+async fn nats_worker(url: &str, jobs: &str, results: &str, slots: Arc<Semaphore>) -> Result<i32, Box<dyn Error>> {
+    use futures::StreamExt;
+
+    let client = async_nats::connect(url).await?;
+
+    // the workers within the same queue group share the jobs
+    let mut subscriber = client.queue_subscribe(jobs.to_string(), "x8".to_string()).await?;
+
+    loop {
+        let slot = Arc::clone(&slots).acquire_owned().await?;
+
+        let message = match subscriber.next().await {
+            Some(val) => val,
+            None => Err("The nats connection was closed")?,
+        };
+
+        let client = client.clone();
+        let results = results.to_string();
+
+        tokio::spawn(async move {
+            let result = process(&message.payload).await;
+
+            if let Err(err) = client.publish(results, result.into()).await {
+                crate::utils::error(format!("Unable to publish the result: {}", err), None, None, None);
+            }
+
+            drop(slot);
+        });
+    }
+}
+
+#[cfg(not(feature = "nats-queue"))]
+async fn nats_worker(_url: &str, _jobs: &str, _results: &str, _slots: Arc<Semaphore>) -> Result<i32, Box<dyn Error>> {
+    Err("x8 was built without the nats-queue feature. Build it with --features queue")?
+}